@@ -0,0 +1,14 @@
+CREATE TABLE post_revisions (
+    id SERIAL PRIMARY KEY,
+    post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    revision_number INTEGER NOT NULL,
+    title VARCHAR NOT NULL,
+    slug VARCHAR NOT NULL,
+    content TEXT NOT NULL,
+    status VARCHAR NOT NULL,
+    author_id INTEGER REFERENCES users(id),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (post_id, revision_number)
+);
+
+CREATE INDEX idx_post_revisions_post_id ON post_revisions(post_id);
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS post_publish_log;
+DROP INDEX IF EXISTS idx_posts_scheduled;
+
+-- `status` stays: it may have existed before `up.sql` ran, with data that
+-- rolling back must not lose. Only its new constraints go.
+ALTER TABLE posts
+    DROP CONSTRAINT IF EXISTS posts_scheduled_needs_publish_at,
+    DROP CONSTRAINT IF EXISTS posts_status_check,
+    DROP COLUMN IF EXISTS published_at,
+    DROP COLUMN IF EXISTS publish_at;
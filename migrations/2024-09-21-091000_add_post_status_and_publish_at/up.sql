@@ -0,0 +1,38 @@
+ALTER TABLE posts
+    ADD COLUMN IF NOT EXISTS status VARCHAR NOT NULL DEFAULT 'draft',
+    ADD COLUMN publish_at TIMESTAMP,
+    ADD COLUMN published_at TIMESTAMP;
+
+-- Databases that already had a free-form status column keep their posts:
+-- known spellings are mapped onto the new set and anything else becomes a
+-- draft. Nothing can be scheduled yet, since publish_at is new.
+UPDATE posts SET status = CASE lower(trim(status))
+        WHEN 'published' THEN 'published'
+        WHEN 'publish' THEN 'published'
+        WHEN 'public' THEN 'published'
+        WHEN 'live' THEN 'published'
+        WHEN 'pending' THEN 'pending_review'
+        WHEN 'pending_review' THEN 'pending_review'
+        WHEN 'review' THEN 'pending_review'
+        WHEN 'archived' THEN 'archived'
+        ELSE 'draft'
+    END;
+UPDATE posts SET published_at = COALESCE(updated_at, created_at, NOW())
+    WHERE status = 'published';
+
+ALTER TABLE posts
+    ALTER COLUMN status SET DEFAULT 'draft',
+    ALTER COLUMN status SET NOT NULL,
+    ADD CONSTRAINT posts_status_check
+        CHECK (status IN ('draft', 'pending_review', 'scheduled', 'published', 'archived')),
+    ADD CONSTRAINT posts_scheduled_needs_publish_at
+        CHECK (status <> 'scheduled' OR publish_at IS NOT NULL);
+
+CREATE INDEX idx_posts_scheduled ON posts(publish_at) WHERE status = 'scheduled';
+
+CREATE TABLE post_publish_log (
+    id SERIAL PRIMARY KEY,
+    post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    scheduled_for TIMESTAMP NOT NULL,
+    published_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,14 @@
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS slug VARCHAR;
+UPDATE posts SET slug = 'post-' || id WHERE slug IS NULL;
+ALTER TABLE posts ALTER COLUMN slug SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_posts_slug ON posts(slug);
+
+ALTER TABLE pages ADD COLUMN slug VARCHAR;
+UPDATE pages SET slug = 'page-' || id;
+ALTER TABLE pages ALTER COLUMN slug SET NOT NULL;
+CREATE UNIQUE INDEX idx_pages_slug ON pages(slug);
+
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS slug VARCHAR;
+UPDATE categories SET slug = 'category-' || id WHERE slug IS NULL;
+ALTER TABLE categories ALTER COLUMN slug SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_categories_slug ON categories(slug);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_media_storage_key;
+
+ALTER TABLE media
+    DROP COLUMN IF EXISTS alt_text,
+    DROP COLUMN IF EXISTS file_size,
+    DROP COLUMN IF EXISTS storage_key;
@@ -0,0 +1,8 @@
+ALTER TABLE media
+    ADD COLUMN storage_key VARCHAR,
+    ADD COLUMN file_size BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN alt_text VARCHAR NOT NULL DEFAULT '';
+
+UPDATE media SET storage_key = 'legacy/' || id WHERE storage_key IS NULL;
+ALTER TABLE media ALTER COLUMN storage_key SET NOT NULL;
+CREATE UNIQUE INDEX idx_media_storage_key ON media(storage_key);
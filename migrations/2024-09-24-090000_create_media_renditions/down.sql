@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS media_renditions;
+DROP INDEX IF EXISTS idx_media_renditions_pending;
+
+ALTER TABLE media DROP COLUMN IF EXISTS renditions_status;
@@ -0,0 +1,23 @@
+ALTER TABLE media
+    ADD COLUMN renditions_status VARCHAR NOT NULL DEFAULT 'none'
+        CHECK (renditions_status IN ('none', 'pending', 'processing', 'ready', 'failed'));
+
+-- Queue images uploaded before renditions existed
+UPDATE media SET renditions_status = 'pending'
+WHERE media_type IN ('image/jpeg', 'image/png', 'image/gif', 'image/webp');
+
+CREATE INDEX idx_media_renditions_pending ON media(id) WHERE renditions_status = 'pending';
+
+CREATE TABLE media_renditions (
+    id SERIAL PRIMARY KEY,
+    media_id INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    storage_key VARCHAR NOT NULL UNIQUE,
+    url VARCHAR NOT NULL,
+    media_type VARCHAR NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    file_size BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (media_id, name)
+);
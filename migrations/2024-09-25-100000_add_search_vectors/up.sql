@@ -0,0 +1,19 @@
+-- Titles weigh more than body text. The default parser skips HTML tags,
+-- so stored markup does not pollute the index.
+ALTER TABLE posts ADD COLUMN search_vector tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('english', coalesce(content, '')), 'B')
+    ) STORED;
+CREATE INDEX idx_posts_search_vector ON posts USING GIN (search_vector);
+
+ALTER TABLE pages ADD COLUMN search_vector tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('english', coalesce(content, '')), 'B')
+    ) STORED;
+CREATE INDEX idx_pages_search_vector ON pages USING GIN (search_vector);
+
+ALTER TABLE comments ADD COLUMN search_vector tsvector
+    GENERATED ALWAYS AS (to_tsvector('english', coalesce(content, ''))) STORED;
+CREATE INDEX idx_comments_search_vector ON comments USING GIN (search_vector);
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_comments_post_parent;
+DROP INDEX IF EXISTS idx_comments_status_created;
+
+ALTER TABLE comments
+    DROP COLUMN IF EXISTS author_email,
+    DROP COLUMN IF EXISTS author_name,
+    DROP COLUMN IF EXISTS status,
+    DROP COLUMN IF EXISTS parent_id;
+
+ALTER TABLE comments ALTER COLUMN post_id DROP NOT NULL;
@@ -0,0 +1,17 @@
+-- Comments without a post can't be shown anywhere
+DELETE FROM comments WHERE post_id IS NULL;
+ALTER TABLE comments ALTER COLUMN post_id SET NOT NULL;
+
+ALTER TABLE comments
+    ADD COLUMN parent_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+    ADD COLUMN status VARCHAR NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'spam', 'trash')),
+    ADD COLUMN author_name VARCHAR NOT NULL DEFAULT '',
+    ADD COLUMN author_email VARCHAR NOT NULL DEFAULT '';
+
+-- Comments from before moderation existed were already public
+UPDATE comments SET status = 'approved';
+UPDATE comments c SET author_name = u.username FROM users u WHERE u.id = c.user_id;
+
+CREATE INDEX idx_comments_status_created ON comments(status, created_at DESC);
+CREATE INDEX idx_comments_post_parent ON comments(post_id, parent_id);
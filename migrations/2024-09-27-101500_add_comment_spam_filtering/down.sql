@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS spam_corpus;
+DROP TABLE IF EXISTS spam_tokens;
+
+ALTER TABLE comments
+    DROP COLUMN IF EXISTS spam_trained,
+    DROP COLUMN IF EXISTS spam_verdicts,
+    DROP COLUMN IF EXISTS author_ip;
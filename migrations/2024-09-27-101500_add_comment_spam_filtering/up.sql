@@ -0,0 +1,20 @@
+ALTER TABLE comments
+    ADD COLUMN author_ip VARCHAR,
+    ADD COLUMN spam_verdicts JSONB NOT NULL DEFAULT '[]',
+    -- Which class the comment was last used to train the classifier as
+    ADD COLUMN spam_trained VARCHAR CHECK (spam_trained IN ('spam', 'ham'));
+
+-- Per-token document counts for the naive-Bayes classifier
+CREATE TABLE spam_tokens (
+    token VARCHAR PRIMARY KEY,
+    spam_count INTEGER NOT NULL DEFAULT 0 CHECK (spam_count >= 0),
+    ham_count INTEGER NOT NULL DEFAULT 0 CHECK (ham_count >= 0)
+);
+
+-- Number of documents trained as each class; always exactly one row
+CREATE TABLE spam_corpus (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    spam_docs INTEGER NOT NULL DEFAULT 0 CHECK (spam_docs >= 0),
+    ham_docs INTEGER NOT NULL DEFAULT 0 CHECK (ham_docs >= 0)
+);
+INSERT INTO spam_corpus DEFAULT VALUES;
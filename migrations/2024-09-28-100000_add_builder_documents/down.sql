@@ -0,0 +1,30 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE component_events
+    DROP CONSTRAINT component_events_component_id_fkey,
+    ADD CONSTRAINT component_events_component_id_fkey FOREIGN KEY (component_id) REFERENCES components(id);
+
+ALTER TABLE component_styles
+    DROP CONSTRAINT component_styles_component_id_fkey,
+    ADD CONSTRAINT component_styles_component_id_fkey FOREIGN KEY (component_id) REFERENCES components(id);
+
+DROP INDEX IF EXISTS idx_page_components_placement;
+ALTER TABLE page_components
+    DROP CONSTRAINT page_components_component_id_fkey,
+    ADD CONSTRAINT page_components_component_id_fkey FOREIGN KEY (component_id) REFERENCES components(id),
+    DROP CONSTRAINT page_components_page_id_fkey,
+    ADD CONSTRAINT page_components_page_id_fkey FOREIGN KEY (page_id) REFERENCES pages(id),
+    DROP COLUMN IF EXISTS module_key,
+    DROP COLUMN IF EXISTS column_key,
+    DROP COLUMN IF EXISTS section_id;
+
+DROP INDEX IF EXISTS idx_components_page;
+ALTER TABLE components DROP COLUMN IF EXISTS page_id;
+
+DROP INDEX IF EXISTS idx_page_sections_key;
+ALTER TABLE page_sections
+    DROP CONSTRAINT page_sections_page_id_fkey,
+    ADD CONSTRAINT page_sections_page_id_fkey FOREIGN KEY (page_id) REFERENCES pages(id),
+    DROP COLUMN IF EXISTS position,
+    DROP COLUMN IF EXISTS section_key;
+
+ALTER TABLE pages DROP COLUMN IF EXISTS builder_version;
@@ -0,0 +1,36 @@
+-- Format version of the builder document a page was saved with; NULL for pages not built in the builder
+ALTER TABLE pages ADD COLUMN builder_version INTEGER;
+
+-- One row per section, holding its row/column layout as JSON in `content`
+ALTER TABLE page_sections
+    ADD COLUMN section_key VARCHAR,
+    ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+UPDATE page_sections SET section_key = 'section-' || id;
+ALTER TABLE page_sections ALTER COLUMN section_key SET NOT NULL;
+ALTER TABLE page_sections
+    DROP CONSTRAINT page_sections_page_id_fkey,
+    ADD CONSTRAINT page_sections_page_id_fkey FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE;
+CREATE UNIQUE INDEX idx_page_sections_key ON page_sections(page_id, section_key);
+
+-- Modules are components owned by the page they sit on
+ALTER TABLE components ADD COLUMN page_id INTEGER REFERENCES pages(id) ON DELETE CASCADE;
+CREATE INDEX idx_components_page ON components(page_id);
+
+-- Where each module sits: section, column within the section's layout, and order within the column
+ALTER TABLE page_components
+    ADD COLUMN section_id INTEGER REFERENCES page_sections(id) ON DELETE CASCADE,
+    ADD COLUMN column_key VARCHAR,
+    ADD COLUMN module_key VARCHAR,
+    DROP CONSTRAINT page_components_page_id_fkey,
+    ADD CONSTRAINT page_components_page_id_fkey FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE,
+    DROP CONSTRAINT page_components_component_id_fkey,
+    ADD CONSTRAINT page_components_component_id_fkey FOREIGN KEY (component_id) REFERENCES components(id) ON DELETE CASCADE;
+CREATE INDEX idx_page_components_placement ON page_components(section_id, column_key, position);
+
+ALTER TABLE component_styles
+    DROP CONSTRAINT component_styles_component_id_fkey,
+    ADD CONSTRAINT component_styles_component_id_fkey FOREIGN KEY (component_id) REFERENCES components(id) ON DELETE CASCADE;
+
+ALTER TABLE component_events
+    DROP CONSTRAINT component_events_component_id_fkey,
+    ADD CONSTRAINT component_events_component_id_fkey FOREIGN KEY (component_id) REFERENCES components(id) ON DELETE CASCADE;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_page_sections_block;
+ALTER TABLE page_sections DROP COLUMN IF EXISTS block_id;
+
+DROP INDEX IF EXISTS idx_components_library;
+ALTER TABLE components DROP COLUMN IF EXISTS library_kind;
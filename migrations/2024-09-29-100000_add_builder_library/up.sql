@@ -0,0 +1,9 @@
+-- Library items are components that belong to no page: global blocks, referenced
+-- from pages and rendered live, and saved sections, copied into a page on insert
+ALTER TABLE components
+    ADD COLUMN library_kind VARCHAR CHECK (library_kind IN ('global_block', 'saved_section'));
+CREATE INDEX idx_components_library ON components(library_kind) WHERE library_kind IS NOT NULL;
+
+-- Sections that show a global block; a block can't be deleted while a page uses it
+ALTER TABLE page_sections ADD COLUMN block_id INTEGER REFERENCES components(id) ON DELETE RESTRICT;
+CREATE INDEX idx_page_sections_block ON page_sections(block_id);
@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`. Only desktop styles survive.
+DROP INDEX IF EXISTS idx_component_styles_breakpoint;
+DELETE FROM component_styles WHERE breakpoint <> 'desktop';
+
+ALTER TABLE component_styles ADD COLUMN css TEXT NOT NULL DEFAULT '';
+UPDATE component_styles SET css = COALESCE((
+    SELECT string_agg(key || ': ' || value || ';', E'\n' ORDER BY key) || E'\n'
+    FROM jsonb_each_text(properties)
+), '');
+
+ALTER TABLE component_styles
+    DROP CONSTRAINT IF EXISTS component_styles_custom_width,
+    DROP COLUMN IF EXISTS properties,
+    DROP COLUMN IF EXISTS max_width,
+    DROP COLUMN IF EXISTS breakpoint;
@@ -0,0 +1,22 @@
+-- One row per component and breakpoint; declarations are stored as a JSON object
+-- of property -> value instead of a CSS blob. Desktop rows have no max_width.
+ALTER TABLE component_styles
+    ADD COLUMN breakpoint VARCHAR NOT NULL DEFAULT 'desktop'
+        CHECK (breakpoint IN ('desktop', 'tablet', 'mobile', 'custom')),
+    ADD COLUMN max_width INTEGER,
+    ADD COLUMN properties JSONB NOT NULL DEFAULT '{}';
+
+-- Existing styles were `property: value;` lines
+UPDATE component_styles SET properties = COALESCE((
+    SELECT jsonb_object_agg(trim(split_part(declaration, ':', 1)), trim(substr(declaration, strpos(declaration, ':') + 1)))
+    FROM unnest(string_to_array(css, ';')) AS declaration
+    WHERE strpos(declaration, ':') > 0
+      AND trim(split_part(declaration, ':', 1)) <> ''
+      AND trim(substr(declaration, strpos(declaration, ':') + 1)) <> ''
+), '{}');
+
+ALTER TABLE component_styles
+    DROP COLUMN css,
+    ADD CONSTRAINT component_styles_custom_width CHECK ((breakpoint = 'custom') = (max_width IS NOT NULL));
+CREATE UNIQUE INDEX idx_component_styles_breakpoint
+    ON component_styles(component_id, breakpoint, COALESCE(max_width, 0));
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`. Actions are not converted back to script.
+DROP INDEX IF EXISTS idx_component_events_component;
+DELETE FROM component_events;
+
+ALTER TABLE component_events
+    DROP CONSTRAINT IF EXISTS component_events_event_type,
+    DROP COLUMN IF EXISTS actions,
+    ADD COLUMN event_handler TEXT NOT NULL;
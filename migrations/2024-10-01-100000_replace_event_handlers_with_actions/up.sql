@@ -0,0 +1,10 @@
+-- Events become a list of declarative actions run by the page runtime instead
+-- of stored JavaScript. Handlers can't be translated automatically, so existing
+-- ones are dropped; rebuild them in the builder's Events panel.
+DELETE FROM component_events;
+
+ALTER TABLE component_events
+    DROP COLUMN event_handler,
+    ADD COLUMN actions JSONB NOT NULL DEFAULT '[]',
+    ADD CONSTRAINT component_events_event_type CHECK (event_type IN ('click', 'visible'));
+CREATE INDEX idx_component_events_component ON component_events(component_id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS theme_activations;
+DROP TABLE IF EXISTS themes;
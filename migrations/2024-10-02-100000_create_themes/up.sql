@@ -0,0 +1,21 @@
+-- Installed theme packages. Each install of a slug/version is kept on disk
+-- under THEMES_ROOT/<slug>/<version>, so an older version can be activated again.
+CREATE TABLE themes (
+    id SERIAL PRIMARY KEY,
+    slug VARCHAR NOT NULL,
+    name VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    manifest JSONB NOT NULL,
+    path VARCHAR NOT NULL,
+    installed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (slug, version)
+);
+
+-- Activation history: the newest row is the active theme, and rolling back
+-- removes it. With no rows the built-in templates are used.
+CREATE TABLE theme_activations (
+    id SERIAL PRIMARY KEY,
+    theme_id INTEGER NOT NULL REFERENCES themes(id) ON DELETE CASCADE,
+    activated_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    activated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
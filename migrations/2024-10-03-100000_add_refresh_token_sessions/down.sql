@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_sessions_active_user;
+DROP INDEX IF EXISTS idx_sessions_family_id;
+
+COMMENT ON COLUMN sessions.session_token IS NULL;
+
+ALTER TABLE sessions
+    DROP CONSTRAINT sessions_user_id_fkey,
+    ADD CONSTRAINT sessions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id),
+    DROP COLUMN revoked_at,
+    DROP COLUMN used_at,
+    DROP COLUMN user_agent,
+    DROP COLUMN family_id,
+    ALTER COLUMN expires_at DROP NOT NULL,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN user_id DROP NOT NULL;
@@ -0,0 +1,21 @@
+-- Each row is one refresh token. Rotating a token marks its row used and
+-- inserts the next one in the same family; a family is one login. Presenting
+-- a used token again revokes the whole family.
+-- Rows written before this migration were never issued to anyone.
+DELETE FROM sessions;
+
+ALTER TABLE sessions
+    ALTER COLUMN user_id SET NOT NULL,
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN expires_at SET NOT NULL,
+    ADD COLUMN family_id VARCHAR(36) NOT NULL,
+    ADD COLUMN user_agent VARCHAR,
+    ADD COLUMN used_at TIMESTAMP,
+    ADD COLUMN revoked_at TIMESTAMP,
+    DROP CONSTRAINT sessions_user_id_fkey,
+    ADD CONSTRAINT sessions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+COMMENT ON COLUMN sessions.session_token IS 'SHA-256 of the refresh token, hex encoded';
+
+CREATE INDEX idx_sessions_family_id ON sessions (family_id);
+CREATE INDEX idx_sessions_active_user ON sessions (user_id) WHERE revoked_at IS NULL AND used_at IS NULL;
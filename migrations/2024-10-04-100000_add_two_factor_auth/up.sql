@@ -0,0 +1,18 @@
+-- TOTP two-factor authentication. `totp_secret` is set when enrolment starts
+-- and 2FA is on once `totp_enabled_at` is set. `totp_last_step` is the time
+-- step of the last accepted code, so a code can't be replayed.
+ALTER TABLE users
+    ADD COLUMN totp_secret VARCHAR,
+    ADD COLUMN totp_enabled_at TIMESTAMP,
+    ADD COLUMN totp_last_step BIGINT;
+
+-- One-time recovery codes for users who lose their authenticator, stored hashed
+CREATE TABLE recovery_codes (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_recovery_codes_user_id ON recovery_codes (user_id);
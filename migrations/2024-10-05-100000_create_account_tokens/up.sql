@@ -0,0 +1,18 @@
+-- Set once the user follows a verification link sent to their current email
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP;
+
+-- Single-use tokens mailed to users. Only a hash of each token is stored.
+-- `email` is the address a verification token was sent to; the token stops
+-- working if the user's email changes.
+CREATE TABLE account_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose VARCHAR NOT NULL CHECK (purpose IN ('password_reset', 'email_verification')),
+    token_hash VARCHAR NOT NULL UNIQUE,
+    email VARCHAR,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_account_tokens_user_purpose ON account_tokens (user_id, purpose);
@@ -0,0 +1,35 @@
+-- Accounts at external identity providers (OpenID Connect) linked to users.
+-- `subject` is the provider's stable `sub` claim for the account.
+CREATE TABLE user_identities (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR NOT NULL,
+    subject VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, subject)
+);
+
+CREATE INDEX idx_user_identities_user_id ON user_identities (user_id);
+
+-- Logins sent to a provider and not back yet. The row is looked up by a
+-- hash of the `state` parameter and deleted when the user returns.
+-- `link_user_id` is set when a signed-in user is linking the provider
+-- account to their own rather than logging in.
+CREATE TABLE oidc_login_states (
+    state_hash VARCHAR PRIMARY KEY,
+    provider VARCHAR NOT NULL,
+    nonce VARCHAR NOT NULL,
+    code_verifier VARCHAR NOT NULL,
+    link_user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP NOT NULL
+);
+
+-- Logins finished at a provider, waiting for the login page to redeem the
+-- single-use code it was sent back with. Only a hash of the code is kept.
+CREATE TABLE sso_handoffs (
+    code_hash VARCHAR PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR NOT NULL,
+    expires_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,22 @@
+-- Keys for scripts and integrations. Only a hash of each key is stored;
+-- `prefix` holds its first characters so keys can be told apart.
+--
+-- Personal keys act as `user_id` with whatever role the user has. Site keys
+-- have `role` set and act with it; their changes are attributed to
+-- `user_id`, the admin who created them.
+CREATE TABLE api_keys (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    prefix VARCHAR NOT NULL,
+    key_hash VARCHAR NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    expires_at TIMESTAMP,
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_keys_user_id ON api_keys (user_id);
@@ -0,0 +1,11 @@
+-- Recent failed logins, counted per attempted username (`scope` 'account')
+-- and per client address (`scope` 'ip'). Rows are reset by a successful
+-- login or an admin unlock, and ignored once `last_failed_at` is old enough.
+CREATE TABLE login_throttles (
+    scope VARCHAR NOT NULL,
+    subject VARCHAR NOT NULL,
+    failures INTEGER NOT NULL,
+    last_failed_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP,
+    PRIMARY KEY (scope, subject)
+);
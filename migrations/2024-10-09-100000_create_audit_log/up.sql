@@ -0,0 +1,20 @@
+-- Who changed what. `actor_name` keeps the username in case the user is
+-- deleted later. For updates `before` and `after` hold only the fields that
+-- changed; for creations and deletions, the whole record.
+CREATE TABLE audit_log (
+    id SERIAL PRIMARY KEY,
+    actor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    actor_name VARCHAR,
+    api_key_id INTEGER REFERENCES api_keys(id) ON DELETE SET NULL,
+    ip VARCHAR,
+    action VARCHAR NOT NULL,
+    entity_type VARCHAR NOT NULL,
+    entity_id VARCHAR,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_log_created_at ON audit_log (created_at);
+CREATE INDEX idx_audit_log_entity ON audit_log (entity_type, entity_id);
+CREATE INDEX idx_audit_log_actor_name ON audit_log (actor_name);
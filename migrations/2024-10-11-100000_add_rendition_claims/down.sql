@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE media DROP COLUMN IF EXISTS renditions_claimed_at;
@@ -0,0 +1,5 @@
+-- When a worker claimed an upload. A claim older than the lease means the
+-- worker died mid-job; other instances leave younger claims alone.
+ALTER TABLE media ADD COLUMN renditions_claimed_at TIMESTAMP;
+
+UPDATE media SET renditions_claimed_at = NOW() WHERE renditions_status = 'processing';
@@ -0,0 +1,101 @@
+use axum::{
+    routing::{delete, get},
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Router,
+};
+use crate::backend::services::api_key_service::ApiKeyServiceError;
+use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::shared::api_keys::NewApiKeyRequest;
+use crate::shared::audit::{AuditAction, AuditEntity};
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+fn api_key_error_response(err: ApiKeyServiceError, action: &str) -> Response {
+    match err {
+        ApiKeyServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        ApiKeyServiceError::Invalid(_) | ApiKeyServiceError::UserNotFound(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid API key",
+                "details": err.to_string(),
+            })),
+        )
+            .into_response(),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler listing every API key
+async fn list_api_keys_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+) -> Response {
+    match state.api_key_service.list().await {
+        Ok(keys) => (StatusCode::OK, Json(SuccessResponse { data: keys })).into_response(),
+        Err(e) => api_key_error_response(e, "list API keys"),
+    }
+}
+
+/// Handler creating a key. The response holds the only copy of the key.
+async fn create_api_key_handler(
+    State(state): State<AppState>,
+    Require(user, ..): Require<caps::ManageUsers>,
+    audit: Audit,
+    Json(request): Json<NewApiKeyRequest>,
+) -> Response {
+    match state.api_key_service.create(&user, request).await {
+        Ok(created) => {
+            audit.created(AuditEntity::ApiKey, created.key.id, &created.key).await;
+            (StatusCode::CREATED, Json(SuccessResponse { data: created })).into_response()
+        }
+        Err(e) => api_key_error_response(e, "create API key"),
+    }
+}
+
+/// Handler revoking a key
+async fn revoke_api_key_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+    audit: Audit,
+    Path(key_id): Path<i32>,
+) -> Response {
+    match state.api_key_service.revoke(key_id).await {
+        Ok(()) => {
+            audit
+                .record(AuditAction::Revoke, AuditEntity::ApiKey, Some(key_id.to_string()), None, None)
+                .await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => api_key_error_response(e, "revoke API key"),
+    }
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/", get(list_api_keys_handler).post(create_api_key_handler))
+        .route("/:key_id", delete(revoke_api_key_handler))
+}
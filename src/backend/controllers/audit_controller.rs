@@ -0,0 +1,93 @@
+use axum::{
+    routing::get,
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Router,
+};
+use crate::backend::services::audit_service::{AuditServiceError, DEFAULT_PER_PAGE};
+use crate::backend::AppState;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::shared::audit::{AuditAction, AuditEntity, AuditFilter};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+/// `GET /audit?actor=alice&entity_type=post&from=2024-10-01&page=2`
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    pub entity_type: Option<AuditEntity>,
+    pub entity_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+fn audit_error_response(err: AuditServiceError, action: &str) -> Response {
+    match err {
+        AuditServiceError::InvalidFilter(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid filter",
+                "details": err.to_string(),
+            })),
+        )
+            .into_response(),
+        AuditServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for browsing the audit log, newest entries first
+async fn list_audit_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ViewAuditLog>,
+    Query(query): Query<AuditQuery>,
+) -> Response {
+    let filter = AuditFilter {
+        actor: query.actor,
+        action: query.action,
+        entity_type: query.entity_type,
+        entity_id: query.entity_id,
+        from: query.from,
+        to: query.to,
+    };
+    match state
+        .audit_service
+        .list(
+            &filter,
+            query.page.unwrap_or(1),
+            query.per_page.unwrap_or(DEFAULT_PER_PAGE),
+        )
+        .await
+    {
+        Ok(page) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: page }),
+        )
+            .into_response(),
+        Err(e) => audit_error_response(e, "retrieve the audit log"),
+    }
+}
+
+/// Initialize the audit log routes
+pub fn routes() -> Router {
+    Router::new().route("/", get(list_audit_handler))
+}
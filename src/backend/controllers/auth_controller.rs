@@ -1,14 +1,26 @@
 use axum::{
-    routing::post,
-    extract::{Json, Extension},
-    http::StatusCode,
-    response::IntoResponse,
+    routing::{delete, get, post},
+    extract::{Json, Path, State},
+    http::{header::{RETRY_AFTER, USER_AGENT}, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Router,
 };
-use crate::services::auth_service::AuthService;
+use crate::backend::models::user::NewUser;
+use crate::backend::services::account_service::AccountServiceError;
+use crate::backend::services::auth_service::{AuthServiceError, TokenPair};
+use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::client_ip::ClientIp;
+use crate::backend::middlewares::permission_middleware::{caps, AuthUser, PermissionError, Require};
+use crate::shared::audit::{AuditAction, AuditEntity};
+use crate::shared::types::UserRole;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use crate::models::auth::{AuthError, AuthToken};
+use serde_json::json;
+use std::net::IpAddr;
+use tracing::error;
+
+/// Longest user agent kept with a session
+const MAX_USER_AGENT_CHARS: usize = 255;
 
 #[derive(Deserialize)]
 pub struct AuthData {
@@ -16,65 +28,616 @@ pub struct AuthData {
     pub password: String,
 }
 
+#[derive(Deserialize)]
+pub struct RegisterData {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+}
+
+/// Body of `/auth/refresh` and `/auth/logout`
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Body of `/auth/login/verify`
+#[derive(Deserialize)]
+pub struct SecondFactorRequest {
+    pub mfa_token: String,
+    /// Authenticator code or recovery code
+    pub code: String,
+}
+
+/// Body of the `/auth/2fa` routes. `mfa_token` stands in for the access
+/// token when a login requires setting up 2FA first.
+#[derive(Deserialize, Default)]
+pub struct TwoFactorRequest {
+    #[serde(default)]
+    pub code: String,
+    #[serde(default)]
+    pub mfa_token: Option<String>,
+}
+
+/// Body of `/auth/password/forgot`
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+/// Body of `/auth/password/reset`
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
+}
+
+/// Body of `/auth/email/verify`
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
 #[derive(Serialize)]
 struct ErrorResponse {
     error: String,
 }
 
-// Login Handler
-async fn login_handler(
-    Json(auth_data): Json<AuthData>,
-    Extension(auth_service): Extension<Arc<AuthService>>,
-) -> impl IntoResponse {
-    match auth_service.login(&auth_data.username, &auth_data.password).await {
-        Ok(token) => (StatusCode::OK, Json(AuthToken { token })),
-        Err(AuthError::InvalidCredentials) => (
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+/// Number of logins ended by `/auth/logout-all` or an administrator
+#[derive(Serialize)]
+struct RevokedSessions {
+    revoked: usize,
+}
+
+/// Result of finishing 2FA setup. `tokens` is set when the setup was the
+/// second step of a login.
+#[derive(Serialize)]
+struct TwoFactorEnabled {
+    recovery_codes: Vec<String>,
+    tokens: Option<TokenPair>,
+}
+
+#[derive(Serialize)]
+struct RecoveryCodes {
+    recovery_codes: Vec<String>,
+}
+
+pub(crate) fn auth_error_response(err: AuthServiceError, action: &str) -> Response {
+    match err {
+        AuthServiceError::InvalidCredentials => (
             StatusCode::UNAUTHORIZED,
             Json(ErrorResponse {
                 error: "Invalid credentials".to_string(),
             }),
-        ),
-        Err(_) => (
+        )
+            .into_response(),
+        AuthServiceError::InvalidRefreshToken
+        | AuthServiceError::RefreshTokenReused
+        | AuthServiceError::InvalidSecondFactor
+        | AuthServiceError::InvalidMfaToken => (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        AuthServiceError::UserAlreadyExists
+        | AuthServiceError::TwoFactorAlreadyEnabled
+        | AuthServiceError::TwoFactorNotStarted
+        | AuthServiceError::TwoFactorRequired => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        AuthServiceError::TooManyAttempts(seconds) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, seconds.to_string())],
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        AuthServiceError::UserNotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        _ => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
-                error: "Internal server error".to_string(),
+                error: format!("Failed to {}", action),
             }),
-        ),
+        )
+            .into_response(),
     }
 }
 
-// Register Handler
-async fn register_handler(
+fn account_error_response(err: AccountServiceError, action: &str) -> Response {
+    match err {
+        AccountServiceError::InvalidToken => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        AccountServiceError::WeakPassword(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid password",
+                "details": err.to_string(),
+            })),
+        )
+            .into_response(),
+        AccountServiceError::AlreadyVerified => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        AccountServiceError::UserNotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        AccountServiceError::Auth(e) => auth_error_response(e, action),
+        AccountServiceError::Mail(_) | AccountServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Response of the password endpoints while only single sign-on is allowed
+fn local_login_disabled() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse {
+            error: "Password login is turned off; sign in with single sign-on".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+pub(crate) fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.chars().take(MAX_USER_AGENT_CHARS).collect())
+}
+
+/// Handler for logging in. Returns an access token and a refresh token, or
+/// an `mfa_token` and the `second_factor` step the user must complete.
+/// Repeated failures get 429 with a `Retry-After` header.
+async fn login_handler(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Json(auth_data): Json<AuthData>,
-    Extension(auth_service): Extension<Arc<AuthService>>,
-) -> impl IntoResponse {
-    match auth_service
-        .register(&auth_data.username, &auth_data.password)
+) -> Response {
+    if !state.oidc_service.local_login_enabled().await {
+        return local_login_disabled();
+    }
+    match state
+        .auth_service
+        .login(&auth_data.username, &auth_data.password, user_agent(&headers), Some(ip))
         .await
     {
-        Ok(_) => (
-            StatusCode::CREATED,
-            Json("User registered".to_string()),
-        ),
-        Err(AuthError::UserAlreadyExists) => (
-            StatusCode::CONFLICT,
-            Json(ErrorResponse {
-                error: "User already exists".to_string(),
+        Ok(outcome) => (StatusCode::OK, Json(outcome)).into_response(),
+        Err(e) => auth_error_response(e, "log in"),
+    }
+}
+
+/// Handler for the second login step of a user with 2FA
+async fn verify_login_handler(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Json(request): Json<SecondFactorRequest>,
+) -> Response {
+    match state
+        .auth_service
+        .verify_second_factor(&request.mfa_token, &request.code, user_agent(&headers), Some(ip))
+        .await
+    {
+        Ok(tokens) => (StatusCode::OK, Json(tokens)).into_response(),
+        Err(e) => auth_error_response(e, "log in"),
+    }
+}
+
+/// Handler for registering a subscriber account
+async fn register_handler(
+    State(state): State<AppState>,
+    audit: Audit,
+    Json(register_data): Json<RegisterData>,
+) -> Response {
+    if !state.oidc_service.local_login_enabled().await {
+        return local_login_disabled();
+    }
+    let new_user = NewUser {
+        username: register_data.username,
+        email: register_data.email,
+        password_hash: register_data.password,
+        role: UserRole::Subscriber.to_string(),
+    };
+    match state.auth_service.register_user(new_user).await {
+        Ok(user) => {
+            audit.created(AuditEntity::User, user.id, &user).await;
+            if let Err(e) = state.account_service.send_verification(user.id).await {
+                error!("Failed to send verification email to new user {}: {}", user.id, e);
+            }
+            (
+                StatusCode::CREATED,
+                Json("User registered".to_string()),
+            )
+                .into_response()
+        }
+        Err(e) => auth_error_response(e, "register user"),
+    }
+}
+
+/// Handler for requesting a password reset link. Always accepted, so it
+/// can't be used to find out which addresses have accounts.
+async fn forgot_password_handler(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Response {
+    if !state.oidc_service.local_login_enabled().await {
+        return local_login_disabled();
+    }
+    match state.account_service.request_password_reset(&request.email).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => account_error_response(e, "request password reset"),
+    }
+}
+
+/// Handler for choosing a new password with a mailed reset token
+async fn reset_password_handler(
+    State(state): State<AppState>,
+    audit: Audit,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Response {
+    if !state.oidc_service.local_login_enabled().await {
+        return local_login_disabled();
+    }
+    match state.account_service.reset_password(&request.token, &request.password).await {
+        Ok(user) => {
+            audit
+                .by_user(&user)
+                .record(
+                    AuditAction::Update,
+                    AuditEntity::User,
+                    Some(user.id.to_string()),
+                    None,
+                    Some(json!({ "password_reset": true })),
+                )
+                .await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => account_error_response(e, "reset password"),
+    }
+}
+
+/// Handler for mailing the current user a new verification link
+async fn send_verification_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Response {
+    match state.account_service.send_verification(user.id).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => account_error_response(e, "send verification email"),
+    }
+}
+
+/// Handler for confirming an email address with a mailed token
+async fn verify_email_handler(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyEmailRequest>,
+) -> Response {
+    match state.account_service.verify_email(&request.token).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => account_error_response(e, "verify email"),
+    }
+}
+
+/// Handler for exchanging a refresh token for new tokens. The old refresh
+/// token stops working; presenting it again ends the session.
+async fn refresh_handler(
+    State(state): State<AppState>,
+    Json(request): Json<RefreshRequest>,
+) -> Response {
+    match state.auth_service.refresh(&request.refresh_token).await {
+        Ok(tokens) => (StatusCode::OK, Json(tokens)).into_response(),
+        Err(e) => auth_error_response(e, "refresh session"),
+    }
+}
+
+/// Handler for ending the session a refresh token belongs to
+async fn logout_handler(
+    State(state): State<AppState>,
+    Json(request): Json<RefreshRequest>,
+) -> Response {
+    match state.auth_service.logout(&request.refresh_token).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => auth_error_response(e, "log out"),
+    }
+}
+
+/// Handler for ending every session of the current user
+async fn logout_all_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Response {
+    match state.auth_service.revoke_all_sessions(user.id).await {
+        Ok(revoked) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: RevokedSessions { revoked } }),
+        )
+            .into_response(),
+        Err(e) => auth_error_response(e, "log out"),
+    }
+}
+
+/// Handler for the current user's 2FA status
+async fn two_factor_status_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Response {
+    match state.auth_service.two_factor_status(user.id).await {
+        Ok(status) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: status }),
+        )
+            .into_response(),
+        Err(e) => auth_error_response(e, "load two-factor status"),
+    }
+}
+
+/// Handler for starting 2FA setup; returns the secret and provisioning URI
+async fn two_factor_setup_handler(
+    State(state): State<AppState>,
+    user: Option<AuthUser>,
+    request: Option<Json<TwoFactorRequest>>,
+) -> Response {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let user_id = match request.mfa_token.as_deref() {
+        Some(mfa_token) => state.auth_service.enrolling_user(mfa_token),
+        None => match user {
+            Some(user) => Ok(user.id),
+            None => return PermissionError::Unauthenticated.into_response(),
+        },
+    };
+    let result = match user_id {
+        Ok(user_id) => state.auth_service.begin_two_factor(user_id).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(setup) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: setup }),
+        )
+            .into_response(),
+        Err(e) => auth_error_response(e, "set up two-factor authentication"),
+    }
+}
+
+/// Record a change to a user's 2FA, e.g. `{ "two_factor": "disabled" }`
+async fn audit_two_factor(audit: &Audit, user_id: i32, change: serde_json::Value) {
+    audit
+        .record(AuditAction::Update, AuditEntity::User, Some(user_id.to_string()), None, Some(change))
+        .await;
+}
+
+/// Handler for finishing 2FA setup with a code from the authenticator
+async fn two_factor_enable_handler(
+    State(state): State<AppState>,
+    user: Option<AuthUser>,
+    audit: Audit,
+    headers: HeaderMap,
+    Json(request): Json<TwoFactorRequest>,
+) -> Response {
+    // Who is enrolling, when it is the second step of a login
+    let enrolling = request
+        .mfa_token
+        .as_deref()
+        .and_then(|mfa_token| state.auth_service.enrolling_user(mfa_token).ok());
+    let user_id = enrolling.or(user.as_ref().map(|user| user.id));
+    let result = match (request.mfa_token.as_deref(), user) {
+        (Some(mfa_token), _) => state
+            .auth_service
+            .complete_enrolment(mfa_token, &request.code, user_agent(&headers))
+            .await
+            .map(|(recovery_codes, tokens)| TwoFactorEnabled {
+                recovery_codes,
+                tokens: Some(tokens),
             }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Internal server error".to_string(),
+        (None, Some(user)) => state
+            .auth_service
+            .enable_two_factor(user.id, &request.code)
+            .await
+            .map(|recovery_codes| TwoFactorEnabled {
+                recovery_codes,
+                tokens: None,
             }),
-        ),
+        (None, None) => return PermissionError::Unauthenticated.into_response(),
+    };
+    match result {
+        Ok(enabled) => {
+            if let Some(user_id) = user_id {
+                audit_two_factor(&audit, user_id, json!({ "two_factor": "enabled" })).await;
+            }
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: enabled }),
+            )
+                .into_response()
+        }
+        Err(e) => auth_error_response(e, "enable two-factor authentication"),
+    }
+}
+
+/// Handler for turning 2FA off, confirmed with a code
+async fn two_factor_disable_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    audit: Audit,
+    Json(request): Json<TwoFactorRequest>,
+) -> Response {
+    match state.auth_service.disable_two_factor(user.id, &request.code).await {
+        Ok(()) => {
+            audit_two_factor(&audit, user.id, json!({ "two_factor": "disabled" })).await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => auth_error_response(e, "disable two-factor authentication"),
+    }
+}
+
+/// Handler for replacing the current user's recovery codes, confirmed with a code
+async fn recovery_codes_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    audit: Audit,
+    Json(request): Json<TwoFactorRequest>,
+) -> Response {
+    match state.auth_service.regenerate_recovery_codes(user.id, &request.code).await {
+        Ok(recovery_codes) => {
+            audit_two_factor(&audit, user.id, json!({ "recovery_codes": "replaced" })).await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: RecoveryCodes { recovery_codes } }),
+            )
+                .into_response()
+        }
+        Err(e) => auth_error_response(e, "replace recovery codes"),
+    }
+}
+
+/// Handler for listing a user's signed-in devices
+async fn list_user_sessions_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+    Path(user_id): Path<i32>,
+) -> Response {
+    match state.auth_service.active_sessions(user_id).await {
+        Ok(sessions) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: sessions }),
+        )
+            .into_response(),
+        Err(e) => auth_error_response(e, "list sessions"),
+    }
+}
+
+/// Handler for ending every session of a user, e.g. after a leaked token
+async fn revoke_user_sessions_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+    audit: Audit,
+    Path(user_id): Path<i32>,
+) -> Response {
+    match state.auth_service.revoke_all_sessions(user_id).await {
+        Ok(revoked) => {
+            audit
+                .record(
+                    AuditAction::Revoke,
+                    AuditEntity::User,
+                    Some(user_id.to_string()),
+                    None,
+                    Some(json!({ "sessions_revoked": revoked })),
+                )
+                .await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: RevokedSessions { revoked } }),
+            )
+                .into_response()
+        }
+        Err(e) => auth_error_response(e, "revoke sessions"),
+    }
+}
+
+/// Handler for listing usernames and addresses locked out by failed logins
+async fn list_lockouts_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+) -> Response {
+    match state.auth_service.lockouts().await {
+        Ok(lockouts) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: lockouts }),
+        )
+            .into_response(),
+        Err(e) => auth_error_response(e, "list lockouts"),
+    }
+}
+
+/// Handler for letting a locked-out user log in again
+async fn unlock_user_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+    audit: Audit,
+    Path(user_id): Path<i32>,
+) -> Response {
+    match state.auth_service.unlock_user(user_id).await {
+        Ok(name) => {
+            audit
+                .record(
+                    AuditAction::Unlock,
+                    AuditEntity::User,
+                    Some(user_id.to_string()),
+                    None,
+                    Some(json!({ "username": name })),
+                )
+                .await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => auth_error_response(e, "unlock user"),
+    }
+}
+
+/// Handler for lifting the lockout of a client address
+async fn unlock_ip_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+    audit: Audit,
+    Path(ip): Path<IpAddr>,
+) -> Response {
+    match state.auth_service.unlock_ip(ip).await {
+        Ok(()) => {
+            audit
+                .record(AuditAction::Unlock, AuditEntity::Login, Some(format!("ip:{}", ip)), None, None)
+                .await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => auth_error_response(e, "unlock address"),
     }
 }
 
-// Initialize Routes
+/// Initialize the authentication routes. They are mounted without
+/// `require_auth`; handlers that need a user extract it themselves.
 pub fn routes() -> Router {
     Router::new()
         .route("/login", post(login_handler))
+        .route("/login/verify", post(verify_login_handler))
         .route("/register", post(register_handler))
-        // `AuthService` should be added to the application state, not per module
+        .route("/refresh", post(refresh_handler))
+        .route("/logout", post(logout_handler))
+        .route("/logout-all", post(logout_all_handler))
+        .route("/password/forgot", post(forgot_password_handler))
+        .route("/password/reset", post(reset_password_handler))
+        .route("/email/verify", post(verify_email_handler))
+        .route("/email/verify/send", post(send_verification_handler))
+        .route("/2fa", get(two_factor_status_handler))
+        .route("/2fa/setup", post(two_factor_setup_handler))
+        .route("/2fa/enable", post(two_factor_enable_handler))
+        .route("/2fa/disable", post(two_factor_disable_handler))
+        .route("/2fa/recovery-codes", post(recovery_codes_handler))
+        .route(
+            "/users/:user_id/sessions",
+            get(list_user_sessions_handler).delete(revoke_user_sessions_handler),
+        )
+        .route("/users/:user_id/unlock", post(unlock_user_handler))
+        .route("/lockouts", get(list_lockouts_handler))
+        .route("/lockouts/ip/:ip", delete(unlock_ip_handler))
 }
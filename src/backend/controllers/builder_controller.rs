@@ -1,14 +1,18 @@
 use axum::{
-    routing::{get, post, put},
+    routing::{get, post},
     extract::{Path, Json, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Router,
 };
 use crate::backend::services::builder_service::BuilderServiceError;
-use crate::backend::models::builder::{Page, CreatePageData, UpdatePageData};
+use crate::backend::models::builder::{CreatePageData, UpdatePageData};
 use crate::backend::AppState;
-use serde::{Deserialize, Serialize};
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::shared::audit::AuditEntity;
+use serde::Serialize;
+use serde_json::json;
 
 #[derive(Serialize)]
 struct ErrorResponse {
@@ -20,82 +24,106 @@ struct SuccessResponse<T> {
     data: T,
 }
 
-/// Handler for creating a new page
-async fn create_page_handler(
-    State(state): State<AppState>,
-    Json(new_page): Json<CreatePageData>,
-) -> impl IntoResponse {
-    let builder_service = &state.builder_service;
-    match builder_service.create_page(new_page).await {
-        Ok(page) => (
-            StatusCode::CREATED,
-            Json(SuccessResponse { data: page }),
-        ),
-        Err(BuilderServiceError::PageAlreadyExists) => (
+fn builder_error_response(err: BuilderServiceError, action: &str) -> Response {
+    match err {
+        BuilderServiceError::InvalidData(reason) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: reason }),
+        )
+            .into_response(),
+        BuilderServiceError::InvalidDocument(errors) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid page document",
+                "details": errors,
+            })),
+        )
+            .into_response(),
+        BuilderServiceError::PageAlreadyExists => (
             StatusCode::CONFLICT,
             Json(ErrorResponse {
                 error: "Page already exists".to_string(),
             }),
-        ),
-        Err(_) => (
+        )
+            .into_response(),
+        BuilderServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Page not found".to_string(),
+            }),
+        )
+            .into_response(),
+        BuilderServiceError::DatabaseError(_) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
-                error: "Failed to create page".to_string(),
+                error: format!("Failed to {}", action),
             }),
-        ),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for creating a new page
+async fn create_page_handler(
+    State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
+    audit: Audit,
+    Json(new_page): Json<CreatePageData>,
+) -> Response {
+    let builder_service = &state.builder_service;
+    match builder_service.create_page(new_page).await {
+        Ok(page) => {
+            audit.created(AuditEntity::Page, page.id, &page).await;
+            (
+                StatusCode::CREATED,
+                Json(SuccessResponse { data: page }),
+            )
+                .into_response()
+        }
+        Err(e) => builder_error_response(e, "create page"),
     }
 }
 
 /// Handler for fetching an existing page by ID
 async fn get_page_handler(
     State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Response {
     let builder_service = &state.builder_service;
     match builder_service.get_page(id).await {
         Ok(page) => (
             StatusCode::OK,
             Json(SuccessResponse { data: page }),
-        ),
-        Err(BuilderServiceError::NotFound) => (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Page not found".to_string(),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to retrieve page".to_string(),
-            }),
-        ),
+        )
+            .into_response(),
+        Err(e) => builder_error_response(e, "retrieve page"),
     }
 }
 
 /// Handler for updating an existing page by ID
 async fn update_page_handler(
     State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
+    audit: Audit,
     Path(id): Path<i32>,
     Json(updated_page): Json<UpdatePageData>,
-) -> impl IntoResponse {
+) -> Response {
     let builder_service = &state.builder_service;
+    let before = match builder_service.get_page(id).await {
+        Ok(page) => page,
+        Err(e) => return builder_error_response(e, "update page"),
+    };
     match builder_service.update_page(id, updated_page).await {
-        Ok(page) => (
-            StatusCode::OK,
-            Json(SuccessResponse { data: page }),
-        ),
-        Err(BuilderServiceError::NotFound) => (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Page not found".to_string(),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to update page".to_string(),
-            }),
-        ),
+        Ok(page) => {
+            audit.updated(AuditEntity::Page, id, &before, &page).await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: page }),
+            )
+                .into_response()
+        }
+        Err(e) => builder_error_response(e, "update page"),
     }
 }
 
@@ -8,6 +8,9 @@ use axum::{
 use crate::backend::services::category_service::CategoryServiceError;
 use crate::backend::models::category::{Category, CreateCategory, UpdateCategory};
 use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::shared::audit::AuditEntity;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize)]
@@ -23,14 +26,19 @@ struct SuccessResponse<T> {
 /// Handler for creating a new category
 async fn create_category_handler(
     State(state): State<AppState>,
+    _: Require<caps::ManageCategories>,
+    audit: Audit,
     Json(category_data): Json<CreateCategory>,
 ) -> impl IntoResponse {
     let category_service = &state.category_service;
     match category_service.create_category(category_data).await {
-        Ok(category) => (
-            StatusCode::CREATED,
-            Json(SuccessResponse { data: category }),
-        ),
+        Ok(category) => {
+            audit.created(AuditEntity::Category, category.id, &category).await;
+            (
+                StatusCode::CREATED,
+                Json(SuccessResponse { data: category }),
+            )
+        }
         Err(CategoryServiceError::InvalidData) => (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -94,15 +102,23 @@ async fn get_category_handler(
 /// Handler for updating a category by ID
 async fn update_category_handler(
     State(state): State<AppState>,
+    _: Require<caps::ManageCategories>,
+    audit: Audit,
     Path(id): Path<i32>,
     Json(category_data): Json<UpdateCategory>,
 ) -> impl IntoResponse {
     let category_service = &state.category_service;
+    let before = category_service.get_category(id).await.ok();
     match category_service.update_category(id, category_data).await {
-        Ok(category) => (
-            StatusCode::OK,
-            Json(SuccessResponse { data: category }),
-        ),
+        Ok(category) => {
+            if let Some(before) = &before {
+                audit.updated(AuditEntity::Category, id, before, &category).await;
+            }
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: category }),
+            )
+        }
         Err(CategoryServiceError::InvalidData) => (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -127,14 +143,22 @@ async fn update_category_handler(
 /// Handler for deleting a category by ID
 async fn delete_category_handler(
     State(state): State<AppState>,
+    _: Require<caps::ManageCategories>,
+    audit: Audit,
     Path(id): Path<i32>,
 ) -> impl IntoResponse {
     let category_service = &state.category_service;
+    let before = category_service.get_category(id).await.ok();
     match category_service.delete_category(id).await {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(serde_json::json!({"message": "Category deleted"})),
-        ),
+        Ok(_) => {
+            if let Some(before) = &before {
+                audit.deleted(AuditEntity::Category, id, before).await;
+            }
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({"message": "Category deleted"})),
+            )
+        }
         Err(CategoryServiceError::NotFound) => (
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
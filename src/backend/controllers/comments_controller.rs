@@ -1,82 +1,283 @@
 use axum::{
-    routing::{get, post, put, delete},
-    extract::{Path, Json, Extension},
+    routing::{get, post},
+    extract::{Path, Json, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Router,
 };
-use std::sync::Arc;
-use crate::services::comment_service::CommentService;
-use crate::models::comment::Comment;
-use crate::models::comment::CommentError;
+use crate::backend::services::comment_service::{CommentFilter, CommentServiceError, DEFAULT_PER_PAGE};
+use crate::backend::models::comment::{CommentStatus, CreateComment, UpdateComment};
+use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::client_ip::ClientIp;
+use crate::backend::middlewares::permission_middleware::{caps, AuthUser, Require};
+use crate::shared::audit::AuditEntity;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
-/// Handler for creating a comment
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+/// `GET /comments?status=pending&post_id=12&page=2&per_page=25`
+#[derive(Deserialize)]
+pub struct CommentListQuery {
+    pub status: Option<CommentStatus>,
+    pub post_id: Option<i32>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+/// Body of the bulk moderation endpoints
+#[derive(Deserialize)]
+pub struct BulkComments {
+    pub ids: Vec<i32>,
+}
+
+/// Moderation action named in `POST /comments/bulk/:action`
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    Approve,
+    /// Send back to the pending queue
+    Unapprove,
+    /// Move to the trash
+    Reject,
+    Spam,
+}
+
+impl BulkAction {
+    fn status(self) -> CommentStatus {
+        match self {
+            BulkAction::Approve => CommentStatus::Approved,
+            BulkAction::Unapprove => CommentStatus::Pending,
+            BulkAction::Reject => CommentStatus::Trash,
+            BulkAction::Spam => CommentStatus::Spam,
+        }
+    }
+}
+
+fn comment_error_response(err: CommentServiceError, action: &str) -> Response {
+    match err {
+        CommentServiceError::InvalidData(reason) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: reason }),
+        )
+            .into_response(),
+        CommentServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Comment not found".to_string(),
+            }),
+        )
+            .into_response(),
+        CommentServiceError::PostUnavailable => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ErrorResponse {
+                error: "Comments are closed on this post".to_string(),
+            }),
+        )
+            .into_response(),
+        CommentServiceError::RateLimited => (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ErrorResponse {
+                error: "You are commenting too quickly, please try again later".to_string(),
+            }),
+        )
+            .into_response(),
+        CommentServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for posting a comment; anonymous visitors may comment too
 async fn create_comment_handler(
-    Json(comment_data): Json<Comment>,
-    Extension(comment_service): Extension<Arc<CommentService>>,
-) -> impl IntoResponse {
-    match comment_service.create_comment(comment_data).await {
-        Ok(comment) => (StatusCode::CREATED, Json(comment)),
-        Err(CommentError::InvalidData) => (StatusCode::BAD_REQUEST, Json("Invalid comment data")),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, Json("Failed to create comment")),
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    user: Option<AuthUser>,
+    Json(comment_data): Json<CreateComment>,
+) -> Response {
+    let comment_service = &state.comment_service;
+    match comment_service
+        .create_comment(comment_data, user.as_ref(), Some(ip))
+        .await
+    {
+        Ok(comment) => (
+            StatusCode::CREATED,
+            Json(SuccessResponse { data: comment }),
+        )
+            .into_response(),
+        Err(e) => comment_error_response(e, "create comment"),
     }
 }
 
-/// Handler for fetching all comments
-async fn get_all_comments_handler(
-    Extension(comment_service): Extension<Arc<CommentService>>,
-) -> impl IntoResponse {
-    match comment_service.list_comments().await {
-        Ok(comments) => (StatusCode::OK, Json(comments)),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, Json("Failed to retrieve comments")),
+/// Handler for the moderation queue
+async fn list_comments_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ModerateComments>,
+    Query(query): Query<CommentListQuery>,
+) -> Response {
+    let filter = CommentFilter {
+        status: query.status,
+        post_id: query.post_id,
+    };
+
+    let comment_service = &state.comment_service;
+    match comment_service
+        .list_comments(
+            &filter,
+            query.page.unwrap_or(1),
+            query.per_page.unwrap_or(DEFAULT_PER_PAGE),
+        )
+        .await
+    {
+        Ok(page) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: page }),
+        )
+            .into_response(),
+        Err(e) => comment_error_response(e, "retrieve comments"),
+    }
+}
+
+/// Handler for the public, threaded comments of a post
+async fn post_thread_handler(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+) -> Response {
+    let comment_service = &state.comment_service;
+    match comment_service.thread(post_id).await {
+        Ok(thread) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: thread }),
+        )
+            .into_response(),
+        Err(e) => comment_error_response(e, "retrieve comments"),
     }
 }
 
 /// Handler for fetching a comment by ID
 async fn get_comment_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ModerateComments>,
     Path(id): Path<i32>,
-    Extension(comment_service): Extension<Arc<CommentService>>,
-) -> impl IntoResponse {
+) -> Response {
+    let comment_service = &state.comment_service;
     match comment_service.get_comment(id).await {
-        Ok(comment) => (StatusCode::OK, Json(comment)),
-        Err(CommentError::NotFound) => (StatusCode::NOT_FOUND, Json("Comment not found")),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, Json("Failed to retrieve comment")),
+        Ok(comment) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: comment }),
+        )
+            .into_response(),
+        Err(e) => comment_error_response(e, "retrieve comment"),
     }
 }
 
-/// Handler for updating a comment by ID
+/// Handler for editing a comment by ID
 async fn update_comment_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ModerateComments>,
+    audit: Audit,
     Path(id): Path<i32>,
-    Json(comment_data): Json<Comment>,
-    Extension(comment_service): Extension<Arc<CommentService>>,
-) -> impl IntoResponse {
+    Json(comment_data): Json<UpdateComment>,
+) -> Response {
+    let comment_service = &state.comment_service;
+    let before = match comment_service.get_comment(id).await {
+        Ok(comment) => comment,
+        Err(e) => return comment_error_response(e, "update comment"),
+    };
     match comment_service.update_comment(id, comment_data).await {
-        Ok(comment) => (StatusCode::OK, Json(comment)),
-        Err(CommentError::NotFound) => (StatusCode::NOT_FOUND, Json("Comment not found")),
-        Err(CommentError::InvalidData) => (StatusCode::BAD_REQUEST, Json("Invalid comment data")),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, Json("Failed to update comment")),
+        Ok(comment) => {
+            audit.updated(AuditEntity::Comment, id, &before, &comment).await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: comment }),
+            )
+                .into_response()
+        }
+        Err(e) => comment_error_response(e, "update comment"),
     }
 }
 
-/// Handler for deleting a comment by ID
+/// Handler for permanently deleting a comment and its replies
 async fn delete_comment_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ModerateComments>,
+    audit: Audit,
     Path(id): Path<i32>,
-    Extension(comment_service): Extension<Arc<CommentService>>,
-) -> impl IntoResponse {
+) -> Response {
+    let comment_service = &state.comment_service;
+    let before = match comment_service.get_comment(id).await {
+        Ok(comment) => comment,
+        Err(e) => return comment_error_response(e, "delete comment"),
+    };
     match comment_service.delete_comment(id).await {
-        Ok(_) => (StatusCode::OK, Json("Comment deleted")),
-        Err(CommentError::NotFound) => (StatusCode::NOT_FOUND, Json("Comment not found")),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, Json("Failed to delete comment")),
+        Ok(_) => {
+            audit.deleted(AuditEntity::Comment, id, &before).await;
+            (
+                StatusCode::OK,
+                Json(json!({"message": "Comment deleted"})),
+            )
+                .into_response()
+        }
+        Err(e) => comment_error_response(e, "delete comment"),
+    }
+}
+
+/// Handler for approving, unapproving, rejecting or marking many comments as spam
+async fn bulk_moderate_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ModerateComments>,
+    audit: Audit,
+    Path(action): Path<BulkAction>,
+    Json(body): Json<BulkComments>,
+) -> Response {
+    let comment_service = &state.comment_service;
+    let before = match comment_service.get_comments(&body.ids).await {
+        Ok(comments) => comments,
+        Err(e) => return comment_error_response(e, "moderate comments"),
+    };
+    match comment_service.set_status(&body.ids, action.status()).await {
+        Ok(comments) => {
+            for comment in &comments {
+                if let Some(old) = before.iter().find(|old| old.id == comment.id) {
+                    audit.updated(AuditEntity::Comment, comment.id, old, comment).await;
+                }
+            }
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: comments }),
+            )
+                .into_response()
+        }
+        Err(e) => comment_error_response(e, "moderate comments"),
     }
 }
 
 /// Initialize the comment routes
-pub fn init_routes(comment_service: Arc<CommentService>) -> Router {
+pub fn routes() -> Router {
     Router::new()
-        .route("/api/comments", post(create_comment_handler))
-        .route("/api/comments", get(get_all_comments_handler))
-        .route("/api/comments/:id", get(get_comment_handler))
-        .route("/api/comments/:id", put(update_comment_handler))
-        .route("/api/comments/:id", delete(delete_comment_handler))
-        .layer(Extension(comment_service))
+        .route(
+            "/",
+            post(create_comment_handler).get(list_comments_handler),
+        )
+        .route("/post/:post_id", get(post_thread_handler))
+        .route("/bulk/:action", post(bulk_moderate_handler))
+        .route(
+            "/:id",
+            get(get_comment_handler)
+                .put(update_comment_handler)
+                .delete(delete_comment_handler),
+        )
 }
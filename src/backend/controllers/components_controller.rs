@@ -0,0 +1,207 @@
+use axum::{
+    routing::get,
+    extract::{Path, Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Router,
+};
+use crate::backend::services::library_service::LibraryServiceError;
+use crate::backend::models::builder::{CreateLibraryItem, LibraryKind, UpdateLibraryItem};
+use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::shared::audit::AuditEntity;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+/// `GET /components?kind=global_block`
+#[derive(Deserialize)]
+pub struct LibraryQuery {
+    pub kind: Option<LibraryKind>,
+}
+
+fn library_error_response(err: LibraryServiceError, action: &str) -> Response {
+    match err {
+        LibraryServiceError::InvalidData(reason) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: reason }),
+        )
+            .into_response(),
+        LibraryServiceError::InvalidSection(errors) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid section",
+                "details": errors,
+            })),
+        )
+            .into_response(),
+        LibraryServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Library item not found".to_string(),
+            }),
+        )
+            .into_response(),
+        LibraryServiceError::InUse(pages) => (
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": "The global block is still used by some pages",
+                "details": pages,
+            })),
+        )
+            .into_response(),
+        LibraryServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for listing global blocks and saved sections
+async fn list_items_handler(
+    State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
+    Query(query): Query<LibraryQuery>,
+) -> Response {
+    let library_service = &state.library_service;
+    match library_service.list(query.kind).await {
+        Ok(items) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: items }),
+        )
+            .into_response(),
+        Err(e) => library_error_response(e, "list library items"),
+    }
+}
+
+/// Handler for saving a section to the library
+async fn create_item_handler(
+    State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
+    audit: Audit,
+    Json(new_item): Json<CreateLibraryItem>,
+) -> Response {
+    let library_service = &state.library_service;
+    match library_service.create(new_item).await {
+        Ok(item) => {
+            audit.created(AuditEntity::Component, item.id, &item).await;
+            (
+                StatusCode::CREATED,
+                Json(SuccessResponse { data: item }),
+            )
+                .into_response()
+        }
+        Err(e) => library_error_response(e, "create library item"),
+    }
+}
+
+/// Handler for fetching a library item by ID
+async fn get_item_handler(
+    State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
+    Path(id): Path<i32>,
+) -> Response {
+    let library_service = &state.library_service;
+    match library_service.get(id).await {
+        Ok(item) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: item }),
+        )
+            .into_response(),
+        Err(e) => library_error_response(e, "retrieve library item"),
+    }
+}
+
+/// Handler for renaming a library item or replacing its section
+async fn update_item_handler(
+    State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
+    audit: Audit,
+    Path(id): Path<i32>,
+    Json(changes): Json<UpdateLibraryItem>,
+) -> Response {
+    let library_service = &state.library_service;
+    let before = match library_service.get(id).await {
+        Ok(item) => item,
+        Err(e) => return library_error_response(e, "update library item"),
+    };
+    match library_service.update(id, changes).await {
+        Ok(item) => {
+            audit.updated(AuditEntity::Component, id, &before, &item).await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: item }),
+            )
+                .into_response()
+        }
+        Err(e) => library_error_response(e, "update library item"),
+    }
+}
+
+/// Handler for deleting a library item that no page uses
+async fn delete_item_handler(
+    State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
+    audit: Audit,
+    Path(id): Path<i32>,
+) -> Response {
+    let library_service = &state.library_service;
+    let before = match library_service.get(id).await {
+        Ok(item) => item,
+        Err(e) => return library_error_response(e, "delete library item"),
+    };
+    match library_service.delete(id).await {
+        Ok(_) => {
+            audit.deleted(AuditEntity::Component, id, &before).await;
+            (
+                StatusCode::OK,
+                Json(json!({"message": "Library item deleted"})),
+            )
+                .into_response()
+        }
+        Err(e) => library_error_response(e, "delete library item"),
+    }
+}
+
+/// Handler for listing the pages that show a global block
+async fn item_usage_handler(
+    State(state): State<AppState>,
+    _: Require<caps::UseBuilder>,
+    Path(id): Path<i32>,
+) -> Response {
+    let library_service = &state.library_service;
+    match library_service.usage(id).await {
+        Ok(pages) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: pages }),
+        )
+            .into_response(),
+        Err(e) => library_error_response(e, "list block usage"),
+    }
+}
+
+/// Initialize the builder library routes
+pub fn routes() -> Router {
+    Router::new()
+        .route("/", get(list_items_handler).post(create_item_handler))
+        .route(
+            "/:id",
+            get(get_item_handler)
+                .put(update_item_handler)
+                .delete(delete_item_handler),
+        )
+        .route("/:id/usage", get(item_usage_handler))
+}
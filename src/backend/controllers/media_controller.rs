@@ -1,14 +1,23 @@
 use axum::{
+    body::StreamBody,
     routing::{get, post, delete},
-    extract::{Path, Json, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{DefaultBodyLimit, Multipart, Path, Json, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Router,
 };
+use futures::StreamExt;
+use std::io::SeekFrom;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
 use crate::backend::services::media_service::MediaServiceError;
-use crate::backend::models::media::{Media, CreateMedia};
+use crate::backend::services::media_storage::{ByteStream, StorageError};
 use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::shared::audit::AuditEntity;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Serialize)]
 struct ErrorResponse {
@@ -20,29 +29,82 @@ struct SuccessResponse<T> {
     data: T,
 }
 
-/// Handler for uploading media
+/// Handler for uploading media as `multipart/form-data` with a `file` field
 async fn upload_media_handler(
     State(state): State<AppState>,
-    Json(media_data): Json<CreateMedia>,
-) -> impl IntoResponse {
+    Require(user, ..): Require<caps::UploadMedia>,
+    audit: Audit,
+    mut multipart: Multipart,
+) -> Response {
     let media_service = &state.media_service;
-    match media_service.upload_media(media_data).await {
-        Ok(media) => (
-            StatusCode::CREATED,
-            Json(SuccessResponse { data: media }),
-        ),
+
+    let field = loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some("file") => break field,
+            Ok(Some(_)) => continue,
+            Ok(None) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "No file field in upload".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            Err(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "Malformed multipart body".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    };
+
+    let file_name = field.file_name().unwrap_or("upload").to_string();
+    let body: ByteStream = Box::pin(
+        field.map(|chunk| chunk.map_err(|e| StorageError::Stream(e.to_string()))),
+    );
+
+    match media_service.upload_media(user.id, &file_name, body).await {
+        Ok(media) => {
+            audit.created(AuditEntity::Media, media.id, &media).await;
+            (
+                StatusCode::CREATED,
+                Json(SuccessResponse { data: media }),
+            )
+                .into_response()
+        }
         Err(MediaServiceError::InvalidData) => (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
                 error: "Invalid media data".to_string(),
             }),
-        ),
+        )
+            .into_response(),
+        Err(MediaServiceError::TooLarge(limit)) => (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(ErrorResponse {
+                error: format!("File exceeds the {} byte limit", limit),
+            }),
+        )
+            .into_response(),
+        Err(MediaServiceError::UnsupportedType(mime)) => (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Json(ErrorResponse {
+                error: format!("Unsupported media type: {}", mime),
+            }),
+        )
+            .into_response(),
         Err(_) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: "Failed to upload media".to_string(),
             }),
-        ),
+        )
+            .into_response(),
     }
 }
 
@@ -94,14 +156,22 @@ async fn get_media_handler(
 /// Handler for deleting media by ID
 async fn delete_media_handler(
     State(state): State<AppState>,
+    _: Require<caps::DeleteMedia>,
+    audit: Audit,
     Path(id): Path<i32>,
 ) -> impl IntoResponse {
     let media_service = &state.media_service;
+    let before = media_service.get_media(id).await.ok();
     match media_service.delete_media(id).await {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(json!({"message": "Media deleted"})),
-        ),
+        Ok(_) => {
+            if let Some(before) = &before {
+                audit.deleted(AuditEntity::Media, id, before).await;
+            }
+            (
+                StatusCode::OK,
+                Json(json!({"message": "Media deleted"})),
+            )
+        }
         Err(MediaServiceError::NotFound) => (
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
@@ -117,11 +187,193 @@ async fn delete_media_handler(
     }
 }
 
+/// Handler for queueing an image's renditions to be generated again
+async fn regenerate_renditions_handler(
+    State(state): State<AppState>,
+    _: Require<caps::UploadMedia>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    let media_service = &state.media_service;
+    match media_service.regenerate_renditions(id).await {
+        Ok(media) => (
+            StatusCode::ACCEPTED,
+            Json(SuccessResponse { data: media }),
+        )
+            .into_response(),
+        Err(MediaServiceError::NotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Media not found".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(MediaServiceError::UnsupportedType(_)) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ErrorResponse {
+                error: "Renditions can only be generated for images".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to queue renditions".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Which part of a stored file a request asked for
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Parse a single-range `Range: bytes=...` header against a file of `size` bytes.
+///
+/// Multi-range and malformed headers fall back to the full file, which RFC 9110 allows.
+fn parse_range(header: Option<&str>, size: u64) -> ByteRange {
+    let spec = match header.and_then(|h| h.trim().strip_prefix("bytes=")) {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return ByteRange::Full,
+    };
+    let (start, end) = match spec.split_once('-') {
+        Some((start, end)) => (start.trim(), end.trim()),
+        None => return ByteRange::Full,
+    };
+
+    let range = match (start, end) {
+        ("", "") => return ByteRange::Full,
+        // Suffix range: the last N bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) if size > 0 => (size.saturating_sub(n), size - 1),
+            Ok(_) => return ByteRange::Unsatisfiable,
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, size.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            Ok((start, end)) => (start, end.min(size.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    if size == 0 || range.0 >= size || range.0 > range.1 {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(range.0, range.1)
+    }
+}
+
+/// Handler serving stored files, with single-range request support
+async fn serve_file_handler(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let media_service = &state.media_service;
+    let (mut object, content_type) = match media_service.open_file(&key).await {
+        Ok(found) => found,
+        Err(MediaServiceError::NotFound) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let range = parse_range(
+        headers.get(header::RANGE).and_then(|v| v.to_str().ok()),
+        object.size,
+    );
+    let common_headers = [
+        (header::CONTENT_TYPE, content_type),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        // Storage keys are unique per upload, so files never change
+        (header::CACHE_CONTROL, "public, max-age=31536000, immutable".to_string()),
+    ];
+
+    match range {
+        ByteRange::Full => (
+            StatusCode::OK,
+            common_headers,
+            [(header::CONTENT_LENGTH, object.size.to_string())],
+            StreamBody::new(ReaderStream::new(object.reader)),
+        )
+            .into_response(),
+        ByteRange::Partial(start, end) => {
+            if object.reader.seek(SeekFrom::Start(start)).await.is_err() {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+            let length = end - start + 1;
+            (
+                StatusCode::PARTIAL_CONTENT,
+                common_headers,
+                [
+                    (header::CONTENT_LENGTH, length.to_string()),
+                    (
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", start, end, object.size),
+                    ),
+                ],
+                StreamBody::new(ReaderStream::new(object.reader.take(length))),
+            )
+                .into_response()
+        }
+        ByteRange::Unsatisfiable => (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{}", object.size))],
+        )
+            .into_response(),
+    }
+}
+
+/// Initialize the routes serving stored files (public, mounted at `/uploads`)
+pub fn file_routes() -> Router {
+    Router::new().route("/*key", get(serve_file_handler))
+}
+
 /// Initialize the media routes
 pub fn routes() -> Router {
     Router::new()
-        .route("/", post(upload_media_handler))
+        // The service enforces its own streaming size limit
+        .route(
+            "/",
+            post(upload_media_handler).layer(DefaultBodyLimit::disable()),
+        )
         .route("/", get(get_all_media_handler))
         .route("/:id", get(get_media_handler))
         .route("/:id", delete(delete_media_handler))
+        .route("/:id/renditions", post(regenerate_renditions_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_range_forms() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), ByteRange::Partial(0, 9));
+        assert_eq!(parse_range(Some("bytes=90-"), 100), ByteRange::Partial(90, 99));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), ByteRange::Partial(90, 99));
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), ByteRange::Partial(50, 99));
+    }
+
+    #[test]
+    fn rejects_ranges_outside_the_file() {
+        assert_eq!(parse_range(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-0"), 0), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn multi_range_and_garbage_fall_back_to_full() {
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=a-b"), 100), ByteRange::Full);
+    }
 }
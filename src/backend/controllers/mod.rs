@@ -4,8 +4,18 @@ pub mod auth_controller;
 pub mod post_controller;
 pub mod media_controller;
 pub mod category_controller;
+pub mod tags_controller;
+pub mod comments_controller;
 pub mod builder_controller;
+pub mod components_controller;
 pub mod settings_controller;
+pub mod public_controller;
+pub mod search_controller;
+pub mod themes_controller;
+pub mod oidc_controller;
+pub mod api_keys_controller;
+pub mod audit_controller;
+pub mod users_controller;
 
 // Optionally, you can re-export common items for easier access
 // pub use auth_controller::AuthController;
@@ -0,0 +1,242 @@
+use axum::{
+    routing::{get, post},
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use crate::backend::AppState;
+use crate::backend::controllers::auth_controller::{auth_error_response, user_agent};
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::AuthUser;
+use crate::backend::services::oidc::OidcError;
+use crate::backend::services::oidc_service::{CallbackOutcome, OidcServiceError, ProviderSummary, SignIn};
+use crate::shared::audit::{AuditAction, AuditEntity};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::error;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+/// Where to send the browser to link a provider account
+#[derive(Serialize)]
+struct LinkStart {
+    url: String,
+}
+
+/// Body of `/auth/oidc/exchange`
+#[derive(Deserialize)]
+pub struct ExchangeRequest {
+    pub code: String,
+}
+
+/// How users can sign in, for the login page
+#[derive(Serialize)]
+struct LoginOptions {
+    providers: Vec<ProviderSummary>,
+    local_login: bool,
+}
+
+/// Query string a provider sends the user back with
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Send the browser to a page of the site with one query parameter
+fn redirect_to_site(state: &AppState, path: &str, name: &str, value: &str) -> Response {
+    let mut url = match Url::parse(&format!("{}{}", state.oidc_service.site_url(), path)) {
+        Ok(url) => url,
+        Err(e) => {
+            error!("SITE_URL is not a valid URL: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    url.query_pairs_mut().append_pair(name, value);
+    Redirect::to(url.as_str()).into_response()
+}
+
+/// Send the browser back to the login page with a message to show
+fn login_failed(state: &AppState, message: &str) -> Response {
+    redirect_to_site(state, "/login", "sso_error", message)
+}
+
+fn oidc_error_response(err: OidcServiceError, action: &str) -> Response {
+    match err {
+        OidcServiceError::Auth(e) => auth_error_response(e, action),
+        OidcServiceError::InvalidState => (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        OidcServiceError::Oidc(OidcError::UnknownProvider(_)) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        _ => {
+            error!("Failed to {}: {}", action, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to {}", action),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Record what a login changed about its user
+async fn audit_sign_in(audit: &Audit, provider_id: &str, sign_in: &SignIn) {
+    let user = &sign_in.user;
+    if sign_in.created {
+        audit.created(AuditEntity::User, user.id, user).await;
+    }
+    if sign_in.linked {
+        audit
+            .record(
+                AuditAction::Update,
+                AuditEntity::User,
+                Some(user.id.to_string()),
+                None,
+                Some(json!({ "linked_provider": provider_id })),
+            )
+            .await;
+    }
+    if let Some(previous_role) = &sign_in.previous_role {
+        audit
+            .record(
+                AuditAction::Update,
+                AuditEntity::User,
+                Some(user.id.to_string()),
+                Some(json!({ "role": previous_role })),
+                Some(json!({ "role": user.role, "synced_from": provider_id })),
+            )
+            .await;
+    }
+}
+
+/// What the user is told when a sign-in fails; the details go to the log
+fn failure_message(err: &OidcServiceError) -> String {
+    match err {
+        OidcServiceError::InvalidState
+        | OidcServiceError::LinkRequired
+        | OidcServiceError::AlreadyLinked
+        | OidcServiceError::Oidc(OidcError::UnknownProvider(_)) => err.to_string(),
+        OidcServiceError::Oidc(OidcError::MissingClaim(_)) => {
+            format!("{}. Ask your administrator to release it to this site.", err)
+        }
+        _ => {
+            error!("Single sign-on failed: {}", err);
+            "Signing in with your identity provider failed. Try again later.".to_string()
+        }
+    }
+}
+
+/// Handler listing the sign-on providers and whether password login is open
+async fn login_options_handler(State(state): State<AppState>) -> Response {
+    let options = LoginOptions {
+        providers: state.oidc_service.providers(),
+        local_login: state.oidc_service.local_login_enabled().await,
+    };
+    (StatusCode::OK, Json(SuccessResponse { data: options })).into_response()
+}
+
+/// Handler starting a login: redirects to the provider
+async fn begin_login_handler(
+    State(state): State<AppState>,
+    Path(provider_id): Path<String>,
+) -> Response {
+    match state.oidc_service.begin_login(&provider_id).await {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(e) => login_failed(&state, &failure_message(&e)),
+    }
+}
+
+/// Handler starting to link a provider account to the signed-in user.
+/// Returns the provider URL for the browser to go to.
+async fn begin_link_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(provider_id): Path<String>,
+) -> Response {
+    match state.oidc_service.begin_link(&provider_id, user.id).await {
+        Ok(url) => (StatusCode::OK, Json(SuccessResponse { data: LinkStart { url } })).into_response(),
+        Err(e) => oidc_error_response(e, "start linking"),
+    }
+}
+
+/// Handler the provider redirects back to. A login is handed to the login
+/// page as a short-lived code it redeems with `/exchange`, so no tokens
+/// appear in the URL; a link goes back to the dashboard.
+async fn callback_handler(
+    State(state): State<AppState>,
+    Path(provider_id): Path<String>,
+    Query(query): Query<CallbackQuery>,
+    audit: Audit,
+) -> Response {
+    if let Some(error) = query.error {
+        let reason = query.error_description.unwrap_or(error);
+        return login_failed(&state, &format!("Your identity provider declined the sign-in: {}", reason));
+    }
+    let (code, login_state) = match (query.code, query.state) {
+        (Some(code), Some(login_state)) => (code, login_state),
+        _ => return login_failed(&state, "The identity provider sent an incomplete response."),
+    };
+
+    match state.oidc_service.complete_login(&provider_id, &code, &login_state).await {
+        Ok(CallbackOutcome::SignedIn { sign_in, handoff_code }) => {
+            audit_sign_in(&audit.by_user(&sign_in.user), &provider_id, &sign_in).await;
+            redirect_to_site(&state, "/login", "sso_code", &handoff_code)
+        }
+        Ok(CallbackOutcome::Linked { user, provider }) => {
+            audit
+                .by_user(&user)
+                .record(
+                    AuditAction::Update,
+                    AuditEntity::User,
+                    Some(user.id.to_string()),
+                    None,
+                    Some(json!({ "linked_provider": provider })),
+                )
+                .await;
+            redirect_to_site(&state, "/dashboard", "sso_linked", &provider)
+        }
+        Err(e) => login_failed(&state, &failure_message(&e)),
+    }
+}
+
+/// Handler redeeming the code a login was handed to the login page with.
+/// Responds like `/auth/login`: tokens, or the second factor step to take.
+async fn exchange_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ExchangeRequest>,
+) -> Response {
+    match state.oidc_service.redeem_handoff(&request.code, user_agent(&headers)).await {
+        Ok(outcome) => (StatusCode::OK, Json(outcome)).into_response(),
+        Err(e) => oidc_error_response(e, "log in"),
+    }
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/providers", get(login_options_handler))
+        .route("/:provider/login", get(begin_login_handler))
+        .route("/:provider/callback", get(callback_handler))
+        .route("/:provider/link", post(begin_link_handler))
+        .route("/exchange", post(exchange_handler))
+}
@@ -1,11 +1,19 @@
 use axum::{
-    extract::{Path, Json, State},
-    response::IntoResponse,
+    routing::{delete, get, post},
+    extract::{Path, Json, Query, State},
+    response::{IntoResponse, Response},
     http::StatusCode,
     Router,
 };
 use crate::services::post_service::PostServiceError;
-use crate::models::post::{Post, CreatePost, UpdatePost};
+use crate::services::revision_service::RevisionServiceError;
+use crate::services::tag_service::TagServiceError;
+use crate::models::post::{Post, NewPost, PostStatus, UpdatePost};
+use crate::middlewares::audit::Audit;
+use crate::middlewares::permission_middleware::{caps, AuthUser, Require};
+use crate::shared::audit::{AuditAction, AuditEntity};
+use crate::shared::tags::{PostTagsRequest, TagSummary};
+use crate::shared::types::Capability;
 use crate::AppState; // Assuming AppState is defined in a common module
 use serde_json::json;
 use serde::{Deserialize, Serialize};
@@ -20,38 +28,127 @@ struct SuccessResponse<T> {
     data: T,
 }
 
+/// `GET /posts?tag=rust`
+#[derive(Deserialize)]
+pub struct PostListQuery {
+    /// Tag slug
+    pub tag: Option<String>,
+}
+
+/// Whether moving a post into `status` makes (or will make) it public
+fn publishes(status: PostStatus) -> bool {
+    matches!(status, PostStatus::Published | PostStatus::Scheduled)
+}
+
+/// Whose unpublished posts `user` may read: everyone's for those who can
+/// edit others' posts, otherwise only their own
+fn drafts_of(user: &AuthUser) -> Option<i32> {
+    if user.can(Capability::EditOthersPosts) {
+        None
+    } else {
+        Some(user.id)
+    }
+}
+
+/// Whether `user` may read `post`
+fn can_read(user: &AuthUser, post: &Post) -> bool {
+    post.status == PostStatus::Published
+        || drafts_of(user).map_or(true, |user_id| post.author_id == user_id)
+}
+
+fn post_not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "Post not found".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Check that `user` may act on post `id`: `own` applies to their own posts,
+/// `others` to everyone else's. Returns the post as it is now.
+async fn authorize_post(
+    state: &AppState,
+    user: &AuthUser,
+    id: i32,
+    own: Capability,
+    others: Capability,
+) -> Result<Post, Response> {
+    match state.post_service.get_post(id).await {
+        Ok(post) => user
+            .require_owned(post.author_id, own, others)
+            .map(|()| post)
+            .map_err(IntoResponse::into_response),
+        Err(PostServiceError::NotFound) => Err(post_not_found()),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to retrieve post".to_string(),
+            }),
+        )
+            .into_response()),
+    }
+}
+
 /// Handler for creating a new post
 async fn create_post_handler(
     State(state): State<AppState>,
-    Json(post_data): Json<CreatePost>,
-) -> impl IntoResponse {
+    Require(user, ..): Require<caps::CreatePosts>,
+    audit: Audit,
+    Json(mut post_data): Json<NewPost>,
+) -> Response {
+    if publishes(post_data.status) {
+        if let Err(e) = user.require(Capability::PublishPosts) {
+            return e.into_response();
+        }
+    }
+    // Posts are always created on behalf of the caller
+    post_data.author_id = user.id;
+
     let post_service = &state.post_service;
     match post_service.create_post(post_data).await {
-        Ok(post) => (
-            StatusCode::CREATED,
-            Json(SuccessResponse { data: post }),
-        ),
+        Ok(post) => {
+            audit.created(AuditEntity::Post, post.id, &post).await;
+            (
+                StatusCode::CREATED,
+                Json(SuccessResponse { data: post }),
+            )
+                .into_response()
+        }
         Err(PostServiceError::InvalidData) => (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
                 error: "Invalid post data".to_string(),
             }),
-        ),
+        )
+            .into_response(),
+        Err(PostServiceError::MissingPublishAt) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Scheduled posts need a publish_at time".to_string(),
+            }),
+        )
+            .into_response(),
         Err(_) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: "Failed to create post".to_string(),
             }),
-        ),
+        )
+            .into_response(),
     }
 }
 
-/// Handler for retrieving all posts
+/// Handler for retrieving all posts, or those with a tag. Unpublished
+/// posts of others are left out for users who can't edit them.
 async fn get_all_posts_handler(
     State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<PostListQuery>,
 ) -> impl IntoResponse {
     let post_service = &state.post_service;
-    match post_service.list_posts().await {
+    match post_service.list_posts(query.tag.as_deref(), drafts_of(&user)).await {
         Ok(posts) => (
             StatusCode::OK,
             Json(SuccessResponse { data: posts }),
@@ -65,82 +162,503 @@ async fn get_all_posts_handler(
     }
 }
 
-/// Handler for retrieving a specific post by ID
+/// Handler for retrieving a specific post by ID. Unpublished posts the
+/// user can't read are reported as missing.
 async fn get_post_handler(
     State(state): State<AppState>,
+    user: AuthUser,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Response {
     let post_service = &state.post_service;
     match post_service.get_post(id).await {
-        Ok(post) => (
+        Ok(post) if can_read(&user, &post) => (
             StatusCode::OK,
             Json(SuccessResponse { data: post }),
-        ),
-        Err(PostServiceError::NotFound) => (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Post not found".to_string(),
-            }),
-        ),
+        )
+            .into_response(),
+        Ok(_) | Err(PostServiceError::NotFound) => post_not_found(),
         Err(_) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: "Failed to retrieve post".to_string(),
             }),
-        ),
+        )
+            .into_response(),
     }
 }
 
 /// Handler for updating a post by ID
 async fn update_post_handler(
     State(state): State<AppState>,
+    user: AuthUser,
+    audit: Audit,
     Path(id): Path<i32>,
     Json(post_data): Json<UpdatePost>,
-) -> impl IntoResponse {
+) -> Response {
+    let before = match authorize_post(
+        &state,
+        &user,
+        id,
+        Capability::EditOwnPosts,
+        Capability::EditOthersPosts,
+    )
+    .await
+    {
+        Ok(post) => post,
+        Err(rejection) => return rejection,
+    };
+    if post_data.status.map_or(false, publishes) {
+        if let Err(e) = user.require(Capability::PublishPosts) {
+            return e.into_response();
+        }
+    }
+
     let post_service = &state.post_service;
-    match post_service.update_post(id, post_data).await {
-        Ok(post) => (
-            StatusCode::OK,
-            Json(SuccessResponse { data: post }),
-        ),
+    match post_service.update_post(id, post_data, user.id).await {
+        Ok(post) => {
+            audit.updated(AuditEntity::Post, id, &before, &post).await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: post }),
+            )
+                .into_response()
+        }
         Err(PostServiceError::InvalidData) => (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
                 error: "Invalid post data".to_string(),
             }),
-        ),
+        )
+            .into_response(),
+        Err(PostServiceError::MissingPublishAt) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Scheduled posts need a publish_at time".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(PostServiceError::InvalidStatusTransition(from, to)) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ErrorResponse {
+                error: format!("Cannot move post from {} to {}", from, to),
+            }),
+        )
+            .into_response(),
+        Err(PostServiceError::NotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Post not found".to_string(),
+            }),
+        )
+            .into_response(),
         Err(_) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: "Failed to update post".to_string(),
             }),
-        ),
+        )
+            .into_response(),
     }
 }
 
 /// Handler for deleting a post by ID
 async fn delete_post_handler(
     State(state): State<AppState>,
+    user: AuthUser,
+    audit: Audit,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Response {
+    let before = match authorize_post(
+        &state,
+        &user,
+        id,
+        Capability::DeleteOwnPosts,
+        Capability::DeleteOthersPosts,
+    )
+    .await
+    {
+        Ok(post) => post,
+        Err(rejection) => return rejection,
+    };
+
     let post_service = &state.post_service;
     match post_service.delete_post(id).await {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(json!({"message": "Post deleted"})),
-        ),
+        Ok(_) => {
+            audit.deleted(AuditEntity::Post, id, &before).await;
+            (
+                StatusCode::OK,
+                Json(json!({"message": "Post deleted"})),
+            )
+                .into_response()
+        }
         Err(PostServiceError::NotFound) => (
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 error: "Post not found".to_string(),
             }),
-        ),
+        )
+            .into_response(),
         Err(_) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: "Failed to delete post".to_string(),
             }),
-        ),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
+/// Handler for listing the revisions of a post. Revisions hold unpublished
+/// versions, so reading them takes the same rights as editing the post.
+async fn list_revisions_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<i32>,
+) -> Response {
+    if let Err(rejection) = authorize_post(
+        &state,
+        &user,
+        id,
+        Capability::EditOwnPosts,
+        Capability::EditOthersPosts,
+    )
+    .await
+    {
+        return rejection;
+    }
+
+    let revision_service = &state.revision_service;
+    match revision_service.list_revisions(id).await {
+        Ok(revisions) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: revisions }),
+        )
+            .into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to retrieve revisions".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for retrieving a single revision of a post
+async fn get_revision_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((id, revision_id)): Path<(i32, i32)>,
+) -> Response {
+    if let Err(rejection) = authorize_post(
+        &state,
+        &user,
+        id,
+        Capability::EditOwnPosts,
+        Capability::EditOthersPosts,
+    )
+    .await
+    {
+        return rejection;
+    }
+
+    let revision_service = &state.revision_service;
+    match revision_service.get_revision(id, revision_id).await {
+        Ok(revision) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: revision }),
+        )
+            .into_response(),
+        Err(RevisionServiceError::NotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Revision not found".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to retrieve revision".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for diffing two revisions of a post (`?from=<id>&to=<id>`)
+async fn diff_revisions_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<i32>,
+    Query(query): Query<DiffQuery>,
+) -> Response {
+    if let Err(rejection) = authorize_post(
+        &state,
+        &user,
+        id,
+        Capability::EditOwnPosts,
+        Capability::EditOthersPosts,
+    )
+    .await
+    {
+        return rejection;
+    }
+
+    let revision_service = &state.revision_service;
+    match revision_service.diff_revisions(id, query.from, query.to).await {
+        Ok(diff) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: diff }),
+        )
+            .into_response(),
+        Err(RevisionServiceError::NotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Revision not found".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to diff revisions".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+fn revision_error_response(err: RevisionServiceError) -> Response {
+    match err {
+        RevisionServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Revision not found".to_string(),
+            }),
+        )
+            .into_response(),
+        RevisionServiceError::MissingPublishAt => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Scheduled posts need a publish_at time".to_string(),
+            }),
+        )
+            .into_response(),
+        RevisionServiceError::InvalidStatusTransition(from, to) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ErrorResponse {
+                error: format!("Cannot move post from {} to {}", from, to),
+            }),
+        )
+            .into_response(),
+        RevisionServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to restore revision".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for restoring a post to one of its revisions
+async fn restore_revision_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    audit: Audit,
+    Path((id, revision_id)): Path<(i32, i32)>,
+) -> Response {
+    let before = match authorize_post(
+        &state,
+        &user,
+        id,
+        Capability::EditOwnPosts,
+        Capability::EditOthersPosts,
+    )
+    .await
+    {
+        Ok(post) => post,
+        Err(rejection) => return rejection,
+    };
+
+    // Restoring sets the revision's status, so it needs the same rights as
+    // moving the post there by hand
+    let revision_service = &state.revision_service;
+    let revision = match revision_service.get_revision(id, revision_id).await {
+        Ok(revision) => revision,
+        Err(e) => return revision_error_response(e),
+    };
+    if publishes(revision.status) {
+        if let Err(e) = user.require(Capability::PublishPosts) {
+            return e.into_response();
+        }
+    }
+    match revision_service.restore_revision(id, revision_id, user.id).await {
+        Ok(post) => {
+            audit.changed(AuditAction::Restore, AuditEntity::Post, id, &before, &post).await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: post }),
+            )
+                .into_response()
+        }
+        Err(e) => revision_error_response(e),
+    }
+}
+
+fn post_tags_error_response(err: TagServiceError, action: &str) -> Response {
+    match err {
+        TagServiceError::InvalidData(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid tag",
+                "details": err.to_string(),
+            })),
+        )
+            .into_response(),
+        TagServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Tag not found".to_string(),
+            }),
+        )
+            .into_response(),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Record a change to a post's tags as an update of the post
+async fn audit_tags(audit: &Audit, id: i32, before: &[TagSummary], after: &[TagSummary]) {
+    let names = |tags: &[TagSummary]| json!({ "tags": tags.iter().map(|tag| &tag.name).collect::<Vec<_>>() });
+    audit
+        .changed(AuditAction::Update, AuditEntity::Post, id, &names(before), &names(after))
+        .await;
+}
+
+/// Handler for listing the tags on a post
+async fn get_post_tags_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Response {
+    match state.tag_service.post_tags(id).await {
+        Ok(tags) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: tags }),
+        )
+            .into_response(),
+        Err(e) => post_tags_error_response(e, "retrieve tags"),
+    }
+}
+
+/// Handler for giving a post exactly the tags named (`PUT`) or adding them
+/// to the ones it has (`POST`). Tags that don't exist yet are created.
+async fn set_post_tags(
+    state: &AppState,
+    user: &AuthUser,
+    audit: &Audit,
+    id: i32,
+    names: &[String],
+    replace: bool,
+) -> Response {
+    if let Err(rejection) = authorize_post(
+        state,
+        user,
+        id,
+        Capability::EditOwnPosts,
+        Capability::EditOthersPosts,
+    )
+    .await
+    {
+        return rejection;
+    }
+
+    let tag_service = &state.tag_service;
+    let before = match tag_service.post_tags(id).await {
+        Ok(tags) => tags,
+        Err(e) => return post_tags_error_response(e, "update tags"),
+    };
+    let result = if replace {
+        tag_service.replace(id, names).await
+    } else {
+        tag_service.attach(id, names).await
+    };
+    match result {
+        Ok(tags) => {
+            audit_tags(audit, id, &before, &tags).await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: tags }),
+            )
+                .into_response()
+        }
+        Err(e) => post_tags_error_response(e, "update tags"),
+    }
+}
+
+async fn replace_post_tags_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    audit: Audit,
+    Path(id): Path<i32>,
+    Json(request): Json<PostTagsRequest>,
+) -> Response {
+    set_post_tags(&state, &user, &audit, id, &request.tags, true).await
+}
+
+async fn attach_post_tags_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    audit: Audit,
+    Path(id): Path<i32>,
+    Json(request): Json<PostTagsRequest>,
+) -> Response {
+    set_post_tags(&state, &user, &audit, id, &request.tags, false).await
+}
+
+/// Handler for taking a tag off a post
+async fn detach_post_tag_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    audit: Audit,
+    Path((id, tag_id)): Path<(i32, i32)>,
+) -> Response {
+    if let Err(rejection) = authorize_post(
+        &state,
+        &user,
+        id,
+        Capability::EditOwnPosts,
+        Capability::EditOthersPosts,
+    )
+    .await
+    {
+        return rejection;
+    }
+
+    let tag_service = &state.tag_service;
+    let before = match tag_service.post_tags(id).await {
+        Ok(tags) => tags,
+        Err(e) => return post_tags_error_response(e, "remove tag"),
+    };
+    match tag_service.detach(id, tag_id).await {
+        Ok(()) => {
+            let after: Vec<TagSummary> = before.iter().filter(|tag| tag.id != tag_id).cloned().collect();
+            audit_tags(&audit, id, &before, &after).await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => post_tags_error_response(e, "remove tag"),
     }
 }
 
@@ -157,4 +675,75 @@ pub fn routes() -> Router {
                 .put(update_post_handler)
                 .delete(delete_post_handler),
         )
+        .route(
+            "/:id/tags",
+            get(get_post_tags_handler)
+                .put(replace_post_tags_handler)
+                .post(attach_post_tags_handler),
+        )
+        .route("/:id/tags/:tag_id", delete(detach_post_tag_handler))
+        .route("/:id/revisions", get(list_revisions_handler))
+        .route("/:id/revisions/diff", get(diff_revisions_handler))
+        .route("/:id/revisions/:revision_id", get(get_revision_handler))
+        .route("/:id/revisions/:revision_id/restore", post(restore_revision_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::types::UserRole;
+
+    fn user(id: i32, role: UserRole) -> AuthUser {
+        AuthUser {
+            id,
+            username: format!("user{}", id),
+            role,
+        }
+    }
+
+    fn post(author_id: i32, status: PostStatus) -> Post {
+        let now = chrono::Utc::now().naive_utc();
+        Post {
+            id: 1,
+            title: "Title".to_string(),
+            slug: "title".to_string(),
+            content: String::new(),
+            status,
+            author_id,
+            created_at: now,
+            updated_at: now,
+            publish_at: None,
+            published_at: None,
+        }
+    }
+
+    #[test]
+    fn subscribers_only_list_their_own_drafts() {
+        assert_eq!(drafts_of(&user(7, UserRole::Subscriber)), Some(7));
+        assert_eq!(drafts_of(&user(7, UserRole::Editor)), None);
+    }
+
+    #[test]
+    fn subscribers_cannot_read_others_unpublished_posts() {
+        let subscriber = user(7, UserRole::Subscriber);
+        assert!(can_read(&subscriber, &post(1, PostStatus::Published)));
+        assert!(can_read(&subscriber, &post(7, PostStatus::Draft)));
+        for status in [
+            PostStatus::Draft,
+            PostStatus::PendingReview,
+            PostStatus::Scheduled,
+            PostStatus::Archived,
+        ] {
+            assert!(!can_read(&subscriber, &post(1, status)));
+        }
+        assert!(can_read(&user(2, UserRole::Editor), &post(1, PostStatus::Draft)));
+    }
+
+    #[test]
+    fn subscribers_cannot_read_revisions_of_others_posts() {
+        let subscriber = user(7, UserRole::Subscriber);
+        assert!(subscriber
+            .require_owned(1, Capability::EditOwnPosts, Capability::EditOthersPosts)
+            .is_err());
+    }
 }
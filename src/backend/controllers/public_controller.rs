@@ -0,0 +1,258 @@
+use axum::{
+    routing::get,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::backend::services::public_service::PublicServiceError;
+use crate::backend::services::render_service::{render_component, render_document, render_themed_document};
+use crate::backend::services::theme_service::{LoadedTheme, ThemeServiceError, ThemeView};
+use crate::backend::AppState;
+use crate::frontend::templates::archive_template::{ArchiveTemplate, ArchiveTemplateProps};
+use crate::frontend::templates::category_template::{CategoryTemplate, CategoryTemplateProps};
+use crate::frontend::templates::document_template::{text_slot, DocumentTemplate, DocumentTemplateProps};
+use crate::frontend::templates::page_template::{PageTemplate, PageTemplateProps};
+use crate::frontend::templates::post_template::{PostTemplate, PostTemplateProps};
+use crate::frontend::templates::tag_template::{TagTemplate, TagTemplateProps};
+use crate::shared::builder::ModuleKind;
+
+/// `?theme_preview=<token>` shows the page with a theme that isn't active yet
+#[derive(Deserialize)]
+pub struct ThemeQuery {
+    pub theme_preview: Option<String>,
+}
+
+/// Map a lookup failure onto a rendered error page
+fn error_page(err: PublicServiceError) -> Response {
+    match err {
+        PublicServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Html(render_document("Not found", "<h1>Page not found</h1>")),
+        )
+            .into_response(),
+        PublicServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Html(render_document("Error", "<h1>Something went wrong</h1>")),
+        )
+            .into_response(),
+    }
+}
+
+/// Render a view through the theme's override if it has one, otherwise
+/// through the built-in template `built_in` renders
+async fn themed_view<F>(theme: Option<&LoadedTheme>, view: ThemeView, context: serde_json::Value, built_in: F) -> String
+where
+    F: std::future::Future<Output = String>,
+{
+    match theme.and_then(|theme| theme.render(view, context)) {
+        Some(html) => html,
+        None => built_in.await,
+    }
+}
+
+/// Handler for rendering a published post
+async fn post_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<ThemeQuery>,
+) -> Response {
+    let post = match state.public_service.published_post(&slug).await {
+        Ok(post) => post,
+        Err(err) => return error_page(err),
+    };
+    let theme = state.theme_service.resolve(query.theme_preview.as_deref());
+
+    let title = post.title.clone();
+    let content = post.content.clone();
+    let context = json!({ "post": &post, "content": &content });
+    let body = themed_view(
+        theme.as_deref(),
+        ThemeView::Post,
+        context,
+        render_component::<PostTemplate>(PostTemplateProps { post }, &[("content", &content)]),
+    )
+    .await;
+
+    Html(render_themed_document(theme.as_deref(), &title, &body)).into_response()
+}
+
+/// Handler for rendering a page
+async fn page_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<ThemeQuery>,
+) -> Response {
+    let page = match state.public_service.page(&slug).await {
+        Ok(page) => page,
+        Err(err) => return error_page(err),
+    };
+    let theme = state.theme_service.resolve(query.theme_preview.as_deref());
+
+    let title = page.title.clone();
+    let content = page.content.clone();
+    let text_slots: Vec<(String, String)> = page
+        .document
+        .iter()
+        .flat_map(|document| document.modules())
+        .filter_map(|module| match &module.kind {
+            ModuleKind::Text { html } => Some((text_slot(&module.id), html.clone())),
+            _ => None,
+        })
+        .collect();
+    let mut slots: Vec<(&str, &str)> = vec![("content", &content)];
+    slots.extend(text_slots.iter().map(|(slot, html)| (slot.as_str(), html.as_str())));
+
+    let body = match theme.as_deref().filter(|theme| theme.overrides(ThemeView::Page)) {
+        Some(theme) => {
+            // The theme places the page body; builder pages are rendered to markup first
+            let content = match &page.document {
+                Some(document) => {
+                    let props = DocumentTemplateProps {
+                        document: document.clone(),
+                        viewport: None,
+                    };
+                    render_component::<DocumentTemplate>(props, &slots).await
+                }
+                None => content.clone(),
+            };
+            let context = json!({
+                "page": { "id": page.id, "title": &page.title, "slug": &page.slug },
+                "content": content,
+            });
+            theme.render(ThemeView::Page, context).unwrap_or_default()
+        }
+        None => render_component::<PageTemplate>(PageTemplateProps { page }, &slots).await,
+    };
+
+    Html(render_themed_document(theme.as_deref(), &title, &body)).into_response()
+}
+
+/// Handler for rendering a category archive
+async fn category_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<ThemeQuery>,
+) -> Response {
+    let (category, posts) = match state.public_service.category_archive(&slug).await {
+        Ok(archive) => archive,
+        Err(err) => return error_page(err),
+    };
+    let theme = state.theme_service.resolve(query.theme_preview.as_deref());
+
+    let title = category.name.clone();
+    let context = json!({ "term": &category, "posts": &posts });
+    let body = themed_view(
+        theme.as_deref(),
+        ThemeView::Category,
+        context,
+        render_component::<CategoryTemplate>(CategoryTemplateProps { category, posts }, &[]),
+    )
+    .await;
+
+    Html(render_themed_document(theme.as_deref(), &title, &body)).into_response()
+}
+
+/// Handler for rendering a tag archive
+async fn tag_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<ThemeQuery>,
+) -> Response {
+    let (tag, posts) = match state.public_service.tag_archive(&slug).await {
+        Ok(archive) => archive,
+        Err(err) => return error_page(err),
+    };
+    let theme = state.theme_service.resolve(query.theme_preview.as_deref());
+
+    let title = tag.name.clone();
+    let context = json!({ "term": &tag, "posts": &posts });
+    let body = themed_view(
+        theme.as_deref(),
+        ThemeView::Tag,
+        context,
+        render_component::<TagTemplate>(TagTemplateProps { tag, posts }, &[]),
+    )
+    .await;
+
+    Html(render_themed_document(theme.as_deref(), &title, &body)).into_response()
+}
+
+/// Handler for rendering the blog archive of recent posts
+async fn archive_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ThemeQuery>,
+) -> Response {
+    let posts = match state.public_service.recent_posts().await {
+        Ok(posts) => posts,
+        Err(err) => return error_page(err),
+    };
+    let theme = state.theme_service.resolve(query.theme_preview.as_deref());
+
+    let context = json!({ "posts": &posts });
+    let body = themed_view(
+        theme.as_deref(),
+        ThemeView::Archive,
+        context,
+        render_component::<ArchiveTemplate>(ArchiveTemplateProps { posts }, &[]),
+    )
+    .await;
+
+    Html(render_themed_document(theme.as_deref(), "Blog", &body)).into_response()
+}
+
+/// Handler serving the public site stylesheet
+async fn theme_css_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/css; charset=utf-8")],
+        include_str!("../../frontend/styles/theme.css"),
+    )
+}
+
+/// Handler serving the runtime for builder page actions
+async fn builder_actions_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript; charset=utf-8")],
+        include_str!("../../frontend/scripts/builder_actions.js"),
+    )
+}
+
+/// Handler serving a file from an installed theme's `assets/`
+async fn theme_asset_handler(
+    State(state): State<AppState>,
+    Path((theme_id, key)): Path<(i32, String)>,
+) -> Response {
+    let (path, content_type) = match state.theme_service.asset(theme_id, &key).await {
+        Ok(asset) => asset,
+        Err(ThemeServiceError::NotFound) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CACHE_CONTROL, "public, max-age=3600"),
+                // SVGs opened directly must not run script
+                (header::CONTENT_SECURITY_POLICY, "default-src 'none'; style-src 'unsafe-inline'; sandbox"),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Initialize the public site routes
+pub fn routes() -> Router {
+    Router::new()
+        .route("/blog", get(archive_handler))
+        .route("/blog/:slug", get(post_handler))
+        .route("/page/:slug", get(page_handler))
+        .route("/category/:slug", get(category_handler))
+        .route("/tag/:slug", get(tag_handler))
+        .route("/static/theme.css", get(theme_css_handler))
+        .route("/static/builder-actions.js", get(builder_actions_handler))
+        .route("/theme-assets/:theme_id/*key", get(theme_asset_handler))
+}
@@ -0,0 +1,97 @@
+use axum::{
+    routing::get,
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Router,
+};
+use crate::backend::services::search_service::{SearchFilters, SearchServiceError, DEFAULT_PER_PAGE};
+use crate::backend::models::post::PostStatus;
+use crate::backend::AppState;
+use crate::backend::middlewares::permission_middleware::AuthUser;
+use crate::shared::types::{Capability, SearchKind};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+/// `GET /search?q=...&type=post&category_id=3&tag=rust&status=draft&author_id=7&page=2&per_page=20`
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    #[serde(rename = "type")]
+    pub kind: Option<SearchKind>,
+    pub category_id: Option<i32>,
+    /// Tag slug
+    pub tag: Option<String>,
+    pub status: Option<PostStatus>,
+    pub author_id: Option<i32>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+/// Handler for searching posts, pages and comments
+async fn search_handler(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<SearchQuery>,
+) -> impl IntoResponse {
+    let filters = SearchFilters {
+        kind: query.kind,
+        category_id: query.category_id,
+        status: query.status,
+        author_id: query.author_id,
+        tag: query.tag,
+        // Users who can't edit others' posts only find their own drafts
+        drafts_of: if user.can(Capability::EditOthersPosts) {
+            None
+        } else {
+            Some(user.id)
+        },
+        // Only moderators find comments that aren't approved yet
+        pending_comments: user.can(Capability::ModerateComments),
+    };
+
+    let search_service = &state.search_service;
+    match search_service
+        .search(
+            &query.q,
+            &filters,
+            query.page.unwrap_or(1),
+            query.per_page.unwrap_or(DEFAULT_PER_PAGE),
+        )
+        .await
+    {
+        Ok(results) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: results }),
+        )
+            .into_response(),
+        Err(SearchServiceError::InvalidQuery) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Search query must be between 1 and 200 characters".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Search failed".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Initialize the search routes
+pub fn routes() -> Router {
+    Router::new().route("/", get(search_handler))
+}
@@ -1,14 +1,19 @@
+use std::collections::BTreeMap;
+
 use axum::{
-    routing::{get, put},
-    extract::{Json, State},
+    routing::get,
+    extract::{Json, Path, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Router,
 };
 use crate::backend::services::settings_service::SettingsServiceError;
-use crate::backend::models::settings::{Settings, UpdateSettings};
 use crate::backend::AppState;
-use serde::{Deserialize, Serialize};
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::shared::audit::{AuditAction, AuditEntity};
+use serde::Serialize;
+use serde_json::{json, Value};
 
 #[derive(Serialize)]
 struct ErrorResponse {
@@ -20,48 +25,136 @@ struct SuccessResponse<T> {
     data: T,
 }
 
-/// Handler for retrieving settings
-async fn get_settings_handler(
-    State(state): State<AppState>,
-) -> impl IntoResponse {
-    let settings_service = &state.settings_service;
-    match settings_service.get_settings().await {
-        Ok(settings) => (
-            StatusCode::OK,
-            Json(SuccessResponse { data: settings }),
-        ),
-        Err(_) => (
+/// One setting's current value, as returned by `GET /settings/:key`
+#[derive(Serialize)]
+struct SettingResponse {
+    key: String,
+    value: Value,
+}
+
+fn settings_error_response(err: SettingsServiceError, action: &str) -> Response {
+    match err {
+        SettingsServiceError::Invalid(errors) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid settings",
+                "details": errors,
+            })),
+        )
+            .into_response(),
+        SettingsServiceError::UnknownSetting(_) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        SettingsServiceError::DatabaseError(_) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
-                error: "Failed to retrieve settings".to_string(),
+                error: format!("Failed to {}", action),
             }),
-        ),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for listing every setting, grouped, with its schema and current value
+async fn list_settings_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageSettings>,
+) -> Response {
+    match state.settings_service.list().await {
+        Ok(groups) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: groups }),
+        )
+            .into_response(),
+        Err(e) => settings_error_response(e, "retrieve settings"),
     }
 }
 
-/// Handler for updating settings
+/// Handler for saving settings given as a `{ key: value }` object. Nothing is
+/// saved if any value is rejected.
 async fn update_settings_handler(
     State(state): State<AppState>,
-    Json(settings_data): Json<UpdateSettings>,
-) -> impl IntoResponse {
+    _: Require<caps::ManageSettings>,
+    audit: Audit,
+    Json(values): Json<BTreeMap<String, Value>>,
+) -> Response {
     let settings_service = &state.settings_service;
-    match settings_service.update_settings(settings_data).await {
-        Ok(settings) => (
+    // Unknown keys fail the update below, so they're skipped here
+    let mut before = BTreeMap::new();
+    for key in values.keys() {
+        if let Ok(value) = settings_service.get(key).await {
+            before.insert(key.clone(), value);
+        }
+    }
+    match settings_service.update(values).await {
+        Ok(groups) => {
+            for (key, was) in before {
+                let is = match settings_service.get(&key).await {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                };
+                if was != is {
+                    audit
+                        .record(AuditAction::Update, AuditEntity::Setting, Some(key), Some(was), Some(is))
+                        .await;
+                }
+            }
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: groups }),
+            )
+                .into_response()
+        }
+        Err(e) => settings_error_response(e, "update settings"),
+    }
+}
+
+/// Handler for retrieving one setting
+async fn get_setting_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageSettings>,
+    Path(key): Path<String>,
+) -> Response {
+    match state.settings_service.get(&key).await {
+        Ok(value) => (
             StatusCode::OK,
-            Json(SuccessResponse { data: settings }),
-        ),
-        Err(SettingsServiceError::InvalidData) => (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Invalid settings data".to_string(),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to update settings".to_string(),
-            }),
-        ),
+            Json(SuccessResponse { data: SettingResponse { key, value } }),
+        )
+            .into_response(),
+        Err(e) => settings_error_response(e, "retrieve setting"),
+    }
+}
+
+/// Handler for resetting one setting to its default
+async fn reset_setting_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageSettings>,
+    audit: Audit,
+    Path(key): Path<String>,
+) -> Response {
+    let before = state.settings_service.get(&key).await.ok();
+    match state.settings_service.reset(&key).await {
+        Ok(value) => {
+            if before.as_ref() != Some(&value) {
+                audit
+                    .record(
+                        AuditAction::Update,
+                        AuditEntity::Setting,
+                        Some(key.clone()),
+                        before,
+                        Some(value.clone()),
+                    )
+                    .await;
+            }
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: SettingResponse { key, value } }),
+            )
+                .into_response()
+        }
+        Err(e) => settings_error_response(e, "reset setting"),
     }
 }
 
@@ -70,6 +163,10 @@ pub fn routes() -> Router {
     Router::new()
         .route(
             "/",
-            get(get_settings_handler).put(update_settings_handler),
+            get(list_settings_handler).put(update_settings_handler),
+        )
+        .route(
+            "/:key",
+            get(get_setting_handler).delete(reset_setting_handler),
         )
 }
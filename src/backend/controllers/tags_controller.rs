@@ -0,0 +1,191 @@
+use axum::{
+    routing::{get, post},
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Router,
+};
+use crate::backend::services::tag_service::TagServiceError;
+use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::shared::audit::{AuditAction, AuditEntity};
+use crate::shared::tags::{MergeTagsRequest, TagRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+/// `GET /tags/autocomplete?q=ru`
+#[derive(Deserialize)]
+pub struct AutocompleteQuery {
+    pub q: String,
+}
+
+fn tag_error_response(err: TagServiceError, action: &str) -> Response {
+    match err {
+        TagServiceError::InvalidData(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid tag",
+                "details": err.to_string(),
+            })),
+        )
+            .into_response(),
+        TagServiceError::AlreadyExists(_) => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        TagServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Tag not found".to_string(),
+            }),
+        )
+            .into_response(),
+        TagServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for listing every tag with its post count
+async fn list_tags_handler(State(state): State<AppState>) -> Response {
+    match state.tag_service.list().await {
+        Ok(tags) => (StatusCode::OK, Json(SuccessResponse { data: tags })).into_response(),
+        Err(e) => tag_error_response(e, "list tags"),
+    }
+}
+
+/// Handler suggesting tags for what has been typed so far
+async fn autocomplete_handler(
+    State(state): State<AppState>,
+    Query(query): Query<AutocompleteQuery>,
+) -> Response {
+    match state.tag_service.autocomplete(&query.q).await {
+        Ok(tags) => (StatusCode::OK, Json(SuccessResponse { data: tags })).into_response(),
+        Err(e) => tag_error_response(e, "suggest tags"),
+    }
+}
+
+/// Handler for creating a tag ahead of tagging posts with it
+async fn create_tag_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageTags>,
+    audit: Audit,
+    Json(request): Json<TagRequest>,
+) -> Response {
+    match state.tag_service.create(request).await {
+        Ok(tag) => {
+            audit.created(AuditEntity::Tag, tag.id, &tag).await;
+            (StatusCode::CREATED, Json(SuccessResponse { data: tag })).into_response()
+        }
+        Err(e) => tag_error_response(e, "create tag"),
+    }
+}
+
+/// Handler for retrieving a tag by ID
+async fn get_tag_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Response {
+    match state.tag_service.get(id).await {
+        Ok(tag) => (StatusCode::OK, Json(SuccessResponse { data: tag })).into_response(),
+        Err(e) => tag_error_response(e, "retrieve tag"),
+    }
+}
+
+/// Handler for renaming a tag
+async fn update_tag_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageTags>,
+    audit: Audit,
+    Path(id): Path<i32>,
+    Json(request): Json<TagRequest>,
+) -> Response {
+    let tag_service = &state.tag_service;
+    let before = match tag_service.get(id).await {
+        Ok(tag) => tag,
+        Err(e) => return tag_error_response(e, "update tag"),
+    };
+    match tag_service.update(id, request).await {
+        Ok(tag) => {
+            audit.updated(AuditEntity::Tag, id, &before, &tag).await;
+            (StatusCode::OK, Json(SuccessResponse { data: tag })).into_response()
+        }
+        Err(e) => tag_error_response(e, "update tag"),
+    }
+}
+
+/// Handler for deleting a tag, which takes it off every post
+async fn delete_tag_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageTags>,
+    audit: Audit,
+    Path(id): Path<i32>,
+) -> Response {
+    let tag_service = &state.tag_service;
+    let before = match tag_service.get(id).await {
+        Ok(tag) => tag,
+        Err(e) => return tag_error_response(e, "delete tag"),
+    };
+    match tag_service.delete(id).await {
+        Ok(()) => {
+            audit.deleted(AuditEntity::Tag, id, &before).await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => tag_error_response(e, "delete tag"),
+    }
+}
+
+/// Handler for folding duplicate tags into this one
+async fn merge_tags_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageTags>,
+    audit: Audit,
+    Path(id): Path<i32>,
+    Json(request): Json<MergeTagsRequest>,
+) -> Response {
+    match state.tag_service.merge(id, &request.tags).await {
+        Ok(tag) => {
+            audit
+                .record(
+                    AuditAction::Update,
+                    AuditEntity::Tag,
+                    Some(id.to_string()),
+                    None,
+                    Some(json!({ "merged": &request.tags, "post_count": tag.post_count })),
+                )
+                .await;
+            (StatusCode::OK, Json(SuccessResponse { data: tag })).into_response()
+        }
+        Err(e) => tag_error_response(e, "merge tags"),
+    }
+}
+
+/// Initialize the tag routes
+pub fn routes() -> Router {
+    Router::new()
+        .route("/", get(list_tags_handler).post(create_tag_handler))
+        .route("/autocomplete", get(autocomplete_handler))
+        .route(
+            "/:id",
+            get(get_tag_handler)
+                .put(update_tag_handler)
+                .delete(delete_tag_handler),
+        )
+        .route("/:id/merge", post(merge_tags_handler))
+}
@@ -0,0 +1,243 @@
+use axum::{
+    routing::{get, post},
+    extract::{DefaultBodyLimit, Multipart, Path, Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Router,
+};
+use crate::backend::services::theme_service::{ThemeServiceError, MAX_PACKAGE_BYTES};
+use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::backend::services::audit_service::snapshot;
+use crate::shared::audit::{AuditAction, AuditEntity};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+/// `POST /themes/directory`: install `<THEMES_ROOT>/incoming/<directory>`
+#[derive(Deserialize)]
+pub struct InstallDirectory {
+    pub directory: String,
+}
+
+fn theme_error_response(err: ThemeServiceError, action: &str) -> Response {
+    match err {
+        ThemeServiceError::InvalidPackage(reason) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid theme package",
+                "details": reason,
+            })),
+        )
+            .into_response(),
+        ThemeServiceError::AlreadyInstalled(..) => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        ThemeServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Theme not found".to_string(),
+            }),
+        )
+            .into_response(),
+        ThemeServiceError::NothingToRollBack => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        ThemeServiceError::Io(_) | ThemeServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for listing installed themes
+async fn list_themes_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageThemes>,
+) -> Response {
+    match state.theme_service.list().await {
+        Ok(themes) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: themes }),
+        )
+            .into_response(),
+        Err(e) => theme_error_response(e, "list themes"),
+    }
+}
+
+/// Handler for installing a theme zip uploaded as `multipart/form-data` with a `file` field
+async fn upload_theme_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageThemes>,
+    audit: Audit,
+    mut multipart: Multipart,
+) -> Response {
+    let field = loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some("file") => break field,
+            Ok(Some(_)) => continue,
+            Ok(None) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "No file field in upload".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            Err(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "Malformed multipart body".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    };
+
+    let bytes = match field.bytes().await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(ErrorResponse {
+                    error: format!("Theme packages are limited to {} bytes", MAX_PACKAGE_BYTES),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    match state.theme_service.install_zip(&bytes).await {
+        Ok(theme) => {
+            audit.created(AuditEntity::Theme, theme.id, &theme).await;
+            (
+                StatusCode::CREATED,
+                Json(SuccessResponse { data: theme }),
+            )
+                .into_response()
+        }
+        Err(e) => theme_error_response(e, "install theme"),
+    }
+}
+
+/// Handler for installing a theme directory already on the server
+async fn install_directory_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageThemes>,
+    audit: Audit,
+    Json(request): Json<InstallDirectory>,
+) -> Response {
+    match state.theme_service.install_directory(&request.directory).await {
+        Ok(theme) => {
+            audit.created(AuditEntity::Theme, theme.id, &theme).await;
+            (
+                StatusCode::CREATED,
+                Json(SuccessResponse { data: theme }),
+            )
+                .into_response()
+        }
+        Err(e) => theme_error_response(e, "install theme"),
+    }
+}
+
+/// Handler for making a theme the active one
+async fn activate_theme_handler(
+    State(state): State<AppState>,
+    Require(user, ..): Require<caps::ManageThemes>,
+    audit: Audit,
+    Path(id): Path<i32>,
+) -> Response {
+    match state.theme_service.activate(id, user.id).await {
+        Ok(theme) => {
+            audit
+                .record(AuditAction::Activate, AuditEntity::Theme, Some(id.to_string()), None, Some(snapshot(&theme)))
+                .await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: theme }),
+            )
+                .into_response()
+        }
+        Err(e) => theme_error_response(e, "activate theme"),
+    }
+}
+
+/// Handler for issuing a preview link for a theme that isn't active
+async fn preview_theme_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageThemes>,
+    Path(id): Path<i32>,
+) -> Response {
+    match state.theme_service.preview(id).await {
+        Ok(preview) => (
+            StatusCode::OK,
+            Json(SuccessResponse { data: preview }),
+        )
+            .into_response(),
+        Err(e) => theme_error_response(e, "preview theme"),
+    }
+}
+
+/// Handler for going back to the previously active theme; `data` is null
+/// when the site is back on the built-in templates
+async fn rollback_theme_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageThemes>,
+    audit: Audit,
+) -> Response {
+    match state.theme_service.rollback().await {
+        Ok(theme) => {
+            // Without a theme id, the site went back to the built-in templates
+            audit
+                .record(
+                    AuditAction::Rollback,
+                    AuditEntity::Theme,
+                    theme.as_ref().map(|theme| theme.id.to_string()),
+                    None,
+                    Some(snapshot(&theme)),
+                )
+                .await;
+            (
+                StatusCode::OK,
+                Json(SuccessResponse { data: theme }),
+            )
+                .into_response()
+        }
+        Err(e) => theme_error_response(e, "roll back theme"),
+    }
+}
+
+/// Initialize the theme routes
+pub fn routes() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_themes_handler)
+                .post(upload_theme_handler)
+                .layer(DefaultBodyLimit::max(MAX_PACKAGE_BYTES + 64 * 1024)),
+        )
+        .route("/directory", post(install_directory_handler))
+        .route("/rollback", post(rollback_theme_handler))
+        .route("/:id/activate", post(activate_theme_handler))
+        .route("/:id/preview", post(preview_theme_handler))
+}
@@ -1,71 +1,159 @@
 use axum::{
-    routing::{get, post, put, delete},
-    extract::{Path, Json, Extension},
+    routing::get,
+    extract::{Json, Path, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Router,
 };
-use crate::services::user_service::UserService;
-use crate::models::user::User;
-use std::sync::Arc;
-use axum::Json;
+use crate::backend::services::user_service::UserServiceError;
+use crate::backend::AppState;
+use crate::backend::middlewares::audit::Audit;
+use crate::backend::middlewares::permission_middleware::{caps, Require};
+use crate::backend::models::user::{NewUserRequest, UpdateUserRequest};
+use crate::shared::audit::{AuditAction, AuditEntity};
+use serde::Serialize;
+use serde_json::json;
 
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct SuccessResponse<T> {
+    data: T,
+}
+
+fn user_error_response(err: UserServiceError, action: &str) -> Response {
+    match err {
+        UserServiceError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        UserServiceError::AlreadyExists => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse { error: err.to_string() }),
+        )
+            .into_response(),
+        UserServiceError::Invalid(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": "Invalid user",
+                "details": err.to_string(),
+            })),
+        )
+            .into_response(),
+        UserServiceError::DatabaseError(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to {}", action),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler creating a user
 async fn create_user_handler(
-    Json(user_data): Json<User>,
-    Extension(user_service): Extension<Arc<UserService>>
-) -> impl IntoResponse {
-    match user_service.create_user(user_data).await {
-        Ok(user) => (StatusCode::CREATED, Json(user)).into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(err)).into_response(),
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+    audit: Audit,
+    Json(request): Json<NewUserRequest>,
+) -> Response {
+    match state.user_service.create_user(request).await {
+        Ok(user) => {
+            audit.created(AuditEntity::User, user.id, &user).await;
+            (StatusCode::CREATED, Json(SuccessResponse { data: user })).into_response()
+        }
+        Err(e) => user_error_response(e, "create user"),
     }
 }
 
+/// Handler listing every user
 async fn get_all_users_handler(
-    Extension(user_service): Extension<Arc<UserService>>
-) -> impl IntoResponse {
-    match user_service.list_users().await {
-        Ok(users) => (StatusCode::OK, Json(users)).into_response(),
-        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response(),
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+) -> Response {
+    match state.user_service.list_users().await {
+        Ok(users) => (StatusCode::OK, Json(SuccessResponse { data: users })).into_response(),
+        Err(e) => user_error_response(e, "list users"),
     }
 }
 
+/// Handler retrieving a user by ID
 async fn get_user_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
     Path(id): Path<i32>,
-    Extension(user_service): Extension<Arc<UserService>>
-) -> impl IntoResponse {
-    match user_service.get_user(id).await {
-        Ok(user) => (StatusCode::OK, Json(user)).into_response(),
-        Err(err) => (StatusCode::NOT_FOUND, Json(err)).into_response(),
+) -> Response {
+    match state.user_service.get_user(id).await {
+        Ok(user) => (StatusCode::OK, Json(SuccessResponse { data: user })).into_response(),
+        Err(e) => user_error_response(e, "retrieve user"),
     }
 }
 
+/// Handler changing a user's email, role or password
 async fn update_user_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+    audit: Audit,
     Path(id): Path<i32>,
-    Json(user_data): Json<User>,
-    Extension(user_service): Extension<Arc<UserService>>
-) -> impl IntoResponse {
-    match user_service.update_user(id, user_data).await {
-        Ok(user) => (StatusCode::OK, Json(user)).into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(err)).into_response(),
+    Json(request): Json<UpdateUserRequest>,
+) -> Response {
+    let before = match state.user_service.get_user(id).await {
+        Ok(user) => user,
+        Err(e) => return user_error_response(e, "update user"),
+    };
+    // Not part of the record, so noted on its own
+    let password_changed = request.password.as_deref().map_or(false, |password| !password.is_empty());
+    match state.user_service.update_user(id, request).await {
+        Ok(user) => {
+            audit.updated(AuditEntity::User, id, &before, &user).await;
+            if password_changed {
+                audit
+                    .record(
+                        AuditAction::Update,
+                        AuditEntity::User,
+                        Some(id.to_string()),
+                        None,
+                        Some(json!({ "password_changed": true })),
+                    )
+                    .await;
+            }
+            (StatusCode::OK, Json(SuccessResponse { data: user })).into_response()
+        }
+        Err(e) => user_error_response(e, "update user"),
     }
 }
 
+/// Handler deleting a user
 async fn delete_user_handler(
+    State(state): State<AppState>,
+    _: Require<caps::ManageUsers>,
+    audit: Audit,
     Path(id): Path<i32>,
-    Extension(user_service): Extension<Arc<UserService>>
-) -> impl IntoResponse {
-    match user_service.delete_user(id).await {
-        Ok(_) => (StatusCode::OK, Json("User deleted")).into_response(),
-        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response(),
+) -> Response {
+    let before = match state.user_service.get_user(id).await {
+        Ok(user) => user,
+        Err(e) => return user_error_response(e, "delete user"),
+    };
+    match state.user_service.delete_user(id).await {
+        Ok(()) => {
+            audit.deleted(AuditEntity::User, id, &before).await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => user_error_response(e, "delete user"),
     }
 }
 
-pub fn init_routes(user_service: Arc<UserService>) -> Router {
+pub fn routes() -> Router {
     Router::new()
-        .route("/api/users", get(get_all_users_handler))
-        .route("/api/users", post(create_user_handler))
-        .route("/api/users/:id", get(get_user_handler))
-        .route("/api/users/:id", put(update_user_handler))
-        .route("/api/users/:id", delete(delete_user_handler))
-        .layer(Extension(user_service))
+        .route("/", get(get_all_users_handler).post(create_user_handler))
+        .route(
+            "/:id",
+            get(get_user_handler)
+                .put(update_user_handler)
+                .delete(delete_user_handler),
+        )
 }
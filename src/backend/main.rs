@@ -12,17 +12,47 @@ use crate::backend::controllers::{
     post_controller,
     media_controller,
     category_controller,
+    tags_controller,
+    comments_controller,
     builder_controller,
+    components_controller,
     settings_controller,
+    public_controller,
+    search_controller,
+    themes_controller,
+    oidc_controller,
+    api_keys_controller,
+    audit_controller,
+    users_controller,
 };
-use crate::backend::utils::{db::establish_connection_pool, auth::require_auth};
+use crate::backend::utils::db::establish_connection_pool;
+use crate::backend::middlewares::auth_middleware::{optional_auth, require_auth};
+use crate::backend::middlewares::client_ip::TrustedProxies;
 use crate::backend::services::{
+    account_service::AccountService,
     auth_service::AuthService,
+    mailer::mailer_from_env,
+    oidc::providers_from_env,
+    oidc_service::OidcService,
+    api_key_service::ApiKeyService,
+    audit_service::AuditService,
     post_service::PostService,
+    revision_service::RevisionService,
+    publisher_service::ScheduledPublisher,
+    rendition_service::RenditionWorker,
     media_service::MediaService,
+    media_storage::{LocalMediaStorage, MediaStorage},
     category_service::CategoryService,
+    tag_service::TagService,
+    comment_service::CommentService,
     builder_service::BuilderService,
-    settings_service::SettingsService,
+    library_service::LibraryService,
+    settings_service::{SettingsRegistry, SettingsService},
+    public_service::PublicService,
+    search_service::SearchService,
+    spam_filter::SpamPipeline,
+    theme_service::ThemeService,
+    user_service::UserService,
 };
 use crate::backend::utils::db::DbPool;
 use std::sync::Arc;
@@ -31,11 +61,24 @@ use std::sync::Arc;
 struct AppState {
     db_pool: DbPool,
     auth_service: Arc<AuthService>,
+    account_service: Arc<AccountService>,
+    oidc_service: Arc<OidcService>,
+    api_key_service: Arc<ApiKeyService>,
+    audit_service: Arc<AuditService>,
     post_service: Arc<PostService>,
+    revision_service: Arc<RevisionService>,
     media_service: Arc<MediaService>,
     category_service: Arc<CategoryService>,
+    tag_service: Arc<TagService>,
+    comment_service: Arc<CommentService>,
     builder_service: Arc<BuilderService>,
+    library_service: Arc<LibraryService>,
     settings_service: Arc<SettingsService>,
+    public_service: Arc<PublicService>,
+    search_service: Arc<SearchService>,
+    theme_service: Arc<ThemeService>,
+    user_service: Arc<UserService>,
+    trusted_proxies: Arc<TrustedProxies>,
     // All shared services have been added
 }
 
@@ -48,22 +91,85 @@ async fn main() {
     let db_pool = establish_connection_pool();
 
     // Initialize shared services
-    let auth_service = Arc::new(AuthService::new(db_pool.clone()));
     let post_service = Arc::new(PostService::new(db_pool.clone()));
-    let media_service = Arc::new(MediaService::new(db_pool.clone()));
+    let revision_service = Arc::new(RevisionService::new(db_pool.clone()));
+    let media_storage: Arc<dyn MediaStorage> = Arc::new(LocalMediaStorage::new(
+        std::env::var("MEDIA_ROOT").unwrap_or_else(|_| "uploads".to_string()),
+    ));
+    let media_service = Arc::new(MediaService::new(db_pool.clone(), media_storage.clone()));
     let category_service = Arc::new(CategoryService::new(db_pool.clone()));
+    let tag_service = Arc::new(TagService::new(db_pool.clone()));
+    let spam_pipeline = Arc::new(SpamPipeline::with_default_filters(db_pool.clone()));
+    let comment_service = Arc::new(CommentService::new(db_pool.clone(), spam_pipeline));
     let builder_service = Arc::new(BuilderService::new(db_pool.clone()));
-    let settings_service = Arc::new(SettingsService::new(db_pool.clone()));
+    let library_service = Arc::new(LibraryService::new(db_pool.clone()));
+    let settings_service = Arc::new(SettingsService::new(db_pool.clone(), SettingsRegistry::standard()));
+    let jwt_secret = std::env::var("SECRET_KEY").expect("SECRET_KEY must be set");
+    let auth_service = Arc::new(AuthService::new(db_pool.clone(), settings_service.clone(), &jwt_secret));
+    let site_url = std::env::var("SITE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    let mailer = mailer_from_env().expect("Failed to configure the mailer");
+    let account_service = Arc::new(AccountService::new(
+        db_pool.clone(),
+        auth_service.clone(),
+        settings_service.clone(),
+        mailer,
+        site_url.clone(),
+    ));
+    let oidc_service = Arc::new(OidcService::new(
+        db_pool.clone(),
+        auth_service.clone(),
+        settings_service.clone(),
+        providers_from_env().expect("Invalid single sign-on configuration"),
+        // Where the API is reachable from browsers; providers redirect here
+        std::env::var("API_URL").unwrap_or_else(|_| format!("{}/api", site_url)),
+        site_url,
+    ));
+    let user_service = Arc::new(UserService::new(db_pool.clone(), auth_service.clone()));
+    let api_key_service = Arc::new(ApiKeyService::new(db_pool.clone()));
+    let audit_service = Arc::new(AuditService::new(db_pool.clone()));
+    let public_service = Arc::new(PublicService::new(db_pool.clone()));
+    let search_service = Arc::new(SearchService::new(db_pool.clone()));
+    let theme_service = Arc::new(ThemeService::new(
+        db_pool.clone(),
+        std::env::var("THEMES_ROOT").unwrap_or_else(|_| "themes".to_string()),
+    ));
+
+    // Proxies allowed to report the client address in X-Forwarded-For
+    let trusted_proxies = Arc::new(TrustedProxies::from_env().expect("Invalid TRUSTED_PROXIES"));
+
+    // Load the active theme before serving; the built-in templates are used if it can't be
+    if let Err(e) = theme_service.load_active() {
+        tracing::warn!("Failed to load the active theme: {}", e);
+    }
+
+    // Start the background worker that publishes scheduled posts
+    ScheduledPublisher::new(db_pool.clone()).spawn();
+
+    // Start the background worker that generates image renditions
+    RenditionWorker::new(db_pool.clone(), media_storage).spawn();
 
     // Create shared application state
     let app_state = AppState {
         db_pool: db_pool.clone(),
         auth_service: auth_service.clone(),
+        account_service: account_service.clone(),
+        oidc_service: oidc_service.clone(),
+        api_key_service: api_key_service.clone(),
+        audit_service: audit_service.clone(),
         post_service: post_service.clone(),
+        revision_service: revision_service.clone(),
         media_service: media_service.clone(),
         category_service: category_service.clone(),
+        tag_service: tag_service.clone(),
+        comment_service: comment_service.clone(),
         builder_service: builder_service.clone(),
+        library_service: library_service.clone(),
         settings_service: settings_service.clone(),
+        public_service: public_service.clone(),
+        search_service: search_service.clone(),
+        theme_service: theme_service.clone(),
+        user_service: user_service.clone(),
+        trusted_proxies,
     };
 
     // Build the application with routes and middleware
@@ -73,36 +179,90 @@ async fn main() {
             "/auth",
             auth_controller::routes(),
         )
+        // Single sign-on through OpenID Connect providers
+        .nest("/auth/oidc", oidc_controller::routes())
         // Post routes (protected)
         .nest(
             "/posts",
             post_controller::routes()
-                .layer(axum::middleware::from_fn(require_auth)),
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
         )
         // Media routes (protected)
         .nest(
             "/media",
             media_controller::routes()
-                .layer(axum::middleware::from_fn(require_auth)),
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
         )
+        // Uploaded files (public)
+        .nest("/uploads", media_controller::file_routes())
         // Category routes (protected)
         .nest(
             "/categories",
             category_controller::routes()
-                .layer(axum::middleware::from_fn(require_auth)),
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
+        )
+        // Tag routes (protected)
+        .nest(
+            "/tags",
+            tags_controller::routes()
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
+        )
+        // Comment routes (anonymous posting; moderation checks capabilities per handler)
+        .nest(
+            "/comments",
+            comments_controller::routes()
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), optional_auth)),
         )
         // Builder routes (protected)
         .nest(
             "/builder",
             builder_controller::routes()
-                .layer(axum::middleware::from_fn(require_auth)),
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
+        )
+        // Builder library: global blocks and saved sections (protected)
+        .nest(
+            "/components",
+            components_controller::routes()
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
         )
         // Settings routes (protected)
         .nest(
             "/settings",
             settings_controller::routes()
-                .layer(axum::middleware::from_fn(require_auth)),
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
+        )
+        // Search routes (protected)
+        .nest(
+            "/search",
+            search_controller::routes()
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
+        )
+        // Theme install, activation, preview and rollback (protected)
+        .nest(
+            "/themes",
+            themes_controller::routes()
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
+        )
+        // User management (protected; access tokens only)
+        .nest(
+            "/users",
+            users_controller::routes()
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
+        )
+        // API key management (protected; access tokens only, keys have no scope for it)
+        .nest(
+            "/api-keys",
+            api_keys_controller::routes()
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
+        )
+        // Audit log (protected; access tokens only)
+        .nest(
+            "/audit",
+            audit_controller::routes()
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), require_auth)),
         )
+        // Public site, rendered on the server (unauthenticated)
+        .merge(public_controller::routes())
         // Add shared application state
         .with_state(app_state)
         // Apply global middleware
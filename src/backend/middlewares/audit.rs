@@ -0,0 +1,124 @@
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::request::Parts,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::backend::AppState;
+use crate::backend::middlewares::client_ip::ClientIp;
+use crate::backend::middlewares::permission_middleware::AuthUser;
+use crate::backend::models::audit::NewAuditRecord;
+use crate::backend::models::user::User;
+use crate::backend::services::api_key_service::ApiKeyPrincipal;
+use crate::backend::services::audit_service::{changes, snapshot, AuditService};
+use crate::shared::audit::{AuditAction, AuditEntity};
+
+/// Extractor for recording what a request changed in the audit log. It
+/// knows who made the request, with which API key, and from where, so
+/// handlers only say what happened:
+///
+/// ```ignore
+/// async fn handler(audit: Audit, ...) -> Response {
+///     let before = service.get(id).await?;
+///     let after = service.update(id, changes).await?;
+///     audit.updated(AuditEntity::Post, id, &before, &after).await;
+/// }
+/// ```
+///
+/// Never rejects; requests without a user are recorded without an actor.
+pub struct Audit {
+    service: Arc<AuditService>,
+    /// Id and username of the user making the request
+    actor: Option<(i32, String)>,
+    api_key_id: Option<i32>,
+    ip: Option<IpAddr>,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Audit {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let actor = AuthUser::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|user| (user.id, user.username));
+        let ip = ClientIp::from_request_parts(parts, state).await.ok().map(|ClientIp(ip)| ip);
+        let api_key_id = parts.extensions.get::<ApiKeyPrincipal>().map(|principal| principal.key_id);
+        Ok(Audit {
+            service: state.audit_service.clone(),
+            actor,
+            api_key_id,
+            ip,
+        })
+    }
+}
+
+impl Audit {
+    /// Attribute entries to a user the request proved to be some other way
+    /// than a token, like the one a single sign-on callback signed in
+    pub fn by_user(self, user: &User) -> Self {
+        Audit {
+            actor: Some((user.id, user.username.clone())),
+            ..self
+        }
+    }
+
+    pub async fn created<T: Serialize>(&self, entity: AuditEntity, id: impl ToString, after: &T) {
+        self.record(AuditAction::Create, entity, Some(id.to_string()), None, Some(snapshot(after)))
+            .await;
+    }
+
+    /// Record an update with the fields that differ between `before` and `after`
+    pub async fn updated<T: Serialize>(&self, entity: AuditEntity, id: impl ToString, before: &T, after: &T) {
+        self.changed(AuditAction::Update, entity, id, before, after).await;
+    }
+
+    pub async fn deleted<T: Serialize>(&self, entity: AuditEntity, id: impl ToString, before: &T) {
+        self.record(AuditAction::Delete, entity, Some(id.to_string()), Some(snapshot(before)), None)
+            .await;
+    }
+
+    /// Like [`Audit::updated`], for another action that changed a record
+    pub async fn changed<T: Serialize>(
+        &self,
+        action: AuditAction,
+        entity: AuditEntity,
+        id: impl ToString,
+        before: &T,
+        after: &T,
+    ) {
+        let (before, after) = changes(snapshot(before), snapshot(after));
+        self.record(action, entity, Some(id.to_string()), Some(before), Some(after))
+            .await;
+    }
+
+    pub async fn record(
+        &self,
+        action: AuditAction,
+        entity: AuditEntity,
+        entity_id: Option<String>,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) {
+        self.service
+            .record(NewAuditRecord {
+                actor_id: self.actor.as_ref().map(|(id, _)| *id),
+                actor_name: self.actor.as_ref().map(|(_, username)| username.clone()),
+                api_key_id: self.api_key_id,
+                ip: self.ip.map(|ip| ip.to_string()),
+                action: action.as_str().to_string(),
+                entity_type: entity.as_str().to_string(),
+                entity_id,
+                before,
+                after,
+            })
+            .await;
+    }
+}
@@ -1,53 +1,68 @@
 use axum::{
-    extract::{Extension, TypedHeader},
-    http::StatusCode,
+    extract::{OriginalUri, State},
+    http::{header::AUTHORIZATION, Method, Request},
     middleware::Next,
-    response::IntoResponse,
-    Request,
+    response::{IntoResponse, Response},
 };
-use crate::services::auth_service::AuthService;
-use headers::Authorization;
-use std::sync::Arc;
 
-// Define the AuthError for handling authorization failures
-#[derive(Debug)]
-struct AuthError;
+use crate::backend::AppState;
+use crate::backend::middlewares::permission_middleware::PermissionError;
+use crate::backend::services::api_key_service::is_api_key;
+use crate::shared::api_keys::{ApiAccess, ApiResource, ApiScope};
 
-impl IntoResponse for AuthError {
-    fn into_response(self) -> axum::response::Response {
-        (StatusCode::UNAUTHORIZED, "Unauthorized").into_response()
-    }
-}
-
-// Auth middleware using Axum's Extension to pass AuthService and validate tokens
-async fn auth_middleware<B>(
-    TypedHeader(auth_header): TypedHeader<Authorization<String>>, // Extract Authorization header
-    Extension(auth_service): Extension<Arc<AuthService>>, // Extract AuthService from the extension
-    req: Request<B>,
-    next: Next<B>,
-) -> impl IntoResponse {
-    // Validate the token using AuthService
-    if let Ok(_) = auth_service.validate_token(&auth_header.0) {
-        next.run(req).await // If valid, proceed to the next middleware/handler
-    } else {
-        AuthError.into_response() // If invalid, return an Unauthorized response
-    }
+/// Rejects requests without a valid access token or API key.
+///
+/// An API key also needs a scope for the part of the API the request is
+/// for; the user it acts as is stored in the request, where the
+/// [`AuthUser`](crate::backend::middlewares::permission_middleware::AuthUser)
+/// extractor finds it.
+pub async fn require_auth<B>(State(state): State<AppState>, req: Request<B>, next: Next<B>) -> Response {
+    authenticate(state, req, next, true).await
 }
 
-// Define the middleware stack
-pub fn with_auth() -> impl tower::Layer<axum::Router> + Clone {
-    tower::ServiceBuilder::new()
-        .layer(axum::middleware::from_fn(auth_middleware))
+/// Like [`require_auth`], for routes that anonymous visitors may use too
+pub async fn optional_auth<B>(State(state): State<AppState>, req: Request<B>, next: Next<B>) -> Response {
+    authenticate(state, req, next, false).await
 }
 
-// Example of integrating the middleware with Axum's router
-pub fn init_routes() -> axum::Router {
-    axum::Router::new()
-        .route("/api/protected_route", axum::routing::get(protected_handler))
-        .layer(with_auth()) // Apply the auth middleware
-}
+async fn authenticate<B>(state: AppState, mut req: Request<B>, next: Next<B>, required: bool) -> Response {
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
 
-// Example handler for a protected route
-async fn protected_handler() -> impl IntoResponse {
-    "Access granted to protected route"
+    match token {
+        Some(token) if is_api_key(&token) => {
+            let principal = match state.api_key_service.authenticate(&token).await {
+                Ok(principal) => principal,
+                Err(_) => return PermissionError::Unauthenticated.into_response(),
+            };
+            // Nested routers see the path without their prefix
+            let path = match req.extensions().get::<OriginalUri>() {
+                Some(OriginalUri(uri)) => uri.path().to_string(),
+                None => req.uri().path().to_string(),
+            };
+            let access = match *req.method() {
+                Method::GET | Method::HEAD => ApiAccess::Read,
+                _ => ApiAccess::Write,
+            };
+            match ApiResource::from_path(&path) {
+                Some(resource) if principal.allows(resource, access) => {}
+                resource => {
+                    let scope = resource.map(|resource| ApiScope { access, resource });
+                    return PermissionError::MissingScope(scope).into_response();
+                }
+            }
+            req.extensions_mut().insert(principal.user.clone());
+            // Kept for the audit log, which notes the key used
+            req.extensions_mut().insert(principal);
+            next.run(req).await
+        }
+        Some(token) if state.auth_service.validate_token(&token).is_ok() => next.run(req).await,
+        // Handlers on optional routes treat a bad access token as anonymous
+        _ if !required => next.run(req).await,
+        _ => PermissionError::Unauthenticated.into_response(),
+    }
 }
@@ -0,0 +1,160 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{rejection::ExtensionRejection, ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+
+use crate::backend::AppState;
+
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Reverse proxies whose `X-Forwarded-For` header is believed, read from
+/// `TRUSTED_PROXIES` as comma-separated addresses or CIDR ranges. Without
+/// any, the address of the connection is the client's.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrustedProxies {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    pub fn from_env() -> Result<Self, String> {
+        std::env::var("TRUSTED_PROXIES")
+            .map(|list| Self::parse(&list))
+            .unwrap_or_else(|_| Ok(Self::default()))
+    }
+
+    /// Parse e.g. `10.0.0.0/8, 127.0.0.1, ::1`
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let ranges = list
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let invalid = || format!("Invalid trusted proxy: {}", entry);
+                let (address, bits) = match entry.split_once('/') {
+                    Some((address, bits)) => (address, Some(bits.parse::<u8>().map_err(|_| invalid())?)),
+                    None => (entry, None),
+                };
+                let address = address.parse::<IpAddr>().map_err(|_| invalid())?;
+                let max_bits = if address.is_ipv4() { 32 } else { 128 };
+                match bits {
+                    Some(bits) if bits > max_bits => Err(invalid()),
+                    bits => Ok((address, bits.unwrap_or(max_bits))),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { ranges })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|(network, bits)| match (network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                same_prefix(u32::from(*network).into(), u32::from(ip).into(), 32, *bits)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => same_prefix(u128::from(*network), u128::from(ip), 128, *bits),
+            _ => false,
+        })
+    }
+
+    /// Address of the client behind a connection from `peer`. Entries in
+    /// `X-Forwarded-For` are read from the right, skipping trusted proxies,
+    /// since only those entries were added by proxies we believe.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(peer) {
+            return peer;
+        }
+        let forwarded: Vec<&str> = headers
+            .get_all(FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+
+        let mut client = peer;
+        for entry in forwarded.into_iter().rev() {
+            match entry.parse::<IpAddr>() {
+                Ok(ip) => {
+                    client = ip;
+                    if !self.contains(ip) {
+                        break;
+                    }
+                }
+                // A proxy we trust wrote something we can't read; stop at the last address we could
+                Err(_) => break,
+            }
+        }
+        client
+    }
+}
+
+fn same_prefix(a: u128, b: u128, width: u32, bits: u8) -> bool {
+    let shift = width - u32::from(bits);
+    shift >= width || (a >> shift) == (b >> shift)
+}
+
+/// Extractor for the address of the client that sent the request, as
+/// reported by trusted proxies
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = ExtensionRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(addr) = ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await?;
+        Ok(ClientIp(state.trusted_proxies.client_ip(addr.ip(), &parts.headers)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(forwarded_for: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(FORWARDED_FOR, HeaderValue::from_str(forwarded_for).unwrap());
+        headers
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_addresses_and_ranges() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8, 127.0.0.1, fd00::/8").unwrap();
+        assert!(proxies.contains(ip("10.1.2.3")));
+        assert!(proxies.contains(ip("127.0.0.1")));
+        assert!(!proxies.contains(ip("127.0.0.2")));
+        assert!(proxies.contains(ip("fd12::1")));
+        assert!(!proxies.contains(ip("11.0.0.1")));
+        assert!(TrustedProxies::parse("0.0.0.0/0").unwrap().contains(ip("8.8.8.8")));
+        assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxies::parse("proxy.local").is_err());
+        assert_eq!(TrustedProxies::parse(" ").unwrap(), TrustedProxies::default());
+    }
+
+    #[test]
+    fn ignores_forwarded_for_from_untrusted_peers() {
+        let proxies = TrustedProxies::parse("10.0.0.1").unwrap();
+        assert_eq!(proxies.client_ip(ip("203.0.113.9"), &headers("198.51.100.1")), ip("203.0.113.9"));
+        assert_eq!(TrustedProxies::default().client_ip(ip("10.0.0.1"), &headers("198.51.100.1")), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn takes_the_first_untrusted_address_from_the_right() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        // The left entry was sent by the client and can't be believed
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.1"), &headers("1.2.3.4, 198.51.100.7, 10.0.0.2")),
+            ip("198.51.100.7")
+        );
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), &headers("10.0.0.3")), ip("10.0.0.3"));
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), &headers("garbage, 10.0.0.3")), ip("10.0.0.3"));
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), &HeaderMap::new()), ip("10.0.0.1"));
+    }
+}
@@ -0,0 +1,8 @@
+// src/backend/middlewares/mod.rs
+
+pub mod audit;
+pub mod auth_middleware;
+pub mod client_ip;
+pub mod cors_middleware;
+pub mod logging_middleware;
+pub mod permission_middleware;
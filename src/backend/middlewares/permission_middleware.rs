@@ -0,0 +1,232 @@
+use std::marker::PhantomData;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::backend::services::auth_service::Claims;
+use crate::backend::AppState;
+use crate::shared::api_keys::ApiScope;
+use crate::shared::types::{Capability, UserRole};
+
+/// Why a request was refused by the permission layer
+#[derive(Debug)]
+pub enum PermissionError {
+    /// No valid access token was presented
+    Unauthenticated,
+    /// The user's role does not grant the capability
+    Forbidden(Capability),
+    /// The API key used has no scope for the request. `None` for parts of
+    /// the API that can't be used with keys at all.
+    MissingScope(Option<ApiScope>),
+}
+
+impl IntoResponse for PermissionError {
+    fn into_response(self) -> Response {
+        match self {
+            PermissionError::Unauthenticated => (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({ "error": "Unauthorized" })),
+            )
+                .into_response(),
+            PermissionError::Forbidden(capability) => (
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "Forbidden",
+                    "missing_capability": capability,
+                })),
+            )
+                .into_response(),
+            PermissionError::MissingScope(scope) => (
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "Forbidden",
+                    "missing_scope": scope,
+                })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// The authenticated user making the request, taken from the bearer token,
+/// or from the API key the auth layer accepted
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: i32,
+    pub username: String,
+    pub role: UserRole,
+}
+
+impl From<Claims> for AuthUser {
+    fn from(claims: Claims) -> Self {
+        Self {
+            id: claims.sub,
+            username: claims.username,
+            role: claims.role,
+        }
+    }
+}
+
+impl AuthUser {
+    pub fn can(&self, capability: Capability) -> bool {
+        self.role.can(capability)
+    }
+
+    pub fn require(&self, capability: Capability) -> Result<(), PermissionError> {
+        if self.can(capability) {
+            Ok(())
+        } else {
+            Err(PermissionError::Forbidden(capability))
+        }
+    }
+
+    /// Check access to a resource owned by `owner_id`: `own` suffices for the
+    /// owner, everyone else needs `others`.
+    pub fn require_owned(
+        &self,
+        owner_id: i32,
+        own: Capability,
+        others: Capability,
+    ) -> Result<(), PermissionError> {
+        if owner_id == self.id {
+            self.require(own)
+        } else {
+            self.require(others)
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = PermissionError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<AuthUser>() {
+            return Ok(user.clone());
+        }
+
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(PermissionError::Unauthenticated)?;
+
+        let claims = state
+            .auth_service
+            .validate_token(token)
+            .map_err(|_| PermissionError::Unauthenticated)?;
+        let user = AuthUser::from(claims);
+        parts.extensions.insert(user.clone());
+        Ok(user)
+    }
+}
+
+/// Marker type naming the capability a [`Require`] extractor enforces
+pub trait RequiredCapability: Send + Sync {
+    const CAPABILITY: Capability;
+}
+
+/// Extractor that rejects the request unless the user's role grants `C`.
+///
+/// ```ignore
+/// async fn update_settings_handler(
+///     Require(user, ..): Require<caps::ManageSettings>,
+///     ...
+/// )
+/// ```
+pub struct Require<C: RequiredCapability>(pub AuthUser, pub PhantomData<C>);
+
+#[async_trait]
+impl<C> FromRequestParts<AppState> for Require<C>
+where
+    C: RequiredCapability,
+{
+    type Rejection = PermissionError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+        user.require(C::CAPABILITY)?;
+        Ok(Require(user, PhantomData))
+    }
+}
+
+macro_rules! capability_markers {
+    ($($name:ident),* $(,)?) => {
+        /// One marker type per [`Capability`], for use with [`Require`]
+        pub mod caps {
+            use super::{Capability, RequiredCapability};
+            $(
+                pub struct $name;
+                impl RequiredCapability for $name {
+                    const CAPABILITY: Capability = Capability::$name;
+                }
+            )*
+        }
+    };
+}
+
+capability_markers!(
+    CreatePosts,
+    EditOwnPosts,
+    EditOthersPosts,
+    PublishPosts,
+    DeleteOwnPosts,
+    DeleteOthersPosts,
+    UploadMedia,
+    DeleteMedia,
+    ManageCategories,
+    ManageTags,
+    ModerateComments,
+    UseBuilder,
+    ManageSettings,
+    ManageUsers,
+    ManageThemes,
+    ViewAuditLog,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: i32, role: UserRole) -> AuthUser {
+        AuthUser {
+            id,
+            username: format!("user{}", id),
+            role,
+        }
+    }
+
+    #[test]
+    fn authors_can_only_delete_their_own_posts() {
+        let author = user(1, UserRole::Author);
+        let own = Capability::DeleteOwnPosts;
+        let others = Capability::DeleteOthersPosts;
+        assert!(author.require_owned(1, own, others).is_ok());
+        assert!(matches!(
+            author.require_owned(2, own, others),
+            Err(PermissionError::Forbidden(Capability::DeleteOthersPosts))
+        ));
+        assert!(user(3, UserRole::Editor).require_owned(2, own, others).is_ok());
+    }
+
+    #[test]
+    fn only_admins_manage_settings() {
+        assert!(user(1, UserRole::Admin).require(Capability::ManageSettings).is_ok());
+        for role in [UserRole::Editor, UserRole::Author, UserRole::Contributor, UserRole::Subscriber] {
+            assert!(user(1, role).require(Capability::ManageSettings).is_err());
+        }
+    }
+
+    #[test]
+    fn contributors_cannot_publish() {
+        assert!(!UserRole::Contributor.can(Capability::PublishPosts));
+        assert!(UserRole::Author.can(Capability::PublishPosts));
+    }
+}
@@ -0,0 +1,34 @@
+// src/backend/models/account_token.rs
+
+use diesel::prelude::*;
+use diesel::Insertable;
+use chrono::NaiveDateTime;
+
+use crate::backend::schema::account_tokens;
+
+/// What a mailed token lets its holder do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    PasswordReset,
+    EmailVerification,
+}
+
+impl TokenPurpose {
+    /// Value stored in `account_tokens.purpose`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::PasswordReset => "password_reset",
+            TokenPurpose::EmailVerification => "email_verification",
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "account_tokens"]
+pub struct NewAccountToken {
+    pub user_id: i32,
+    pub purpose: String,
+    pub token_hash: String,
+    pub email: Option<String>,
+    pub expires_at: NaiveDateTime,
+}
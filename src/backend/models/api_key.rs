@@ -0,0 +1,38 @@
+// src/backend/models/api_key.rs
+
+use diesel::prelude::*;
+use diesel::{Insertable, Queryable};
+use chrono::NaiveDateTime;
+
+use crate::backend::schema::api_keys;
+
+#[derive(Debug, Queryable, Identifiable)]
+#[table_name = "api_keys"]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub user_id: i32,
+    /// Set on site keys, which act with this role instead of the user's
+    pub role: Option<String>,
+    pub created_by: Option<i32>,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "api_keys"]
+pub struct NewApiKey {
+    pub name: String,
+    pub user_id: i32,
+    pub role: Option<String>,
+    pub created_by: Option<i32>,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+}
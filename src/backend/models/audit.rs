@@ -0,0 +1,38 @@
+// src/backend/models/audit.rs
+
+use diesel::prelude::*;
+use diesel::{Insertable, Queryable};
+use chrono::NaiveDateTime;
+use serde_json::Value;
+
+use crate::backend::schema::audit_log;
+
+#[derive(Debug, Queryable, Identifiable)]
+#[table_name = "audit_log"]
+pub struct AuditRecord {
+    pub id: i32,
+    pub actor_id: Option<i32>,
+    pub actor_name: Option<String>,
+    pub api_key_id: Option<i32>,
+    pub ip: Option<String>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "audit_log"]
+pub struct NewAuditRecord {
+    pub actor_id: Option<i32>,
+    pub actor_name: Option<String>,
+    pub api_key_id: Option<i32>,
+    pub ip: Option<String>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use diesel::{Queryable, Insertable, Identifiable};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use chrono::NaiveDateTime;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::backend::schema::{component_events, component_styles, components, page_components, page_sections};
+use crate::shared::builder::{PageDocument, Section};
+
+/// A page as edited in the builder
+#[derive(Serialize, Debug)]
+pub struct BuilderPage {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+    pub document: PageDocument,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePageData {
+    pub title: String,
+    pub slug: String,
+    #[serde(default)]
+    pub document: PageDocument,
+}
+
+#[derive(Deserialize)]
+pub struct UpdatePageData {
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub document: Option<PageDocument>,
+}
+
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "page_sections"]
+pub struct PageSection {
+    pub id: i32,
+    pub page_id: Option<i32>,
+    pub section_name: String,
+    /// The section's [`SectionLayout`] as JSON
+    pub content: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub section_key: String,
+    pub position: i32,
+    /// Global block shown in the section
+    pub block_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "page_sections"]
+pub struct NewPageSection {
+    pub page_id: i32,
+    pub section_name: String,
+    pub content: String,
+    pub section_key: String,
+    pub position: i32,
+    pub block_id: Option<i32>,
+}
+
+/// Rows and columns of a section, without their modules
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SectionLayout {
+    pub rows: Vec<RowLayout>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RowLayout {
+    pub id: String,
+    pub columns: Vec<ColumnLayout>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ColumnLayout {
+    pub id: String,
+    pub width: u8,
+}
+
+/// What a library item is, stored as text in `components.library_kind`
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum LibraryKind {
+    /// Stored once and shown live on every page that references it
+    GlobalBlock,
+    /// Copied into a page when inserted
+    SavedSection,
+}
+
+impl LibraryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LibraryKind::GlobalBlock => "global_block",
+            LibraryKind::SavedSection => "saved_section",
+        }
+    }
+}
+
+impl fmt::Display for LibraryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LibraryKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "global_block" => Ok(LibraryKind::GlobalBlock),
+            "saved_section" => Ok(LibraryKind::SavedSection),
+            other => Err(format!("Unknown library kind: {}", other)),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for LibraryKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for LibraryKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
+
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "components"]
+pub struct Component {
+    pub id: i32,
+    /// Module type, e.g. `text`, or the name of a library item
+    pub name: String,
+    pub template_id: Option<i32>,
+    /// The module's settings, tagged with its type, or a library item's section
+    pub component_data: serde_json::Value,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    /// Page the component belongs to; `None` for library items
+    pub page_id: Option<i32>,
+    pub library_kind: Option<LibraryKind>,
+}
+
+#[derive(Insertable)]
+#[table_name = "components"]
+pub struct NewComponent {
+    pub name: String,
+    pub component_data: serde_json::Value,
+    pub page_id: Option<i32>,
+    pub library_kind: Option<LibraryKind>,
+}
+
+/// A reusable section from the builder library
+#[derive(Serialize, Debug)]
+pub struct LibraryItem {
+    pub id: i32,
+    pub name: String,
+    pub kind: LibraryKind,
+    pub section: Section,
+    /// Pages showing the item; only counted for global blocks
+    pub usage_count: i64,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateLibraryItem {
+    pub name: String,
+    pub kind: LibraryKind,
+    pub section: Section,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateLibraryItem {
+    pub name: Option<String>,
+    pub section: Option<Section>,
+}
+
+/// A page that shows a global block
+#[derive(Serialize, Queryable, Debug)]
+pub struct BlockUsage {
+    pub page_id: i32,
+    pub title: String,
+    pub slug: String,
+}
+
+/// Placement of a component on a page
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "page_components"]
+pub struct PageComponent {
+    pub id: i32,
+    pub page_id: Option<i32>,
+    pub component_id: Option<i32>,
+    /// Order within the column
+    pub position: i32,
+    pub created_at: Option<NaiveDateTime>,
+    pub section_id: Option<i32>,
+    pub column_key: Option<String>,
+    pub module_key: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "page_components"]
+pub struct NewPageComponent {
+    pub page_id: i32,
+    pub component_id: i32,
+    pub position: i32,
+    pub section_id: i32,
+    pub column_key: String,
+    pub module_key: String,
+}
+
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "component_styles"]
+pub struct ComponentStyle {
+    pub id: i32,
+    pub component_id: Option<i32>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    /// `desktop`, `tablet`, `mobile` or `custom`
+    pub breakpoint: String,
+    /// Width of a `custom` breakpoint, in pixels
+    pub max_width: Option<i32>,
+    /// Declarations as a JSON object of property to value
+    pub properties: serde_json::Value,
+}
+
+#[derive(Insertable)]
+#[table_name = "component_styles"]
+pub struct NewComponentStyle {
+    pub component_id: i32,
+    pub breakpoint: String,
+    pub max_width: Option<i32>,
+    pub properties: serde_json::Value,
+}
+
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "component_events"]
+pub struct ComponentEvent {
+    pub id: i32,
+    pub component_id: Option<i32>,
+    /// `click`, `submit` or `visible`
+    pub event_type: String,
+    pub created_at: Option<NaiveDateTime>,
+    /// The binding's actions, as a JSON array
+    pub actions: serde_json::Value,
+}
+
+#[derive(Insertable)]
+#[table_name = "component_events"]
+pub struct NewComponentEvent {
+    pub component_id: i32,
+    pub event_type: String,
+    pub actions: serde_json::Value,
+}
@@ -1,14 +1,158 @@
 use serde::{Deserialize, Serialize};
 use diesel::prelude::*;
+use diesel::{Queryable, Insertable, Identifiable, AsChangeset};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use chrono::NaiveDateTime;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Queryable, Insertable)]
+use crate::backend::models::spam::{SpamLabel, SpamVerdicts};
+use crate::backend::schema::comments;
+
+/// Moderation state of a comment, stored as text in `comments.status`
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum CommentStatus {
+    Pending,
+    Approved,
+    Spam,
+    Trash,
+}
+
+impl CommentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentStatus::Pending => "pending",
+            CommentStatus::Approved => "approved",
+            CommentStatus::Spam => "spam",
+            CommentStatus::Trash => "trash",
+        }
+    }
+}
+
+impl Default for CommentStatus {
+    fn default() -> Self {
+        CommentStatus::Pending
+    }
+}
+
+impl fmt::Display for CommentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CommentStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(CommentStatus::Pending),
+            "approved" => Ok(CommentStatus::Approved),
+            "spam" => Ok(CommentStatus::Spam),
+            "trash" => Ok(CommentStatus::Trash),
+            other => Err(format!("Unknown comment status: {}", other)),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for CommentStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for CommentStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
+
+#[derive(Serialize, Queryable, Identifiable, Debug, Clone)]
 #[table_name = "comments"]
 pub struct Comment {
     pub id: i32,
     pub post_id: i32,
+    pub user_id: Option<i32>,
+    pub content: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub parent_id: Option<i32>,
+    pub status: CommentStatus,
     pub author_name: String,
     pub author_email: String,
+    pub author_ip: Option<String>,
+    /// What each spam filter made of the comment when it was submitted
+    pub spam_verdicts: SpamVerdicts,
+    #[serde(skip_serializing)]
+    pub spam_trained: Option<SpamLabel>,
+}
+
+#[derive(Insertable)]
+#[table_name = "comments"]
+pub struct NewComment {
+    pub post_id: i32,
+    pub user_id: Option<i32>,
+    pub parent_id: Option<i32>,
+    pub content: String,
+    pub status: CommentStatus,
+    pub author_name: String,
+    pub author_email: String,
+    pub author_ip: Option<String>,
+    pub spam_verdicts: SpamVerdicts,
+}
+
+/// A comment as submitted by a visitor. Name and email are only read for
+/// anonymous comments; signed-in users comment under their account.
+#[derive(Deserialize)]
+pub struct CreateComment {
+    pub post_id: i32,
+    pub parent_id: Option<i32>,
     pub content: String,
-    pub status: String,
-    pub created_at: chrono::NaiveDateTime,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Honeypot: comment forms render this field hidden, so only bots fill it in
+    #[serde(default)]
+    pub website: String,
+}
+
+#[derive(Deserialize, AsChangeset)]
+#[table_name = "comments"]
+pub struct UpdateComment {
+    pub content: Option<String>,
+}
+
+/// An approved comment with its approved replies, as shown under a post
+#[derive(Serialize, Debug, PartialEq)]
+pub struct CommentNode {
+    pub id: i32,
+    pub author_name: String,
+    pub content: String,
+    pub created_at: NaiveDateTime,
+    pub replies: Vec<CommentNode>,
+}
+
+/// Number of comments in each moderation state
+#[derive(Serialize, Debug, Default)]
+pub struct StatusCounts {
+    pub pending: i64,
+    pub approved: i64,
+    pub spam: i64,
+    pub trash: i64,
+}
+
+/// A page of the moderation queue
+#[derive(Serialize, Debug)]
+pub struct CommentPage {
+    pub items: Vec<Comment>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub counts: StatusCounts,
 }
@@ -0,0 +1,20 @@
+// src/backend/models/login_throttle.rs
+
+use diesel::prelude::*;
+use diesel::{Insertable, Queryable};
+use chrono::NaiveDateTime;
+
+use crate::backend::schema::login_throttles;
+
+/// Failed logins for one username or client address
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[table_name = "login_throttles"]
+#[primary_key(scope, subject)]
+pub struct LoginThrottle {
+    /// `account` or `ip`
+    pub scope: String,
+    pub subject: String,
+    pub failures: i32,
+    pub last_failed_at: NaiveDateTime,
+    pub locked_until: Option<NaiveDateTime>,
+}
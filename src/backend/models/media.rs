@@ -1,29 +1,220 @@
 use serde::{Deserialize, Serialize};
 use diesel::prelude::*;
-use diesel::{Queryable, Insertable, Identifiable, AsChangeset};
+use diesel::{Queryable, Insertable, Identifiable, Associations, AsChangeset};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
 use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
 
-use crate::backend::schema::media;
+use crate::backend::schema::{media, media_renditions};
+
+/// Progress of the background job generating an item's renditions,
+/// stored as text in `media.renditions_status`
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum RenditionStatus {
+    /// Not an image the pipeline can process
+    None,
+    Pending,
+    Processing,
+    Ready,
+    Failed,
+}
+
+impl RenditionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RenditionStatus::None => "none",
+            RenditionStatus::Pending => "pending",
+            RenditionStatus::Processing => "processing",
+            RenditionStatus::Ready => "ready",
+            RenditionStatus::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for RenditionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RenditionStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(RenditionStatus::None),
+            "pending" => Ok(RenditionStatus::Pending),
+            "processing" => Ok(RenditionStatus::Processing),
+            "ready" => Ok(RenditionStatus::Ready),
+            "failed" => Ok(RenditionStatus::Failed),
+            other => Err(format!("Unknown rendition status: {}", other)),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for RenditionStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for RenditionStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
+
+/// Named derivative of an uploaded image, stored as text in `media_renditions.name`
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum RenditionName {
+    Thumbnail,
+    Medium,
+    Large,
+    /// `large`-sized WebP for browsers that support it
+    Webp,
+    /// `large`-sized AVIF for browsers that support it
+    Avif,
+}
+
+impl RenditionName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RenditionName::Thumbnail => "thumbnail",
+            RenditionName::Medium => "medium",
+            RenditionName::Large => "large",
+            RenditionName::Webp => "webp",
+            RenditionName::Avif => "avif",
+        }
+    }
+}
+
+impl fmt::Display for RenditionName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RenditionName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "thumbnail" => Ok(RenditionName::Thumbnail),
+            "medium" => Ok(RenditionName::Medium),
+            "large" => Ok(RenditionName::Large),
+            "webp" => Ok(RenditionName::Webp),
+            "avif" => Ok(RenditionName::Avif),
+            other => Err(format!("Unknown rendition: {}", other)),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for RenditionName {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for RenditionName {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
 
 #[derive(Serialize, Queryable, Identifiable, Debug)]
 #[table_name = "media"]
 pub struct Media {
     pub id: i32,
+    pub file_name: String,
     pub url: String,
-    pub alt_text: String,
+    pub media_type: Option<String>,
     pub uploaded_at: NaiveDateTime,
+    pub user_id: Option<i32>,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub file_size: i64,
+    pub alt_text: String,
+    pub renditions_status: RenditionStatus,
+    /// When a rendition worker took the upload; see `RenditionWorker`
+    #[serde(skip_serializing)]
+    pub renditions_claimed_at: Option<NaiveDateTime>,
 }
 
-#[derive(Deserialize, Insertable)]
+#[derive(Insertable)]
 #[table_name = "media"]
 pub struct NewMedia {
+    pub file_name: String,
     pub url: String,
+    pub media_type: Option<String>,
+    pub user_id: Option<i32>,
+    pub storage_key: String,
+    pub file_size: i64,
     pub alt_text: String,
+    pub renditions_status: RenditionStatus,
 }
 
 #[derive(Deserialize, AsChangeset)]
 #[table_name = "media"]
 pub struct UpdateMedia {
-    pub url: Option<String>,
+    pub file_name: Option<String>,
     pub alt_text: Option<String>,
 }
+
+#[derive(Serialize, Queryable, Identifiable, Associations, Debug)]
+#[table_name = "media_renditions"]
+#[belongs_to(Media)]
+pub struct MediaRendition {
+    pub id: i32,
+    pub media_id: i32,
+    pub name: RenditionName,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub url: String,
+    pub media_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub file_size: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "media_renditions"]
+pub struct NewMediaRendition {
+    pub media_id: i32,
+    pub name: RenditionName,
+    pub storage_key: String,
+    pub url: String,
+    pub media_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub file_size: i64,
+}
+
+/// A media library entry as returned by the API, with its renditions keyed by name
+#[derive(Serialize, Debug)]
+pub struct MediaItem {
+    #[serde(flatten)]
+    pub media: Media,
+    pub renditions: BTreeMap<RenditionName, MediaRendition>,
+}
+
+impl MediaItem {
+    pub fn new(media: Media, renditions: Vec<MediaRendition>) -> Self {
+        Self {
+            media,
+            renditions: renditions.into_iter().map(|r| (r.name, r)).collect(),
+        }
+    }
+}
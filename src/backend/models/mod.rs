@@ -2,10 +2,22 @@
 
 pub mod user;
 pub mod post;
+pub mod page;
+pub mod comment;
+pub mod spam;
+pub mod post_revision;
 pub mod media;
 pub mod category;
+pub mod tag;
 pub mod settings;
 pub mod builder;
+pub mod theme;
+pub mod session;
+pub mod account_token;
+pub mod user_identity;
+pub mod api_key;
+pub mod audit;
+pub mod login_throttle;
 
 // Optionally, you can re-export common structs or enums for easier access
 // pub use user::User;
@@ -9,4 +9,7 @@ pub struct Page {
     pub content: String,  // JSON or a serialized format for the page structure
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub slug: String,
+    /// Builder document format the page was saved with, if built in the builder
+    pub builder_version: Option<i32>,
 }
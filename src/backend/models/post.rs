@@ -1,11 +1,96 @@
 use serde::{Deserialize, Serialize};
 use diesel::prelude::*;
 use diesel::{Queryable, Insertable, Identifiable, Associations, AsChangeset};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
 use chrono::NaiveDateTime;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
 
-use crate::backend::schema::posts;
+use crate::backend::schema::{post_publish_log, posts};
 use crate::backend::models::user::User;
 
+/// Lifecycle of a post, stored as text in `posts.status`
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum PostStatus {
+    Draft,
+    PendingReview,
+    Scheduled,
+    Published,
+    Archived,
+}
+
+impl PostStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::PendingReview => "pending_review",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Published => "published",
+            PostStatus::Archived => "archived",
+        }
+    }
+
+    /// Whether a post may move from this status to `next`
+    pub fn can_transition_to(&self, next: PostStatus) -> bool {
+        use PostStatus::*;
+        match (self, next) {
+            (a, b) if *a == b => true,
+            (Draft, PendingReview | Scheduled | Published | Archived) => true,
+            (PendingReview, Draft | Scheduled | Published | Archived) => true,
+            (Scheduled, Draft | PendingReview | Published | Archived) => true,
+            (Published, Draft | Archived) => true,
+            (Archived, Draft) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Default for PostStatus {
+    fn default() -> Self {
+        PostStatus::Draft
+    }
+}
+
+impl fmt::Display for PostStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PostStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(PostStatus::Draft),
+            "pending_review" => Ok(PostStatus::PendingReview),
+            "scheduled" => Ok(PostStatus::Scheduled),
+            "published" => Ok(PostStatus::Published),
+            "archived" => Ok(PostStatus::Archived),
+            other => Err(format!("Unknown post status: {}", other)),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for PostStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for PostStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
+
 #[derive(Serialize, Queryable, Identifiable, Associations, Debug)]
 #[table_name = "posts"]
 #[belongs_to(User, foreign_key = "author_id")]
@@ -14,10 +99,12 @@ pub struct Post {
     pub title: String,
     pub slug: String,
     pub content: String,
-    pub status: String,
+    pub status: PostStatus,
     pub author_id: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub publish_at: Option<NaiveDateTime>,
+    pub published_at: Option<NaiveDateTime>,
 }
 
 #[derive(Deserialize, Insertable)]
@@ -26,8 +113,12 @@ pub struct NewPost {
     pub title: String,
     pub slug: String,
     pub content: String,
-    pub status: String,
+    #[serde(default)]
+    pub status: PostStatus,
+    /// Set from the signed-in user, never from the request
+    #[serde(skip_deserializing)]
     pub author_id: i32,
+    pub publish_at: Option<NaiveDateTime>,
 }
 
 #[derive(Deserialize, AsChangeset)]
@@ -36,5 +127,47 @@ pub struct UpdatePost {
     pub title: Option<String>,
     pub slug: Option<String>,
     pub content: Option<String>,
-    pub status: Option<String>,
+    pub status: Option<PostStatus>,
+    pub publish_at: Option<NaiveDateTime>,
+}
+
+/// Record of the background publisher flipping a scheduled post to published
+#[derive(Serialize, Queryable, Debug)]
+pub struct PublishLogEntry {
+    pub id: i32,
+    pub post_id: i32,
+    pub scheduled_for: NaiveDateTime,
+    pub published_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "post_publish_log"]
+pub struct NewPublishLogEntry {
+    pub post_id: i32,
+    pub scheduled_for: NaiveDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_round_trips_through_str() {
+        for status in [
+            PostStatus::Draft,
+            PostStatus::PendingReview,
+            PostStatus::Scheduled,
+            PostStatus::Published,
+            PostStatus::Archived,
+        ] {
+            assert_eq!(status.as_str().parse::<PostStatus>(), Ok(status));
+        }
+    }
+
+    #[test]
+    fn archived_posts_must_go_back_to_draft() {
+        assert!(PostStatus::Archived.can_transition_to(PostStatus::Draft));
+        assert!(!PostStatus::Archived.can_transition_to(PostStatus::Published));
+        assert!(!PostStatus::Published.can_transition_to(PostStatus::Scheduled));
+    }
 }
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use diesel::{Queryable, Insertable, Identifiable, Associations};
+use chrono::NaiveDateTime;
+
+use crate::backend::schema::post_revisions;
+use crate::backend::models::post::{Post, PostStatus};
+
+/// A snapshot of a post as it was before an update overwrote it
+#[derive(Serialize, Queryable, Identifiable, Associations, Debug, Clone)]
+#[table_name = "post_revisions"]
+#[belongs_to(Post, foreign_key = "post_id")]
+pub struct PostRevision {
+    pub id: i32,
+    pub post_id: i32,
+    pub revision_number: i32,
+    pub title: String,
+    pub slug: String,
+    pub content: String,
+    pub status: PostStatus,
+    /// The user whose edit or restore replaced this state
+    pub author_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Insertable)]
+#[table_name = "post_revisions"]
+pub struct NewPostRevision {
+    pub post_id: i32,
+    pub revision_number: i32,
+    pub title: String,
+    pub slug: String,
+    pub content: String,
+    pub status: PostStatus,
+    pub author_id: Option<i32>,
+}
+
+impl NewPostRevision {
+    /// Builds the snapshot of `post` stored under `revision_number`, credited
+    /// to `editor_id`, the user about to change it
+    pub fn from_post(post: &Post, revision_number: i32, editor_id: i32) -> Self {
+        Self {
+            post_id: post.id,
+            revision_number,
+            title: post.title.clone(),
+            slug: post.slug.clone(),
+            content: post.content.clone(),
+            status: post.status,
+            author_id: Some(editor_id),
+        }
+    }
+}
+
+/// Kind of change for a single line in a revision diff
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// Line-level diff between two revisions of the same post
+#[derive(Serialize, Debug)]
+pub struct RevisionDiff {
+    pub post_id: i32,
+    pub from_revision: i32,
+    pub to_revision: i32,
+    pub title: Vec<DiffLine>,
+    pub slug: Vec<DiffLine>,
+    pub status: Vec<DiffLine>,
+    pub content: Vec<DiffLine>,
+}
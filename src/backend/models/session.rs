@@ -0,0 +1,47 @@
+// src/backend/models/session.rs
+
+use serde::Serialize;
+use diesel::prelude::*;
+use diesel::{Queryable, Insertable, Identifiable};
+use chrono::NaiveDateTime;
+
+use crate::backend::schema::sessions;
+
+/// One refresh token. Only a hash of the token is stored.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name = "sessions"]
+pub struct Session {
+    pub id: i32,
+    pub user_id: i32,
+    pub session_token: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    /// Shared by every token rotated from the same login
+    pub family_id: String,
+    pub user_agent: Option<String>,
+    /// Set when the token was exchanged for the next one in its family
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[table_name = "sessions"]
+pub struct NewSession {
+    pub user_id: i32,
+    pub session_token: String,
+    pub expires_at: NaiveDateTime,
+    pub family_id: String,
+    pub user_agent: Option<String>,
+}
+
+/// A signed-in device, as listed to administrators
+#[derive(Serialize, Debug)]
+pub struct SessionSummary {
+    pub family_id: String,
+    pub user_agent: Option<String>,
+    /// When the login happened
+    pub started_at: NaiveDateTime,
+    /// When the current refresh token was issued
+    pub last_refreshed_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
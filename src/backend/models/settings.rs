@@ -1,34 +1,24 @@
 // src/backend/models/settings.rs
 
-use serde::{Deserialize, Serialize};
 use diesel::prelude::*;
-use diesel::{Queryable, Insertable, Identifiable, AsChangeset};
+use diesel::{Queryable, Insertable, Identifiable};
 use crate::backend::schema::settings;
 use chrono::NaiveDateTime;
 
-#[derive(Serialize, Queryable, Identifiable, Debug)]
+/// A stored setting. Which keys exist, and what their values mean, is
+/// declared in the settings registry.
+#[derive(Queryable, Identifiable, Debug)]
 #[table_name = "settings"]
-pub struct Settings {
+pub struct Setting {
     pub id: i32,
-    pub site_name: String,
-    pub site_description: String,
-    pub admin_email: String,
-    pub created_at: NaiveDateTime,
-    pub updated_at: NaiveDateTime,
+    pub setting_key: String,
+    pub setting_value: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
 }
 
-#[derive(Deserialize, Insertable)]
+#[derive(Insertable)]
 #[table_name = "settings"]
-pub struct NewSettings {
-    pub site_name: String,
-    pub site_description: String,
-    pub admin_email: String,
-}
-
-#[derive(Deserialize, AsChangeset)]
-#[table_name = "settings"]
-pub struct UpdateSettings {
-    pub site_name: Option<String>,
-    pub site_description: Option<String>,
-    pub admin_email: Option<String>,
+pub struct NewSetting {
+    pub setting_key: String,
+    pub setting_value: Option<String>,
 }
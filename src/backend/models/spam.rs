@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::{Jsonb, Text};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// What a spam filter wants done with a comment, from least to most severe
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SpamOutcome {
+    /// Nothing suspicious
+    Pass,
+    /// Hold for a moderator instead of publishing
+    Hold,
+    /// File straight into the spam queue
+    Spam,
+    /// Refuse the comment without saving it
+    Throttle,
+}
+
+/// One filter's opinion of a comment, kept so moderators can see why it was flagged
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpamVerdict {
+    pub filter: String,
+    pub outcome: SpamOutcome,
+    /// Filter-specific score, e.g. the classifier's spam probability
+    pub score: Option<f64>,
+    pub reason: String,
+}
+
+/// Every verdict recorded for a comment, stored as JSON in `comments.spam_verdicts`
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+#[sql_type = "Jsonb"]
+pub struct SpamVerdicts(pub Vec<SpamVerdict>);
+
+impl ToSql<Jsonb, Pg> for SpamVerdicts {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let value = serde_json::to_value(&self.0)?;
+        <serde_json::Value as ToSql<Jsonb, Pg>>::to_sql(&value, out)
+    }
+}
+
+impl FromSql<Jsonb, Pg> for SpamVerdicts {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as FromSql<Jsonb, Pg>>::from_sql(bytes)?;
+        Ok(SpamVerdicts(serde_json::from_value(value)?))
+    }
+}
+
+/// Class a moderator decision trains the classifier with, stored as text in `comments.spam_trained`
+#[derive(Serialize, Deserialize, AsExpression, FromSqlRow, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum SpamLabel {
+    Spam,
+    Ham,
+}
+
+impl SpamLabel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpamLabel::Spam => "spam",
+            SpamLabel::Ham => "ham",
+        }
+    }
+}
+
+impl fmt::Display for SpamLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SpamLabel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spam" => Ok(SpamLabel::Spam),
+            "ham" => Ok(SpamLabel::Ham),
+            other => Err(format!("Unknown spam label: {}", other)),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for SpamLabel {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for SpamLabel {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
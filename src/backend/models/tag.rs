@@ -1,10 +1,37 @@
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use diesel::prelude::*;
+use diesel::{Queryable, Insertable, Identifiable, AsChangeset};
+use chrono::NaiveDateTime;
 
-#[derive(Serialize, Deserialize, Queryable, Insertable)]
+use crate::backend::schema::{post_tags, tags};
+
+#[derive(Serialize, Queryable, Identifiable, Debug)]
 #[table_name = "tags"]
 pub struct Tag {
     pub id: i32,
     pub name: String,
     pub slug: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "tags"]
+pub struct NewTag {
+    pub name: String,
+    pub slug: String,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "tags"]
+pub struct UpdateTag {
+    pub name: String,
+    pub slug: String,
+}
+
+/// A tag on a post
+#[derive(Queryable, Insertable, Debug)]
+#[table_name = "post_tags"]
+pub struct PostTag {
+    pub post_id: i32,
+    pub tag_id: i32,
 }
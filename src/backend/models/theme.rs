@@ -0,0 +1,71 @@
+// src/backend/models/theme.rs
+
+use serde::Serialize;
+use diesel::prelude::*;
+use diesel::{Queryable, Insertable, Identifiable};
+use chrono::NaiveDateTime;
+
+use crate::backend::schema::{theme_activations, themes};
+
+/// An installed theme package
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name = "themes"]
+pub struct Theme {
+    pub id: i32,
+    pub slug: String,
+    pub name: String,
+    pub version: String,
+    /// The package's `theme.json`, as validated on install
+    pub manifest: serde_json::Value,
+    /// Directory the package was unpacked to
+    pub path: String,
+    pub installed_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "themes"]
+pub struct NewTheme {
+    pub slug: String,
+    pub name: String,
+    pub version: String,
+    pub manifest: serde_json::Value,
+    pub path: String,
+}
+
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "theme_activations"]
+pub struct ThemeActivation {
+    pub id: i32,
+    pub theme_id: i32,
+    pub activated_by: Option<i32>,
+    pub activated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "theme_activations"]
+pub struct NewThemeActivation {
+    pub theme_id: i32,
+    pub activated_by: Option<i32>,
+}
+
+/// A theme as listed by the API
+#[derive(Serialize, Debug)]
+pub struct ThemeSummary {
+    pub id: i32,
+    pub slug: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    /// Views the theme overrides, e.g. `post` or `layout`
+    pub templates: Vec<String>,
+    pub active: bool,
+    pub installed_at: NaiveDateTime,
+}
+
+/// A short-lived link showing the public site with an inactive theme
+#[derive(Serialize, Debug)]
+pub struct ThemePreview {
+    pub token: String,
+    pub url: String,
+    pub expires_in_secs: u64,
+}
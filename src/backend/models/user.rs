@@ -3,7 +3,7 @@ use diesel::prelude::*;
 use diesel::Queryable;
 use chrono::NaiveDateTime;
 
-use crate::backend::schema::users;
+use crate::backend::schema::{recovery_codes, users};
 
 #[derive(Serialize, Queryable, Identifiable)]
 #[table_name = "users"]
@@ -24,3 +24,36 @@ pub struct NewUser {
     pub password_hash: String,
     pub role: String,
 }
+
+/// `POST /users`: an account created by an administrator
+#[derive(Deserialize)]
+pub struct NewUserRequest {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    pub role: String,
+}
+
+/// `PUT /users/:id`; absent fields are left as they are
+#[derive(Deserialize)]
+pub struct UpdateUserRequest {
+    pub email: Option<String>,
+    pub role: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "users"]
+pub struct UserChanges {
+    pub email: Option<String>,
+    pub role: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A one-time code for signing in without the authenticator. Only a hash is stored.
+#[derive(Insertable)]
+#[table_name = "recovery_codes"]
+pub struct NewRecoveryCode {
+    pub user_id: i32,
+    pub code_hash: String,
+}
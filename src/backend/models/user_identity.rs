@@ -0,0 +1,39 @@
+// src/backend/models/user_identity.rs
+
+use diesel::prelude::*;
+use diesel::Insertable;
+use chrono::NaiveDateTime;
+
+use crate::backend::schema::{oidc_login_states, sso_handoffs, user_identities};
+
+/// Links a user to their account at a single sign-on provider
+#[derive(Insertable)]
+#[table_name = "user_identities"]
+pub struct NewUserIdentity {
+    pub user_id: i32,
+    pub provider: String,
+    pub subject: String,
+}
+
+/// What is needed to finish a login once the provider sends the user back
+#[derive(Insertable)]
+#[table_name = "oidc_login_states"]
+pub struct NewOidcLoginState {
+    pub state_hash: String,
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    /// The signed-in user linking the account, when not a login
+    pub link_user_id: Option<i32>,
+    pub expires_at: NaiveDateTime,
+}
+
+/// A finished login the login page can redeem once for a session
+#[derive(Insertable)]
+#[table_name = "sso_handoffs"]
+pub struct NewSsoHandoff {
+    pub code_hash: String,
+    pub user_id: i32,
+    pub provider: String,
+    pub expires_at: NaiveDateTime,
+}
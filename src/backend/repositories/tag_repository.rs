@@ -1,31 +0,0 @@
-use crate::models::tag::Tag;
-use diesel::prelude::*;
-
-pub struct TagRepository;
-
-impl TagRepository {
-    pub fn create(new_tag: Tag) -> Result<Tag, &'static str> {
-        // Implement logic to insert the tag into the database
-        Ok(new_tag)
-    }
-
-    pub fn find_by_id(id: i32) -> Result<Tag, &'static str> {
-        // Implement logic to find a tag by id
-        Err("Tag not found")
-    }
-
-    pub fn update(id: i32, updated_tag: Tag) -> Result<Tag, &'static str> {
-        // Implement logic to update a tag by id
-        Ok(updated_tag)
-    }
-
-    pub fn delete(id: i32) -> Result<(), &'static str> {
-        // Implement logic to delete a tag by id
-        Ok(())
-    }
-
-    pub fn find_all() -> Result<Vec<Tag>, &'static str> {
-        // Implement logic to list all tags
-        Ok(vec![])
-    }
-}
@@ -0,0 +1,265 @@
+// src/backend/services/account_service.rs
+
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::sql_types::Text;
+use thiserror::Error;
+use tracing::{error, info};
+
+use crate::backend::models::account_token::{NewAccountToken, TokenPurpose};
+use crate::backend::models::user::User;
+use crate::backend::schema::{account_tokens, users};
+use crate::backend::services::auth_service::{generate_token, hash_token, AuthService, AuthServiceError};
+use crate::backend::services::mailer::{Email, Mailer, MailerError};
+use crate::backend::services::settings_service::SettingsService;
+use crate::backend::utils::db::DbPool;
+
+/// Shortest password accepted when resetting
+pub const MIN_PASSWORD_CHARS: usize = 8;
+
+const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+const EMAIL_VERIFICATION_TTL_HOURS: i64 = 48;
+
+diesel::sql_function!(fn lower(x: Text) -> Text);
+
+#[derive(Debug, Error)]
+pub enum AccountServiceError {
+    #[error("This link is invalid or has expired")]
+    InvalidToken,
+    #[error("Passwords must be at least {0} characters")]
+    WeakPassword(usize),
+    #[error("Email address is already verified")]
+    AlreadyVerified,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Failed to send email: {0}")]
+    Mail(#[from] MailerError),
+    #[error(transparent)]
+    Auth(#[from] AuthServiceError),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for AccountServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        error!("Database error: {:?}", err);
+        AccountServiceError::DatabaseError(err.to_string())
+    }
+}
+
+/// Password reset and email verification, both done by mailing the user a
+/// single-use link
+pub struct AccountService {
+    db_pool: DbPool,
+    auth_service: Arc<AuthService>,
+    settings_service: Arc<SettingsService>,
+    mailer: Arc<dyn Mailer>,
+    /// Origin the mailed links point at, without a trailing slash
+    site_url: String,
+}
+
+impl AccountService {
+    pub fn new(
+        db_pool: DbPool,
+        auth_service: Arc<AuthService>,
+        settings_service: Arc<SettingsService>,
+        mailer: Arc<dyn Mailer>,
+        site_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            db_pool,
+            auth_service,
+            settings_service,
+            mailer,
+            site_url: site_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Mail a password reset link if an account uses `email`. Succeeds either
+    /// way, and without waiting for the mail to go out, so callers can't learn
+    /// which addresses have accounts.
+    pub async fn request_password_reset(&self, email: &str) -> Result<(), AccountServiceError> {
+        let conn = self.get_connection()?;
+        let user = users::table
+            .filter(lower(users::email).eq(email.trim().to_lowercase()))
+            .select((users::id, users::email))
+            .first::<(i32, String)>(&conn)
+            .optional()?;
+        let (user_id, address) = match user {
+            Some(user) => user,
+            None => {
+                info!("Password reset requested for an unknown address");
+                return Ok(());
+            }
+        };
+
+        // Only the newest link works
+        diesel::update(
+            account_tokens::table
+                .filter(account_tokens::user_id.eq(user_id))
+                .filter(account_tokens::purpose.eq(TokenPurpose::PasswordReset.as_str()))
+                .filter(account_tokens::used_at.is_null()),
+        )
+        .set(account_tokens::used_at.eq(Utc::now().naive_utc()))
+        .execute(&conn)?;
+        let token = insert_token(
+            &conn,
+            user_id,
+            TokenPurpose::PasswordReset,
+            None,
+            Utc::now() + Duration::minutes(PASSWORD_RESET_TTL_MINUTES),
+        )?;
+        drop(conn);
+
+        let site_name = self.site_name().await;
+        let email = Email {
+            to: address,
+            subject: format!("Reset your {} password", site_name),
+            text: format!(
+                "Someone asked to reset the password for your account on {}.\n\n\
+                 To choose a new password, open this link within {} minutes:\n\n{}/login?reset_token={}\n\n\
+                 If it wasn't you, ignore this email; your password won't change.",
+                site_name, PASSWORD_RESET_TTL_MINUTES, self.site_url, token
+            ),
+        };
+        let mailer = self.mailer.clone();
+        tokio::spawn(async move {
+            if let Err(e) = mailer.send(email).await {
+                // Not reported to the caller, which would reveal the account exists
+                error!("Failed to send password reset email: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    /// Set a new password with a reset token and return whose it was. Every
+    /// session of the user ends.
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<User, AccountServiceError> {
+        if new_password.chars().count() < MIN_PASSWORD_CHARS {
+            return Err(AccountServiceError::WeakPassword(MIN_PASSWORD_CHARS));
+        }
+        let conn = self.get_connection()?;
+        let (user_id, _) = consume_token(&conn, token, TokenPurpose::PasswordReset)?;
+        self.auth_service.set_password(user_id, new_password).await?;
+        Ok(users::table.find(user_id).first::<User>(&conn)?)
+    }
+
+    /// Mail a verification link to the user's current email
+    pub async fn send_verification(&self, user_id: i32) -> Result<(), AccountServiceError> {
+        let conn = self.get_connection()?;
+        let (address, verified_at) = users::table
+            .find(user_id)
+            .select((users::email, users::email_verified_at))
+            .first::<(String, Option<NaiveDateTime>)>(&conn)
+            .optional()?
+            .ok_or(AccountServiceError::UserNotFound)?;
+        if verified_at.is_some() {
+            return Err(AccountServiceError::AlreadyVerified);
+        }
+
+        let token = insert_token(
+            &conn,
+            user_id,
+            TokenPurpose::EmailVerification,
+            Some(address.clone()),
+            Utc::now() + Duration::hours(EMAIL_VERIFICATION_TTL_HOURS),
+        )?;
+        drop(conn);
+
+        let site_name = self.site_name().await;
+        self.mailer
+            .send(Email {
+                to: address,
+                subject: format!("Verify your email for {}", site_name),
+                text: format!(
+                    "Confirm this is your email address by opening this link within {} hours:\n\n\
+                     {}/login?verify_token={}\n\n\
+                     If you didn't create an account on {}, ignore this email.",
+                    EMAIL_VERIFICATION_TTL_HOURS, self.site_url, token, site_name
+                ),
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Mark the address a verification token was sent to as verified
+    pub async fn verify_email(&self, token: &str) -> Result<(), AccountServiceError> {
+        let conn = self.get_connection()?;
+        conn.transaction::<_, AccountServiceError, _>(|| {
+            let (user_id, address) = consume_token(&conn, token, TokenPurpose::EmailVerification)?;
+            let verified = diesel::update(
+                users::table
+                    .find(user_id)
+                    .filter(users::email.nullable().eq(address)),
+            )
+            .set(users::email_verified_at.eq(Utc::now().naive_utc()))
+            .execute(&conn)?;
+            // The user changed their email since the link was sent
+            if verified == 0 {
+                return Err(AccountServiceError::InvalidToken);
+            }
+            Ok(())
+        })
+    }
+
+    async fn site_name(&self) -> String {
+        self.settings_service
+            .get_string("site_name")
+            .await
+            .unwrap_or_else(|_| "the site".to_string())
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, AccountServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            AccountServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+/// Store a new token and return it
+fn insert_token(
+    conn: &PgConnection,
+    user_id: i32,
+    purpose: TokenPurpose,
+    email: Option<String>,
+    expires_at: chrono::DateTime<Utc>,
+) -> Result<String, AccountServiceError> {
+    let token = generate_token();
+    diesel::insert_into(account_tokens::table)
+        .values(&NewAccountToken {
+            user_id,
+            purpose: purpose.as_str().to_string(),
+            token_hash: hash_token(&token),
+            email,
+            expires_at: expires_at.naive_utc(),
+        })
+        .execute(conn)?;
+    Ok(token)
+}
+
+/// Mark an unused, unexpired token as used, returning its user and email.
+/// Only one request can consume a token.
+fn consume_token(
+    conn: &PgConnection,
+    token: &str,
+    purpose: TokenPurpose,
+) -> Result<(i32, Option<String>), AccountServiceError> {
+    let now = Utc::now().naive_utc();
+    diesel::update(
+        account_tokens::table
+            .filter(account_tokens::token_hash.eq(hash_token(token)))
+            .filter(account_tokens::purpose.eq(purpose.as_str()))
+            .filter(account_tokens::used_at.is_null())
+            .filter(account_tokens::expires_at.gt(now)),
+    )
+    .set(account_tokens::used_at.eq(now))
+    .returning((account_tokens::user_id, account_tokens::email))
+    .get_result::<(i32, Option<String>)>(conn)
+    .optional()?
+    .ok_or(AccountServiceError::InvalidToken)
+}
@@ -0,0 +1,266 @@
+// src/backend/services/api_key_service.rs
+
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use thiserror::Error;
+use tracing::{error, info};
+
+use crate::backend::middlewares::permission_middleware::AuthUser;
+use crate::backend::models::api_key::{ApiKey, NewApiKey};
+use crate::backend::schema::{api_keys, users};
+use crate::backend::services::auth_service::{generate_token, hash_token};
+use crate::backend::utils::db::DbPool;
+use crate::shared::api_keys::{
+    scopes_allow, ApiAccess, ApiKeyOwner, ApiKeySummary, ApiResource, ApiScope, CreatedApiKey, NewApiKeyRequest,
+};
+use crate::shared::types::UserRole;
+
+/// Start of every key, so the auth layer can tell keys from access tokens
+pub const KEY_PREFIX: &str = "cms_";
+
+/// Characters of a key kept in the clear to identify it
+const DISPLAY_PREFIX_CHARS: usize = 12;
+
+const MAX_NAME_CHARS: usize = 100;
+
+/// `last_used_at` is only written when it is older than this, so busy keys
+/// don't cost a write per request
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+#[derive(Debug, Error)]
+pub enum ApiKeyServiceError {
+    #[error("API key not found")]
+    NotFound,
+    #[error("User {0} not found")]
+    UserNotFound(String),
+    #[error("{0}")]
+    Invalid(String),
+    #[error("Invalid, expired or revoked API key")]
+    InvalidKey,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for ApiKeyServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        error!("Database error: {:?}", err);
+        ApiKeyServiceError::DatabaseError(err.to_string())
+    }
+}
+
+/// Whether a bearer token is an API key rather than an access token
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(KEY_PREFIX)
+}
+
+/// Who a request made with a key acts as, and what the key may touch
+#[derive(Debug, Clone)]
+pub struct ApiKeyPrincipal {
+    pub key_id: i32,
+    pub user: AuthUser,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiKeyPrincipal {
+    pub fn allows(&self, resource: ApiResource, access: ApiAccess) -> bool {
+        scopes_allow(&self.scopes, resource, access)
+    }
+}
+
+pub struct ApiKeyService {
+    db_pool: DbPool,
+}
+
+impl ApiKeyService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Every key, newest first, revoked and expired ones included
+    pub async fn list(&self) -> Result<Vec<ApiKeySummary>, ApiKeyServiceError> {
+        let conn = self.get_connection()?;
+        let keys = api_keys::table
+            .order(api_keys::created_at.desc())
+            .load::<ApiKey>(&conn)?;
+
+        let user_ids: Vec<i32> = keys
+            .iter()
+            .flat_map(|key| std::iter::once(key.user_id).chain(key.created_by))
+            .collect();
+        let usernames: HashMap<i32, String> = users::table
+            .filter(users::id.eq_any(user_ids))
+            .select((users::id, users::username))
+            .load::<(i32, String)>(&conn)?
+            .into_iter()
+            .collect();
+
+        Ok(keys.into_iter().map(|key| summarize(key, &usernames)).collect())
+    }
+
+    /// Create a key for `creator`'s request. The returned secret can't be
+    /// recovered later.
+    pub async fn create(
+        &self,
+        creator: &AuthUser,
+        request: NewApiKeyRequest,
+    ) -> Result<CreatedApiKey, ApiKeyServiceError> {
+        let name = request.name.trim().to_string();
+        if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+            return Err(ApiKeyServiceError::Invalid(format!(
+                "Names must be 1 to {} characters",
+                MAX_NAME_CHARS
+            )));
+        }
+        if request.scopes.is_empty() {
+            return Err(ApiKeyServiceError::Invalid("Choose at least one scope".to_string()));
+        }
+        let mut scopes: Vec<String> = request.scopes.iter().map(ApiScope::to_string).collect();
+        scopes.sort();
+        scopes.dedup();
+
+        let conn = self.get_connection()?;
+        let (user_id, role) = match &request.owner {
+            ApiKeyOwner::Personal { username } => {
+                let user_id = users::table
+                    .filter(users::username.eq(username.trim()))
+                    .select(users::id)
+                    .first::<i32>(&conn)
+                    .optional()?
+                    .ok_or_else(|| ApiKeyServiceError::UserNotFound(username.trim().to_string()))?;
+                (user_id, None)
+            }
+            ApiKeyOwner::Site { role } => {
+                let role = role.parse::<UserRole>().map_err(ApiKeyServiceError::Invalid)?;
+                (creator.id, Some(role.as_str().to_string()))
+            }
+        };
+
+        let secret = format!("{}{}", KEY_PREFIX, generate_token());
+        let key = diesel::insert_into(api_keys::table)
+            .values(&NewApiKey {
+                name,
+                user_id,
+                role,
+                created_by: Some(creator.id),
+                prefix: secret.chars().take(DISPLAY_PREFIX_CHARS).collect(),
+                key_hash: hash_token(&secret),
+                scopes,
+                expires_at: request
+                    .expires_in_days
+                    .map(|days| Utc::now().naive_utc() + Duration::days(i64::from(days))),
+            })
+            .get_result::<ApiKey>(&conn)?;
+        info!("User {} created API key {} ({})", creator.id, key.id, key.prefix);
+
+        let usernames = users::table
+            .filter(users::id.eq_any(vec![key.user_id, creator.id]))
+            .select((users::id, users::username))
+            .load::<(i32, String)>(&conn)?
+            .into_iter()
+            .collect();
+        Ok(CreatedApiKey {
+            key: summarize(key, &usernames),
+            secret,
+        })
+    }
+
+    /// Stop a key from working. Revoking a revoked key does nothing.
+    pub async fn revoke(&self, key_id: i32) -> Result<(), ApiKeyServiceError> {
+        let conn = self.get_connection()?;
+        let revoked = diesel::update(api_keys::table.find(key_id).filter(api_keys::revoked_at.is_null()))
+            .set(api_keys::revoked_at.eq(Utc::now().naive_utc()))
+            .execute(&conn)?;
+        if revoked == 0 {
+            api_keys::table
+                .find(key_id)
+                .select(api_keys::id)
+                .first::<i32>(&conn)
+                .optional()?
+                .ok_or(ApiKeyServiceError::NotFound)?;
+        }
+        Ok(())
+    }
+
+    /// Look up the key presented with a request and note that it was used
+    pub async fn authenticate(&self, secret: &str) -> Result<ApiKeyPrincipal, ApiKeyServiceError> {
+        let conn = self.get_connection()?;
+        let now = Utc::now().naive_utc();
+        let (key_id, key_role, scopes, user_id, username, user_role) = api_keys::table
+            .inner_join(users::table.on(users::id.eq(api_keys::user_id)))
+            .filter(api_keys::key_hash.eq(hash_token(secret)))
+            .filter(api_keys::revoked_at.is_null())
+            .filter(api_keys::expires_at.is_null().or(api_keys::expires_at.gt(now)))
+            .select((
+                api_keys::id,
+                api_keys::role,
+                api_keys::scopes,
+                users::id,
+                users::username,
+                users::role,
+            ))
+            .first::<(i32, Option<String>, Vec<String>, i32, String, String)>(&conn)
+            .optional()?
+            .ok_or(ApiKeyServiceError::InvalidKey)?;
+
+        diesel::update(
+            api_keys::table.find(key_id).filter(
+                api_keys::last_used_at
+                    .is_null()
+                    .or(api_keys::last_used_at.lt(now - Duration::seconds(LAST_USED_RESOLUTION_SECONDS))),
+            ),
+        )
+        .set(api_keys::last_used_at.eq(now))
+        .execute(&conn)?;
+
+        let role = key_role
+            .unwrap_or(user_role)
+            .parse::<UserRole>()
+            .map_err(ApiKeyServiceError::DatabaseError)?;
+        Ok(ApiKeyPrincipal {
+            key_id,
+            user: AuthUser {
+                id: user_id,
+                username,
+                role,
+            },
+            // Scopes that no longer exist are dropped rather than failing the request
+            scopes: scopes.iter().filter_map(|scope| scope.parse().ok()).collect(),
+        })
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, ApiKeyServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            ApiKeyServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+fn summarize(key: ApiKey, usernames: &HashMap<i32, String>) -> ApiKeySummary {
+    let username = |id: i32| usernames.get(&id).cloned().unwrap_or_else(|| format!("#{}", id));
+    ApiKeySummary {
+        id: key.id,
+        name: key.name,
+        prefix: key.prefix,
+        owner: match key.role {
+            Some(role) => ApiKeyOwner::Site { role },
+            None => ApiKeyOwner::Personal {
+                username: username(key.user_id),
+            },
+        },
+        scopes: key.scopes.iter().filter_map(|scope| scope.parse().ok()).collect(),
+        created_by: key.created_by.map(username),
+        expires_at: key.expires_at.map(display_time),
+        last_used_at: key.last_used_at.map(display_time),
+        revoked_at: key.revoked_at.map(display_time),
+        created_at: display_time(key.created_at),
+    }
+}
+
+fn display_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
@@ -0,0 +1,249 @@
+// src/backend/services/audit_service.rs
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+use tracing::error;
+
+use crate::backend::models::audit::{AuditRecord, NewAuditRecord};
+use crate::backend::schema::audit_log;
+use crate::backend::utils::db::DbPool;
+use crate::shared::audit::{AuditEntry, AuditFilter, AuditPage};
+
+pub const DEFAULT_PER_PAGE: i64 = 50;
+pub const MAX_PER_PAGE: i64 = 200;
+/// Deepest page served; keeps the offset far from overflowing
+const MAX_PAGE: i64 = 10_000;
+
+/// Fields whose values never go into the log, at any depth
+const REDACTED_FIELDS: &[&str] = &["password", "password_hash", "key_hash", "secret", "token", "two_factor_secret"];
+
+#[derive(Debug, Error)]
+pub enum AuditServiceError {
+    #[error("{0}")]
+    InvalidFilter(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for AuditServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        error!("Database error: {:?}", err);
+        AuditServiceError::DatabaseError(err.to_string())
+    }
+}
+
+/// Add an entry with a connection the caller already holds
+pub fn record(conn: &PgConnection, entry: &NewAuditRecord) -> QueryResult<()> {
+    diesel::insert_into(audit_log::table)
+        .values(entry)
+        .execute(conn)
+        .map(|_| ())
+}
+
+/// A record as it goes into the log, with secrets taken out
+pub fn snapshot<T: Serialize>(value: &T) -> Value {
+    let mut value = serde_json::to_value(value).unwrap_or(Value::Null);
+    redact(&mut value);
+    value
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) {
+                    *field = Value::from("[redacted]");
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// The fields that differ between two snapshots, as `(before, after)`.
+/// Values that aren't objects are compared whole.
+pub fn changes(before: Value, after: Value) -> (Value, Value) {
+    match (before, after) {
+        (Value::Object(mut before), Value::Object(mut after)) => {
+            let keys: Vec<String> = before.keys().chain(after.keys()).cloned().collect();
+            let mut old = Map::new();
+            let mut new = Map::new();
+            for key in keys {
+                if old.contains_key(&key) || new.contains_key(&key) {
+                    continue;
+                }
+                let was = before.remove(&key).unwrap_or(Value::Null);
+                let is = after.remove(&key).unwrap_or(Value::Null);
+                if was != is {
+                    old.insert(key.clone(), was);
+                    new.insert(key, is);
+                }
+            }
+            (Value::Object(old), Value::Object(new))
+        }
+        (before, after) => (before, after),
+    }
+}
+
+pub struct AuditService {
+    db_pool: DbPool,
+}
+
+impl AuditService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Add an entry. Failures are logged rather than returned, since the
+    /// change being recorded has already been made.
+    pub async fn record(&self, entry: NewAuditRecord) {
+        let result = self
+            .get_connection()
+            .and_then(|conn| record(&conn, &entry).map_err(AuditServiceError::from));
+        if let Err(e) = result {
+            error!("Failed to write audit entry {:?}: {}", entry, e);
+        }
+    }
+
+    /// Entries matching `filter`, newest first
+    pub async fn list(&self, filter: &AuditFilter, page: i64, per_page: i64) -> Result<AuditPage, AuditServiceError> {
+        let page = page.clamp(1, MAX_PAGE);
+        let per_page = per_page.clamp(1, MAX_PER_PAGE);
+        let from = filter.from.as_deref().map(parse_date).transpose()?;
+        // `to` includes the whole day
+        let until = filter
+            .to
+            .as_deref()
+            .map(parse_date)
+            .transpose()?
+            .map(|day| day + Duration::days(1));
+        let conn = self.get_connection()?;
+
+        let filtered = || {
+            let mut query: audit_log::BoxedQuery<Pg> = audit_log::table.into_boxed();
+            if let Some(actor) = filter.actor.as_deref().map(str::trim).filter(|actor| !actor.is_empty()) {
+                query = query.filter(audit_log::actor_name.eq(actor.to_string()));
+            }
+            if let Some(action) = filter.action {
+                query = query.filter(audit_log::action.eq(action.as_str()));
+            }
+            if let Some(entity_type) = filter.entity_type {
+                query = query.filter(audit_log::entity_type.eq(entity_type.as_str()));
+            }
+            if let Some(entity_id) = filter.entity_id.as_deref().map(str::trim).filter(|id| !id.is_empty()) {
+                query = query.filter(audit_log::entity_id.eq(entity_id.to_string()));
+            }
+            if let Some(from) = from {
+                query = query.filter(audit_log::created_at.ge(from));
+            }
+            if let Some(until) = until {
+                query = query.filter(audit_log::created_at.lt(until));
+            }
+            query
+        };
+
+        let total = filtered().count().get_result::<i64>(&conn)?;
+        let items = filtered()
+            .order((audit_log::created_at.desc(), audit_log::id.desc()))
+            .limit(per_page)
+            .offset((page - 1) * per_page)
+            .load::<AuditRecord>(&conn)?
+            .into_iter()
+            .filter_map(entry)
+            .collect();
+
+        Ok(AuditPage {
+            items,
+            total,
+            page,
+            per_page,
+        })
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, AuditServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            AuditServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+fn parse_date(date: &str) -> Result<NaiveDateTime, AuditServiceError> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .ok()
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .ok_or_else(|| AuditServiceError::InvalidFilter(format!("Invalid date: {}", date)))
+}
+
+/// Entries with an action or entity this version doesn't know are skipped
+fn entry(record: AuditRecord) -> Option<AuditEntry> {
+    Some(AuditEntry {
+        id: record.id,
+        actor: record.actor_name,
+        actor_id: record.actor_id,
+        api_key_id: record.api_key_id,
+        ip: record.ip,
+        action: record.action.parse().ok()?,
+        entity_type: record.entity_type.parse().ok()?,
+        entity_id: record.entity_id,
+        before: record.before,
+        after: record.after,
+        created_at: record.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_only_changed_fields() {
+        let (before, after) = changes(
+            json!({"id": 1, "title": "Draft", "status": "draft", "tags": ["a"]}),
+            json!({"id": 1, "title": "Draft", "status": "published", "tags": ["a", "b"], "slug": "draft"}),
+        );
+        assert_eq!(before, json!({"status": "draft", "tags": ["a"], "slug": null}));
+        assert_eq!(after, json!({"status": "published", "tags": ["a", "b"], "slug": "draft"}));
+
+        assert_eq!(changes(json!(1), json!(2)), (json!(1), json!(2)));
+    }
+
+    #[test]
+    fn redacts_secrets_at_any_depth() {
+        #[derive(Serialize)]
+        struct Account {
+            username: &'static str,
+            password_hash: &'static str,
+            keys: Vec<Value>,
+        }
+        let value = snapshot(&Account {
+            username: "alice",
+            password_hash: "$argon2id$...",
+            keys: vec![json!({"name": "ci", "secret": "cms_abc"})],
+        });
+        assert_eq!(
+            value,
+            json!({
+                "username": "alice",
+                "password_hash": "[redacted]",
+                "keys": [{"name": "ci", "secret": "[redacted]"}],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_dates() {
+        assert!(parse_date("2024-10-09").is_ok());
+        assert!(matches!(parse_date("10/09/2024"), Err(AuditServiceError::InvalidFilter(_))));
+    }
+}
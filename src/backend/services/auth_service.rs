@@ -1,13 +1,30 @@
 // src/backend/services/auth_service.rs
 
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
 use argon2::{self, Config};
+use chrono::{Duration, NaiveDateTime, Utc};
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, PooledConnection};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
-use tracing::error;
+use tracing::{error, warn};
+use uuid::Uuid;
 
-use crate::backend::models::user::{NewUser, User};
+use crate::backend::models::session::{NewSession, Session, SessionSummary};
+use crate::backend::models::user::{NewRecoveryCode, NewUser, User};
+use crate::backend::services::login_throttle::{self, Lockout};
+use crate::backend::services::settings_service::SettingsService;
+use crate::backend::services::totp;
+use crate::shared::settings::{SettingDef, SettingGroup, SettingKind};
+use crate::shared::types::UserRole;
+use crate::backend::schema::{recovery_codes, sessions};
 use crate::backend::schema::users::dsl::*;
 use crate::backend::utils::db::DbPool;
 
@@ -19,17 +36,191 @@ pub enum AuthServiceError {
     VerificationError(String),
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("User already exists")]
+    UserAlreadyExists,
     #[error("Database error: {0}")]
     DatabaseError(String),
+    #[error("Token error: {0}")]
+    TokenError(String),
+    #[error("Invalid or expired refresh token")]
+    InvalidRefreshToken,
+    /// A refresh token was presented after it had been rotated; its whole
+    /// family has been revoked
+    #[error("Refresh token reused")]
+    RefreshTokenReused,
+    #[error("Invalid verification code")]
+    InvalidSecondFactor,
+    #[error("Login step expired; sign in again")]
+    InvalidMfaToken,
+    #[error("Two-factor authentication is already enabled")]
+    TwoFactorAlreadyEnabled,
+    #[error("Two-factor setup has not been started")]
+    TwoFactorNotStarted,
+    #[error("Two-factor authentication is required for your role")]
+    TwoFactorRequired,
+    /// Too many recent failed logins for the username or address
+    #[error("Too many failed attempts; try again in {0} seconds")]
+    TooManyAttempts(i64),
+    #[error("User not found")]
+    UserNotFound,
+}
+
+impl From<diesel::result::Error> for AuthServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        error!("Database error: {:?}", err);
+        AuthServiceError::DatabaseError(err.to_string())
+    }
+}
+
+/// Lifetime of an issued access token. Access tokens can't be revoked, so
+/// this bounds how long one outlives its session.
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+
+/// Lifetime of a refresh token; every refresh issues a new one
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Lifetime of the token that carries a login from the password step to the code step
+const MFA_TOKEN_TTL_MINUTES: i64 = 5;
+
+/// Recovery codes issued at a time
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// `settings` key making 2FA mandatory for admins and editors
+pub const TWO_FACTOR_REQUIRED_SETTING: &str = "require_two_factor_for_staff";
+
+/// `settings` key for password login, which can be turned off while single
+/// sign-on is configured
+pub const LOCAL_LOGIN_SETTING: &str = "local_login_enabled";
+
+/// Settings read by authentication
+pub fn settings() -> SettingGroup {
+    SettingGroup {
+        id: "security".to_string(),
+        label: "Security".to_string(),
+        settings: vec![
+            SettingDef::new(
+                TWO_FACTOR_REQUIRED_SETTING,
+                "Require two-factor authentication for admins and editors",
+                SettingKind::Boolean,
+                json!(false),
+            )
+            .description("Admins and editors without two-factor authentication must set it up at their next login."),
+            SettingDef::new(
+                LOCAL_LOGIN_SETTING,
+                "Allow login with a password",
+                SettingKind::Boolean,
+                json!(true),
+            )
+            .description("Turn off to require single sign-on. Only applies while a sign-on provider is configured."),
+        ],
+    }
+}
+
+/// Tokens returned by login and refresh
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+}
+
+impl TokenPair {
+    fn new(access_token: String, refresh_token: String) -> Self {
+        Self {
+            access_token,
+            refresh_token,
+            token_type: "Bearer",
+            expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+        }
+    }
+}
+
+/// What the second login step asks for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecondFactor {
+    /// A code from the user's authenticator, or a recovery code
+    Verify,
+    /// 2FA is required for the user's role but isn't set up yet
+    Enrol,
+}
+
+/// Result of the password step of a login
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    Tokens(TokenPair),
+    SecondFactor {
+        second_factor: SecondFactor,
+        /// Identifies the login in the second step; not an access token
+        mfa_token: String,
+    },
+}
+
+/// Secret shown while setting up an authenticator
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// Whether the user's role must use 2FA
+    pub required: bool,
+}
+
+/// Claims of an `mfa_token`. They lack the `role` of access [`Claims`], so
+/// one can't be used in place of the other.
+#[derive(Debug, Serialize, Deserialize)]
+struct MfaClaims {
+    sub: i32,
+    second_factor: SecondFactor,
+    exp: usize,
+}
+
+/// The `users` columns behind 2FA
+struct TwoFactorState {
+    secret: Option<String>,
+    enabled_at: Option<NaiveDateTime>,
+    last_step: Option<i64>,
+}
+
+/// JWT claims identifying the user and the role their permissions come from
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Claims {
+    pub sub: i32,
+    pub username: String,
+    pub role: UserRole,
+    pub exp: usize,
+}
+
+/// Decode and validate an access token
+fn decode_token(key: &DecodingKey, token: &str) -> Result<Claims, AuthServiceError> {
+    decode::<Claims>(token, key, &Validation::default())
+        .map(|data| data.claims)
+        .map_err(|e| AuthServiceError::TokenError(e.to_string()))
 }
 
 pub struct AuthService {
     db_pool: DbPool,
+    settings_service: Arc<SettingsService>,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
 }
 
 impl AuthService {
-    pub fn new(db_pool: DbPool) -> Self {
-        Self { db_pool }
+    /// `jwt_secret` signs access tokens and login-step tokens
+    pub fn new(db_pool: DbPool, settings_service: Arc<SettingsService>, jwt_secret: &str) -> Self {
+        Self {
+            db_pool,
+            settings_service,
+            encoding_key: EncodingKey::from_secret(jwt_secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(jwt_secret.as_bytes()),
+        }
     }
 
     /// Hash password using Argon2
@@ -68,22 +259,40 @@ impl AuthService {
         diesel::insert_into(users)
             .values(&new_user)
             .get_result::<User>(&conn)
-            .map_err(|e| {
-                error!("Database error: {:?}", e);
-                AuthServiceError::DatabaseError(e.to_string())
+            .map_err(|e| match e {
+                diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation,
+                    _,
+                ) => AuthServiceError::UserAlreadyExists,
+                e => AuthServiceError::from(e),
             })
     }
 
+    /// Replace a user's password and sign them out everywhere
+    pub async fn set_password(&self, user_id: i32, new_password: &str) -> Result<(), AuthServiceError> {
+        let hashed_password = self.hash_password(new_password)?;
+        let conn = self.get_connection()?;
+        let name = diesel::update(users.find(user_id))
+            .set(password_hash.eq(hashed_password))
+            .returning(username)
+            .get_result::<String>(&conn)?;
+        // Guesses at the old password no longer keep the owner out
+        login_throttle::clear_account(&conn, &name)?;
+        drop(conn);
+        self.revoke_all_sessions(user_id).await?;
+        Ok(())
+    }
+
     /// Authenticate a user
-    pub async fn authenticate_user(
+    pub fn authenticate_user(
         &self,
+        conn: &PgConnection,
         username_input: &str,
         password_input: &str,
     ) -> Result<User, AuthServiceError> {
-        let conn = self.get_connection()?;
         let user = users
             .filter(username.eq(username_input))
-            .first::<User>(&conn)
+            .first::<User>(conn)
             .map_err(|_| AuthServiceError::InvalidCredentials)?;
 
         let is_valid = self.verify_password(&user.password_hash, password_input)?;
@@ -94,6 +303,428 @@ impl AuthService {
         }
     }
 
+    /// Issue an access token for an authenticated user
+    pub fn issue_token(&self, user: &User) -> Result<String, AuthServiceError> {
+        let role = user
+            .role
+            .parse::<UserRole>()
+            .map_err(AuthServiceError::TokenError)?;
+        let claims = Claims {
+            sub: user.id,
+            username: user.username.clone(),
+            role,
+            exp: (Utc::now() + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).timestamp() as usize,
+        };
+
+        encode(&Header::default(), &claims, &self.encoding_key)
+            .map_err(|e| AuthServiceError::TokenError(e.to_string()))
+    }
+
+    /// Validate an access token
+    pub fn validate_token(&self, token: &str) -> Result<Claims, AuthServiceError> {
+        decode_token(&self.decoding_key, token)
+    }
+
+    /// Check credentials and start a session. Users with 2FA, and users who
+    /// must set it up first, get an `mfa_token` for the second step instead.
+    ///
+    /// Failures are counted against the username and `ip`; after too many
+    /// the login is refused without checking the password.
+    pub async fn login(
+        &self,
+        username_input: &str,
+        password_input: &str,
+        user_agent: Option<String>,
+        ip: Option<IpAddr>,
+    ) -> Result<LoginOutcome, AuthServiceError> {
+        let user = self.throttled(username_input, ip, |conn| {
+            self.authenticate_user(conn, username_input, password_input)
+        })?;
+        self.begin_session(&user, user_agent).await
+    }
+
+    /// Start a session for a user who has proven who they are, first asking
+    /// for their second factor if they have one, or for them to set one up if
+    /// their role requires it
+    pub async fn begin_session(&self, user: &User, user_agent: Option<String>) -> Result<LoginOutcome, AuthServiceError> {
+        let second_factor = if self.two_factor_enabled(user.id)? {
+            Some(SecondFactor::Verify)
+        } else if self.two_factor_required(&user.role).await? {
+            Some(SecondFactor::Enrol)
+        } else {
+            None
+        };
+
+        match second_factor {
+            Some(second_factor) => Ok(LoginOutcome::SecondFactor {
+                second_factor,
+                mfa_token: issue_mfa_token(&self.encoding_key, user.id, second_factor)?,
+            }),
+            None => self.start_session(user, user_agent).map(LoginOutcome::Tokens),
+        }
+    }
+
+    /// Second login step: check an authenticator or recovery code and start
+    /// the session. Wrong codes count as failed logins.
+    pub async fn verify_second_factor(
+        &self,
+        mfa_token: &str,
+        code: &str,
+        user_agent: Option<String>,
+        ip: Option<IpAddr>,
+    ) -> Result<TokenPair, AuthServiceError> {
+        let user_id = decode_mfa_token(&self.decoding_key, mfa_token, SecondFactor::Verify)?;
+        let user = self.find_user(user_id)?;
+        self.throttled(&user.username, ip, |conn| self.check_second_factor(conn, user_id, code))?;
+        self.start_session(&user, user_agent)
+    }
+
+    /// Make a login attempt unless the username or address is throttled,
+    /// counting wrong passwords and codes as failures. The throttle stays
+    /// locked until the attempt is counted, so concurrent attempts can't
+    /// slip past it. The attempt runs on the connection holding the lock;
+    /// checking out another while it is held could exhaust the pool.
+    fn throttled<T>(
+        &self,
+        username_input: &str,
+        ip: Option<IpAddr>,
+        attempt: impl FnOnce(&PgConnection) -> Result<T, AuthServiceError>,
+    ) -> Result<T, AuthServiceError> {
+        let conn = self.get_connection()?;
+        // The attempt's own result is returned as `Ok`, so a failure still
+        // commits its count
+        conn.transaction::<_, AuthServiceError, _>(|| {
+            if let Some(seconds) = login_throttle::lock(&conn, username_input, ip)? {
+                return Ok(Err(AuthServiceError::TooManyAttempts(seconds)));
+            }
+            let result = attempt(&conn);
+            match &result {
+                Err(AuthServiceError::InvalidCredentials | AuthServiceError::InvalidSecondFactor) => {
+                    login_throttle::record_failure(&conn, username_input, ip)?
+                }
+                Err(_) => {}
+                Ok(_) => login_throttle::release(&conn, username_input, ip)?,
+            }
+            Ok(result)
+        })?
+    }
+
+    /// Usernames and addresses currently locked out by failed logins
+    pub async fn lockouts(&self) -> Result<Vec<Lockout>, AuthServiceError> {
+        let conn = self.get_connection()?;
+        Ok(login_throttle::lockouts(&conn)?)
+    }
+
+    /// Let a user log in again straight away, forgetting their failed
+    /// logins. Returns the username that was unlocked.
+    pub async fn unlock_user(&self, user_id: i32) -> Result<String, AuthServiceError> {
+        let conn = self.get_connection()?;
+        let name = users
+            .find(user_id)
+            .select(username)
+            .first::<String>(&conn)
+            .optional()?
+            .ok_or(AuthServiceError::UserNotFound)?;
+        login_throttle::clear_account(&conn, &name)?;
+        Ok(name)
+    }
+
+    /// Forget the failed logins from an address
+    pub async fn unlock_ip(&self, ip: IpAddr) -> Result<(), AuthServiceError> {
+        let conn = self.get_connection()?;
+        login_throttle::clear_ip(&conn, ip)?;
+        Ok(())
+    }
+
+    /// User an enrolment `mfa_token` was issued to
+    pub fn enrolling_user(&self, mfa_token: &str) -> Result<i32, AuthServiceError> {
+        decode_mfa_token(&self.decoding_key, mfa_token, SecondFactor::Enrol)
+    }
+
+    pub async fn two_factor_status(&self, user_id: i32) -> Result<TwoFactorStatus, AuthServiceError> {
+        let user = self.find_user(user_id)?;
+        let conn = self.get_connection()?;
+        let enabled = two_factor_state(&conn, user_id)?.enabled_at.is_some();
+        Ok(TwoFactorStatus {
+            enabled,
+            required: self.two_factor_required(&user.role).await?,
+        })
+    }
+
+    /// Start setting up an authenticator, replacing any unfinished setup
+    pub async fn begin_two_factor(&self, user_id: i32) -> Result<TwoFactorSetup, AuthServiceError> {
+        let conn = self.get_connection()?;
+        if two_factor_state(&conn, user_id)?.enabled_at.is_some() {
+            return Err(AuthServiceError::TwoFactorAlreadyEnabled);
+        }
+
+        let secret = totp::generate_secret();
+        diesel::update(users.find(user_id))
+            .set((totp_secret.eq(&secret), totp_last_step.eq(None::<i64>)))
+            .execute(&conn)?;
+        let account = users.find(user_id).select(username).first::<String>(&conn)?;
+        drop(conn);
+
+        let issuer = self
+            .settings_service
+            .get_string("site_name")
+            .await
+            .map_err(|e| AuthServiceError::DatabaseError(e.to_string()))?;
+        Ok(TwoFactorSetup {
+            provisioning_uri: totp::provisioning_uri(&issuer, &account, &secret),
+            secret,
+        })
+    }
+
+    /// Finish setup with a code from the new authenticator. Returns recovery
+    /// codes, which are only ever shown now.
+    pub async fn enable_two_factor(&self, user_id: i32, code: &str) -> Result<Vec<String>, AuthServiceError> {
+        let conn = self.get_connection()?;
+        let state = two_factor_state(&conn, user_id)?;
+        if state.enabled_at.is_some() {
+            return Err(AuthServiceError::TwoFactorAlreadyEnabled);
+        }
+        let secret = state.secret.ok_or(AuthServiceError::TwoFactorNotStarted)?;
+        let step = totp::verify(&secret, code, Utc::now().timestamp(), None)
+            .ok_or(AuthServiceError::InvalidSecondFactor)?;
+
+        conn.transaction::<_, AuthServiceError, _>(|| {
+            diesel::update(users.find(user_id))
+                .set((totp_enabled_at.eq(Utc::now().naive_utc()), totp_last_step.eq(step)))
+                .execute(&conn)?;
+            replace_recovery_codes(&conn, user_id)
+        })
+    }
+
+    /// Finish the setup a login required: enable 2FA, then start the session
+    pub async fn complete_enrolment(
+        &self,
+        mfa_token: &str,
+        code: &str,
+        user_agent: Option<String>,
+    ) -> Result<(Vec<String>, TokenPair), AuthServiceError> {
+        let user_id = self.enrolling_user(mfa_token)?;
+        let recovery = self.enable_two_factor(user_id, code).await?;
+        let user = self.find_user(user_id)?;
+        Ok((recovery, self.start_session(&user, user_agent)?))
+    }
+
+    /// Turn 2FA off after checking a code. Refused while the user's role requires it.
+    pub async fn disable_two_factor(&self, user_id: i32, code: &str) -> Result<(), AuthServiceError> {
+        let user = self.find_user(user_id)?;
+        if self.two_factor_required(&user.role).await? {
+            return Err(AuthServiceError::TwoFactorRequired);
+        }
+        let conn = self.get_connection()?;
+        self.check_second_factor(&conn, user_id, code)?;
+        conn.transaction::<_, AuthServiceError, _>(|| {
+            diesel::update(users.find(user_id))
+                .set((
+                    totp_secret.eq(None::<String>),
+                    totp_enabled_at.eq(None::<NaiveDateTime>),
+                    totp_last_step.eq(None::<i64>),
+                ))
+                .execute(&conn)?;
+            diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id))).execute(&conn)?;
+            Ok(())
+        })
+    }
+
+    /// Replace a user's recovery codes after checking a code
+    pub async fn regenerate_recovery_codes(&self, user_id: i32, code: &str) -> Result<Vec<String>, AuthServiceError> {
+        let conn = self.get_connection()?;
+        self.check_second_factor(&conn, user_id, code)?;
+        conn.transaction::<_, AuthServiceError, _>(|| replace_recovery_codes(&conn, user_id))
+    }
+
+    /// Accept a current authenticator code or an unused recovery code
+    fn check_second_factor(&self, conn: &PgConnection, user_id: i32, code: &str) -> Result<(), AuthServiceError> {
+        let state = two_factor_state(conn, user_id)?;
+        let secret = match (state.secret, state.enabled_at) {
+            (Some(secret), Some(_)) => secret,
+            _ => return Err(AuthServiceError::InvalidSecondFactor),
+        };
+
+        if let Some(step) = totp::verify(&secret, code, Utc::now().timestamp(), state.last_step) {
+            // Only one request can move past a step, so a code works once
+            let accepted = diesel::update(
+                users
+                    .find(user_id)
+                    .filter(totp_last_step.is_null().or(totp_last_step.lt(step))),
+            )
+            .set(totp_last_step.eq(step))
+            .execute(conn)?;
+            return if accepted == 1 { Ok(()) } else { Err(AuthServiceError::InvalidSecondFactor) };
+        }
+
+        let redeemed = diesel::update(
+            recovery_codes::table
+                .filter(recovery_codes::user_id.eq(user_id))
+                .filter(recovery_codes::code_hash.eq(hash_recovery_code(code)))
+                .filter(recovery_codes::used_at.is_null()),
+        )
+        .set(recovery_codes::used_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?;
+        if redeemed == 0 {
+            return Err(AuthServiceError::InvalidSecondFactor);
+        }
+        warn!("User {} signed in with a recovery code", user_id);
+        Ok(())
+    }
+
+    /// Whether the site requires 2FA for a role
+    async fn two_factor_required(&self, role_name: &str) -> Result<bool, AuthServiceError> {
+        if !is_staff(role_name) {
+            return Ok(false);
+        }
+        self.settings_service
+            .get_bool(TWO_FACTOR_REQUIRED_SETTING)
+            .await
+            .map_err(|e| AuthServiceError::DatabaseError(e.to_string()))
+    }
+
+    /// Whether a user has finished setting up 2FA
+    pub fn two_factor_enabled(&self, user_id: i32) -> Result<bool, AuthServiceError> {
+        let conn = self.get_connection()?;
+        Ok(two_factor_state(&conn, user_id)?.enabled_at.is_some())
+    }
+
+    fn find_user(&self, user_id: i32) -> Result<User, AuthServiceError> {
+        let conn = self.get_connection()?;
+        Ok(users.find(user_id).first::<User>(&conn)?)
+    }
+
+    /// Issue tokens for a new login, starting a new refresh token family.
+    /// The user's failed logins are forgotten.
+    pub fn start_session(&self, user: &User, user_agent: Option<String>) -> Result<TokenPair, AuthServiceError> {
+        let conn = self.get_connection()?;
+        login_throttle::clear_account(&conn, &user.username)?;
+        let family_id = Uuid::new_v4().to_string();
+        let refresh_token = insert_refresh_token(&conn, user.id, &family_id, user_agent)?;
+        Ok(TokenPair::new(self.issue_token(user)?, refresh_token))
+    }
+
+    /// Exchange a refresh token for a new access token and refresh token.
+    ///
+    /// The presented token can't be used again. If it already has been, someone
+    /// else holds a copy, so every token from the same login is revoked.
+    ///
+    /// While 2FA is required for staff, admins and editors without it are
+    /// refused, so they have to log in again and set it up.
+    pub async fn refresh(&self, refresh_token: &str) -> Result<TokenPair, AuthServiceError> {
+        let staff_need_two_factor = self
+            .settings_service
+            .get_bool(TWO_FACTOR_REQUIRED_SETTING)
+            .await
+            .map_err(|e| AuthServiceError::DatabaseError(e.to_string()))?;
+        let conn = self.get_connection()?;
+        let token_hash = hash_token(refresh_token);
+
+        let rotated = conn.transaction::<_, AuthServiceError, _>(|| {
+            let session = sessions::table
+                .filter(sessions::session_token.eq(&token_hash))
+                .for_update()
+                .first::<Session>(&conn)
+                .optional()?
+                .ok_or(AuthServiceError::InvalidRefreshToken)?;
+
+            if session.revoked_at.is_some() {
+                return Err(AuthServiceError::InvalidRefreshToken);
+            }
+            if session.used_at.is_some() {
+                revoke_family(&conn, &session.family_id)?;
+                return Ok(None);
+            }
+            let now = Utc::now().naive_utc();
+            if session.expires_at <= now {
+                return Err(AuthServiceError::InvalidRefreshToken);
+            }
+
+            let user = users.find(session.user_id).first::<User>(&conn)?;
+            if staff_need_two_factor
+                && is_staff(&user.role)
+                && two_factor_state(&conn, user.id)?.enabled_at.is_none()
+            {
+                return Err(AuthServiceError::TwoFactorRequired);
+            }
+
+            diesel::update(&session)
+                .set(sessions::used_at.eq(now))
+                .execute(&conn)?;
+            let next = insert_refresh_token(&conn, session.user_id, &session.family_id, session.user_agent.clone())?;
+            Ok(Some((user, next)))
+        })?;
+
+        match rotated {
+            Some((user, next)) => Ok(TokenPair::new(self.issue_token(&user)?, next)),
+            None => {
+                warn!("Refresh token reused; revoked its session family");
+                Err(AuthServiceError::RefreshTokenReused)
+            }
+        }
+    }
+
+    /// End the session a refresh token belongs to. Unknown tokens are ignored,
+    /// so logging out twice is harmless.
+    pub async fn logout(&self, refresh_token: &str) -> Result<(), AuthServiceError> {
+        let conn = self.get_connection()?;
+        let family_id = sessions::table
+            .filter(sessions::session_token.eq(hash_token(refresh_token)))
+            .select(sessions::family_id)
+            .first::<String>(&conn)
+            .optional()?;
+        if let Some(family_id) = family_id {
+            revoke_family(&conn, &family_id)?;
+        }
+        Ok(())
+    }
+
+    /// End every session of a user, returning how many logins were ended
+    pub async fn revoke_all_sessions(&self, user_id: i32) -> Result<usize, AuthServiceError> {
+        let conn = self.get_connection()?;
+        let now = Utc::now().naive_utc();
+        let mut families = diesel::update(
+            sessions::table
+                .filter(sessions::user_id.eq(user_id))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set(sessions::revoked_at.eq(now))
+        .returning(sessions::family_id)
+        .get_results::<String>(&conn)?;
+
+        families.sort();
+        families.dedup();
+        Ok(families.len())
+    }
+
+    /// A user's signed-in devices: one entry per login whose current refresh
+    /// token is still usable
+    pub async fn active_sessions(&self, user_id: i32) -> Result<Vec<SessionSummary>, AuthServiceError> {
+        let conn = self.get_connection()?;
+        let rows = sessions::table
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::revoked_at.is_null())
+            .order(sessions::created_at.asc())
+            .load::<Session>(&conn)?;
+
+        let now = Utc::now().naive_utc();
+        let mut started: HashMap<&str, NaiveDateTime> = HashMap::new();
+        for row in &rows {
+            started.entry(row.family_id.as_str()).or_insert(row.created_at);
+        }
+        Ok(rows
+            .iter()
+            .filter(|row| row.used_at.is_none() && row.expires_at > now)
+            .map(|row| SessionSummary {
+                family_id: row.family_id.clone(),
+                user_agent: row.user_agent.clone(),
+                started_at: started[row.family_id.as_str()],
+                last_refreshed_at: row.created_at,
+                expires_at: row.expires_at,
+            })
+            .collect())
+    }
+
     /// Helper function to get a database connection
     fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, AuthServiceError> {
         self.db_pool.get().map_err(|e| {
@@ -102,3 +733,149 @@ impl AuthService {
         })
     }
 }
+
+/// A new random bearer token (refresh, password reset, ...), as handed to the client
+pub(crate) fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// What the database stores for a token from [`generate_token`]
+pub(crate) fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Store a new refresh token in a family and return it
+fn insert_refresh_token(
+    conn: &PgConnection,
+    user_id: i32,
+    family_id: &str,
+    user_agent: Option<String>,
+) -> Result<String, AuthServiceError> {
+    let token = generate_token();
+    diesel::insert_into(sessions::table)
+        .values(&NewSession {
+            user_id,
+            session_token: hash_token(&token),
+            expires_at: (Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS)).naive_utc(),
+            family_id: family_id.to_string(),
+            user_agent,
+        })
+        .execute(conn)?;
+    Ok(token)
+}
+
+/// Admins and editors, the roles the 2FA requirement applies to
+pub fn is_staff(role_name: &str) -> bool {
+    matches!(role_name.parse::<UserRole>(), Ok(UserRole::Admin | UserRole::Editor))
+}
+
+fn two_factor_state(conn: &PgConnection, user_id: i32) -> QueryResult<TwoFactorState> {
+    let (secret, enabled_at, last_step) = users
+        .find(user_id)
+        .select((totp_secret, totp_enabled_at, totp_last_step))
+        .first::<(Option<String>, Option<NaiveDateTime>, Option<i64>)>(conn)?;
+    Ok(TwoFactorState { secret, enabled_at, last_step })
+}
+
+fn issue_mfa_token(key: &EncodingKey, user_id: i32, second_factor: SecondFactor) -> Result<String, AuthServiceError> {
+    let claims = MfaClaims {
+        sub: user_id,
+        second_factor,
+        exp: (Utc::now() + Duration::minutes(MFA_TOKEN_TTL_MINUTES)).timestamp() as usize,
+    };
+    encode(&Header::default(), &claims, key).map_err(|e| AuthServiceError::TokenError(e.to_string()))
+}
+
+/// User an `mfa_token` for the given step was issued to
+fn decode_mfa_token(key: &DecodingKey, token: &str, expected: SecondFactor) -> Result<i32, AuthServiceError> {
+    let claims = decode::<MfaClaims>(token, key, &Validation::default())
+        .map_err(|_| AuthServiceError::InvalidMfaToken)?
+        .claims;
+    if claims.second_factor != expected {
+        return Err(AuthServiceError::InvalidMfaToken);
+    }
+    Ok(claims.sub)
+}
+
+/// A new recovery code, e.g. `3f9a1-07c2e`
+fn generate_recovery_code() -> String {
+    let bytes: [u8; 5] = rand::thread_rng().gen();
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}", &hex[..5], &hex[5..])
+}
+
+/// What `recovery_codes.code_hash` holds; ignores case and separators
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+/// Swap a user's recovery codes for new ones and return them
+fn replace_recovery_codes(conn: &PgConnection, user_id: i32) -> Result<Vec<String>, AuthServiceError> {
+    diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id))).execute(conn)?;
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+    let rows: Vec<NewRecoveryCode> = codes
+        .iter()
+        .map(|code| NewRecoveryCode {
+            user_id,
+            code_hash: hash_recovery_code(code),
+        })
+        .collect();
+    diesel::insert_into(recovery_codes::table).values(&rows).execute(conn)?;
+    Ok(codes)
+}
+
+fn revoke_family(conn: &PgConnection, family_id: &str) -> QueryResult<usize> {
+    diesel::update(
+        sessions::table
+            .filter(sessions::family_id.eq(family_id))
+            .filter(sessions::revoked_at.is_null()),
+    )
+    .set(sessions::revoked_at.eq(Utc::now().naive_utc()))
+    .execute(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_tokens_are_random_and_stored_hashed() {
+        let first = generate_token();
+        let second = generate_token();
+        assert_eq!(first.len(), 64);
+        assert_ne!(first, second);
+
+        let stored = hash_token(&first);
+        assert_eq!(stored, hash_token(&first));
+        assert_ne!(stored, first);
+        assert_eq!(stored.len(), 64);
+    }
+
+    #[test]
+    fn recovery_codes_match_however_they_are_typed() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(hash_recovery_code(&code), hash_recovery_code(&code.to_uppercase().replace('-', " ")));
+        assert_ne!(hash_recovery_code(&code), hash_recovery_code(&generate_recovery_code()));
+    }
+
+    #[test]
+    fn login_steps_are_not_interchangeable() {
+        let encoding_key = EncodingKey::from_secret(b"test-secret");
+        let decoding_key = DecodingKey::from_secret(b"test-secret");
+        let token = issue_mfa_token(&encoding_key, 7, SecondFactor::Enrol).unwrap();
+        assert_eq!(decode_mfa_token(&decoding_key, &token, SecondFactor::Enrol).unwrap(), 7);
+        assert!(matches!(
+            decode_mfa_token(&decoding_key, &token, SecondFactor::Verify),
+            Err(AuthServiceError::InvalidMfaToken)
+        ));
+        // Nor is an mfa_token an access token
+        assert!(decode_token(&decoding_key, &token).is_err());
+    }
+}
@@ -1,146 +1,491 @@
-use serde::{Deserialize, Serialize};
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
-use reqwasm::http::Request;
-use std::fmt;
+// src/backend/services/builder_service.rs
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
 use thiserror::Error;
-use web_sys::console;
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct CMSComponent {
-    pub id: Option<i32>,
-    pub name: String,
-    pub html: String,
-    pub css: String,
-    pub js: String,
-}
+use tracing::{error, warn};
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_name = API_BASE_URL)]
-    static API_BASE_URL: JsValue;
+use crate::backend::models::builder::{
+    BuilderPage, ColumnLayout, Component, ComponentEvent, ComponentStyle, CreatePageData, LibraryKind,
+    NewComponent, NewComponentEvent, NewComponentStyle, NewPageComponent, NewPageSection, PageComponent, PageSection, RowLayout,
+    SectionLayout, UpdatePageData,
+};
+use crate::backend::schema::{component_events, component_styles, components, page_components, page_sections, pages};
+use crate::backend::services::public_service::sanitize_html;
+use crate::backend::utils::db::DbPool;
+use crate::shared::builder::{
+    Action, Breakpoint, BreakpointStyle, Column, DocumentError, EventBinding, Module, ModuleKind, PageDocument, Row, Section,
+    Trigger, DOCUMENT_VERSION,
+};
+
+#[derive(Debug, Error)]
+pub enum BuilderServiceError {
+    #[error("Invalid page data: {0}")]
+    InvalidData(String),
+    #[error("Invalid page document")]
+    InvalidDocument(Vec<DocumentError>),
+    #[error("Page already exists")]
+    PageAlreadyExists,
+    #[error("Page not found")]
+    NotFound,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
 }
 
-fn get_api_base_url() -> String {
-    API_BASE_URL
-        .as_string()
-        .unwrap_or_else(|| "http://localhost:8080".to_string())
+impl From<diesel::result::Error> for BuilderServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => BuilderServiceError::NotFound,
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            ) => BuilderServiceError::PageAlreadyExists,
+            e => {
+                error!("Database error: {:?}", e);
+                BuilderServiceError::DatabaseError(e.to_string())
+            }
+        }
+    }
 }
 
-#[derive(Debug, Error)]
-pub enum BuilderError {
-    #[error("Request error: {0}")]
-    RequestError(String),
-    #[error("Parse error: {0}")]
-    ParseError(String),
-    #[error("Unknown error occurred")]
-    UnknownError,
+/// Stores builder pages, splitting each document across `page_sections`
+/// (section layout), `components` and `page_components` (modules and where
+/// they sit), `component_styles` (module styles, one row per breakpoint) and
+/// `component_events` (module actions, one row per binding)
+pub struct BuilderService {
+    db_pool: DbPool,
 }
 
-impl From<reqwasm::Error> for BuilderError {
-    fn from(err: reqwasm::Error) -> Self {
-        BuilderError::RequestError(err.to_string())
+impl BuilderService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
     }
-}
 
-impl From<JsValue> for BuilderError {
-    fn from(_: JsValue) -> Self {
-        BuilderError::UnknownError
+    /// Create a page from a builder document
+    pub async fn create_page(&self, data: CreatePageData) -> Result<BuilderPage, BuilderServiceError> {
+        if data.title.trim().is_empty() || data.slug.trim().is_empty() {
+            return Err(BuilderServiceError::InvalidData("A title and slug are required".to_string()));
+        }
+        let document = prepare_document(data.document)?;
+
+        let conn = self.get_connection()?;
+        conn.transaction::<_, BuilderServiceError, _>(|| {
+            let slug_taken = diesel::select(diesel::dsl::exists(
+                pages::table.filter(pages::slug.eq(&data.slug)),
+            ))
+            .get_result::<bool>(&conn)?;
+            if slug_taken {
+                return Err(BuilderServiceError::PageAlreadyExists);
+            }
+
+            let (page_id, updated_at) = diesel::insert_into(pages::table)
+                .values((
+                    pages::title.eq(&data.title),
+                    pages::slug.eq(&data.slug),
+                    pages::content.eq(""),
+                    pages::builder_version.eq(DOCUMENT_VERSION as i32),
+                    pages::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .returning((pages::id, pages::updated_at))
+                .get_result::<(i32, Option<NaiveDateTime>)>(&conn)?;
+            save_document(&conn, page_id, &document)?;
+
+            Ok(BuilderPage {
+                id: page_id,
+                title: data.title,
+                slug: data.slug,
+                document,
+                updated_at,
+            })
+        })
+    }
+
+    /// Load a page and its document for editing
+    pub async fn get_page(&self, page_id: i32) -> Result<BuilderPage, BuilderServiceError> {
+        let conn = self.get_connection()?;
+        let (title, slug, updated_at) = pages::table
+            .find(page_id)
+            .select((pages::title, pages::slug, pages::updated_at))
+            .first::<(String, String, Option<NaiveDateTime>)>(&conn)?;
+
+        Ok(BuilderPage {
+            id: page_id,
+            title,
+            slug,
+            document: load_document(&conn, page_id)?,
+            updated_at,
+        })
+    }
+
+    /// Update a page's title, slug and/or document. A new document replaces
+    /// the stored one wholesale.
+    pub async fn update_page(
+        &self,
+        page_id: i32,
+        changes: UpdatePageData,
+    ) -> Result<BuilderPage, BuilderServiceError> {
+        if changes.title.as_deref().map_or(false, |t| t.trim().is_empty())
+            || changes.slug.as_deref().map_or(false, |s| s.trim().is_empty())
+        {
+            return Err(BuilderServiceError::InvalidData("A title and slug are required".to_string()));
+        }
+        let document = changes.document.map(prepare_document).transpose()?;
+
+        let conn = self.get_connection()?;
+        conn.transaction::<_, BuilderServiceError, _>(|| {
+            let (current_title, current_slug) = pages::table
+                .find(page_id)
+                .select((pages::title, pages::slug))
+                .for_update()
+                .first::<(String, String)>(&conn)?;
+
+            let title = changes.title.unwrap_or(current_title);
+            let slug = changes.slug.unwrap_or(current_slug);
+            let updated_at = diesel::update(pages::table.find(page_id))
+                .set((
+                    pages::title.eq(&title),
+                    pages::slug.eq(&slug),
+                    pages::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .returning(pages::updated_at)
+                .get_result::<Option<NaiveDateTime>>(&conn)?;
+
+            let document = match document {
+                Some(document) => {
+                    save_document(&conn, page_id, &document)?;
+                    diesel::update(pages::table.find(page_id))
+                        .set(pages::builder_version.eq(DOCUMENT_VERSION as i32))
+                        .execute(&conn)?;
+                    document
+                }
+                None => load_document(&conn, page_id)?,
+            };
+
+            Ok(BuilderPage {
+                id: page_id,
+                title,
+                slug,
+                document,
+                updated_at,
+            })
+        })
     }
-}
 
-enum HttpMethod {
-    GET,
-    POST,
-    DELETE,
-    PUT,
-    PATCH,
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, BuilderServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            BuilderServiceError::DatabaseError(e.to_string())
+        })
+    }
 }
 
-impl HttpMethod {
-    fn as_str(&self) -> &'static str {
-        match self {
-            HttpMethod::GET => "GET",
-            HttpMethod::POST => "POST",
-            HttpMethod::DELETE => "DELETE",
-            HttpMethod::PUT => "PUT",
-            HttpMethod::PATCH => "PATCH",
+/// Validate a submitted document and sanitize its rich text
+pub(crate) fn prepare_document(mut document: PageDocument) -> Result<PageDocument, BuilderServiceError> {
+    document.validate().map_err(BuilderServiceError::InvalidDocument)?;
+    for module in document.modules_mut() {
+        if let ModuleKind::Text { html } = &mut module.kind {
+            *html = sanitize_html(html);
         }
     }
+    Ok(document)
 }
 
-async fn make_request(
-    method: HttpMethod,
-    url: &str,
-    body: Option<impl Into<JsValue>>,
-) -> Result<reqwasm::Response, BuilderError> {
-    let mut request = Request::new(url).method(method.as_str());
+/// Replace the stored document of a page
+fn save_document(conn: &PgConnection, page_id: i32, document: &PageDocument) -> Result<(), BuilderServiceError> {
+    let block_ids = document.block_ids();
+    if !block_ids.is_empty() {
+        let known: Vec<i32> = components::table
+            .filter(components::id.eq_any(&block_ids))
+            .filter(components::library_kind.eq(LibraryKind::GlobalBlock))
+            .select(components::id)
+            .load(conn)?;
+        if let Some(missing) = block_ids.iter().find(|id| !known.contains(id)) {
+            return Err(BuilderServiceError::InvalidData(format!("Global block {} does not exist", missing)));
+        }
+    }
+
+    // Placements and styles go with their components
+    diesel::delete(components::table.filter(components::page_id.eq(page_id))).execute(conn)?;
+    diesel::delete(page_sections::table.filter(page_sections::page_id.eq(page_id))).execute(conn)?;
+
+    for (section_position, section) in document.sections.iter().enumerate() {
+        let layout = SectionLayout {
+            rows: section
+                .rows
+                .iter()
+                .map(|row| RowLayout {
+                    id: row.id.clone(),
+                    columns: row
+                        .columns
+                        .iter()
+                        .map(|column| ColumnLayout {
+                            id: column.id.clone(),
+                            width: column.width,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        };
+        let content = serde_json::to_string(&layout)
+            .map_err(|e| BuilderServiceError::DatabaseError(e.to_string()))?;
 
-    if let Some(body) = body {
-        request = request
-            .header("Content-Type", "application/json")
-            .body(body);
+        let section_id = diesel::insert_into(page_sections::table)
+            .values(&NewPageSection {
+                page_id,
+                section_name: section.name.clone(),
+                content,
+                section_key: section.id.clone(),
+                position: section_position as i32,
+                block_id: section.block_id,
+            })
+            .returning(page_sections::id)
+            .get_result::<i32>(conn)?;
+
+        for column in section.rows.iter().flat_map(|row| &row.columns) {
+            for (position, module) in column.modules.iter().enumerate() {
+                save_module(conn, page_id, section_id, &column.id, position as i32, module)?;
+            }
+        }
     }
+    Ok(())
+}
 
-    let response = request.send().await.map_err(BuilderError::from)?;
+fn save_module(
+    conn: &PgConnection,
+    page_id: i32,
+    section_id: i32,
+    column_key: &str,
+    position: i32,
+    module: &Module,
+) -> Result<(), BuilderServiceError> {
+    let component_data = serde_json::to_value(&module.kind)
+        .map_err(|e| BuilderServiceError::DatabaseError(e.to_string()))?;
+    let component_id = diesel::insert_into(components::table)
+        .values(&NewComponent {
+            name: module.kind.type_name().to_string(),
+            component_data,
+            page_id: Some(page_id),
+            library_kind: None,
+        })
+        .returning(components::id)
+        .get_result::<i32>(conn)?;
+
+    diesel::insert_into(page_components::table)
+        .values(&NewPageComponent {
+            page_id,
+            component_id,
+            position,
+            section_id,
+            column_key: column_key.to_string(),
+            module_key: module.id.clone(),
+        })
+        .execute(conn)?;
+
+    let desktop = std::iter::once((None, &module.style));
+    let overrides = module.breakpoints.iter().map(|b| (Some(b.breakpoint), &b.style));
+    let rows = desktop
+        .chain(overrides)
+        .filter(|(_, style)| !style.is_empty())
+        .map(|(breakpoint, style)| {
+            let (name, max_width) = breakpoint_columns(breakpoint);
+            Ok(NewComponentStyle {
+                component_id,
+                breakpoint: name.to_string(),
+                max_width,
+                properties: serde_json::to_value(style)
+                    .map_err(|e| BuilderServiceError::DatabaseError(e.to_string()))?,
+            })
+        })
+        .collect::<Result<Vec<_>, BuilderServiceError>>()?;
+    if !rows.is_empty() {
+        diesel::insert_into(component_styles::table)
+            .values(&rows)
+            .execute(conn)?;
+    }
 
-    if response.ok() {
-        Ok(response)
-    } else {
-        Err(BuilderError::RequestError(format!(
-            "Failed with status: {}",
-            response.status()
-        )))
+    let events = module
+        .events
+        .iter()
+        .map(|binding| {
+            Ok(NewComponentEvent {
+                component_id,
+                event_type: binding.trigger.as_str().to_string(),
+                actions: serde_json::to_value(&binding.actions)
+                    .map_err(|e| BuilderServiceError::DatabaseError(e.to_string()))?,
+            })
+        })
+        .collect::<Result<Vec<_>, BuilderServiceError>>()?;
+    if !events.is_empty() {
+        diesel::insert_into(component_events::table)
+            .values(&events)
+            .execute(conn)?;
     }
+    Ok(())
 }
 
-/// Saves a CMS component
-pub async fn save_component(component: CMSComponent) -> Result<(), BuilderError> {
-    let url = format!("{}/api/components", get_api_base_url());
-    let body = serde_json::to_string(&component)
-        .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+/// Rebuild a page's document from its sections, placements, styles and events
+pub fn load_document(conn: &PgConnection, page_id: i32) -> QueryResult<PageDocument> {
+    let sections = page_sections::table
+        .filter(page_sections::page_id.eq(page_id))
+        .order(page_sections::position.asc())
+        .load::<PageSection>(conn)?;
 
-    let result = make_request(HttpMethod::POST, &url, Some(body)).await;
+    let placements = page_components::table
+        .inner_join(components::table)
+        .filter(page_components::page_id.eq(page_id))
+        .order(page_components::position.asc())
+        .load::<(PageComponent, Component)>(conn)?;
 
-    match result {
-        Ok(_) => {
-            console::log_1(&"Component saved successfully!".into());
-            Ok(())
+    let component_ids: Vec<i32> = placements.iter().map(|(_, c)| c.id).collect();
+    let mut styles: HashMap<i32, Vec<ComponentStyle>> = HashMap::new();
+    for style in component_styles::table
+        .filter(component_styles::component_id.eq_any(&component_ids))
+        .load::<ComponentStyle>(conn)?
+    {
+        if let Some(component_id) = style.component_id {
+            styles.entry(component_id).or_default().push(style);
         }
-        Err(e) => {
-            console::error_1(&format!("Failed to save component: {}", e).into());
-            Err(e)
+    }
+    let mut events: HashMap<i32, Vec<ComponentEvent>> = HashMap::new();
+    for event in component_events::table
+        .filter(component_events::component_id.eq_any(&component_ids))
+        .order(component_events::id.asc())
+        .load::<ComponentEvent>(conn)?
+    {
+        if let Some(component_id) = event.component_id {
+            events.entry(component_id).or_default().push(event);
         }
     }
-}
 
-/// Fetches all CMS components
-pub async fn fetch_components() -> Result<Vec<CMSComponent>, BuilderError> {
-    let url = format!("{}/api/components", get_api_base_url());
+    // Modules of each (section, column), already in order
+    let mut modules: HashMap<(i32, String), Vec<Module>> = HashMap::new();
+    for (placement, component) in placements {
+        let (section_id, column_key) = match (placement.section_id, placement.column_key) {
+            (Some(section_id), Some(column_key)) => (section_id, column_key),
+            _ => continue,
+        };
+        let kind = match serde_json::from_value::<ModuleKind>(component.component_data) {
+            Ok(kind) => kind,
+            Err(e) => {
+                warn!("Skipping unreadable component {}: {}", component.id, e);
+                continue;
+            }
+        };
+        let mut module = Module {
+            id: placement.module_key.unwrap_or_else(|| format!("component-{}", component.id)),
+            kind,
+            style: BTreeMap::new(),
+            breakpoints: Vec::new(),
+            events: Vec::new(),
+        };
+        for event in events.remove(&component.id).unwrap_or_default() {
+            let trigger = match Trigger::parse(&event.event_type) {
+                Some(trigger) => trigger,
+                None => {
+                    warn!("Skipping event {} with unknown trigger {}", event.id, event.event_type);
+                    continue;
+                }
+            };
+            match serde_json::from_value::<Vec<Action>>(event.actions) {
+                Ok(actions) => module.events.push(EventBinding { trigger, actions }),
+                Err(e) => warn!("Skipping unreadable event {}: {}", event.id, e),
+            }
+        }
+        for row in styles.remove(&component.id).unwrap_or_default() {
+            let breakpoint = match parse_breakpoint(&row.breakpoint, row.max_width) {
+                Some(breakpoint) => breakpoint,
+                None => {
+                    warn!("Skipping style {} with unknown breakpoint {}", row.id, row.breakpoint);
+                    continue;
+                }
+            };
+            let style = serde_json::from_value::<BTreeMap<String, String>>(row.properties).unwrap_or_default();
+            match breakpoint {
+                None => module.style = style,
+                Some(breakpoint) => module.breakpoints.push(BreakpointStyle { breakpoint, style }),
+            }
+        }
+        module
+            .breakpoints
+            .sort_by(|a, b| b.breakpoint.max_width().cmp(&a.breakpoint.max_width()));
+        modules.entry((section_id, column_key)).or_default().push(module);
+    }
 
-    let response = make_request(HttpMethod::GET, &url, None::<JsValue>).await?;
-    let components = response
-        .json::<Vec<CMSComponent>>()
-        .await
-        .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+    let sections = sections
+        .into_iter()
+        .map(|section| {
+            let layout = section
+                .content
+                .as_deref()
+                .and_then(|content| serde_json::from_str::<SectionLayout>(content).ok())
+                .unwrap_or_default();
+            Section {
+                rows: layout
+                    .rows
+                    .into_iter()
+                    .map(|row| Row {
+                        id: row.id,
+                        columns: row
+                            .columns
+                            .into_iter()
+                            .map(|column| Column {
+                                modules: modules.remove(&(section.id, column.id.clone())).unwrap_or_default(),
+                                id: column.id,
+                                width: column.width,
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+                id: section.section_key,
+                name: section.section_name,
+                block_id: section.block_id,
+            }
+        })
+        .collect();
 
-    Ok(components)
+    Ok(PageDocument {
+        version: DOCUMENT_VERSION,
+        sections,
+    })
 }
 
-/// Deletes a CMS component by ID
-pub async fn delete_component(id: i32) -> Result<(), BuilderError> {
-    let url = format!("{}/api/components/{}", get_api_base_url(), id);
+/// `component_styles` breakpoint name and custom width for a breakpoint;
+/// `None` is desktop
+fn breakpoint_columns(breakpoint: Option<Breakpoint>) -> (&'static str, Option<i32>) {
+    match breakpoint {
+        None => ("desktop", None),
+        Some(Breakpoint::Tablet) => ("tablet", None),
+        Some(Breakpoint::Mobile) => ("mobile", None),
+        Some(Breakpoint::Custom(width)) => ("custom", Some(i32::from(width))),
+    }
+}
 
-    let result = make_request(HttpMethod::DELETE, &url, None::<JsValue>).await;
+/// Inverse of [`breakpoint_columns`]; `None` for values it doesn't write
+fn parse_breakpoint(name: &str, max_width: Option<i32>) -> Option<Option<Breakpoint>> {
+    match (name, max_width) {
+        ("desktop", _) => Some(None),
+        ("tablet", _) => Some(Some(Breakpoint::Tablet)),
+        ("mobile", _) => Some(Some(Breakpoint::Mobile)),
+        ("custom", Some(width)) => u16::try_from(width).ok().map(|w| Some(Breakpoint::Custom(w))),
+        _ => None,
+    }
+}
 
-    match result {
-        Ok(_) => {
-            console::log_1(&"Component deleted successfully!".into());
-            Ok(())
-        }
-        Err(e) => {
-            console::error_1(&format!("Failed to delete component: {}", e).into());
-            Err(e)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breakpoints_round_trip_through_their_columns() {
+        for breakpoint in [None, Some(Breakpoint::Tablet), Some(Breakpoint::Mobile), Some(Breakpoint::Custom(600))] {
+            let (name, max_width) = breakpoint_columns(breakpoint);
+            assert_eq!(parse_breakpoint(name, max_width), Some(breakpoint));
         }
+        assert_eq!(parse_breakpoint("custom", None), None);
+        assert_eq!(parse_breakpoint("watch", None), None);
     }
 }
@@ -1,146 +1,454 @@
-use serde::{Deserialize, Serialize};
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
-use reqwasm::http::Request;
-use std::fmt;
+// src/backend/services/comment_service.rs
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use chrono::Utc;
+use diesel::dsl::count_star;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
 use thiserror::Error;
-use web_sys::console;
-
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub struct Comment {
-    pub id: Option<i32>,
-    pub post_id: i32,
-    pub author: String,
-    pub content: String,
-    pub created_at: String,
-}
+use tracing::error;
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_name = API_BASE_URL)]
-    static API_BASE_URL: JsValue;
-}
+use crate::backend::middlewares::permission_middleware::AuthUser;
+use crate::backend::models::comment::{
+    Comment, CommentNode, CommentPage, CommentStatus, CreateComment, NewComment, StatusCounts,
+    UpdateComment,
+};
+use crate::backend::models::post::PostStatus;
+use crate::backend::models::spam::{SpamLabel, SpamOutcome, SpamVerdicts};
+use crate::backend::schema::{comments, posts};
+use crate::backend::services::spam_filter::{CommentCandidate, SpamPipeline};
+use crate::backend::utils::db::DbPool;
+use crate::shared::types::Capability;
 
-fn get_api_base_url() -> String {
-    API_BASE_URL
-        .as_string()
-        .unwrap_or_else(|| "http://localhost:8080".to_string())
-}
+pub const DEFAULT_PER_PAGE: i64 = 25;
+pub const MAX_PER_PAGE: i64 = 100;
+/// Deepest page served; keeps the offset far from overflowing
+const MAX_PAGE: i64 = 10_000;
+
+/// Longest comment body accepted, in characters
+const MAX_CONTENT_CHARS: usize = 10_000;
+const MAX_AUTHOR_NAME_CHARS: usize = 100;
 
 #[derive(Debug, Error)]
-pub enum CommentError {
-    #[error("Request error: {0}")]
-    RequestError(String),
-    #[error("Parse error: {0}")]
-    ParseError(String),
-    #[error("Unknown error occurred")]
-    UnknownError,
+pub enum CommentServiceError {
+    #[error("Invalid comment data: {0}")]
+    InvalidData(String),
+    #[error("Comment not found")]
+    NotFound,
+    #[error("Comments are closed on this post")]
+    PostUnavailable,
+    #[error("Too many comments, try again later")]
+    RateLimited,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
 }
 
-impl From<reqwasm::Error> for CommentError {
-    fn from(err: reqwasm::Error) -> Self {
-        CommentError::RequestError(err.to_string())
+impl From<diesel::result::Error> for CommentServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => CommentServiceError::NotFound,
+            e => {
+                error!("Database error: {:?}", e);
+                CommentServiceError::DatabaseError(e.to_string())
+            }
+        }
     }
 }
 
-impl From<JsValue> for CommentError {
-    fn from(_: JsValue) -> Self {
-        CommentError::UnknownError
-    }
+/// Narrowing applied to the moderation queue
+#[derive(Debug, Default)]
+pub struct CommentFilter {
+    pub status: Option<CommentStatus>,
+    pub post_id: Option<i32>,
 }
 
-enum HttpMethod {
-    GET,
-    POST,
-    DELETE,
-    PUT,
-    PATCH,
+pub struct CommentService {
+    db_pool: DbPool,
+    spam: Arc<SpamPipeline>,
 }
 
-impl HttpMethod {
-    fn as_str(&self) -> &'static str {
-        match self {
-            HttpMethod::GET => "GET",
-            HttpMethod::POST => "POST",
-            HttpMethod::DELETE => "DELETE",
-            HttpMethod::PUT => "PUT",
-            HttpMethod::PATCH => "PATCH",
+impl CommentService {
+    pub fn new(db_pool: DbPool, spam: Arc<SpamPipeline>) -> Self {
+        Self { db_pool, spam }
+    }
+
+    /// Add a comment or reply to a published post.
+    ///
+    /// Signed-in users are published straight away; anonymous comments wait
+    /// in the moderation queue. Unless the author is a moderator the comment
+    /// goes through the spam filters first, which may hold it, file it as
+    /// spam or refuse it outright, and their verdicts are saved with it.
+    pub async fn create_comment(
+        &self,
+        input: CreateComment,
+        user: Option<&AuthUser>,
+        ip: Option<IpAddr>,
+    ) -> Result<Comment, CommentServiceError> {
+        let content = input.content.trim().to_string();
+        if content.is_empty() || content.chars().count() > MAX_CONTENT_CHARS {
+            return Err(CommentServiceError::InvalidData(format!(
+                "Comments must be between 1 and {} characters",
+                MAX_CONTENT_CHARS
+            )));
+        }
+
+        let (user_id, author_name, author_email, mut status) = match user {
+            Some(user) => (Some(user.id), user.username.clone(), String::new(), CommentStatus::Approved),
+            None => {
+                let name = input.author_name.as_deref().unwrap_or("").trim().to_string();
+                let email = input.author_email.as_deref().unwrap_or("").trim().to_string();
+                if name.is_empty() || name.chars().count() > MAX_AUTHOR_NAME_CHARS {
+                    return Err(CommentServiceError::InvalidData("A name is required".to_string()));
+                }
+                if !is_plausible_email(&email) {
+                    return Err(CommentServiceError::InvalidData("A valid email is required".to_string()));
+                }
+                (None, name, email, CommentStatus::Pending)
+            }
+        };
+
+        let conn = self.get_connection()?;
+        let post_status = posts::table
+            .find(input.post_id)
+            .select(posts::status)
+            .first::<PostStatus>(&conn)
+            .optional()?;
+        if post_status != Some(PostStatus::Published) {
+            return Err(CommentServiceError::PostUnavailable);
         }
+
+        if let Some(parent_id) = input.parent_id {
+            let parent = comments::table
+                .find(parent_id)
+                .first::<Comment>(&conn)
+                .optional()?;
+            match parent {
+                Some(parent) if parent.post_id == input.post_id && parent.status == CommentStatus::Approved => {}
+                _ => {
+                    return Err(CommentServiceError::InvalidData(
+                        "Replies must be to an approved comment on the same post".to_string(),
+                    ))
+                }
+            }
+        }
+
+        let mut spam_verdicts = SpamVerdicts::default();
+        if !user.map_or(false, |user| user.can(Capability::ModerateComments)) {
+            let report = self
+                .spam
+                .check(&CommentCandidate {
+                    content: &content,
+                    author_name: &author_name,
+                    author_email: &author_email,
+                    ip,
+                    honeypot: &input.website,
+                })
+                .await;
+            match report.outcome {
+                SpamOutcome::Throttle => return Err(CommentServiceError::RateLimited),
+                SpamOutcome::Spam => status = CommentStatus::Spam,
+                SpamOutcome::Hold => status = CommentStatus::Pending,
+                SpamOutcome::Pass => {}
+            }
+            spam_verdicts = SpamVerdicts(report.verdicts);
+        }
+
+        let new_comment = NewComment {
+            post_id: input.post_id,
+            user_id,
+            parent_id: input.parent_id,
+            content,
+            status,
+            author_name,
+            author_email,
+            author_ip: ip.map(|ip| ip.to_string()),
+            spam_verdicts,
+        };
+        diesel::insert_into(comments::table)
+            .values(&new_comment)
+            .get_result::<Comment>(&conn)
+            .map_err(CommentServiceError::from)
     }
-}
 
-async fn make_request(
-    method: HttpMethod,
-    url: &str,
-    body: Option<impl Into<JsValue>>,
-) -> Result<reqwasm::Response, CommentError> {
-    let mut request = Request::new(url).method(method.as_str());
+    /// List comments for moderation, newest first, with per-status counts
+    pub async fn list_comments(
+        &self,
+        filter: &CommentFilter,
+        page: i64,
+        per_page: i64,
+    ) -> Result<CommentPage, CommentServiceError> {
+        let page = page.clamp(1, MAX_PAGE);
+        let per_page = per_page.clamp(1, MAX_PER_PAGE);
+        let conn = self.get_connection()?;
+
+        let filtered = || {
+            let mut query: comments::BoxedQuery<Pg> = comments::table.into_boxed();
+            if let Some(status) = filter.status {
+                query = query.filter(comments::status.eq(status));
+            }
+            if let Some(post_id) = filter.post_id {
+                query = query.filter(comments::post_id.eq(post_id));
+            }
+            query
+        };
 
-    if let Some(body) = body {
-        request = request
-            .header("Content-Type", "application/json")
-            .body(body);
+        let total = filtered().count().get_result::<i64>(&conn)?;
+        let items = filtered()
+            .order((comments::created_at.desc(), comments::id.desc()))
+            .limit(per_page)
+            .offset((page - 1) * per_page)
+            .load::<Comment>(&conn)?;
+
+        // Counts for the status tabs ignore the status filter itself
+        let status_counts = match filter.post_id {
+            Some(post_id) => comments::table
+                .filter(comments::post_id.eq(post_id))
+                .group_by(comments::status)
+                .select((comments::status, count_star()))
+                .load::<(CommentStatus, i64)>(&conn)?,
+            None => comments::table
+                .group_by(comments::status)
+                .select((comments::status, count_star()))
+                .load::<(CommentStatus, i64)>(&conn)?,
+        };
+        let mut counts = StatusCounts::default();
+        for (status, count) in status_counts {
+            match status {
+                CommentStatus::Pending => counts.pending = count,
+                CommentStatus::Approved => counts.approved = count,
+                CommentStatus::Spam => counts.spam = count,
+                CommentStatus::Trash => counts.trash = count,
+            }
+        }
+
+        Ok(CommentPage {
+            items,
+            total,
+            page,
+            per_page,
+            counts,
+        })
     }
 
-    let response = request.send().await.map_err(CommentError::from)?;
+    /// Fetch a comment by ID
+    pub async fn get_comment(&self, comment_id: i32) -> Result<Comment, CommentServiceError> {
+        let conn = self.get_connection()?;
+        comments::table
+            .find(comment_id)
+            .first::<Comment>(&conn)
+            .map_err(CommentServiceError::from)
+    }
 
-    if response.ok() {
-        Ok(response)
-    } else {
-        Err(CommentError::RequestError(format!(
-            "Failed with status: {}",
-            response.status()
-        )))
+    /// Fetch the comments with these IDs; missing ones are skipped
+    pub async fn get_comments(&self, comment_ids: &[i32]) -> Result<Vec<Comment>, CommentServiceError> {
+        let conn = self.get_connection()?;
+        comments::table
+            .filter(comments::id.eq_any(comment_ids))
+            .load::<Comment>(&conn)
+            .map_err(CommentServiceError::from)
     }
-}
 
-/// Sends a POST request to save a new comment on the server.
-pub async fn save_comment(comment: Comment) -> Result<(), CommentError> {
-    let url = format!("{}/api/comments", get_api_base_url());
-    let body = serde_json::to_string(&comment)
-        .map_err(|e| CommentError::ParseError(e.to_string()))?;
+    /// Edit a comment's text
+    pub async fn update_comment(
+        &self,
+        comment_id: i32,
+        changes: UpdateComment,
+    ) -> Result<Comment, CommentServiceError> {
+        if let Some(content) = &changes.content {
+            let length = content.trim().chars().count();
+            if length == 0 || length > MAX_CONTENT_CHARS {
+                return Err(CommentServiceError::InvalidData(format!(
+                    "Comments must be between 1 and {} characters",
+                    MAX_CONTENT_CHARS
+                )));
+            }
+        }
+
+        let conn = self.get_connection()?;
+        diesel::update(comments::table.find(comment_id))
+            .set((&changes, comments::updated_at.eq(Utc::now().naive_utc())))
+            .get_result::<Comment>(&conn)
+            .map_err(CommentServiceError::from)
+    }
 
-    let result = make_request(HttpMethod::POST, &url, Some(body)).await;
+    /// Delete a comment permanently, along with its replies
+    pub async fn delete_comment(&self, comment_id: i32) -> Result<(), CommentServiceError> {
+        let conn = self.get_connection()?;
+        let deleted = diesel::delete(comments::table.find(comment_id)).execute(&conn)?;
+        if deleted == 0 {
+            return Err(CommentServiceError::NotFound);
+        }
+        Ok(())
+    }
 
-    match result {
-        Ok(_) => {
-            console::log_1(&"Comment saved successfully!".into());
-            Ok(())
+    /// Move several comments into `status` at once, returning the updated comments.
+    ///
+    /// Approving a comment or marking it as spam trains the spam filters,
+    /// replacing whatever the comment last taught them.
+    pub async fn set_status(
+        &self,
+        comment_ids: &[i32],
+        status: CommentStatus,
+    ) -> Result<Vec<Comment>, CommentServiceError> {
+        if comment_ids.is_empty() {
+            return Ok(Vec::new());
         }
-        Err(e) => {
-            console::error_1(&format!("Failed to save comment: {}", e).into());
-            Err(e)
+
+        let conn = self.get_connection()?;
+        let mut updated = diesel::update(comments::table.filter(comments::id.eq_any(comment_ids)))
+            .set((
+                comments::status.eq(status),
+                comments::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .get_results::<Comment>(&conn)?;
+
+        let label = match status {
+            CommentStatus::Spam => SpamLabel::Spam,
+            CommentStatus::Approved => SpamLabel::Ham,
+            CommentStatus::Pending | CommentStatus::Trash => return Ok(updated),
+        };
+        for comment in updated.iter_mut().filter(|c| c.spam_trained != Some(label)) {
+            let candidate = CommentCandidate {
+                content: &comment.content,
+                author_name: &comment.author_name,
+                author_email: &comment.author_email,
+                ip: comment.author_ip.as_deref().and_then(|ip| ip.parse().ok()),
+                honeypot: "",
+            };
+            self.spam.learn(&candidate, label, comment.spam_trained).await;
+
+            diesel::update(comments::table.find(comment.id))
+                .set(comments::spam_trained.eq(label))
+                .execute(&conn)?;
+            comment.spam_trained = Some(label);
         }
+
+        Ok(updated)
     }
-}
 
-/// Fetches all comments related to a specific post.
-pub async fn fetch_comments(post_id: i32) -> Result<Vec<Comment>, CommentError> {
-    let url = format!("{}/api/comments?post_id={}", get_api_base_url(), post_id);
+    /// The approved comments on a published post, arranged into threads
+    pub async fn thread(&self, post_id: i32) -> Result<Vec<CommentNode>, CommentServiceError> {
+        let conn = self.get_connection()?;
+        let approved = comments::table
+            .inner_join(posts::table)
+            .filter(comments::post_id.eq(post_id))
+            .filter(posts::status.eq(PostStatus::Published))
+            .filter(comments::status.eq(CommentStatus::Approved))
+            .select(comments::all_columns)
+            .order((comments::created_at.asc(), comments::id.asc()))
+            .load::<Comment>(&conn)?;
 
-    let response = make_request(HttpMethod::GET, &url, None::<JsValue>).await?;
-    let comments = response
-        .json::<Vec<Comment>>()
-        .await
-        .map_err(|e| CommentError::ParseError(e.to_string()))?;
+        Ok(build_thread(approved))
+    }
 
-    Ok(comments)
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, CommentServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            CommentServiceError::DatabaseError(e.to_string())
+        })
+    }
 }
 
-/// Deletes a comment by sending a DELETE request to the server.
-pub async fn delete_comment(id: i32) -> Result<(), CommentError> {
-    let url = format!("{}/api/comments/{}", get_api_base_url(), id);
+/// Arrange comments (oldest first) into reply trees. Replies whose parent is
+/// missing from `comments`, e.g. because it was trashed, are left out.
+pub fn build_thread(comments: Vec<Comment>) -> Vec<CommentNode> {
+    let mut children: HashMap<Option<i32>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        children.entry(comment.parent_id).or_default().push(comment);
+    }
+
+    fn attach(parent: Option<i32>, children: &mut HashMap<Option<i32>, Vec<Comment>>) -> Vec<CommentNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|comment| CommentNode {
+                replies: attach(Some(comment.id), children),
+                id: comment.id,
+                author_name: comment.author_name,
+                content: comment.content,
+                created_at: comment.created_at,
+            })
+            .collect()
+    }
 
-    let result = make_request(HttpMethod::DELETE, &url, None::<JsValue>).await;
+    attach(None, &mut children)
+}
 
-    match result {
-        Ok(_) => {
-            console::log_1(&"Comment deleted successfully!".into());
-            Ok(())
+/// Loose sanity check on a commenter's email; it is never sent mail
+fn is_plausible_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.chars().any(char::is_whitespace)
+                && email.len() <= 254
         }
-        Err(e) => {
-            console::error_1(&format!("Failed to delete comment: {}", e).into());
-            Err(e)
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn comment(id: i32, parent_id: Option<i32>) -> Comment {
+        Comment {
+            id,
+            post_id: 1,
+            user_id: None,
+            content: format!("comment {}", id),
+            created_at: NaiveDate::from_ymd_opt(2024, 9, 26)
+                .and_then(|d| d.and_hms_opt(12, 0, id as u32))
+                .unwrap(),
+            updated_at: None,
+            parent_id,
+            status: CommentStatus::Approved,
+            author_name: "reader".to_string(),
+            author_email: "reader@example.com".to_string(),
+            author_ip: None,
+            spam_verdicts: SpamVerdicts::default(),
+            spam_trained: None,
         }
     }
+
+    #[test]
+    fn replies_nest_under_their_parents() {
+        let thread = build_thread(vec![
+            comment(1, None),
+            comment(2, Some(1)),
+            comment(3, None),
+            comment(4, Some(2)),
+        ]);
+
+        assert_eq!(thread.iter().map(|c| c.id).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(thread[0].replies[0].id, 2);
+        assert_eq!(thread[0].replies[0].replies[0].id, 4);
+        assert!(thread[1].replies.is_empty());
+    }
+
+    #[test]
+    fn replies_to_hidden_comments_are_dropped() {
+        // Comment 1 is not approved, so it and its reply stay hidden
+        let thread = build_thread(vec![comment(2, Some(1)), comment(3, None)]);
+        assert_eq!(thread.len(), 1);
+        assert_eq!(thread[0].id, 3);
+    }
+
+    #[test]
+    fn email_check_rejects_obvious_garbage() {
+        assert!(is_plausible_email("reader@example.com"));
+        assert!(!is_plausible_email("reader"));
+        assert!(!is_plausible_email("@example.com"));
+        assert!(!is_plausible_email("reader@localhost"));
+        assert!(!is_plausible_email("read er@example.com"));
+    }
 }
@@ -0,0 +1,262 @@
+// src/backend/services/library_service.rs
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use thiserror::Error;
+use tracing::{error, warn};
+
+use crate::backend::models::builder::{
+    BlockUsage, Component, CreateLibraryItem, LibraryItem, LibraryKind, NewComponent, UpdateLibraryItem,
+};
+use crate::backend::schema::{components, page_sections, pages};
+use crate::backend::services::builder_service::{prepare_document, BuilderServiceError};
+use crate::backend::utils::db::DbPool;
+use crate::shared::builder::{DocumentError, PageDocument, Section};
+
+const MAX_NAME_CHARS: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum LibraryServiceError {
+    #[error("Invalid library item: {0}")]
+    InvalidData(String),
+    #[error("Invalid section")]
+    InvalidSection(Vec<DocumentError>),
+    #[error("Library item not found")]
+    NotFound,
+    #[error("The global block is used by {} page(s)", .0.len())]
+    InUse(Vec<BlockUsage>),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for LibraryServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => LibraryServiceError::NotFound,
+            e => {
+                error!("Database error: {:?}", e);
+                LibraryServiceError::DatabaseError(e.to_string())
+            }
+        }
+    }
+}
+
+/// Global blocks and saved sections, kept in `components` with a
+/// `library_kind` and no page. The item's section is stored as JSON in
+/// `component_data`.
+pub struct LibraryService {
+    db_pool: DbPool,
+}
+
+impl LibraryService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// All library items, optionally of one kind, by name
+    pub async fn list(&self, kind: Option<LibraryKind>) -> Result<Vec<LibraryItem>, LibraryServiceError> {
+        let conn = self.get_connection()?;
+        let mut query = components::table
+            .filter(components::library_kind.is_not_null())
+            .order(components::name.asc())
+            .into_boxed();
+        if let Some(kind) = kind {
+            query = query.filter(components::library_kind.eq(kind));
+        }
+        let rows = query.load::<Component>(&conn)?;
+
+        let ids: Vec<i32> = rows.iter().map(|c| c.id).collect();
+        let mut counts = usage_counts(&conn, &ids)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|component| {
+                let count = counts.remove(&component.id).unwrap_or(0);
+                library_item(component, count)
+                    .map_err(|e| warn!("Skipping unreadable library item: {}", e))
+                    .ok()
+            })
+            .collect())
+    }
+
+    pub async fn get(&self, id: i32) -> Result<LibraryItem, LibraryServiceError> {
+        let conn = self.get_connection()?;
+        let component = find_item(&conn, id)?;
+        let count = usage_counts(&conn, &[id])?.remove(&id).unwrap_or(0);
+        library_item(component, count)
+    }
+
+    pub async fn create(&self, data: CreateLibraryItem) -> Result<LibraryItem, LibraryServiceError> {
+        let name = check_name(&data.name)?;
+        let section = prepare_section(data.section, &name)?;
+        let component_data = serde_json::to_value(&section)
+            .map_err(|e| LibraryServiceError::DatabaseError(e.to_string()))?;
+
+        let conn = self.get_connection()?;
+        let component = diesel::insert_into(components::table)
+            .values(&NewComponent {
+                name,
+                component_data,
+                page_id: None,
+                library_kind: Some(data.kind),
+            })
+            .get_result::<Component>(&conn)?;
+        library_item(component, 0)
+    }
+
+    /// Rename an item and/or replace its section. Pages showing a global
+    /// block pick up the change the next time they render.
+    pub async fn update(&self, id: i32, changes: UpdateLibraryItem) -> Result<LibraryItem, LibraryServiceError> {
+        let conn = self.get_connection()?;
+        let current = library_item(find_item(&conn, id)?, 0)?;
+
+        let name = match changes.name {
+            Some(name) => check_name(&name)?,
+            None => current.name,
+        };
+        let section = prepare_section(changes.section.unwrap_or(current.section), &name)?;
+        let component_data = serde_json::to_value(&section)
+            .map_err(|e| LibraryServiceError::DatabaseError(e.to_string()))?;
+
+        let component = diesel::update(components::table.find(id))
+            .set((
+                components::name.eq(&name),
+                components::component_data.eq(component_data),
+                components::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .get_result::<Component>(&conn)?;
+        let count = usage_counts(&conn, &[id])?.remove(&id).unwrap_or(0);
+        library_item(component, count)
+    }
+
+    /// Delete an item. Global blocks still shown on a page are kept.
+    pub async fn delete(&self, id: i32) -> Result<(), LibraryServiceError> {
+        let conn = self.get_connection()?;
+        conn.transaction::<_, LibraryServiceError, _>(|| {
+            find_item(&conn, id)?;
+            let usage = block_usage(&conn, id)?;
+            if !usage.is_empty() {
+                return Err(LibraryServiceError::InUse(usage));
+            }
+            diesel::delete(components::table.find(id)).execute(&conn)?;
+            Ok(())
+        })
+    }
+
+    /// Pages that show a global block
+    pub async fn usage(&self, id: i32) -> Result<Vec<BlockUsage>, LibraryServiceError> {
+        let conn = self.get_connection()?;
+        find_item(&conn, id)?;
+        Ok(block_usage(&conn, id)?)
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, LibraryServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            LibraryServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+fn find_item(conn: &PgConnection, id: i32) -> QueryResult<Component> {
+    components::table
+        .find(id)
+        .filter(components::library_kind.is_not_null())
+        .first::<Component>(conn)
+}
+
+fn library_item(component: Component, usage_count: i64) -> Result<LibraryItem, LibraryServiceError> {
+    let kind = component.library_kind.ok_or(LibraryServiceError::NotFound)?;
+    let section = serde_json::from_value::<Section>(component.component_data).map_err(|e| {
+        LibraryServiceError::DatabaseError(format!("Unreadable library item {}: {}", component.id, e))
+    })?;
+    Ok(LibraryItem {
+        id: component.id,
+        name: component.name,
+        kind,
+        section,
+        usage_count,
+        updated_at: component.updated_at,
+    })
+}
+
+fn check_name(name: &str) -> Result<String, LibraryServiceError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(LibraryServiceError::InvalidData(format!(
+            "A name of 1 to {} characters is required",
+            MAX_NAME_CHARS
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// Validate and sanitize a section the way a page document is
+fn prepare_section(mut section: Section, name: &str) -> Result<Section, LibraryServiceError> {
+    if section.block_id.is_some() {
+        return Err(LibraryServiceError::InvalidData(
+            "A library item can't contain a global block".to_string(),
+        ));
+    }
+    section.name = name.to_string();
+    let document = PageDocument {
+        sections: vec![section],
+        ..PageDocument::default()
+    };
+    let mut document = prepare_document(document).map_err(|err| match err {
+        BuilderServiceError::InvalidDocument(errors) => LibraryServiceError::InvalidSection(errors),
+        other => LibraryServiceError::InvalidData(other.to_string()),
+    })?;
+    Ok(document.sections.remove(0))
+}
+
+/// Number of pages showing each of the given global blocks
+fn usage_counts(conn: &PgConnection, ids: &[i32]) -> QueryResult<HashMap<i32, i64>> {
+    let placements = page_sections::table
+        .filter(page_sections::block_id.eq_any(ids))
+        .select((page_sections::block_id, page_sections::page_id))
+        .distinct()
+        .load::<(Option<i32>, Option<i32>)>(conn)?;
+
+    let mut counts = HashMap::new();
+    for (block_id, _) in placements {
+        if let Some(block_id) = block_id {
+            *counts.entry(block_id).or_insert(0) += 1;
+        }
+    }
+    Ok(counts)
+}
+
+fn block_usage(conn: &PgConnection, id: i32) -> QueryResult<Vec<BlockUsage>> {
+    pages::table
+        .inner_join(page_sections::table)
+        .filter(page_sections::block_id.eq(id))
+        .select((pages::id, pages::title, pages::slug))
+        .distinct()
+        .order(pages::title.asc())
+        .load::<BlockUsage>(conn)
+}
+
+/// Fill the global block sections of a document from the library, for rendering
+pub fn resolve_global_blocks(conn: &PgConnection, document: &mut PageDocument) -> QueryResult<()> {
+    let ids = document.block_ids();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let blocks: HashMap<i32, Section> = components::table
+        .filter(components::id.eq_any(&ids))
+        .filter(components::library_kind.eq(LibraryKind::GlobalBlock))
+        .load::<Component>(conn)?
+        .into_iter()
+        .filter_map(|component| {
+            serde_json::from_value::<Section>(component.component_data)
+                .map(|section| (component.id, section))
+                .ok()
+        })
+        .collect();
+    document.resolve_blocks(&blocks);
+    Ok(())
+}
@@ -0,0 +1,383 @@
+// src/backend/services/login_throttle.rs
+//
+// Slows down password guessing. Failed logins are counted per attempted
+// username and per client address. Past a few failures each attempt has to
+// wait twice as long as the one before, and enough failures lock the
+// username or address for a while.
+
+use std::net::IpAddr;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::backend::models::audit::NewAuditRecord;
+use crate::backend::models::login_throttle::LoginThrottle;
+use crate::backend::schema::login_throttles;
+use crate::backend::services::audit_service;
+use crate::shared::audit::{AuditAction, AuditEntity};
+
+pub const ACCOUNT_SCOPE: &str = "account";
+pub const IP_SCOPE: &str = "ip";
+
+/// Failures are forgotten this long after the last one
+const FAILURE_WINDOW_MINUTES: i64 = 60;
+
+/// Most expired rows a login attempt deletes on its way
+const SWEEP_BATCH: i64 = 100;
+
+struct Policy {
+    /// Failures allowed before attempts are delayed
+    free_failures: i32,
+    /// Longest delay between attempts
+    max_delay_seconds: i64,
+    /// Failures that lock the username or address
+    lockout_failures: i32,
+    lockout_minutes: i64,
+}
+
+const ACCOUNT_POLICY: Policy = Policy {
+    free_failures: 3,
+    max_delay_seconds: 300,
+    lockout_failures: 10,
+    lockout_minutes: 15,
+};
+
+/// Addresses get more room, since many users can share one
+const IP_POLICY: Policy = Policy {
+    free_failures: 10,
+    max_delay_seconds: 300,
+    lockout_failures: 50,
+    lockout_minutes: 60,
+};
+
+impl Policy {
+    /// How long until another attempt is allowed, if it isn't now
+    fn wait(&self, throttle: &LoginThrottle, now: NaiveDateTime) -> Option<Duration> {
+        if let Some(locked_until) = throttle.locked_until.filter(|until| *until > now) {
+            return Some(locked_until - now);
+        }
+        if is_stale(throttle, now) || throttle.failures < self.free_failures {
+            return None;
+        }
+        let doublings = (throttle.failures - self.free_failures).min(30) as u32;
+        let delay = Duration::seconds((1i64 << doublings).min(self.max_delay_seconds));
+        let next_attempt = throttle.last_failed_at + delay;
+        (next_attempt > now).then(|| next_attempt - now)
+    }
+
+    /// The throttle after another failure, and whether that failure locked it
+    fn fail(
+        &self,
+        scope: &str,
+        subject: &str,
+        previous: Option<LoginThrottle>,
+        now: NaiveDateTime,
+    ) -> (LoginThrottle, bool) {
+        let previous = previous.filter(|throttle| !is_stale(throttle, now));
+        let failures = previous.as_ref().map_or(0, |throttle| throttle.failures) + 1;
+        let locked_until = previous
+            .and_then(|throttle| throttle.locked_until)
+            .filter(|until| *until > now);
+        let newly_locked = locked_until.is_none() && failures >= self.lockout_failures;
+        let throttle = LoginThrottle {
+            scope: scope.to_string(),
+            subject: subject.to_string(),
+            failures,
+            last_failed_at: now,
+            locked_until: if newly_locked {
+                Some(now + Duration::minutes(self.lockout_minutes))
+            } else {
+                locked_until
+            },
+        };
+        (throttle, newly_locked)
+    }
+}
+
+fn is_stale(throttle: &LoginThrottle, now: NaiveDateTime) -> bool {
+    now - throttle.last_failed_at > Duration::minutes(FAILURE_WINDOW_MINUTES)
+}
+
+/// Whether a row no longer affects anything and can be deleted: its
+/// failures are forgotten and it isn't locked
+fn is_expired(throttle: &LoginThrottle, now: NaiveDateTime) -> bool {
+    is_stale(throttle, now) && throttle.locked_until.map_or(true, |until| until <= now)
+}
+
+fn policy(scope: &str) -> &'static Policy {
+    if scope == IP_SCOPE {
+        &IP_POLICY
+    } else {
+        &ACCOUNT_POLICY
+    }
+}
+
+/// Usernames are counted case-insensitively, whether or not they exist
+fn account_subject(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+fn subjects(username: &str, ip: Option<IpAddr>) -> Vec<(&'static str, String)> {
+    std::iter::once((ACCOUNT_SCOPE, account_subject(username)))
+        .chain(ip.map(|ip| (IP_SCOPE, ip.to_string())))
+        .collect()
+}
+
+/// A username or address that can't log in until `locked_until`
+#[derive(Debug, Serialize)]
+pub struct Lockout {
+    pub scope: String,
+    pub subject: String,
+    pub failures: i32,
+    pub locked_until: NaiveDateTime,
+}
+
+/// Lock the failures of `username` and `ip` until the caller's transaction
+/// ends, and return the seconds to wait if an attempt isn't allowed now.
+///
+/// Holding the lock for the whole attempt makes concurrent attempts at the
+/// same username or address take turns, so they can't all pass the check
+/// before any of their failures are counted. Rows are always locked in the
+/// same order, account before address.
+///
+/// Every username and address tried gets a row, so expired rows are swept
+/// first to keep the table from growing without bound.
+pub fn lock(conn: &PgConnection, username: &str, ip: Option<IpAddr>) -> QueryResult<Option<i64>> {
+    let now = Utc::now().naive_utc();
+    sweep(conn, now)?;
+    let mut wait = None;
+    for (scope, subject) in subjects(username, ip) {
+        // Something to lock for a username or address with no failures yet
+        diesel::insert_into(login_throttles::table)
+            .values(&LoginThrottle {
+                scope: scope.to_string(),
+                subject: subject.clone(),
+                failures: 0,
+                last_failed_at: now,
+                locked_until: None,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        let throttle = login_throttles::table
+            .find((scope, &subject))
+            .for_update()
+            .first::<LoginThrottle>(conn)?;
+        if let Some(delay) = policy(scope).wait(&throttle, now) {
+            // Round up so clients retrying on time aren't turned away again
+            let seconds = (delay.num_milliseconds() + 999) / 1000;
+            wait = wait.max(Some(seconds));
+        }
+    }
+    Ok(wait)
+}
+
+/// Delete a batch of expired rows. Rows another login has locked are
+/// skipped rather than waited on; a later sweep gets them.
+fn sweep(conn: &PgConnection, now: NaiveDateTime) -> QueryResult<()> {
+    let candidates = login_throttles::table
+        .filter(login_throttles::last_failed_at.lt(now - Duration::minutes(FAILURE_WINDOW_MINUTES)))
+        .limit(SWEEP_BATCH)
+        .for_update()
+        .skip_locked()
+        .load::<LoginThrottle>(conn)?;
+    for scope in [ACCOUNT_SCOPE, IP_SCOPE] {
+        let expired: Vec<&str> = candidates
+            .iter()
+            .filter(|throttle| throttle.scope == scope && is_expired(throttle, now))
+            .map(|throttle| throttle.subject.as_str())
+            .collect();
+        if !expired.is_empty() {
+            diesel::delete(
+                login_throttles::table
+                    .filter(login_throttles::scope.eq(scope))
+                    .filter(login_throttles::subject.eq_any(expired)),
+            )
+            .execute(conn)?;
+        }
+    }
+    Ok(())
+}
+
+/// Count a failed login, locking the username or address once it has
+/// failed too often. Lockouts go into the audit log. Call it in the same
+/// transaction as [`lock`].
+pub fn record_failure(conn: &PgConnection, username: &str, ip: Option<IpAddr>) -> QueryResult<()> {
+    let now = Utc::now().naive_utc();
+    for (scope, subject) in subjects(username, ip) {
+        let locked = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let previous = login_throttles::table
+                .find((scope, &subject))
+                .for_update()
+                .first::<LoginThrottle>(conn)
+                .optional()?;
+            let (throttle, newly_locked) = policy(scope).fail(scope, &subject, previous, now);
+            diesel::insert_into(login_throttles::table)
+                .values(&throttle)
+                .on_conflict((login_throttles::scope, login_throttles::subject))
+                .do_update()
+                .set(&throttle)
+                .execute(conn)?;
+            Ok(newly_locked.then(|| throttle))
+        })?;
+        if let Some(throttle) = locked {
+            warn!(
+                "Locked login {} {} after {} failures, until {}",
+                throttle.scope, throttle.subject, throttle.failures, throttle.locked_until.unwrap_or(now)
+            );
+            audit_service::record(
+                conn,
+                &NewAuditRecord {
+                    actor_id: None,
+                    actor_name: None,
+                    api_key_id: None,
+                    ip: ip.map(|ip| ip.to_string()),
+                    action: AuditAction::Lockout.as_str().to_string(),
+                    entity_type: AuditEntity::Login.as_str().to_string(),
+                    entity_id: Some(format!("{}:{}", throttle.scope, throttle.subject)),
+                    before: None,
+                    after: Some(serde_json::json!({
+                        "failures": throttle.failures,
+                        "locked_until": throttle.locked_until,
+                    })),
+                },
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Drop the rows [`lock`] added for a username and address that haven't
+/// failed, once the attempt has succeeded
+pub fn release(conn: &PgConnection, username: &str, ip: Option<IpAddr>) -> QueryResult<()> {
+    for (scope, subject) in subjects(username, ip) {
+        diesel::delete(
+            login_throttles::table
+                .find((scope, &subject))
+                .filter(login_throttles::failures.eq(0)),
+        )
+        .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Forget the failures of a username, after it logs in, is unlocked or has
+/// its password reset.
+/// Addresses are only cleared by an admin, so logging in to one account
+/// doesn't reset guessing at others.
+pub fn clear_account(conn: &PgConnection, username: &str) -> QueryResult<usize> {
+    diesel::delete(login_throttles::table.find((ACCOUNT_SCOPE, account_subject(username)))).execute(conn)
+}
+
+pub fn clear_ip(conn: &PgConnection, ip: IpAddr) -> QueryResult<usize> {
+    diesel::delete(login_throttles::table.find((IP_SCOPE, ip.to_string()))).execute(conn)
+}
+
+/// Usernames and addresses locked right now, most recently locked first
+pub fn lockouts(conn: &PgConnection) -> QueryResult<Vec<Lockout>> {
+    let now = Utc::now().naive_utc();
+    let locked = login_throttles::table
+        .filter(login_throttles::locked_until.gt(now))
+        .order(login_throttles::locked_until.desc())
+        .load::<LoginThrottle>(conn)?;
+    Ok(locked
+        .into_iter()
+        .filter_map(|throttle| {
+            Some(Lockout {
+                locked_until: throttle.locked_until?,
+                scope: throttle.scope,
+                subject: throttle.subject,
+                failures: throttle.failures,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64) -> NaiveDateTime {
+        NaiveDateTime::from_timestamp_opt(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn fail_times(policy: &Policy, times: &[i64]) -> (Option<LoginThrottle>, bool) {
+        let mut throttle = None;
+        let mut locked = false;
+        for &time in times {
+            let (next, newly_locked) = policy.fail(ACCOUNT_SCOPE, "alice", throttle, at(time));
+            throttle = Some(next);
+            locked = newly_locked;
+        }
+        (throttle, locked)
+    }
+
+    #[test]
+    fn delays_double_after_the_free_failures() {
+        let (throttle, _) = fail_times(&ACCOUNT_POLICY, &[0, 0, 0]);
+        let throttle = throttle.unwrap();
+        assert_eq!(ACCOUNT_POLICY.wait(&throttle, at(0)), Some(Duration::seconds(1)));
+        assert_eq!(ACCOUNT_POLICY.wait(&throttle, at(1)), None);
+
+        let (throttle, _) = fail_times(&ACCOUNT_POLICY, &[0, 0, 0, 1, 3]);
+        assert_eq!(ACCOUNT_POLICY.wait(&throttle.unwrap(), at(3)), Some(Duration::seconds(4)));
+
+        let (throttle, _) = fail_times(&ACCOUNT_POLICY, &[0, 0]);
+        assert_eq!(ACCOUNT_POLICY.wait(&throttle.unwrap(), at(0)), None);
+    }
+
+    #[test]
+    fn locks_once_at_the_threshold() {
+        let times: Vec<i64> = (0..10).map(|n| n * 600).collect();
+        let (throttle, locked) = fail_times(&ACCOUNT_POLICY, &times[..9]);
+        assert!(!locked);
+        assert_eq!(throttle.unwrap().locked_until, None);
+
+        let (throttle, locked) = fail_times(&ACCOUNT_POLICY, &times);
+        let throttle = throttle.unwrap();
+        assert!(locked);
+        assert_eq!(throttle.locked_until, Some(at(5400) + Duration::minutes(15)));
+        assert_eq!(ACCOUNT_POLICY.wait(&throttle, at(5400)), Some(Duration::minutes(15)));
+
+        // A failure during the lockout doesn't start another
+        let (again, locked) = ACCOUNT_POLICY.fail(ACCOUNT_SCOPE, "alice", Some(throttle.clone()), at(5460));
+        assert!(!locked);
+        assert_eq!(again.locked_until, throttle.locked_until);
+    }
+
+    #[test]
+    fn forgets_old_failures() {
+        let (throttle, _) = fail_times(&ACCOUNT_POLICY, &[0, 0, 0, 0, 0]);
+        let throttle = throttle.unwrap();
+        let later = at(FAILURE_WINDOW_MINUTES * 60 + 1);
+        assert_eq!(ACCOUNT_POLICY.wait(&throttle, later), None);
+        let (next, _) = ACCOUNT_POLICY.fail(ACCOUNT_SCOPE, "alice", Some(throttle), later);
+        assert_eq!(next.failures, 1);
+    }
+
+    #[test]
+    fn expires_rows_once_forgotten_and_unlocked() {
+        let window = FAILURE_WINDOW_MINUTES * 60;
+        let (throttle, _) = fail_times(&ACCOUNT_POLICY, &[0]);
+        let throttle = throttle.unwrap();
+        assert!(!is_expired(&throttle, at(window)));
+        assert!(is_expired(&throttle, at(window + 1)));
+
+        // A lockout outlasting the window keeps the row until it ends
+        let locked = LoginThrottle {
+            locked_until: Some(at(window + 600)),
+            ..throttle
+        };
+        assert!(!is_expired(&locked, at(window + 1)));
+        assert!(is_expired(&locked, at(window + 600)));
+    }
+
+    #[test]
+    fn counts_usernames_case_insensitively() {
+        assert_eq!(
+            subjects(" Alice ", Some("203.0.113.9".parse().unwrap())),
+            vec![(ACCOUNT_SCOPE, "alice".to_string()), (IP_SCOPE, "203.0.113.9".to_string())]
+        );
+    }
+}
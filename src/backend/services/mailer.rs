@@ -0,0 +1,183 @@
+// src/backend/services/mailer.rs
+
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use thiserror::Error;
+use tokio::fs;
+use tracing::info;
+use uuid::Uuid;
+
+#[derive(Debug, Error)]
+pub enum MailerError {
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Failed to build message: {0}")]
+    Message(String),
+    #[error("SMTP error: {0}")]
+    Smtp(String),
+    #[error("Mail is not configured: {0}")]
+    Config(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// A plain-text message to one recipient
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+}
+
+/// Backend that delivers the site's emails
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), MailerError>;
+}
+
+/// Delivers mail through an SMTP relay, upgrading the connection with STARTTLS
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, String)>,
+        from: &str,
+    ) -> Result<Self, MailerError> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .map_err(|e| MailerError::Smtp(e.to_string()))?
+            .port(port);
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        let from = from
+            .parse::<Mailbox>()
+            .map_err(|e| MailerError::InvalidAddress(e.to_string()))?;
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), MailerError> {
+        let to = email
+            .to
+            .parse::<Mailbox>()
+            .map_err(|e| MailerError::InvalidAddress(e.to_string()))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.text)
+            .map_err(|e| MailerError::Message(e.to_string()))?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| MailerError::Smtp(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Writes each message to a file in a directory instead of sending it, for
+/// development and tests
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), MailerError> {
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            Uuid::new_v4().simple()
+        ));
+        let contents = format!("To: {}\r\nSubject: {}\r\n\r\n{}\r\n", email.to, email.subject, email.text);
+        fs::write(&path, contents).await?;
+        info!("Wrote email \"{}\" for {} to {}", email.subject, email.to, path.display());
+        Ok(())
+    }
+}
+
+/// The mailer configured by the environment: SMTP when `MAIL_TRANSPORT=smtp`,
+/// files under `MAIL_DIR` when `MAIL_TRANSPORT=file`. There is no default, so
+/// a production server can't quietly write reset links to disk.
+pub fn mailer_from_env() -> Result<Arc<dyn Mailer>, MailerError> {
+    match env::var("MAIL_TRANSPORT").as_deref() {
+        Ok("smtp") => {}
+        Ok("file") => {
+            let dir = env::var("MAIL_DIR").unwrap_or_else(|_| "mail".to_string());
+            return Ok(Arc::new(FileMailer::new(dir)));
+        }
+        Ok(other) => {
+            return Err(MailerError::Config(format!(
+                "unknown MAIL_TRANSPORT {:?}; use \"smtp\" or \"file\"",
+                other
+            )))
+        }
+        Err(_) => {
+            return Err(MailerError::Config(
+                "set MAIL_TRANSPORT to \"smtp\" or, for development, \"file\"".to_string(),
+            ))
+        }
+    }
+
+    let host = env::var("SMTP_HOST").map_err(|_| MailerError::Smtp("SMTP_HOST must be set".to_string()))?;
+    let port = env::var("SMTP_PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(587);
+    let credentials = env::var("SMTP_USERNAME")
+        .ok()
+        .zip(env::var("SMTP_PASSWORD").ok());
+    let from = env::var("MAIL_FROM").unwrap_or_else(|_| "no-reply@localhost".to_string());
+    Ok(Arc::new(SmtpMailer::new(&host, port, credentials, &from)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_mailer_writes_one_file_per_message() {
+        let dir = std::env::temp_dir().join(format!("mailer-test-{}", Uuid::new_v4().simple()));
+        let mailer = FileMailer::new(&dir);
+        mailer
+            .send(Email {
+                to: "ada@example.com".to_string(),
+                subject: "Hello".to_string(),
+                text: "First".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let mut entries = std::fs::read_dir(&dir).unwrap();
+        let written = std::fs::read_to_string(entries.next().unwrap().unwrap().path()).unwrap();
+        assert!(entries.next().is_none());
+        assert!(written.starts_with("To: ada@example.com\r\nSubject: Hello\r\n\r\nFirst"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
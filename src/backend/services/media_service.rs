@@ -1,150 +1,315 @@
-use serde::{Deserialize, Serialize};
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
-use web_sys::{console, File, FormData};
-use yew::Callback;
-use reqwasm::http::Request;
-use std::fmt;
+// src/backend/services/media_service.rs
+
+use std::env;
+use std::sync::Arc;
+
+use bytes::{Bytes, BytesMut};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use futures::{stream, StreamExt};
 use thiserror::Error;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::backend::models::media::{Media, MediaItem, MediaRendition, NewMedia, RenditionStatus};
+use crate::backend::schema::{media, media_renditions};
+use crate::backend::services::media_storage::{ByteStream, MediaStorage, StorageError, StoredObject};
+use crate::backend::services::rendition_service::RENDERABLE_TYPES;
+use crate::backend::utils::db::DbPool;
+
+/// Upload size limit when `MEDIA_MAX_UPLOAD_BYTES` is unset (25 MiB)
+const DEFAULT_MAX_UPLOAD_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Bytes buffered from the start of an upload to detect its real type
+const SNIFF_BYTES: usize = 512;
+
+/// MIME types accepted into the library, with the extension used for storage keys.
+/// SVG is deliberately absent: it can carry script.
+const ALLOWED_TYPES: &[(&str, &str)] = &[
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+    ("image/avif", "avif"),
+    ("video/mp4", "mp4"),
+    ("video/webm", "webm"),
+    ("audio/mpeg", "mp3"),
+    ("audio/ogg", "ogg"),
+    ("application/pdf", "pdf"),
+];
+
+/// Public URL prefix under which stored files are served
+pub const MEDIA_URL_PREFIX: &str = "/uploads";
 
-/// Define custom error types for media operations
 #[derive(Debug, Error)]
-pub enum MediaError {
-    #[error("Request error: {0}")]
-    RequestError(String),
-    #[error("Parse error: {0}")]
-    ParseError(String),
-    #[error("Unknown error occurred")]
-    UnknownError,
+pub enum MediaServiceError {
+    #[error("Invalid media data")]
+    InvalidData,
+    #[error("Media not found")]
+    NotFound,
+    #[error("File exceeds the {0} byte limit")]
+    TooLarge(u64),
+    #[error("Unsupported media type: {0}")]
+    UnsupportedType(String),
+    #[error("Storage error: {0}")]
+    StorageError(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
 }
 
-impl From<reqwasm::Error> for MediaError {
-    fn from(err: reqwasm::Error) -> Self {
-        MediaError::RequestError(format!("{:?}", err))
+impl From<diesel::result::Error> for MediaServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => MediaServiceError::NotFound,
+            e => {
+                error!("Database error: {:?}", e);
+                MediaServiceError::DatabaseError(e.to_string())
+            }
+        }
     }
 }
 
-impl From<JsValue> for MediaError {
-    fn from(_: JsValue) -> Self {
-        MediaError::UnknownError
+impl From<StorageError> for MediaServiceError {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::NotFound => MediaServiceError::NotFound,
+            StorageError::TooLarge(limit) => MediaServiceError::TooLarge(limit),
+            e => {
+                error!("Storage error: {:?}", e);
+                MediaServiceError::StorageError(e.to_string())
+            }
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub struct Media {
-    pub id: Option<i32>,
-    pub file_name: String,
-    pub file_url: String,
-    pub mime_type: String,
-    pub uploaded_at: String,
+pub struct MediaService {
+    db_pool: DbPool,
+    storage: Arc<dyn MediaStorage>,
+    max_upload_bytes: u64,
 }
 
-/// Retrieves the API base URL from a global JavaScript variable.
-/// Make sure to define `API_BASE_URL` in your JavaScript code.
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_name = API_BASE_URL)]
-    static API_BASE_URL: JsValue;
-}
+impl MediaService {
+    pub fn new(db_pool: DbPool, storage: Arc<dyn MediaStorage>) -> Self {
+        let max_upload_bytes = env::var("MEDIA_MAX_UPLOAD_BYTES")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES);
 
-fn get_api_base_url() -> String {
-    API_BASE_URL.as_string().unwrap_or_else(|| "http://localhost:8080".to_string())
-}
+        Self {
+            db_pool,
+            storage,
+            max_upload_bytes,
+        }
+    }
 
-/// Enum representing HTTP methods
-enum HttpMethod {
-    GET,
-    POST,
-    DELETE,
-    PUT,
-    PATCH,
-}
+    pub fn max_upload_bytes(&self) -> u64 {
+        self.max_upload_bytes
+    }
+
+    /// Stream an upload into storage and record it in the media library.
+    ///
+    /// The type is sniffed from the file's leading bytes; the client's
+    /// declared content type and file extension are not trusted. Images are
+    /// queued for the rendition worker.
+    pub async fn upload_media<'a>(
+        &self,
+        user_id: i32,
+        file_name: &str,
+        mut body: ByteStream<'a>,
+    ) -> Result<MediaItem, MediaServiceError> {
+        let file_name = sanitize_file_name(file_name).ok_or(MediaServiceError::InvalidData)?;
 
-impl HttpMethod {
-    fn as_str(&self) -> &'static str {
-        match self {
-            HttpMethod::GET => "GET",
-            HttpMethod::POST => "POST",
-            HttpMethod::DELETE => "DELETE",
-            HttpMethod::PUT => "PUT",
-            HttpMethod::PATCH => "PATCH",
+        // Buffer just enough of the stream to identify the file type
+        let mut head = BytesMut::new();
+        while head.len() < SNIFF_BYTES {
+            match body.next().await {
+                Some(chunk) => head.extend_from_slice(&chunk?),
+                None => break,
+            }
+        }
+        if head.is_empty() {
+            return Err(MediaServiceError::InvalidData);
         }
-    }
-}
 
-/// Makes a request to the API
-async fn make_request(
-    method: HttpMethod,
-    url: &str,
-    body: Option<impl Into<JsValue>>,
-) -> Result<reqwasm::Response, MediaError> {
-    let mut request = Request::new(url).method(method.as_str());
+        let mime = infer::get(&head)
+            .map(|kind| kind.mime_type())
+            .unwrap_or("application/octet-stream");
+        let extension = ALLOWED_TYPES
+            .iter()
+            .find(|(allowed, _)| *allowed == mime)
+            .map(|(_, ext)| *ext)
+            .ok_or_else(|| MediaServiceError::UnsupportedType(mime.to_string()))?;
 
-    if let Some(body) = body {
-        request = request.body(body);
-    }
+        let now = Utc::now();
+        let storage_key = format!("{}/{}.{}", now.format("%Y/%m"), Uuid::new_v4(), extension);
 
-    let response = request.send().await.map_err(MediaError::from)?;
+        // Re-assemble the stream and cut it off as soon as it passes the limit
+        let limit = self.max_upload_bytes;
+        let mut seen = 0u64;
+        let limited: ByteStream<'a> = Box::pin(
+            stream::once(async move { Ok::<Bytes, StorageError>(head.freeze()) })
+                .chain(body)
+                .map(move |chunk| {
+                    let chunk = chunk?;
+                    seen += chunk.len() as u64;
+                    if seen > limit {
+                        Err(StorageError::TooLarge(limit))
+                    } else {
+                        Ok(chunk)
+                    }
+                }),
+        );
 
-    if response.ok() {
-        Ok(response)
-    } else {
-        Err(MediaError::RequestError(format!(
-            "Failed with status: {}",
-            response.status()
-        )))
+        let file_size = self.storage.put(&storage_key, limited).await?;
+
+        let new_media = NewMedia {
+            file_name,
+            url: format!("{}/{}", MEDIA_URL_PREFIX, storage_key),
+            media_type: Some(mime.to_string()),
+            user_id: Some(user_id),
+            storage_key: storage_key.clone(),
+            file_size: file_size as i64,
+            alt_text: String::new(),
+            renditions_status: if RENDERABLE_TYPES.contains(&mime) {
+                RenditionStatus::Pending
+            } else {
+                RenditionStatus::None
+            },
+        };
+
+        let conn = self.get_connection()?;
+        match diesel::insert_into(media::table)
+            .values(&new_media)
+            .get_result::<Media>(&conn)
+        {
+            Ok(media) => Ok(MediaItem::new(media, Vec::new())),
+            Err(e) => {
+                // Don't leave an orphaned file behind when the row can't be written
+                if let Err(cleanup) = self.storage.delete(&storage_key).await {
+                    error!("Failed to remove orphaned upload {}: {:?}", storage_key, cleanup);
+                }
+                Err(MediaServiceError::from(e))
+            }
+        }
     }
-}
 
-/// Uploads new media using FormData
-pub async fn upload_media(file: File) -> Result<(), MediaError> {
-    let url = format!("{}/api/media", get_api_base_url());
+    /// List the media library, newest first, with each item's renditions
+    pub async fn list_media(&self) -> Result<Vec<MediaItem>, MediaServiceError> {
+        let conn = self.get_connection()?;
+        let items = media::table
+            .order(media::uploaded_at.desc())
+            .load::<Media>(&conn)?;
+        let renditions = MediaRendition::belonging_to(&items)
+            .load::<MediaRendition>(&conn)?
+            .grouped_by(&items);
 
-    let form_data = FormData::new().map_err(|_| MediaError::UnknownError)?;
-    form_data
-        .append_with_blob("file", &file)
-        .map_err(|_| MediaError::UnknownError)?;
+        Ok(items
+            .into_iter()
+            .zip(renditions)
+            .map(|(item, renditions)| MediaItem::new(item, renditions))
+            .collect())
+    }
 
-    let result = make_request(HttpMethod::POST, &url, Some(form_data)).await;
+    /// Fetch a media record by ID, with its renditions
+    pub async fn get_media(&self, media_id: i32) -> Result<MediaItem, MediaServiceError> {
+        let conn = self.get_connection()?;
+        let item = media::table.find(media_id).first::<Media>(&conn)?;
+        let renditions = MediaRendition::belonging_to(&item).load::<MediaRendition>(&conn)?;
+        Ok(MediaItem::new(item, renditions))
+    }
 
-    match result {
-        Ok(_) => {
-            console::log_1(&"Media uploaded successfully!".into());
-            Ok(())
+    /// Queue an image's renditions to be generated again
+    pub async fn regenerate_renditions(&self, media_id: i32) -> Result<MediaItem, MediaServiceError> {
+        let conn = self.get_connection()?;
+        let item = media::table.find(media_id).first::<Media>(&conn)?;
+        let renderable = item
+            .media_type
+            .as_deref()
+            .map_or(false, |mime| RENDERABLE_TYPES.contains(&mime));
+        if !renderable {
+            return Err(MediaServiceError::UnsupportedType(
+                item.media_type.unwrap_or_default(),
+            ));
         }
-        Err(e) => {
-            console::error_1(&format!("Failed to upload media: {:?}", e).into());
-            Err(e)
+
+        diesel::update(media::table.find(media_id))
+            .set(media::renditions_status.eq(RenditionStatus::Pending))
+            .execute(&conn)?;
+        drop(conn);
+        self.get_media(media_id).await
+    }
+
+    /// Delete a media record, its renditions and their stored files
+    pub async fn delete_media(&self, media_id: i32) -> Result<(), MediaServiceError> {
+        let conn = self.get_connection()?;
+        let item = media::table.find(media_id).first::<Media>(&conn)?;
+        let renditions = MediaRendition::belonging_to(&item).load::<MediaRendition>(&conn)?;
+        // Rendition rows go with the media row (ON DELETE CASCADE)
+        diesel::delete(media::table.find(media_id)).execute(&conn)?;
+
+        for rendition in &renditions {
+            self.storage.delete(&rendition.storage_key).await?;
         }
+        self.storage.delete(&item.storage_key).await?;
+        Ok(())
     }
-}
 
-/// Fetches all media items from the server
-pub async fn fetch_all_media() -> Result<Vec<Media>, MediaError> {
-    let url = format!("{}/api/media", get_api_base_url());
+    /// Open a stored original or rendition by its storage key, along with its MIME type
+    pub async fn open_file(&self, key: &str) -> Result<(StoredObject, String), MediaServiceError> {
+        let conn = self.get_connection()?;
+        let media_type = match media::table
+            .filter(media::storage_key.eq(key))
+            .select(media::media_type)
+            .first::<Option<String>>(&conn)
+            .optional()?
+        {
+            Some(media_type) => media_type,
+            None => Some(
+                media_renditions::table
+                    .filter(media_renditions::storage_key.eq(key))
+                    .select(media_renditions::media_type)
+                    .first::<String>(&conn)?,
+            ),
+        };
+        let object = self.storage.open(key).await?;
 
-    let response = make_request(HttpMethod::GET, &url, None::<JsValue>).await?;
-    let media_items = response
-        .json::<Vec<Media>>()
-        .await
-        .map_err(|e| MediaError::ParseError(e.to_string()))?;
+        Ok((
+            object,
+            media_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+        ))
+    }
 
-    Ok(media_items)
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, MediaServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            MediaServiceError::DatabaseError(e.to_string())
+        })
+    }
 }
 
-/// Deletes media
-pub async fn delete_media(id: i32) -> Result<(), MediaError> {
-    let url = format!("{}/api/media/{}", get_api_base_url(), id);
-    let result = make_request(HttpMethod::DELETE, &url, None::<JsValue>).await;
+/// Reduce a client-supplied file name to its final path component, without control characters
+fn sanitize_file_name(name: &str) -> Option<String> {
+    let base = name.rsplit(|c| c == '/' || c == '\\').next().unwrap_or("");
+    let cleaned: String = base.chars().filter(|c| !c.is_control()).collect();
+    let cleaned = cleaned.trim();
 
-    match result {
-        Ok(_) => {
-            console::log_1(&"Media deleted successfully!".into());
-            Ok(())
-        }
-        Err(e) => {
-            console::error_1(&format!("Failed to delete media: {:?}", e).into());
-            Err(e)
-        }
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        None
+    } else {
+        Some(cleaned.chars().take(255).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_lose_their_directories() {
+        assert_eq!(sanitize_file_name("C:\\Users\\me\\photo.jpg").as_deref(), Some("photo.jpg"));
+        assert_eq!(sanitize_file_name("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize_file_name("dir/"), None);
     }
 }
@@ -0,0 +1,160 @@
+// src/backend/services/media_storage.rs
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use thiserror::Error;
+use tokio::fs::{self, File};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWriteExt};
+use tracing::error;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Object not found")]
+    NotFound,
+    #[error("Invalid storage key: {0}")]
+    InvalidKey(String),
+    #[error("Upload exceeds the {0} byte limit")]
+    TooLarge(u64),
+    #[error("Upload stream error: {0}")]
+    Stream(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Body of an upload, consumed chunk by chunk as it is written to storage
+pub type ByteStream<'a> = Pin<Box<dyn Stream<Item = Result<Bytes, StorageError>> + Send + 'a>>;
+
+/// Seekable reader over a stored object, used to answer range requests
+pub trait StorageReader: AsyncRead + AsyncSeek + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncSeek + Send + Unpin> StorageReader for T {}
+
+pub struct StoredObject {
+    pub reader: Box<dyn StorageReader>,
+    pub size: u64,
+}
+
+/// Backend that stores the bytes of media uploads.
+///
+/// Keys are relative, `/`-separated paths such as `2024/09/<uuid>.jpg`.
+#[async_trait]
+pub trait MediaStorage: Send + Sync {
+    /// Write `body` under `key`, returning the number of bytes stored.
+    /// Nothing is left behind if the stream fails part-way.
+    async fn put<'a>(&self, key: &str, body: ByteStream<'a>) -> Result<u64, StorageError>;
+
+    /// Open a stored object for reading
+    async fn open(&self, key: &str) -> Result<StoredObject, StorageError>;
+
+    /// Remove a stored object; missing objects are not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Stores media on the local filesystem under a root directory
+pub struct LocalMediaStorage {
+    root: PathBuf,
+}
+
+impl LocalMediaStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Resolve a key to a path under the root, rejecting anything that could escape it
+    fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let valid = !key.is_empty()
+            && !key.starts_with('/')
+            && key.split('/').all(|part| !part.is_empty() && part != "." && part != "..")
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.'));
+
+        if valid {
+            Ok(self.root.join(Path::new(key)))
+        } else {
+            Err(StorageError::InvalidKey(key.to_string()))
+        }
+    }
+}
+
+#[async_trait]
+impl MediaStorage for LocalMediaStorage {
+    async fn put<'a>(&self, key: &str, mut body: ByteStream<'a>) -> Result<u64, StorageError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        // Write to a temporary file and rename it into place once complete
+        let tmp_path = path.with_extension("part");
+        let mut file = File::create(&tmp_path).await?;
+        let mut written = 0u64;
+
+        let result: Result<(), StorageError> = async {
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk?;
+                file.write_all(&chunk).await?;
+                written += chunk.len() as u64;
+            }
+            file.flush().await?;
+            Ok(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                fs::rename(&tmp_path, &path).await?;
+                Ok(written)
+            }
+            Err(e) => {
+                if let Err(cleanup) = fs::remove_file(&tmp_path).await {
+                    error!("Failed to remove partial upload {:?}: {:?}", tmp_path, cleanup);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn open(&self, key: &str) -> Result<StoredObject, StorageError> {
+        let path = self.path_for(key)?;
+        let file = File::open(&path).await.map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => StorageError::NotFound,
+            _ => StorageError::Io(e),
+        })?;
+        let size = file.metadata().await?.len();
+
+        Ok(StoredObject {
+            reader: Box::new(file),
+            size,
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        match fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_cannot_escape_the_root() {
+        let storage = LocalMediaStorage::new("/srv/media");
+        assert!(storage.path_for("2024/09/abc.jpg").is_ok());
+        assert!(storage.path_for("../etc/passwd").is_err());
+        assert!(storage.path_for("/etc/passwd").is_err());
+        assert!(storage.path_for("2024//abc.jpg").is_err());
+        assert!(storage.path_for("2024/09/a b.jpg").is_err());
+    }
+}
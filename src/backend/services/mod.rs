@@ -1,9 +1,31 @@
 // src/backend/services/mod.rs
 
-pub mod media_service;
+pub mod account_service;
+pub mod api_key_service;
+pub mod audit_service;
+pub mod auth_service;
 pub mod builder_service;
 pub mod comment_service;
+pub mod library_service;
+pub mod login_throttle;
+pub mod mailer;
+pub mod media_service;
+pub mod media_storage;
+pub mod oidc;
+pub mod oidc_service;
 pub mod post_service;
+pub mod public_service;
+pub mod publisher_service;
+pub mod render_service;
+pub mod rendition_service;
+pub mod revision_service;
+pub mod search_service;
+pub mod settings_service;
+pub mod spam_filter;
+pub mod tag_service;
+pub mod theme_service;
+pub mod theme_template;
+pub mod totp;
 pub mod user_service;
 
 // Common imports
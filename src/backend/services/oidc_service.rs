@@ -0,0 +1,471 @@
+// src/backend/services/oidc_service.rs
+
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::sql_types::Text;
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{error, info};
+
+use crate::backend::models::user::{NewUser, User};
+use crate::backend::models::user_identity::{NewOidcLoginState, NewSsoHandoff, NewUserIdentity};
+use crate::backend::schema::{oidc_login_states, sso_handoffs, user_identities, users};
+use crate::backend::services::auth_service::{
+    generate_token, hash_token, is_staff, AuthService, AuthServiceError, LoginOutcome, LOCAL_LOGIN_SETTING,
+};
+use crate::backend::services::oidc::{IdTokenClaims, OidcError, Provider, ProviderConfig};
+use crate::backend::services::settings_service::SettingsService;
+use crate::backend::utils::db::DbPool;
+
+/// How long a user has to finish signing in at the provider
+const LOGIN_STATE_TTL_MINUTES: i64 = 10;
+
+/// How long the login page has to redeem the code it is sent back with
+const HANDOFF_TTL_SECONDS: i64 = 60;
+
+/// Timeout of each request to a provider
+const PROVIDER_TIMEOUT_SECONDS: u64 = 10;
+
+const MAX_USERNAME_CHARS: usize = 50;
+
+diesel::sql_function!(fn lower(x: Text) -> Text);
+
+#[derive(Debug, Error)]
+pub enum OidcServiceError {
+    #[error("This sign-in has expired or was already used; start again")]
+    InvalidState,
+    #[error("An account with this email already exists. Sign in with your password, then link your identity provider from the dashboard.")]
+    LinkRequired,
+    #[error("This identity provider account is already linked to another user")]
+    AlreadyLinked,
+    #[error(transparent)]
+    Oidc(#[from] OidcError),
+    #[error(transparent)]
+    Auth(#[from] AuthServiceError),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for OidcServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        error!("Database error: {:?}", err);
+        OidcServiceError::DatabaseError(err.to_string())
+    }
+}
+
+/// A provider as offered on the login page
+#[derive(Debug, Serialize)]
+pub struct ProviderSummary {
+    pub id: String,
+    pub name: String,
+}
+
+/// How the user behind a provider login was found
+pub struct SignIn {
+    pub user: User,
+    /// Created at this login
+    pub created: bool,
+    /// An existing user with the same email, linked at this login
+    pub linked: bool,
+    /// The role the provider's groups replaced, if they changed it
+    pub previous_role: Option<String>,
+}
+
+/// What a provider sending the user back led to
+pub enum CallbackOutcome {
+    /// A login; the login page redeems `handoff_code` for a session
+    SignedIn { sign_in: SignIn, handoff_code: String },
+    /// A signed-in user linked the provider account to theirs
+    Linked { user: User, provider: String },
+}
+
+/// Single sign-on through OpenID Connect providers. Users are created on
+/// their first login, taking their role from the provider's groups when it
+/// maps them. Existing users keep their role unless the provider syncs roles.
+///
+/// An existing user with the same verified email is linked at login only if
+/// they are neither staff nor using 2FA; others link the provider themselves
+/// while signed in. Logins ask for the CMS's second factor unless the
+/// provider is configured to skip it.
+pub struct OidcService {
+    db_pool: DbPool,
+    auth_service: Arc<AuthService>,
+    settings_service: Arc<SettingsService>,
+    providers: Vec<Provider>,
+    /// Public URL of the API, which providers send users back to
+    redirect_base: String,
+    /// Origin of the site, whose login page finishes the sign-in
+    site_url: String,
+}
+
+impl OidcService {
+    pub fn new(
+        db_pool: DbPool,
+        auth_service: Arc<AuthService>,
+        settings_service: Arc<SettingsService>,
+        configs: Vec<ProviderConfig>,
+        redirect_base: impl Into<String>,
+        site_url: impl Into<String>,
+    ) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(PROVIDER_TIMEOUT_SECONDS))
+            .build()
+            .expect("Failed to build the HTTP client");
+        Self {
+            db_pool,
+            auth_service,
+            settings_service,
+            providers: configs
+                .into_iter()
+                .map(|config| Provider::new(config, http.clone()))
+                .collect(),
+            redirect_base: redirect_base.into().trim_end_matches('/').to_string(),
+            site_url: site_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn site_url(&self) -> &str {
+        &self.site_url
+    }
+
+    pub fn providers(&self) -> Vec<ProviderSummary> {
+        self.providers
+            .iter()
+            .map(|provider| ProviderSummary {
+                id: provider.config.id.clone(),
+                name: provider.config.display_name.clone(),
+            })
+            .collect()
+    }
+
+    /// Whether password login, registration and password resets are open.
+    /// They can only be turned off while a provider is configured, so an
+    /// empty provider list can't lock everyone out.
+    pub async fn local_login_enabled(&self) -> bool {
+        self.providers.is_empty()
+            || self
+                .settings_service
+                .get_bool(LOCAL_LOGIN_SETTING)
+                .await
+                .unwrap_or(true)
+    }
+
+    /// Start a login at `provider_id`, returning the URL to send the browser to
+    pub async fn begin_login(&self, provider_id: &str) -> Result<String, OidcServiceError> {
+        self.authorize(provider_id, None).await
+    }
+
+    /// Start linking an account at `provider_id` to the signed-in user,
+    /// returning the URL to send the browser to
+    pub async fn begin_link(&self, provider_id: &str, user_id: i32) -> Result<String, OidcServiceError> {
+        self.authorize(provider_id, Some(user_id)).await
+    }
+
+    async fn authorize(&self, provider_id: &str, link_user_id: Option<i32>) -> Result<String, OidcServiceError> {
+        let provider = self.provider(provider_id)?;
+        let request = provider.authorization_request(&self.redirect_uri(provider_id)).await?;
+
+        let conn = self.get_connection()?;
+        let now = Utc::now().naive_utc();
+        // Logins that were abandoned at the provider
+        diesel::delete(oidc_login_states::table.filter(oidc_login_states::expires_at.lt(now))).execute(&conn)?;
+        diesel::insert_into(oidc_login_states::table)
+            .values(&NewOidcLoginState {
+                state_hash: hash_token(&request.state),
+                provider: provider_id.to_string(),
+                nonce: request.nonce,
+                code_verifier: request.code_verifier,
+                link_user_id,
+                expires_at: now + Duration::minutes(LOGIN_STATE_TTL_MINUTES),
+            })
+            .execute(&conn)?;
+        Ok(request.url)
+    }
+
+    /// Finish a sign-in when the provider sends the user back with `code`.
+    /// For a link, the provider account is linked to the user who started
+    /// it; otherwise the user is found or created and a code is issued for
+    /// the login page to redeem.
+    pub async fn complete_login(
+        &self,
+        provider_id: &str,
+        code: &str,
+        state: &str,
+    ) -> Result<CallbackOutcome, OidcServiceError> {
+        let provider = self.provider(provider_id)?;
+        let conn = self.get_connection()?;
+        // Deleting the row makes each state usable once
+        let (nonce, code_verifier, link_user_id) = diesel::delete(
+            oidc_login_states::table
+                .filter(oidc_login_states::state_hash.eq(hash_token(state)))
+                .filter(oidc_login_states::provider.eq(provider_id))
+                .filter(oidc_login_states::expires_at.gt(Utc::now().naive_utc())),
+        )
+        .returning((
+            oidc_login_states::nonce,
+            oidc_login_states::code_verifier,
+            oidc_login_states::link_user_id,
+        ))
+        .get_result::<(String, String, Option<i32>)>(&conn)
+        .optional()?
+        .ok_or(OidcServiceError::InvalidState)?;
+        drop(conn);
+
+        let claims = provider
+            .exchange_code(code, &code_verifier, &self.redirect_uri(provider_id), &nonce)
+            .await?;
+        if let Some(user_id) = link_user_id {
+            let user = self.link_identity(&provider.config, &claims, user_id)?;
+            return Ok(CallbackOutcome::Linked {
+                user,
+                provider: provider_id.to_string(),
+            });
+        }
+
+        let sign_in = self.provision_user(&provider.config, &claims).await?;
+        let handoff_code = generate_token();
+        let conn = self.get_connection()?;
+        let now = Utc::now().naive_utc();
+        // Codes the login page never redeemed
+        diesel::delete(sso_handoffs::table.filter(sso_handoffs::expires_at.lt(now))).execute(&conn)?;
+        diesel::insert_into(sso_handoffs::table)
+            .values(&NewSsoHandoff {
+                code_hash: hash_token(&handoff_code),
+                user_id: sign_in.user.id,
+                provider: provider_id.to_string(),
+                expires_at: now + Duration::seconds(HANDOFF_TTL_SECONDS),
+            })
+            .execute(&conn)?;
+        Ok(CallbackOutcome::SignedIn { sign_in, handoff_code })
+    }
+
+    /// Redeem the code a login was handed to the login page with. As after a
+    /// password, the user is then asked for their second factor unless the
+    /// provider is trusted to have checked one.
+    pub async fn redeem_handoff(
+        &self,
+        code: &str,
+        user_agent: Option<String>,
+    ) -> Result<LoginOutcome, OidcServiceError> {
+        let conn = self.get_connection()?;
+        // Deleting the row makes each code usable once
+        let (user_id, provider_id) = diesel::delete(
+            sso_handoffs::table
+                .filter(sso_handoffs::code_hash.eq(hash_token(code)))
+                .filter(sso_handoffs::expires_at.gt(Utc::now().naive_utc())),
+        )
+        .returning((sso_handoffs::user_id, sso_handoffs::provider))
+        .get_result::<(i32, String)>(&conn)
+        .optional()?
+        .ok_or(OidcServiceError::InvalidState)?;
+        let user = users::table.find(user_id).first::<User>(&conn)?;
+        drop(conn);
+
+        let skip_second_factor = self
+            .provider(&provider_id)
+            .map_or(false, |provider| provider.config.skip_second_factor);
+        if skip_second_factor {
+            Ok(LoginOutcome::Tokens(self.auth_service.start_session(&user, user_agent)?))
+        } else {
+            Ok(self.auth_service.begin_session(&user, user_agent).await?)
+        }
+    }
+
+    /// Link a provider account to `user_id`, who asked for it while signed in
+    fn link_identity(&self, config: &ProviderConfig, claims: &IdTokenClaims, user_id: i32) -> Result<User, OidcServiceError> {
+        let conn = self.get_connection()?;
+        let linked_to = user_identities::table
+            .filter(user_identities::provider.eq(&config.id))
+            .filter(user_identities::subject.eq(&claims.sub))
+            .select(user_identities::user_id)
+            .first::<i32>(&conn)
+            .optional()?;
+        match linked_to {
+            Some(owner) if owner != user_id => return Err(OidcServiceError::AlreadyLinked),
+            Some(_) => {}
+            None => {
+                diesel::insert_into(user_identities::table)
+                    .values(&NewUserIdentity {
+                        user_id,
+                        provider: config.id.clone(),
+                        subject: claims.sub.clone(),
+                    })
+                    .execute(&conn)?;
+                info!("Linked {} account {} to user {}", config.id, claims.sub, user_id);
+            }
+        }
+        Ok(users::table.find(user_id).first::<User>(&conn)?)
+    }
+
+    /// The user for a provider account: the one linked earlier, an existing
+    /// user with the same verified email, or a new user
+    async fn provision_user(&self, config: &ProviderConfig, claims: &IdTokenClaims) -> Result<SignIn, OidcServiceError> {
+        let mapped_role = (!config.role_mappings.is_empty())
+            .then(|| config.role_for_groups(&claims.groups(&config.groups_claim)));
+        let conn = self.get_connection()?;
+
+        let linked = diesel::update(
+            user_identities::table
+                .filter(user_identities::provider.eq(&config.id))
+                .filter(user_identities::subject.eq(&claims.sub)),
+        )
+        .set(user_identities::last_login_at.eq(Utc::now().naive_utc()))
+        .returning(user_identities::user_id)
+        .get_result::<i32>(&conn)
+        .optional()?;
+
+        let (user_id, created, linked_now) = match linked {
+            Some(user_id) => (user_id, false, false),
+            None => {
+                let email = claims.email.clone().ok_or(OidcError::MissingClaim("email"))?;
+                // Only an address the provider vouches for may take over an account
+                let existing = if claims.email_verified() {
+                    users::table
+                        .filter(lower(users::email).eq(email.to_lowercase()))
+                        .select((users::id, users::role))
+                        .first::<(i32, String)>(&conn)
+                        .optional()?
+                } else {
+                    None
+                };
+                let (user_id, created) = match existing {
+                    Some((user_id, role)) => {
+                        // Staff and users with 2FA link while signed in, so the
+                        // provider never gets past their password or second factor
+                        if is_staff(&role) || self.auth_service.two_factor_enabled(user_id)? {
+                            info!("Not linking {} account {} to user {} by email", config.id, claims.sub, user_id);
+                            return Err(OidcServiceError::LinkRequired);
+                        }
+                        info!("Linking {} account {} to user {}", config.id, claims.sub, user_id);
+                        (user_id, false)
+                    }
+                    None => {
+                        let role = mapped_role.unwrap_or(config.default_role);
+                        let user = self
+                            .auth_service
+                            .register_user(NewUser {
+                                username: available_username(&conn, claims)?,
+                                email,
+                                // Never shown to anyone; a password can be set with a reset
+                                password_hash: generate_token(),
+                                role: role.as_str().to_string(),
+                            })
+                            .await?;
+                        info!("Created user {} for {} account {}", user.id, config.id, claims.sub);
+                        if claims.email_verified() {
+                            diesel::update(users::table.find(user.id))
+                                .set(users::email_verified_at.eq(Utc::now().naive_utc()))
+                                .execute(&conn)?;
+                        }
+                        (user.id, true)
+                    }
+                };
+                diesel::insert_into(user_identities::table)
+                    .values(&NewUserIdentity {
+                        user_id,
+                        provider: config.id.clone(),
+                        subject: claims.sub.clone(),
+                    })
+                    .execute(&conn)?;
+                (user_id, created, !created)
+            }
+        };
+
+        // New users already got the mapped role; others only when syncing
+        let previous_role = match mapped_role {
+            Some(role) if config.sync_roles && !created => conn.transaction::<_, OidcServiceError, _>(|| {
+                let current = users::table
+                    .find(user_id)
+                    .select(users::role)
+                    .for_update()
+                    .first::<String>(&conn)?;
+                if current == role.as_str() {
+                    return Ok(None);
+                }
+                diesel::update(users::table.find(user_id))
+                    .set(users::role.eq(role.as_str()))
+                    .execute(&conn)?;
+                info!("{} groups changed the role of user {} from {} to {}", config.id, user_id, current, role.as_str());
+                Ok(Some(current))
+            })?,
+            _ => None,
+        };
+
+        Ok(SignIn {
+            user: users::table.find(user_id).first::<User>(&conn)?,
+            created,
+            linked: linked_now,
+            previous_role,
+        })
+    }
+
+    fn provider(&self, provider_id: &str) -> Result<&Provider, OidcServiceError> {
+        self.providers
+            .iter()
+            .find(|provider| provider.config.id == provider_id)
+            .ok_or_else(|| OidcError::UnknownProvider(provider_id.to_string()).into())
+    }
+
+    /// Must match a redirect URI registered with the provider
+    fn redirect_uri(&self, provider_id: &str) -> String {
+        format!("{}/auth/oidc/{}/callback", self.redirect_base, provider_id)
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, OidcServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            OidcServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+/// A free username based on the provider's username or the email's local
+/// part, with a number appended if it's taken
+fn available_username(conn: &PgConnection, claims: &IdTokenClaims) -> Result<String, OidcServiceError> {
+    let wanted = claims
+        .preferred_username
+        .as_deref()
+        .or_else(|| claims.email.as_deref().and_then(|email| email.split('@').next()))
+        .unwrap_or("user");
+    let base = username_base(wanted);
+
+    let taken = users::table
+        .filter(users::username.like(format!("{}%", base)))
+        .select(users::username)
+        .load::<String>(conn)?;
+    Ok(std::iter::once(base.clone())
+        .chain((2..).map(|n| format!("{}{}", base, n)))
+        .find(|candidate| !taken.contains(candidate))
+        .expect("the candidates are endless"))
+}
+
+fn username_base(wanted: &str) -> String {
+    let base: String = wanted
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .take(MAX_USERNAME_CHARS - 4)
+        .collect();
+    if base.is_empty() {
+        "user".to_string()
+    } else {
+        base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usernames_keep_only_safe_characters() {
+        assert_eq!(username_base("ada.lovelace"), "ada.lovelace");
+        assert_eq!(username_base("Ada Lovelace <3"), "AdaLovelace3");
+        assert_eq!(username_base("José"), "Jos");
+        assert_eq!(username_base("日本"), "user");
+        assert_eq!(username_base(&"a".repeat(80)).len(), MAX_USERNAME_CHARS - 4);
+    }
+}
@@ -1,144 +1,168 @@
-use serde::{Deserialize, Serialize};
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
-use gloo_net::http::Request;
-use log::{error, info};
+// src/backend/services/post_service.rs
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
 use thiserror::Error;
+use tracing::error;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Post {
-    pub id: Option<i32>,
-    pub title: String,
-    pub content: String,
-}
+use crate::backend::models::post::{NewPost, Post, PostStatus, UpdatePost};
+use crate::backend::schema::posts::dsl::*;
+use crate::backend::schema::{post_tags, tags};
+use crate::backend::services::revision_service::record_snapshot;
+use crate::backend::utils::db::DbPool;
 
 #[derive(Debug, Error)]
 pub enum PostServiceError {
-    #[error("Network error: {0}")]
-    NetworkError(String),
-    #[error("Serialization error: {0}")]
-    SerializationError(String),
-    #[error("Deserialization error: {0}")]
-    DeserializationError(String),
-    #[error("API error with status code: {0}")]
-    ApiError(u16),
+    #[error("Invalid post data")]
+    InvalidData,
+    #[error("Post not found")]
+    NotFound,
+    #[error("Cannot move post from {0} to {1}")]
+    InvalidStatusTransition(PostStatus, PostStatus),
+    #[error("Scheduled posts need a publish_at time")]
+    MissingPublishAt,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
 }
 
-impl From<gloo_net::Error> for PostServiceError {
-    fn from(error: gloo_net::Error) -> Self {
-        PostServiceError::NetworkError(error.to_string())
+impl From<diesel::result::Error> for PostServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => PostServiceError::NotFound,
+            e => {
+                error!("Database error: {:?}", e);
+                PostServiceError::DatabaseError(e.to_string())
+            }
+        }
     }
 }
 
-impl From<serde_json::Error> for PostServiceError {
-    fn from(error: serde_json::Error) -> Self {
-        PostServiceError::DeserializationError(error.to_string())
-    }
+pub struct PostService {
+    db_pool: DbPool,
 }
 
-impl From<String> for PostServiceError {
-    fn from(error: String) -> Self {
-        PostServiceError::SerializationError(error)
+impl PostService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
     }
-}
-
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_name = API_BASE_URL)]
-    static API_BASE_URL: JsValue;
-}
 
-fn get_api_base_url() -> String {
-    API_BASE_URL.as_string().unwrap_or_else(|| "".to_string())
-}
+    /// Create a new post
+    pub async fn create_post(&self, new_post: NewPost) -> Result<Post, PostServiceError> {
+        if new_post.title.trim().is_empty() || new_post.slug.trim().is_empty() {
+            return Err(PostServiceError::InvalidData);
+        }
+        if new_post.status == PostStatus::Scheduled && new_post.publish_at.is_none() {
+            return Err(PostServiceError::MissingPublishAt);
+        }
 
-enum HttpMethod {
-    GET,
-    POST,
-    PUT,
-    DELETE,
-}
+        let conn = self.get_connection()?;
+        diesel::insert_into(posts)
+            .values(&new_post)
+            .get_result::<Post>(&conn)
+            .map_err(PostServiceError::from)
+    }
 
-impl HttpMethod {
-    fn as_str(&self) -> &'static str {
-        match self {
-            HttpMethod::GET => "GET",
-            HttpMethod::POST => "POST",
-            HttpMethod::PUT => "PUT",
-            HttpMethod::DELETE => "DELETE",
+    /// List all posts, newest first; only those tagged `tag_slug` if given.
+    /// With `drafts_of`, unpublished posts are limited to that user's own.
+    pub async fn list_posts(
+        &self,
+        tag_slug: Option<&str>,
+        drafts_of: Option<i32>,
+    ) -> Result<Vec<Post>, PostServiceError> {
+        let conn = self.get_connection()?;
+        let mut query = posts.order(created_at.desc()).into_boxed();
+        if let Some(user_id) = drafts_of {
+            query = query.filter(status.eq(PostStatus::Published).or(author_id.eq(user_id)));
         }
+        if let Some(tag_slug) = tag_slug {
+            let tag_ids = tags::table.filter(tags::slug.eq(tag_slug.to_string())).select(tags::id);
+            query = query.filter(
+                id.eq_any(
+                    post_tags::table
+                        .filter(post_tags::tag_id.eq_any(tag_ids))
+                        .select(post_tags::post_id),
+                ),
+            );
+        }
+        query
+            .load::<Post>(&conn)
+            .map_err(PostServiceError::from)
     }
-}
 
-async fn make_request(
-    method: HttpMethod,
-    endpoint: &str,
-    body: Option<impl Into<JsValue>>,
-) -> Result<gloo_net::http::Response, PostServiceError> {
-    let url = format!("{}{}", get_api_base_url(), endpoint);
-    let mut request = Request::new(&url).method(method.as_str());
-
-    if let Some(body) = body {
-        request = request
-            .header("Content-Type", "application/json")
-            .body(body);
+    /// Fetch a single post by ID
+    pub async fn get_post(&self, post_id: i32) -> Result<Post, PostServiceError> {
+        let conn = self.get_connection()?;
+        posts
+            .find(post_id)
+            .first::<Post>(&conn)
+            .map_err(PostServiceError::from)
     }
 
-    let response = request.send().await.map_err(PostServiceError::from)?;
+    /// Update a post on behalf of `editor_id`, keeping a revision of what it
+    /// looked like beforehand
+    pub async fn update_post(
+        &self,
+        post_id: i32,
+        changes: UpdatePost,
+        editor_id: i32,
+    ) -> Result<Post, PostServiceError> {
+        if changes.title.as_deref().map_or(false, |t| t.trim().is_empty())
+            || changes.slug.as_deref().map_or(false, |s| s.trim().is_empty())
+        {
+            return Err(PostServiceError::InvalidData);
+        }
 
-    if response.ok() {
-        Ok(response)
-    } else {
-        Err(PostServiceError::ApiError(response.status()))
+        let conn = self.get_connection()?;
+        conn.transaction::<_, PostServiceError, _>(|| {
+            let current = posts.find(post_id).for_update().first::<Post>(&conn)?;
+
+            if let Some(next) = changes.status {
+                if !current.status.can_transition_to(next) {
+                    return Err(PostServiceError::InvalidStatusTransition(current.status, next));
+                }
+                if next == PostStatus::Scheduled
+                    && changes.publish_at.or(current.publish_at).is_none()
+                {
+                    return Err(PostServiceError::MissingPublishAt);
+                }
+            }
+
+            record_snapshot(&conn, &current, editor_id)?;
+
+            let now = Utc::now().naive_utc();
+            let first_publish = changes.status == Some(PostStatus::Published)
+                && current.published_at.is_none();
+            diesel::update(posts.find(post_id))
+                .set((
+                    &changes,
+                    updated_at.eq(now),
+                    published_at.eq(if first_publish { Some(now) } else { current.published_at }),
+                ))
+                .get_result::<Post>(&conn)
+                .map_err(PostServiceError::from)
+        })
     }
-}
-
-async fn handle_api_response<T>(response: gloo_net::http::Response) -> Result<T, PostServiceError>
-where
-    T: for<'de> Deserialize<'de>,
-{
-    response
-        .json::<T>()
-        .await
-        .map_err(PostServiceError::from)
-}
-
-// Fetch all posts
-pub async fn fetch_posts() -> Result<Vec<Post>, PostServiceError> {
-    info!("Fetching all posts...");
-    let response = make_request(HttpMethod::GET, "/api/posts", None::<JsValue>).await?;
-    handle_api_response::<Vec<Post>>(response).await
-}
-
-// Create a new post
-pub async fn create_post(new_post: &Post) -> Result<Post, PostServiceError> {
-    info!("Creating new post: {:?}", new_post);
-    let body = serde_json::to_string(new_post).map_err(PostServiceError::from)?;
-
-    let response = make_request(HttpMethod::POST, "/api/posts", Some(body)).await?;
-    handle_api_response::<Post>(response).await
-}
-
-// Update an existing post
-pub async fn update_post(post_id: i32, updated_post: &Post) -> Result<Post, PostServiceError> {
-    info!("Updating post ID {}: {:?}", post_id, updated_post);
-    let endpoint = format!("/api/posts/{}", post_id);
-    let body = serde_json::to_string(updated_post).map_err(PostServiceError::from)?;
-
-    let response = make_request(HttpMethod::PUT, &endpoint, Some(body)).await?;
-    handle_api_response::<Post>(response).await
-}
 
-// Delete a post
-pub async fn delete_post(post_id: i32) -> Result<(), PostServiceError> {
-    info!("Deleting post with ID {}", post_id);
-    let endpoint = format!("/api/posts/{}", post_id);
-
-    let response = make_request(HttpMethod::DELETE, &endpoint, None::<JsValue>).await?;
+    /// Delete a post by ID
+    pub async fn delete_post(&self, post_id: i32) -> Result<(), PostServiceError> {
+        let conn = self.get_connection()?;
+        let deleted = diesel::delete(posts.find(post_id))
+            .execute(&conn)
+            .map_err(PostServiceError::from)?;
+
+        if deleted == 0 {
+            Err(PostServiceError::NotFound)
+        } else {
+            Ok(())
+        }
+    }
 
-    if response.ok() {
-        Ok(())
-    } else {
-        Err(PostServiceError::ApiError(response.status()))
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, PostServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            PostServiceError::DatabaseError(e.to_string())
+        })
     }
 }
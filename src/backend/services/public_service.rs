@@ -0,0 +1,249 @@
+// src/backend/services/public_service.rs
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use thiserror::Error;
+use tracing::error;
+
+use crate::backend::models::category::Category;
+use crate::backend::models::page::Page;
+use crate::backend::models::post::{Post, PostStatus};
+use crate::backend::models::tag::Tag;
+use crate::backend::schema::{categories, pages, post_tags, posts, tags};
+use crate::backend::services::builder_service::load_document;
+use crate::backend::services::library_service::resolve_global_blocks;
+use crate::backend::utils::db::DbPool;
+use crate::shared::builder::ModuleKind;
+use crate::shared::types::{PageView, PostSummary, PostView, TermView};
+
+/// Number of posts shown on an archive page
+const ARCHIVE_PAGE_SIZE: i64 = 50;
+
+/// Length of the plain-text excerpt shown in archive listings
+const EXCERPT_CHARS: usize = 200;
+
+#[derive(Debug, Error)]
+pub enum PublicServiceError {
+    #[error("Not found")]
+    NotFound,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for PublicServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => PublicServiceError::NotFound,
+            e => {
+                error!("Database error: {:?}", e);
+                PublicServiceError::DatabaseError(e.to_string())
+            }
+        }
+    }
+}
+
+/// Read-only access to published content for the public site
+pub struct PublicService {
+    db_pool: DbPool,
+}
+
+impl PublicService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Fetch a published post by slug
+    pub async fn published_post(&self, post_slug: &str) -> Result<PostView, PublicServiceError> {
+        let conn = self.get_connection()?;
+        let post = posts::table
+            .filter(posts::slug.eq(post_slug))
+            .filter(posts::status.eq(PostStatus::Published))
+            .first::<Post>(&conn)?;
+        let tagged = tags::table
+            .filter(tags::id.eq_any(post_tags::table.filter(post_tags::post_id.eq(post.id)).select(post_tags::tag_id)))
+            .order(tags::name.asc())
+            .load::<Tag>(&conn)?;
+
+        Ok(PostView {
+            id: post.id,
+            title: post.title,
+            slug: post.slug,
+            content: sanitize_html(&post.content),
+            published_at: post.published_at.map(|t| t.format("%Y-%m-%d").to_string()),
+            tags: tagged
+                .into_iter()
+                .map(|tag| TermView {
+                    name: tag.name,
+                    slug: tag.slug,
+                })
+                .collect(),
+        })
+    }
+
+    /// Fetch a page by slug
+    pub async fn page(&self, page_slug: &str) -> Result<PageView, PublicServiceError> {
+        let conn = self.get_connection()?;
+        let page = pages::table
+            .filter(pages::slug.eq(page_slug))
+            .first::<Page>(&conn)?;
+
+        let document = match page.builder_version {
+            Some(_) => {
+                let mut document = load_document(&conn, page.id)?;
+                resolve_global_blocks(&conn, &mut document)?;
+                for module in document.modules_mut() {
+                    if let ModuleKind::Text { html } = &mut module.kind {
+                        *html = sanitize_html(html);
+                    }
+                }
+                Some(document)
+            }
+            None => None,
+        };
+
+        Ok(PageView {
+            id: page.id,
+            title: page.title,
+            slug: page.slug,
+            content: sanitize_html(&page.content),
+            document,
+        })
+    }
+
+    /// Fetch a category and its most recent published posts
+    pub async fn category_archive(
+        &self,
+        category_slug: &str,
+    ) -> Result<(TermView, Vec<PostSummary>), PublicServiceError> {
+        let conn = self.get_connection()?;
+        let category = categories::table
+            .filter(categories::slug.eq(category_slug))
+            .first::<Category>(&conn)?;
+
+        let posts = posts::table
+            .filter(posts::category_id.eq(category.id))
+            .filter(posts::status.eq(PostStatus::Published))
+            .order(posts::published_at.desc())
+            .limit(ARCHIVE_PAGE_SIZE)
+            .load::<Post>(&conn)?;
+
+        Ok((
+            TermView {
+                name: category.name,
+                slug: category.slug,
+            },
+            posts.iter().map(summarize).collect(),
+        ))
+    }
+
+    /// Fetch a tag and its most recent published posts
+    pub async fn tag_archive(
+        &self,
+        tag_slug: &str,
+    ) -> Result<(TermView, Vec<PostSummary>), PublicServiceError> {
+        let conn = self.get_connection()?;
+        let tag = tags::table
+            .filter(tags::slug.eq(tag_slug))
+            .first::<Tag>(&conn)?;
+
+        let posts = posts::table
+            .filter(posts::id.eq_any(post_tags::table.filter(post_tags::tag_id.eq(tag.id)).select(post_tags::post_id)))
+            .filter(posts::status.eq(PostStatus::Published))
+            .order(posts::published_at.desc())
+            .limit(ARCHIVE_PAGE_SIZE)
+            .load::<Post>(&conn)?;
+
+        Ok((
+            TermView {
+                name: tag.name,
+                slug: tag.slug,
+            },
+            posts.iter().map(summarize).collect(),
+        ))
+    }
+
+    /// The most recent published posts, for the blog archive
+    pub async fn recent_posts(&self) -> Result<Vec<PostSummary>, PublicServiceError> {
+        let conn = self.get_connection()?;
+        let posts = posts::table
+            .filter(posts::status.eq(PostStatus::Published))
+            .order(posts::published_at.desc())
+            .limit(ARCHIVE_PAGE_SIZE)
+            .load::<Post>(&conn)?;
+        Ok(posts.iter().map(summarize).collect())
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, PublicServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            PublicServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+/// Strip scripts, event handlers and other unsafe markup from stored HTML
+pub fn sanitize_html(html: &str) -> String {
+    ammonia::clean(html)
+}
+
+/// Build an archive entry with a plain-text excerpt of the post body
+pub fn summarize(post: &Post) -> PostSummary {
+    let text = html_to_text(&post.content);
+    let excerpt = match text.char_indices().nth(EXCERPT_CHARS) {
+        Some((cut, _)) => format!("{}…", text[..cut].trim_end()),
+        None => text,
+    };
+
+    PostSummary {
+        title: post.title.clone(),
+        slug: post.slug.clone(),
+        excerpt,
+        published_at: post.published_at.map(|t| t.format("%Y-%m-%d").to_string()),
+    }
+}
+
+/// Plain text of an HTML fragment, with whitespace collapsed
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            }
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_to_text_strips_tags_and_entities() {
+        assert_eq!(
+            html_to_text("<p>Fish &amp; chips</p><p>are <b>great</b></p>"),
+            "Fish & chips are great"
+        );
+    }
+
+    #[test]
+    fn sanitize_html_drops_scripts() {
+        let clean = sanitize_html("<p onclick=\"x()\">hi</p><script>alert(1)</script>");
+        assert_eq!(clean, "<p>hi</p>");
+    }
+}
@@ -0,0 +1,131 @@
+// src/backend/services/publisher_service.rs
+
+use std::env;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info};
+
+use crate::backend::models::post::{NewPublishLogEntry, PostStatus, PublishLogEntry};
+use crate::backend::schema::{post_publish_log, posts};
+use crate::backend::utils::db::DbPool;
+
+/// How often the publisher looks for due posts when `PUBLISH_INTERVAL_SECS` is unset
+const DEFAULT_PUBLISH_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Error)]
+pub enum PublisherError {
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for PublisherError {
+    fn from(err: diesel::result::Error) -> Self {
+        PublisherError::DatabaseError(err.to_string())
+    }
+}
+
+/// Background worker that flips scheduled posts to published once their
+/// `publish_at` time has passed.
+pub struct ScheduledPublisher {
+    db_pool: DbPool,
+    period: Duration,
+}
+
+impl ScheduledPublisher {
+    pub fn new(db_pool: DbPool) -> Self {
+        let secs = env::var("PUBLISH_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_PUBLISH_INTERVAL_SECS);
+
+        Self {
+            db_pool,
+            period: Duration::from_secs(secs),
+        }
+    }
+
+    /// Run the publisher on the tokio runtime until the process exits
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = interval(self.period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            info!("Scheduled publisher running every {:?}", self.period);
+
+            loop {
+                ticker.tick().await;
+
+                let db_pool = self.db_pool.clone();
+                match tokio::task::spawn_blocking(move || publish_due_posts(&db_pool)).await {
+                    Ok(Ok(published)) => {
+                        for entry in published {
+                            info!(
+                                "Published post {} (scheduled for {})",
+                                entry.post_id, entry.scheduled_for
+                            );
+                        }
+                    }
+                    Ok(Err(e)) => error!("Scheduled publishing failed: {:?}", e),
+                    Err(e) => error!("Scheduled publisher task panicked: {:?}", e),
+                }
+            }
+        })
+    }
+}
+
+/// Publish every scheduled post whose time has come and log each one.
+///
+/// Rows are locked with `SKIP LOCKED` so several backend instances can run
+/// the publisher without publishing the same post twice.
+pub fn publish_due_posts(db_pool: &DbPool) -> Result<Vec<PublishLogEntry>, PublisherError> {
+    let conn = db_pool.get().map_err(|e| {
+        error!("Database connection error: {:?}", e);
+        PublisherError::DatabaseError(e.to_string())
+    })?;
+    let now = Utc::now().naive_utc();
+
+    conn.transaction::<_, PublisherError, _>(|| {
+        let due = posts::table
+            .filter(posts::status.eq(PostStatus::Scheduled))
+            .filter(posts::publish_at.le(now))
+            .select((posts::id, posts::publish_at))
+            .for_update()
+            .skip_locked()
+            .load::<(i32, Option<NaiveDateTime>)>(&conn)?;
+
+        if due.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<i32> = due.iter().map(|(id, _)| *id).collect();
+        diesel::update(posts::table.filter(posts::id.eq_any(&ids)))
+            .set((posts::status.eq(PostStatus::Published), posts::updated_at.eq(now)))
+            .execute(&conn)?;
+        // A post published before and rescheduled keeps its first publish date
+        diesel::update(
+            posts::table
+                .filter(posts::id.eq_any(&ids))
+                .filter(posts::published_at.is_null()),
+        )
+        .set(posts::published_at.eq(now))
+        .execute(&conn)?;
+
+        let entries: Vec<NewPublishLogEntry> = due
+            .into_iter()
+            .map(|(post_id, publish_at)| NewPublishLogEntry {
+                post_id,
+                scheduled_for: publish_at.unwrap_or(now),
+            })
+            .collect();
+
+        diesel::insert_into(post_publish_log::table)
+            .values(&entries)
+            .get_results::<PublishLogEntry>(&conn)
+            .map_err(PublisherError::from)
+    })
+}
@@ -0,0 +1,109 @@
+// src/backend/services/render_service.rs
+
+use serde_json::json;
+use yew::{BaseComponent, ServerRenderer};
+
+use crate::backend::services::theme_service::{LoadedTheme, ThemeView};
+
+/// Render a Yew component to an HTML fragment on the server.
+///
+/// `raw_slots` fills the elements left empty by `RawHtml` (matched by their
+/// `data-raw-html` slot name) with already-sanitized markup.
+pub async fn render_component<C>(props: C::Properties, raw_slots: &[(&str, &str)]) -> String
+where
+    C: BaseComponent,
+    C::Properties: Send,
+{
+    let mut html = ServerRenderer::<C>::with_props(move || props).render().await;
+    for (slot, content) in raw_slots {
+        fill_raw_slot(&mut html, slot, content);
+    }
+    html
+}
+
+/// Stylesheet and scripts every page needs, themed or not
+const BASE_HEAD: &str = r#"<link rel="stylesheet" href="/static/theme.css">
+    <script src="/static/builder-actions.js" defer></script>"#;
+
+/// Wrap a rendered fragment in a complete HTML document
+pub fn render_document(title: &str, body: &str) -> String {
+    document_shell(title, BASE_HEAD, body)
+}
+
+/// Wrap a rendered fragment in the theme's layout, or in the built-in shell
+/// with the theme's stylesheet and tokens when it has no layout
+pub fn render_themed_document(theme: Option<&LoadedTheme>, title: &str, body: &str) -> String {
+    let theme = match theme {
+        Some(theme) => theme,
+        None => return render_document(title, body),
+    };
+    let head = format!("{}{}", BASE_HEAD, theme.head());
+    let context = json!({ "title": title, "head": head, "body": body });
+    theme
+        .render(ThemeView::Layout, context)
+        .unwrap_or_else(|| document_shell(title, &head, body))
+}
+
+fn document_shell(title: &str, head: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title}</title>
+    {head}
+</head>
+<body>
+    <main class="site-content">{body}</main>
+</body>
+</html>"#,
+        title = escape_html(title),
+        head = head,
+        body = body,
+    )
+}
+
+/// Insert `content` into the empty element marked `data-raw-html="<slot>"`
+fn fill_raw_slot(html: &mut String, slot: &str, content: &str) {
+    let marker = format!("data-raw-html=\"{}\">", slot);
+    if let Some(pos) = html.find(&marker) {
+        html.insert_str(pos + marker.len(), content);
+    }
+}
+
+/// Escape text for use in HTML element content or attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_only_the_named_slot() {
+        let mut html = r#"<div data-raw-html="content"></div><div data-raw-html="other"></div>"#.to_string();
+        fill_raw_slot(&mut html, "content", "<p>hi</p>");
+        assert_eq!(
+            html,
+            r#"<div data-raw-html="content"><p>hi</p></div><div data-raw-html="other"></div>"#
+        );
+    }
+
+    #[test]
+    fn document_title_is_escaped() {
+        assert!(render_document("<b>x</b>", "").contains("<title>&lt;b&gt;x&lt;/b&gt;</title>"));
+    }
+}
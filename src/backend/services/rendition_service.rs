@@ -0,0 +1,509 @@
+// src/backend/services/rendition_service.rs
+
+use std::env;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use futures::stream;
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageReader, Limits};
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info};
+
+use crate::backend::models::media::{
+    Media, MediaRendition, NewMediaRendition, RenditionName, RenditionStatus,
+};
+use crate::backend::schema::{media, media_renditions};
+use crate::backend::services::media_service::MEDIA_URL_PREFIX;
+use crate::backend::services::media_storage::{MediaStorage, StorageError};
+use crate::backend::utils::db::DbPool;
+
+/// How often the worker looks for new uploads when `RENDITION_INTERVAL_SECS` is unset
+const DEFAULT_RENDITION_INTERVAL_SECS: u64 = 5;
+
+/// Uploads claimed per tick; each one is decoded fully into memory
+const BATCH_SIZE: i64 = 4;
+
+/// How long a claim on an upload holds. Older claims are taken to belong to
+/// a worker that died mid-job and are put back in the queue; it is well past
+/// the time a batch takes, so live workers aren't raced.
+const CLAIM_LEASE_MINUTES: i64 = 15;
+
+/// Largest source image, per side, the pipeline will decode
+const MAX_SOURCE_DIMENSION: u32 = 12_000;
+
+const JPEG_QUALITY: u8 = 82;
+const AVIF_QUALITY: u8 = 70;
+/// rav1e speed preset (1 slowest .. 10 fastest)
+const AVIF_SPEED: u8 = 8;
+
+/// Upload types the pipeline can decode. AVIF sources are left alone:
+/// decoding them needs a C library.
+pub const RENDERABLE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fit {
+    /// Scale down to fit inside the box, keeping the aspect ratio
+    Contain,
+    /// Scale and centre-crop to fill the box; a source smaller than the box
+    /// is cropped to the part of it that fits, never enlarged
+    Cover,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// JPEG, or PNG when the source has transparency
+    Web,
+    /// Lossless WebP, the only kind the pure Rust encoder writes
+    Webp,
+    Avif,
+}
+
+struct RenditionSpec {
+    name: RenditionName,
+    width: u32,
+    height: u32,
+    fit: Fit,
+    format: OutputFormat,
+}
+
+const RENDITION_SPECS: &[RenditionSpec] = &[
+    RenditionSpec { name: RenditionName::Thumbnail, width: 300, height: 300, fit: Fit::Cover, format: OutputFormat::Web },
+    RenditionSpec { name: RenditionName::Medium, width: 768, height: 768, fit: Fit::Contain, format: OutputFormat::Web },
+    RenditionSpec { name: RenditionName::Large, width: 1600, height: 1600, fit: Fit::Contain, format: OutputFormat::Web },
+    RenditionSpec { name: RenditionName::Webp, width: 1600, height: 1600, fit: Fit::Contain, format: OutputFormat::Webp },
+    RenditionSpec { name: RenditionName::Avif, width: 1600, height: 1600, fit: Fit::Contain, format: OutputFormat::Avif },
+];
+
+#[derive(Debug, Error)]
+pub enum RenditionError {
+    #[error("Image error: {0}")]
+    ImageError(#[from] image::ImageError),
+    #[error("Storage error: {0}")]
+    StorageError(#[from] StorageError),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+    #[error("Worker task failed: {0}")]
+    TaskError(String),
+}
+
+impl From<diesel::result::Error> for RenditionError {
+    fn from(err: diesel::result::Error) -> Self {
+        RenditionError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for RenditionError {
+    fn from(err: std::io::Error) -> Self {
+        RenditionError::StorageError(StorageError::Io(err))
+    }
+}
+
+/// An encoded rendition, ready to be written to storage
+#[derive(Debug)]
+pub struct EncodedRendition {
+    pub name: RenditionName,
+    pub bytes: Vec<u8>,
+    pub media_type: &'static str,
+    pub extension: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Background worker that generates the named renditions of uploaded images.
+///
+/// Uploads are queued by setting `media.renditions_status` to `pending`, so
+/// work survives restarts and is shared between backend instances. Claimed
+/// uploads carry `renditions_claimed_at`; every tick, claims past their lease
+/// are requeued, whichever instance made them.
+pub struct RenditionWorker {
+    db_pool: DbPool,
+    storage: Arc<dyn MediaStorage>,
+    period: Duration,
+}
+
+impl RenditionWorker {
+    pub fn new(db_pool: DbPool, storage: Arc<dyn MediaStorage>) -> Self {
+        let secs = env::var("RENDITION_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_RENDITION_INTERVAL_SECS);
+
+        Self {
+            db_pool,
+            storage,
+            period: Duration::from_secs(secs),
+        }
+    }
+
+    /// Run the worker on the tokio runtime until the process exits
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = interval(self.period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            info!("Rendition worker running every {:?}", self.period);
+
+            loop {
+                ticker.tick().await;
+
+                let db_pool = self.db_pool.clone();
+                match tokio::task::spawn_blocking(move || requeue_expired(&db_pool)).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(count)) => info!("Requeued {} interrupted rendition jobs", count),
+                    Ok(Err(e)) => error!("Failed to requeue interrupted renditions: {:?}", e),
+                    Err(e) => error!("Rendition recovery task panicked: {:?}", e),
+                }
+
+                let db_pool = self.db_pool.clone();
+                let claimed = match tokio::task::spawn_blocking(move || claim_pending(&db_pool)).await {
+                    Ok(Ok(claimed)) => claimed,
+                    Ok(Err(e)) => {
+                        error!("Claiming rendition jobs failed: {:?}", e);
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Rendition worker task panicked: {:?}", e);
+                        continue;
+                    }
+                };
+
+                for item in claimed {
+                    match self.render(&item).await {
+                        Ok(count) => info!("Generated {} renditions for media {}", count, item.id),
+                        Err(e) => {
+                            error!("Generating renditions for media {} failed: {:?}", item.id, e);
+                            let db_pool = self.db_pool.clone();
+                            let id = item.id;
+                            match tokio::task::spawn_blocking(move || {
+                                set_status(&db_pool, id, RenditionStatus::Failed)
+                            })
+                            .await
+                            {
+                                Ok(Ok(())) => {}
+                                Ok(Err(e)) => error!("Failed to mark media {} as failed: {:?}", id, e),
+                                Err(e) => error!("Rendition worker task panicked: {:?}", e),
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Generate, store and record every rendition of one upload
+    async fn render(&self, item: &Media) -> Result<usize, RenditionError> {
+        let mut object = self.storage.open(&item.storage_key).await?;
+        let mut source = Vec::with_capacity(object.size as usize);
+        object.reader.read_to_end(&mut source).await?;
+
+        let encoded = tokio::task::spawn_blocking(move || generate_renditions(&source))
+            .await
+            .map_err(|e| RenditionError::TaskError(e.to_string()))??;
+
+        let mut records = Vec::with_capacity(encoded.len());
+        for rendition in encoded {
+            let key = rendition_key(&item.storage_key, rendition.name, rendition.extension);
+            let body = Bytes::from(rendition.bytes);
+            let file_size = self
+                .storage
+                .put(&key, Box::pin(stream::once(async move { Ok::<Bytes, StorageError>(body) })))
+                .await?;
+
+            records.push(NewMediaRendition {
+                media_id: item.id,
+                name: rendition.name,
+                url: format!("{}/{}", MEDIA_URL_PREFIX, key),
+                storage_key: key,
+                media_type: rendition.media_type.to_string(),
+                width: rendition.width as i32,
+                height: rendition.height as i32,
+                file_size: file_size as i64,
+            });
+        }
+
+        let count = records.len();
+        let db_pool = self.db_pool.clone();
+        let media_id = item.id;
+        tokio::task::spawn_blocking(move || save_renditions(&db_pool, media_id, records))
+            .await
+            .map_err(|e| RenditionError::TaskError(e.to_string()))??;
+        Ok(count)
+    }
+}
+
+/// Storage key of a rendition, next to the original: `2024/09/<uuid>-thumbnail.jpg`
+fn rendition_key(original_key: &str, name: RenditionName, extension: &str) -> String {
+    let stem = original_key
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(original_key);
+    format!("{}-{}.{}", stem, name, extension)
+}
+
+fn get_connection(
+    db_pool: &DbPool,
+) -> Result<diesel::r2d2::PooledConnection<diesel::r2d2::ConnectionManager<PgConnection>>, RenditionError> {
+    db_pool.get().map_err(|e| {
+        error!("Database connection error: {:?}", e);
+        RenditionError::DatabaseError(e.to_string())
+    })
+}
+
+/// Claims made before this are past their lease
+fn lease_cutoff(now: NaiveDateTime) -> NaiveDateTime {
+    now - chrono::Duration::minutes(CLAIM_LEASE_MINUTES)
+}
+
+/// Put jobs whose claim has run out back in the queue. Jobs claimed within
+/// the lease are left to the worker, in this instance or another, that has them.
+fn requeue_expired(db_pool: &DbPool) -> Result<usize, RenditionError> {
+    let conn = get_connection(db_pool)?;
+    let cutoff = lease_cutoff(Utc::now().naive_utc());
+    diesel::update(
+        media::table
+            .filter(media::renditions_status.eq(RenditionStatus::Processing))
+            .filter(
+                media::renditions_claimed_at
+                    .lt(cutoff)
+                    .or(media::renditions_claimed_at.is_null()),
+            ),
+    )
+    .set((
+        media::renditions_status.eq(RenditionStatus::Pending),
+        media::renditions_claimed_at.eq(None::<NaiveDateTime>),
+    ))
+    .execute(&conn)
+    .map_err(RenditionError::from)
+}
+
+/// Claim a batch of pending uploads, marking them `processing`.
+///
+/// Rows are locked with `SKIP LOCKED` so concurrent workers never claim the
+/// same upload.
+fn claim_pending(db_pool: &DbPool) -> Result<Vec<Media>, RenditionError> {
+    let conn = get_connection(db_pool)?;
+
+    conn.transaction::<_, RenditionError, _>(|| {
+        let ids = media::table
+            .filter(media::renditions_status.eq(RenditionStatus::Pending))
+            .select(media::id)
+            .order(media::id)
+            .limit(BATCH_SIZE)
+            .for_update()
+            .skip_locked()
+            .load::<i32>(&conn)?;
+
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        diesel::update(media::table.filter(media::id.eq_any(&ids)))
+            .set((
+                media::renditions_status.eq(RenditionStatus::Processing),
+                media::renditions_claimed_at.eq(Utc::now().naive_utc()),
+            ))
+            .get_results::<Media>(&conn)
+            .map_err(RenditionError::from)
+    })
+}
+
+/// Replace an upload's rendition records and mark it ready
+fn save_renditions(
+    db_pool: &DbPool,
+    media_id: i32,
+    records: Vec<NewMediaRendition>,
+) -> Result<Vec<MediaRendition>, RenditionError> {
+    let conn = get_connection(db_pool)?;
+
+    conn.transaction::<_, RenditionError, _>(|| {
+        diesel::delete(media_renditions::table.filter(media_renditions::media_id.eq(media_id)))
+            .execute(&conn)?;
+        let saved = diesel::insert_into(media_renditions::table)
+            .values(&records)
+            .get_results::<MediaRendition>(&conn)?;
+        diesel::update(media::table.find(media_id))
+            .set(media::renditions_status.eq(RenditionStatus::Ready))
+            .execute(&conn)?;
+        Ok(saved)
+    })
+}
+
+fn set_status(db_pool: &DbPool, media_id: i32, status: RenditionStatus) -> Result<(), RenditionError> {
+    let conn = get_connection(db_pool)?;
+    diesel::update(media::table.find(media_id))
+        .set(media::renditions_status.eq(status))
+        .execute(&conn)?;
+    Ok(())
+}
+
+/// Decode an uploaded image and encode every rendition of it.
+///
+/// EXIF orientation is applied first so phone photos come out upright.
+/// Sources are never upscaled: `medium` and `large` are skipped when the
+/// original is already smaller, the WebP and AVIF copies keep the original
+/// size, and a small thumbnail is only cropped.
+pub fn generate_renditions(source: &[u8]) -> Result<Vec<EncodedRendition>, RenditionError> {
+    let mut reader = ImageReader::new(Cursor::new(source)).with_guessed_format()?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let has_alpha = image.color().has_alpha();
+    let mut renditions = Vec::with_capacity(RENDITION_SPECS.len());
+
+    for spec in RENDITION_SPECS {
+        let fits = image.width() <= spec.width && image.height() <= spec.height;
+        let resized = match spec.fit {
+            Fit::Cover => {
+                let (width, height) = (spec.width.min(image.width()), spec.height.min(image.height()));
+                if (width, height) == (image.width(), image.height()) {
+                    image.clone()
+                } else {
+                    image.resize_to_fill(width, height, FilterType::Lanczos3)
+                }
+            }
+            Fit::Contain if fits && spec.format == OutputFormat::Web => continue,
+            Fit::Contain if fits => image.clone(),
+            Fit::Contain => image.resize(spec.width, spec.height, FilterType::Lanczos3),
+        };
+
+        let mut bytes = Vec::new();
+        let (media_type, extension) = match (spec.format, has_alpha) {
+            (OutputFormat::Web, false) => {
+                DynamicImage::ImageRgb8(resized.to_rgb8())
+                    .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY))?;
+                ("image/jpeg", "jpg")
+            }
+            (OutputFormat::Web, true) => {
+                DynamicImage::ImageRgba8(resized.to_rgba8())
+                    .write_with_encoder(PngEncoder::new(&mut bytes))?;
+                ("image/png", "png")
+            }
+            (OutputFormat::Webp, alpha) => {
+                let pixels = if alpha {
+                    DynamicImage::ImageRgba8(resized.to_rgba8())
+                } else {
+                    DynamicImage::ImageRgb8(resized.to_rgb8())
+                };
+                pixels.write_with_encoder(WebPEncoder::new_lossless(&mut bytes))?;
+                ("image/webp", "webp")
+            }
+            (OutputFormat::Avif, alpha) => {
+                let pixels = if alpha {
+                    DynamicImage::ImageRgba8(resized.to_rgba8())
+                } else {
+                    DynamicImage::ImageRgb8(resized.to_rgb8())
+                };
+                pixels.write_with_encoder(AvifEncoder::new_with_speed_quality(
+                    &mut bytes,
+                    AVIF_SPEED,
+                    AVIF_QUALITY,
+                ))?;
+                ("image/avif", "avif")
+            }
+        };
+
+        renditions.push(EncodedRendition {
+            name: spec.name,
+            bytes,
+            media_type,
+            extension,
+            width: resized.width(),
+            height: resized.height(),
+        });
+    }
+
+    Ok(renditions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    fn find(renditions: &[EncodedRendition], name: RenditionName) -> Option<&EncodedRendition> {
+        renditions.iter().find(|r| r.name == name)
+    }
+
+    #[test]
+    fn large_photos_get_every_rendition() {
+        let photo = RgbImage::from_pixel(2000, 1000, Rgb([200, 120, 40]));
+        let renditions = generate_renditions(&encode(DynamicImage::ImageRgb8(photo), ImageFormat::Jpeg)).unwrap();
+
+        let thumbnail = find(&renditions, RenditionName::Thumbnail).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (300, 300));
+        assert_eq!(thumbnail.media_type, "image/jpeg");
+
+        let medium = find(&renditions, RenditionName::Medium).unwrap();
+        assert_eq!((medium.width, medium.height), (768, 384));
+
+        let large = find(&renditions, RenditionName::Large).unwrap();
+        assert_eq!((large.width, large.height), (1600, 800));
+
+        let webp = find(&renditions, RenditionName::Webp).unwrap();
+        assert_eq!(webp.media_type, "image/webp");
+        assert_eq!((webp.width, webp.height), (1600, 800));
+
+        let avif = find(&renditions, RenditionName::Avif).unwrap();
+        assert_eq!(avif.media_type, "image/avif");
+        assert_eq!((avif.width, avif.height), (1600, 800));
+    }
+
+    #[test]
+    fn small_images_are_not_upscaled() {
+        let icon = RgbaImage::from_pixel(64, 48, Rgba([0, 0, 0, 128]));
+        let renditions = generate_renditions(&encode(DynamicImage::ImageRgba8(icon), ImageFormat::Png)).unwrap();
+
+        assert!(find(&renditions, RenditionName::Medium).is_none());
+        assert!(find(&renditions, RenditionName::Large).is_none());
+        let thumbnail = find(&renditions, RenditionName::Thumbnail).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (64, 48));
+        // Transparency survives as PNG
+        assert_eq!(thumbnail.media_type, "image/png");
+        let webp = find(&renditions, RenditionName::Webp).unwrap();
+        assert_eq!((webp.width, webp.height), (64, 48));
+        let avif = find(&renditions, RenditionName::Avif).unwrap();
+        assert_eq!((avif.width, avif.height), (64, 48));
+    }
+
+    #[test]
+    fn narrow_images_are_cropped_not_enlarged_for_thumbnails() {
+        let banner = RgbImage::from_pixel(1200, 100, Rgb([10, 20, 30]));
+        let renditions = generate_renditions(&encode(DynamicImage::ImageRgb8(banner), ImageFormat::Png)).unwrap();
+        let thumbnail = find(&renditions, RenditionName::Thumbnail).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (300, 100));
+    }
+
+    #[test]
+    fn rendition_keys_sit_next_to_the_original() {
+        assert_eq!(
+            rendition_key("2024/09/abc.jpeg", RenditionName::Thumbnail, "jpg"),
+            "2024/09/abc-thumbnail.jpg"
+        );
+        assert_eq!(rendition_key("legacy/7", RenditionName::Avif, "avif"), "legacy/7-avif.avif");
+    }
+}
@@ -0,0 +1,281 @@
+// src/backend/services/revision_service.rs
+
+use chrono::Utc;
+use diesel::dsl::max;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use thiserror::Error;
+use tracing::error;
+
+use crate::backend::models::post::{Post, PostStatus};
+use crate::backend::models::post_revision::{
+    DiffLine, DiffOp, NewPostRevision, PostRevision, RevisionDiff,
+};
+use crate::backend::schema::{post_revisions, posts};
+use crate::backend::utils::db::DbPool;
+
+#[derive(Debug, Error)]
+pub enum RevisionServiceError {
+    #[error("Revision not found")]
+    NotFound,
+    #[error("Cannot move post from {0} to {1}")]
+    InvalidStatusTransition(PostStatus, PostStatus),
+    #[error("Scheduled posts need a publish_at time")]
+    MissingPublishAt,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for RevisionServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => RevisionServiceError::NotFound,
+            e => {
+                error!("Database error: {:?}", e);
+                RevisionServiceError::DatabaseError(e.to_string())
+            }
+        }
+    }
+}
+
+/// Store the current state of `post` as its next revision, credited to
+/// `editor_id`, the user whose change overwrites it.
+///
+/// Runs on the caller's connection so it can share the transaction that
+/// overwrites the post.
+pub(crate) fn record_snapshot(
+    conn: &PgConnection,
+    post: &Post,
+    editor_id: i32,
+) -> Result<PostRevision, diesel::result::Error> {
+    let latest = post_revisions::table
+        .filter(post_revisions::post_id.eq(post.id))
+        .select(max(post_revisions::revision_number))
+        .first::<Option<i32>>(conn)?;
+
+    diesel::insert_into(post_revisions::table)
+        .values(&NewPostRevision::from_post(post, latest.unwrap_or(0) + 1, editor_id))
+        .get_result::<PostRevision>(conn)
+}
+
+pub struct RevisionService {
+    db_pool: DbPool,
+}
+
+impl RevisionService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// List the revisions of a post, newest first
+    pub async fn list_revisions(&self, post_id: i32) -> Result<Vec<PostRevision>, RevisionServiceError> {
+        let conn = self.get_connection()?;
+        post_revisions::table
+            .filter(post_revisions::post_id.eq(post_id))
+            .order(post_revisions::revision_number.desc())
+            .load::<PostRevision>(&conn)
+            .map_err(RevisionServiceError::from)
+    }
+
+    /// Fetch a single revision belonging to `post_id`
+    pub async fn get_revision(
+        &self,
+        post_id: i32,
+        revision_id: i32,
+    ) -> Result<PostRevision, RevisionServiceError> {
+        let conn = self.get_connection()?;
+        Self::find_revision(&conn, post_id, revision_id)
+    }
+
+    /// Line-level diff between two revisions of the same post
+    pub async fn diff_revisions(
+        &self,
+        post_id: i32,
+        from_id: i32,
+        to_id: i32,
+    ) -> Result<RevisionDiff, RevisionServiceError> {
+        let conn = self.get_connection()?;
+        let from = Self::find_revision(&conn, post_id, from_id)?;
+        let to = Self::find_revision(&conn, post_id, to_id)?;
+
+        Ok(RevisionDiff {
+            post_id,
+            from_revision: from.revision_number,
+            to_revision: to.revision_number,
+            title: diff_lines(&from.title, &to.title),
+            slug: diff_lines(&from.slug, &to.slug),
+            status: diff_lines(from.status.as_str(), to.status.as_str()),
+            content: diff_lines(&from.content, &to.content),
+        })
+    }
+
+    /// Restore a post to the given revision.
+    ///
+    /// The current state is snapshotted first, credited to `editor_id`, so a
+    /// restore can itself be undone. The revision's status must be one the
+    /// post may move to, as for any other update.
+    pub async fn restore_revision(
+        &self,
+        post_id: i32,
+        revision_id: i32,
+        editor_id: i32,
+    ) -> Result<Post, RevisionServiceError> {
+        let conn = self.get_connection()?;
+        conn.transaction::<_, RevisionServiceError, _>(|| {
+            let revision = Self::find_revision(&conn, post_id, revision_id)?;
+            let current = posts::table.find(post_id).for_update().first::<Post>(&conn)?;
+            if !current.status.can_transition_to(revision.status) {
+                return Err(RevisionServiceError::InvalidStatusTransition(current.status, revision.status));
+            }
+            if revision.status == PostStatus::Scheduled && current.publish_at.is_none() {
+                return Err(RevisionServiceError::MissingPublishAt);
+            }
+            record_snapshot(&conn, &current, editor_id)?;
+
+            let now = Utc::now().naive_utc();
+            let first_publish = revision.status == PostStatus::Published && current.published_at.is_none();
+            diesel::update(posts::table.find(post_id))
+                .set((
+                    posts::title.eq(&revision.title),
+                    posts::slug.eq(&revision.slug),
+                    posts::content.eq(&revision.content),
+                    posts::status.eq(revision.status),
+                    posts::updated_at.eq(now),
+                    posts::published_at.eq(if first_publish { Some(now) } else { current.published_at }),
+                ))
+                .get_result::<Post>(&conn)
+                .map_err(RevisionServiceError::from)
+        })
+    }
+
+    fn find_revision(
+        conn: &PgConnection,
+        post_id: i32,
+        revision_id: i32,
+    ) -> Result<PostRevision, RevisionServiceError> {
+        post_revisions::table
+            .filter(post_revisions::post_id.eq(post_id))
+            .filter(post_revisions::id.eq(revision_id))
+            .first::<PostRevision>(conn)
+            .map_err(RevisionServiceError::from)
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, RevisionServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            RevisionServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+/// Largest LCS table `diff_lines` will build, in cells. Past it the changed
+/// middle of the text is shown as a plain delete and insert.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Compute a line-level diff using the longest common subsequence of lines
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Unchanged lines at either end need no table
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let line = |op, text: &str| DiffLine { op, text: text.to_string() };
+    let mut result = Vec::with_capacity(old.len().max(new.len()));
+    result.extend(old[..prefix].iter().map(|l| line(DiffOp::Equal, l)));
+    if a.len().saturating_mul(b.len()) > MAX_DIFF_CELLS {
+        result.extend(a.iter().map(|l| line(DiffOp::Delete, l)));
+        result.extend(b.iter().map(|l| line(DiffOp::Insert, l)));
+    } else {
+        diff_middle(a, b, &mut result);
+    }
+    result.extend(old[old.len() - suffix..].iter().map(|l| line(DiffOp::Equal, l)));
+    result
+}
+
+fn diff_middle(a: &[&str], b: &[&str], result: &mut Vec<DiffLine>) {
+    // lcs[i][j] holds the LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let line = |op, text: &str| DiffLine { op, text: text.to_string() };
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            result.push(line(DiffOp::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            result.push(line(DiffOp::Delete, a[i]));
+            i += 1;
+        } else {
+            result.push(line(DiffOp::Insert, b[j]));
+            j += 1;
+        }
+    }
+    result.extend(a[i..].iter().map(|l| line(DiffOp::Delete, l)));
+    result.extend(b[j..].iter().map(|l| line(DiffOp::Insert, l)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ops(diff: &[DiffLine]) -> Vec<(DiffOp, &str)> {
+        diff.iter().map(|l| (l.op, l.text.as_str())).collect()
+    }
+
+    #[test]
+    fn identical_text_is_all_equal() {
+        let diff = diff_lines("a\nb", "a\nb");
+        assert_eq!(ops(&diff), vec![(DiffOp::Equal, "a"), (DiffOp::Equal, "b")]);
+    }
+
+    #[test]
+    fn changed_line_is_delete_then_insert() {
+        let diff = diff_lines("a\nb\nc", "a\nx\nc");
+        assert_eq!(
+            ops(&diff),
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Delete, "b"),
+                (DiffOp::Insert, "x"),
+                (DiffOp::Equal, "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_changes_fall_back_to_delete_then_insert() {
+        let old: String = (0..3000).map(|n| format!("old {}\n", n)).collect();
+        let new: String = (0..3000).map(|n| format!("new {}\n", n)).collect();
+        let diff = diff_lines(&format!("head\n{}tail", old), &format!("head\n{}tail", new));
+        assert_eq!(diff.len(), 6002);
+        assert_eq!(ops(&diff[..2]), vec![(DiffOp::Equal, "head"), (DiffOp::Delete, "old 0")]);
+        assert_eq!(ops(&diff[3001..3002]), vec![(DiffOp::Insert, "new 0")]);
+        assert_eq!(ops(&diff[6001..]), vec![(DiffOp::Equal, "tail")]);
+    }
+
+    #[test]
+    fn empty_old_text_is_all_inserts() {
+        let diff = diff_lines("", "a\nb");
+        assert_eq!(ops(&diff), vec![(DiffOp::Insert, "a"), (DiffOp::Insert, "b")]);
+    }
+}
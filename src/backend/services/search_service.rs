@@ -0,0 +1,232 @@
+// src/backend/services/search_service.rs
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::sql_types::{BigInt, Bool, Float4, Integer, Nullable, Text};
+use thiserror::Error;
+use tracing::error;
+
+use crate::backend::models::post::PostStatus;
+use crate::backend::services::public_service::html_to_text;
+use crate::backend::services::render_service::escape_html;
+use crate::backend::utils::db::DbPool;
+use crate::shared::types::{SearchHit, SearchKind, SearchResults};
+
+pub const DEFAULT_PER_PAGE: i64 = 20;
+pub const MAX_PER_PAGE: i64 = 100;
+/// Deepest page served; keeps the offset far from overflowing
+const MAX_PAGE: i64 = 10_000;
+
+/// Longest query string accepted, in characters
+const MAX_QUERY_CHARS: usize = 200;
+
+/// Markers `ts_headline` wraps matches in. Control characters can't occur in
+/// the stripped text, so they survive escaping and become `<mark>` afterwards.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// Posts, pages and comments matching the query, ranked together. Filters are
+/// passed as nullable parameters so the statement keeps a single shape; page
+/// hits are dropped whenever a post-only filter is set. Snippets are built
+/// after pagination so `ts_headline` only runs on the rows returned.
+const SEARCH_SQL: &str = r#"
+WITH q AS (SELECT websearch_to_tsquery('english', $1) AS query),
+hits AS (
+    SELECT 'post' AS kind, p.id, p.title, p.slug, NULL::integer AS post_id,
+           ts_rank_cd(p.search_vector, q.query) AS rank, p.content AS body
+    FROM posts p, q
+    WHERE p.search_vector @@ q.query
+      AND ($2::text IS NULL OR $2::text = 'post')
+      AND ($3::integer IS NULL OR p.category_id = $3::integer)
+      AND ($4::text IS NULL OR p.status = $4::text)
+      AND ($5::integer IS NULL OR p.author_id = $5::integer)
+      AND ($6::integer IS NULL OR p.status = 'published' OR p.author_id = $6::integer)
+      AND ($10::text IS NULL OR EXISTS (
+          SELECT 1 FROM post_tags pt JOIN tags t ON t.id = pt.tag_id
+          WHERE pt.post_id = p.id AND t.slug = $10::text))
+    UNION ALL
+    SELECT 'page', pg.id, pg.title, pg.slug, NULL::integer,
+           ts_rank_cd(pg.search_vector, q.query), pg.content
+    FROM pages pg, q
+    WHERE pg.search_vector @@ q.query
+      AND ($2::text IS NULL OR $2::text = 'page')
+      AND $3::integer IS NULL
+      AND $4::text IS NULL
+      AND $10::text IS NULL
+      AND ($5::integer IS NULL OR pg.user_id = $5::integer)
+    UNION ALL
+    SELECT 'comment', c.id, p.title, p.slug, c.post_id,
+           ts_rank_cd(c.search_vector, q.query), c.content
+    FROM comments c
+    JOIN posts p ON p.id = c.post_id, q
+    WHERE c.search_vector @@ q.query
+      AND (c.status = 'approved' OR ($11::boolean AND c.status = 'pending'))
+      AND ($2::text IS NULL OR $2::text = 'comment')
+      AND ($3::integer IS NULL OR p.category_id = $3::integer)
+      AND ($4::text IS NULL OR p.status = $4::text)
+      AND ($5::integer IS NULL OR c.user_id = $5::integer)
+      AND ($6::integer IS NULL OR p.status = 'published' OR p.author_id = $6::integer)
+      AND ($10::text IS NULL OR EXISTS (
+          SELECT 1 FROM post_tags pt JOIN tags t ON t.id = pt.tag_id
+          WHERE pt.post_id = p.id AND t.slug = $10::text))
+),
+paged AS (
+    SELECT hits.*, count(*) OVER () AS total
+    FROM hits
+    ORDER BY rank DESC, id DESC
+    LIMIT $8 OFFSET $9
+)
+SELECT kind, id, title, slug, post_id, rank, total,
+       ts_headline('english', regexp_replace(body, '<[^>]*>', ' ', 'g'), q.query, $7) AS snippet
+FROM paged, q
+ORDER BY rank DESC, id DESC
+"#;
+
+#[derive(Debug, Error)]
+pub enum SearchServiceError {
+    #[error("Invalid search query")]
+    InvalidQuery,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for SearchServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        error!("Database error: {:?}", err);
+        SearchServiceError::DatabaseError(err.to_string())
+    }
+}
+
+/// Narrowing applied to a search; `None` means "any"
+#[derive(Debug, Default)]
+pub struct SearchFilters {
+    pub kind: Option<SearchKind>,
+    pub category_id: Option<i32>,
+    pub status: Option<PostStatus>,
+    pub author_id: Option<i32>,
+    /// Only show unpublished posts (and their comments) written by this user
+    pub drafts_of: Option<i32>,
+    /// Slug of a tag the posts must have
+    pub tag: Option<String>,
+    /// Also match comments awaiting moderation; others only match once approved
+    pub pending_comments: bool,
+}
+
+#[derive(QueryableByName)]
+struct SearchRow {
+    #[sql_type = "Text"]
+    kind: String,
+    #[sql_type = "Integer"]
+    id: i32,
+    #[sql_type = "Text"]
+    title: String,
+    #[sql_type = "Text"]
+    slug: String,
+    #[sql_type = "Nullable<Integer>"]
+    post_id: Option<i32>,
+    #[sql_type = "Float4"]
+    rank: f32,
+    #[sql_type = "BigInt"]
+    total: i64,
+    #[sql_type = "Text"]
+    snippet: String,
+}
+
+pub struct SearchService {
+    db_pool: DbPool,
+}
+
+impl SearchService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Run a web-style query (`"exact phrase"`, `or`, `-exclude`) across
+    /// posts, pages and comments, best matches first.
+    pub async fn search(
+        &self,
+        query: &str,
+        filters: &SearchFilters,
+        page: i64,
+        per_page: i64,
+    ) -> Result<SearchResults, SearchServiceError> {
+        let query = query.trim();
+        if query.is_empty() || query.chars().count() > MAX_QUERY_CHARS {
+            return Err(SearchServiceError::InvalidQuery);
+        }
+        let page = page.clamp(1, MAX_PAGE);
+        let per_page = per_page.clamp(1, MAX_PER_PAGE);
+        let headline_options = format!(
+            "StartSel={}, StopSel={}, MaxFragments=2, MaxWords=30, MinWords=10, FragmentDelimiter=\" … \"",
+            MATCH_START, MATCH_END
+        );
+
+        let conn = self.get_connection()?;
+        let rows = diesel::sql_query(SEARCH_SQL)
+            .bind::<Text, _>(query)
+            .bind::<Nullable<Text>, _>(filters.kind.map(|k| k.as_str()))
+            .bind::<Nullable<Integer>, _>(filters.category_id)
+            .bind::<Nullable<Text>, _>(filters.status.map(|s| s.as_str()))
+            .bind::<Nullable<Integer>, _>(filters.author_id)
+            .bind::<Nullable<Integer>, _>(filters.drafts_of)
+            .bind::<Text, _>(headline_options)
+            .bind::<BigInt, _>(per_page)
+            .bind::<BigInt, _>((page - 1) * per_page)
+            .bind::<Nullable<Text>, _>(filters.tag.as_deref())
+            .bind::<Bool, _>(filters.pending_comments)
+            .load::<SearchRow>(&conn)?;
+
+        let total = rows.first().map_or(0, |row| row.total);
+        let hits = rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(SearchHit {
+                    kind: row.kind.parse().ok()?,
+                    id: row.id,
+                    title: row.title,
+                    slug: row.slug,
+                    post_id: row.post_id,
+                    rank: row.rank,
+                    snippet: highlight_snippet(&row.snippet),
+                })
+            })
+            .collect();
+
+        Ok(SearchResults {
+            query: query.to_string(),
+            total,
+            page,
+            per_page,
+            hits,
+        })
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, SearchServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            SearchServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+/// Turn a `ts_headline` fragment into safe HTML with matches wrapped in `<mark>`
+fn highlight_snippet(headline: &str) -> String {
+    escape_html(&html_to_text(headline))
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_mark_matches_and_escape_everything_else() {
+        let headline = "fish &amp; \u{2}chips\u{3} &lt;script&gt;";
+        assert_eq!(
+            highlight_snippet(headline),
+            "fish &amp; <mark>chips</mark> &lt;script&gt;"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+// src/backend/services/settings_service.rs
+
+use std::collections::{BTreeMap, HashMap};
+
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tracing::error;
+
+use crate::backend::models::settings::{NewSetting, Setting};
+use crate::backend::schema::settings;
+use crate::backend::services::{auth_service, spam_filter};
+use crate::backend::utils::db::DbPool;
+use crate::shared::settings::{
+    SettingDef, SettingError, SettingGroup, SettingGroupValues, SettingKind, SettingValue,
+};
+
+#[derive(Debug, Error)]
+pub enum SettingsServiceError {
+    #[error("Invalid settings")]
+    Invalid(Vec<SettingError>),
+    #[error("Unknown setting: {0}")]
+    UnknownSetting(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for SettingsServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        error!("Database error: {:?}", err);
+        SettingsServiceError::DatabaseError(err.to_string())
+    }
+}
+
+/// Every setting the site knows about, grouped as the Settings page shows them.
+///
+/// Modules declare the settings they read with a `settings()` function
+/// returning a [`SettingGroup`]; [`SettingsRegistry::standard`] collects them.
+/// Modules contributing to the same group id are shown together.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsRegistry {
+    groups: Vec<SettingGroup>,
+}
+
+impl SettingsRegistry {
+    /// The registry with every built-in module's settings
+    pub fn standard() -> Self {
+        let mut registry = Self::default();
+        registry.register(general_settings());
+        registry.register(auth_service::settings());
+        registry.register(spam_filter::settings());
+        registry
+    }
+
+    /// Add a module's settings. Keys must be unique across the registry.
+    pub fn register(&mut self, group: SettingGroup) {
+        for def in &group.settings {
+            assert!(self.get(&def.key).is_none(), "setting {} is registered twice", def.key);
+        }
+        match self.groups.iter_mut().find(|existing| existing.id == group.id) {
+            Some(existing) => existing.settings.extend(group.settings),
+            None => self.groups.push(group),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SettingDef> {
+        self.defs().find(|def| def.key == key)
+    }
+
+    pub fn groups(&self) -> &[SettingGroup] {
+        &self.groups
+    }
+
+    fn defs(&self) -> impl Iterator<Item = &SettingDef> {
+        self.groups.iter().flat_map(|group| &group.settings)
+    }
+}
+
+/// Site identity, shown first on the Settings page
+fn general_settings() -> SettingGroup {
+    SettingGroup {
+        id: "general".to_string(),
+        label: "General".to_string(),
+        settings: vec![
+            SettingDef::new(
+                "site_name",
+                "Site name",
+                SettingKind::Text { max_chars: Some(100), multiline: false, required: true },
+                json!("My Rust CMS"),
+            ),
+            SettingDef::new(
+                "site_description",
+                "Site description",
+                SettingKind::Text { max_chars: Some(300), multiline: true, required: false },
+                json!(""),
+            ),
+            SettingDef::new("admin_email", "Administrator email", SettingKind::Email { required: false }, json!(""))
+                .description("Where the site sends notices meant for administrators"),
+        ],
+    }
+}
+
+/// Reads and writes settings declared in the registry, stored as text in
+/// the `settings` key/value table
+pub struct SettingsService {
+    db_pool: DbPool,
+    registry: SettingsRegistry,
+}
+
+impl SettingsService {
+    pub fn new(db_pool: DbPool, registry: SettingsRegistry) -> Self {
+        Self { db_pool, registry }
+    }
+
+    /// Every registered setting with its current value
+    pub async fn list(&self) -> Result<Vec<SettingGroupValues>, SettingsServiceError> {
+        let conn = self.get_connection()?;
+        let stored = stored_values(&conn)?;
+        Ok(self
+            .registry
+            .groups()
+            .iter()
+            .map(|group| SettingGroupValues {
+                id: group.id.clone(),
+                label: group.label.clone(),
+                settings: group
+                    .settings
+                    .iter()
+                    .map(|def| SettingValue {
+                        value: def.decode(stored.get(&def.key).and_then(Option::as_deref)),
+                        def: def.clone(),
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// Current value of one setting, or its default
+    pub async fn get(&self, key: &str) -> Result<Value, SettingsServiceError> {
+        let def = self
+            .registry
+            .get(key)
+            .ok_or_else(|| SettingsServiceError::UnknownSetting(key.to_string()))?;
+        let conn = self.get_connection()?;
+        let stored = settings::table
+            .filter(settings::setting_key.eq(key))
+            .select(settings::setting_value)
+            .first::<Option<String>>(&conn)
+            .optional()?
+            .flatten();
+        Ok(def.decode(stored.as_deref()))
+    }
+
+    pub async fn get_bool(&self, key: &str) -> Result<bool, SettingsServiceError> {
+        Ok(self.get(key).await?.as_bool().unwrap_or(false))
+    }
+
+    pub async fn get_string(&self, key: &str) -> Result<String, SettingsServiceError> {
+        Ok(self.get(key).await?.as_str().unwrap_or_default().to_string())
+    }
+
+    /// Validate and save several settings at once. Nothing is saved unless
+    /// every value is valid.
+    pub async fn update(&self, values: BTreeMap<String, Value>) -> Result<Vec<SettingGroupValues>, SettingsServiceError> {
+        let mut errors = Vec::new();
+        let mut rows = Vec::new();
+        for (key, value) in &values {
+            let def = match self.registry.get(key) {
+                Some(def) => def,
+                None => {
+                    errors.push(SettingError { key: key.clone(), message: "is not a known setting".to_string() });
+                    continue;
+                }
+            };
+            match def.validate(value) {
+                Ok(value) => rows.push(NewSetting {
+                    setting_key: key.clone(),
+                    setting_value: Some(def.encode(&value)),
+                }),
+                Err(message) => errors.push(SettingError { key: key.clone(), message }),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(SettingsServiceError::Invalid(errors));
+        }
+
+        let conn = self.get_connection()?;
+        if !rows.is_empty() {
+            diesel::insert_into(settings::table)
+                .values(&rows)
+                .on_conflict(settings::setting_key)
+                .do_update()
+                .set(settings::setting_value.eq(excluded(settings::setting_value)))
+                .execute(&conn)?;
+        }
+        drop(conn);
+        self.list().await
+    }
+
+    /// Forget a saved value so the default applies again
+    pub async fn reset(&self, key: &str) -> Result<Value, SettingsServiceError> {
+        let def = self
+            .registry
+            .get(key)
+            .ok_or_else(|| SettingsServiceError::UnknownSetting(key.to_string()))?;
+        let conn = self.get_connection()?;
+        diesel::delete(settings::table.filter(settings::setting_key.eq(key))).execute(&conn)?;
+        Ok(def.default.clone())
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, SettingsServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            SettingsServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+fn stored_values(conn: &PgConnection) -> QueryResult<HashMap<String, Option<String>>> {
+    Ok(settings::table
+        .load::<Setting>(conn)?
+        .into_iter()
+        .map(|setting| (setting.setting_key, setting.setting_value))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modules_sharing_a_group_are_shown_together() {
+        let registry = SettingsRegistry::standard();
+        assert_eq!(registry.groups()[0].id, "general");
+        assert!(registry.get(spam_filter::BLOCKLIST_SETTING).is_some());
+
+        let mut registry = SettingsRegistry::default();
+        registry.register(general_settings());
+        registry.register(SettingGroup {
+            id: "general".to_string(),
+            label: "General".to_string(),
+            settings: vec![SettingDef::new("home_url", "Home link", SettingKind::Url, json!("/"))],
+        });
+        assert_eq!(registry.groups().len(), 1);
+        assert_eq!(registry.groups()[0].settings.len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "registered twice")]
+    fn keys_are_unique() {
+        let mut registry = SettingsRegistry::standard();
+        registry.register(general_settings());
+    }
+}
@@ -0,0 +1,629 @@
+// src/backend/services/spam_filter.rs
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::env;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Integer, Text};
+use serde_json::json;
+use thiserror::Error;
+use tracing::error;
+
+use crate::backend::models::spam::{SpamLabel, SpamOutcome, SpamVerdict};
+use crate::backend::schema::{settings, spam_corpus, spam_tokens};
+use crate::backend::utils::db::DbPool;
+use crate::shared::settings::{SettingDef, SettingGroup, SettingKind};
+
+/// Comments allowed per IP within the window when `COMMENT_RATE_LIMIT` is unset
+const DEFAULT_RATE_LIMIT: usize = 5;
+/// Length of the rate-limit window when `COMMENT_RATE_WINDOW_SECS` is unset
+const DEFAULT_RATE_WINDOW_SECS: u64 = 600;
+/// Tracked IPs beyond which the rate limiter sweeps out idle entries
+const RATE_LIMIT_SWEEP_AT: usize = 10_000;
+
+/// Links in a comment before it is held for moderation, and before it is filed as spam
+const LINKS_HOLD_AT: usize = 2;
+const LINKS_SPAM_AT: usize = 5;
+
+/// `settings` key holding the blocklist, one term per line
+pub const BLOCKLIST_SETTING: &str = "comment_blocklist";
+/// Longest blocklist accepted
+const MAX_BLOCKLIST_CHARS: usize = 50_000;
+
+/// Classifier probabilities at which a comment is held, and filed as spam
+const BAYES_HOLD_AT: f64 = 0.7;
+const BAYES_SPAM_AT: f64 = 0.9;
+/// Documents of each class the classifier needs before it gives an opinion
+const BAYES_MIN_DOCS: i32 = 10;
+
+const MIN_TOKEN_CHARS: usize = 3;
+const MAX_TOKEN_CHARS: usize = 24;
+
+#[derive(Debug, Error)]
+pub enum SpamFilterError {
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for SpamFilterError {
+    fn from(err: diesel::result::Error) -> Self {
+        SpamFilterError::DatabaseError(err.to_string())
+    }
+}
+
+/// Settings read by the spam filters
+pub fn settings() -> SettingGroup {
+    SettingGroup {
+        id: "comments".to_string(),
+        label: "Comments".to_string(),
+        settings: vec![SettingDef::new(
+            BLOCKLIST_SETTING,
+            "Comment blocklist",
+            SettingKind::Text {
+                max_chars: Some(MAX_BLOCKLIST_CHARS),
+                multiline: true,
+                required: false,
+            },
+            json!(""),
+        )
+        .description("Words, domains, email addresses or IPs, one per line. Matching comments are filed as spam.")],
+    }
+}
+
+/// The parts of a comment the filters look at
+#[derive(Debug, Clone)]
+pub struct CommentCandidate<'a> {
+    pub content: &'a str,
+    pub author_name: &'a str,
+    pub author_email: &'a str,
+    pub ip: Option<IpAddr>,
+    /// Value of the hidden honeypot form field; people leave it empty
+    pub honeypot: &'a str,
+}
+
+/// A check run on every comment before it is saved.
+///
+/// Filters that learn from moderators override [`SpamFilter::learn`], which
+/// is called whenever a comment is approved or marked as spam.
+#[async_trait]
+pub trait SpamFilter: Send + Sync {
+    /// Identifier recorded with each verdict
+    fn name(&self) -> &'static str;
+
+    async fn check(&self, comment: &CommentCandidate<'_>) -> Result<SpamVerdict, SpamFilterError>;
+
+    /// Train on a moderator decision. `previous` is the label the comment was
+    /// last trained with, which should be unlearned first.
+    async fn learn(
+        &self,
+        _comment: &CommentCandidate<'_>,
+        _label: SpamLabel,
+        _previous: Option<SpamLabel>,
+    ) -> Result<(), SpamFilterError> {
+        Ok(())
+    }
+}
+
+fn verdict(filter: &dyn SpamFilter, outcome: SpamOutcome, score: Option<f64>, reason: impl Into<String>) -> SpamVerdict {
+    SpamVerdict {
+        filter: filter.name().to_string(),
+        outcome,
+        score,
+        reason: reason.into(),
+    }
+}
+
+/// Combined result of running every filter over a comment
+#[derive(Debug)]
+pub struct SpamReport {
+    /// The most severe outcome any filter returned
+    pub outcome: SpamOutcome,
+    pub verdicts: Vec<SpamVerdict>,
+}
+
+/// The filters a comment goes through, in order
+pub struct SpamPipeline {
+    filters: Vec<Box<dyn SpamFilter>>,
+}
+
+impl SpamPipeline {
+    pub fn new(filters: Vec<Box<dyn SpamFilter>>) -> Self {
+        Self { filters }
+    }
+
+    /// The built-in filters: honeypot, per-IP rate limit, link count,
+    /// blocklist and the naive-Bayes classifier
+    pub fn with_default_filters(db_pool: DbPool) -> Self {
+        Self::new(vec![
+            Box::new(HoneypotFilter),
+            Box::new(RateLimitFilter::from_env()),
+            Box::new(LinkCountFilter::default()),
+            Box::new(BlocklistFilter::new(db_pool.clone())),
+            Box::new(BayesFilter::new(db_pool)),
+        ])
+    }
+
+    /// Run every filter. A throttling verdict stops the pipeline; a filter
+    /// that errors is recorded as passing so an outage can't block comments.
+    pub async fn check(&self, comment: &CommentCandidate<'_>) -> SpamReport {
+        let mut verdicts = Vec::with_capacity(self.filters.len());
+        for filter in &self.filters {
+            let result = match filter.check(comment).await {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("Spam filter {} failed: {:?}", filter.name(), e);
+                    verdict(filter.as_ref(), SpamOutcome::Pass, None, format!("Check failed: {}", e))
+                }
+            };
+            let throttled = result.outcome == SpamOutcome::Throttle;
+            verdicts.push(result);
+            if throttled {
+                break;
+            }
+        }
+
+        SpamReport {
+            outcome: verdicts
+                .iter()
+                .map(|v| v.outcome)
+                .max()
+                .unwrap_or(SpamOutcome::Pass),
+            verdicts,
+        }
+    }
+
+    /// Pass a moderator decision on to every filter that learns
+    pub async fn learn(&self, comment: &CommentCandidate<'_>, label: SpamLabel, previous: Option<SpamLabel>) {
+        for filter in &self.filters {
+            if let Err(e) = filter.learn(comment, label, previous).await {
+                error!("Spam filter {} failed to learn: {:?}", filter.name(), e);
+            }
+        }
+    }
+}
+
+/// Flags comments that fill in the hidden `website` field only bots can see
+pub struct HoneypotFilter;
+
+#[async_trait]
+impl SpamFilter for HoneypotFilter {
+    fn name(&self) -> &'static str {
+        "honeypot"
+    }
+
+    async fn check(&self, comment: &CommentCandidate<'_>) -> Result<SpamVerdict, SpamFilterError> {
+        Ok(if comment.honeypot.trim().is_empty() {
+            verdict(self, SpamOutcome::Pass, None, "Honeypot empty")
+        } else {
+            verdict(self, SpamOutcome::Spam, None, "Hidden honeypot field was filled in")
+        })
+    }
+}
+
+/// Refuses comments from an IP that has posted too many within a sliding
+/// window. Counts are kept in memory, per backend instance.
+pub struct RateLimitFilter {
+    limit: usize,
+    window: Duration,
+    recent: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+}
+
+impl RateLimitFilter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let limit = env::var("COMMENT_RATE_LIMIT")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|limit| *limit > 0)
+            .unwrap_or(DEFAULT_RATE_LIMIT);
+        let secs = env::var("COMMENT_RATE_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_RATE_WINDOW_SECS);
+        Self::new(limit, Duration::from_secs(secs))
+    }
+
+    /// Record an attempt from `ip` at `now`, returning how many attempts
+    /// (including this one) fall in the window, or `None` if over the limit
+    fn record(&self, ip: IpAddr, now: Instant) -> Option<usize> {
+        let mut recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let window = self.window;
+        let expired = |at: &Instant| now.duration_since(*at) >= window;
+
+        if recent.len() >= RATE_LIMIT_SWEEP_AT {
+            recent.retain(|_, hits| {
+                while hits.front().map_or(false, expired) {
+                    hits.pop_front();
+                }
+                !hits.is_empty()
+            });
+        }
+
+        let hits = recent.entry(ip).or_default();
+        while hits.front().map_or(false, expired) {
+            hits.pop_front();
+        }
+        if hits.len() >= self.limit {
+            return None;
+        }
+        hits.push_back(now);
+        Some(hits.len())
+    }
+}
+
+#[async_trait]
+impl SpamFilter for RateLimitFilter {
+    fn name(&self) -> &'static str {
+        "rate_limit"
+    }
+
+    async fn check(&self, comment: &CommentCandidate<'_>) -> Result<SpamVerdict, SpamFilterError> {
+        let ip = match comment.ip {
+            Some(ip) => ip,
+            None => return Ok(verdict(self, SpamOutcome::Pass, None, "No client address")),
+        };
+
+        Ok(match self.record(ip, Instant::now()) {
+            Some(count) => verdict(
+                self,
+                SpamOutcome::Pass,
+                None,
+                format!("{} of {} comments allowed in {:?}", count, self.limit, self.window),
+            ),
+            None => verdict(
+                self,
+                SpamOutcome::Throttle,
+                None,
+                format!("More than {} comments from {} in {:?}", self.limit, ip, self.window),
+            ),
+        })
+    }
+}
+
+/// Holds or flags comments stuffed with links
+pub struct LinkCountFilter {
+    hold_at: usize,
+    spam_at: usize,
+}
+
+impl Default for LinkCountFilter {
+    fn default() -> Self {
+        Self {
+            hold_at: LINKS_HOLD_AT,
+            spam_at: LINKS_SPAM_AT,
+        }
+    }
+}
+
+/// Number of links in a piece of text, counting URLs and anchor tags
+fn count_links(text: &str) -> usize {
+    let lower = text.to_lowercase();
+    let urls = lower.matches("http://").count()
+        + lower.matches("https://").count()
+        + lower
+            .match_indices("www.")
+            .filter(|(i, _)| !lower[..*i].ends_with("//"))
+            .count();
+    // Anchors usually wrap a URL that was already counted
+    urls.max(lower.matches("<a ").count())
+}
+
+#[async_trait]
+impl SpamFilter for LinkCountFilter {
+    fn name(&self) -> &'static str {
+        "link_count"
+    }
+
+    async fn check(&self, comment: &CommentCandidate<'_>) -> Result<SpamVerdict, SpamFilterError> {
+        if count_links(comment.author_name) > 0 {
+            return Ok(verdict(self, SpamOutcome::Hold, None, "Author name contains a link"));
+        }
+
+        let links = count_links(comment.content);
+        let outcome = if links >= self.spam_at {
+            SpamOutcome::Spam
+        } else if links >= self.hold_at {
+            SpamOutcome::Hold
+        } else {
+            SpamOutcome::Pass
+        };
+        Ok(verdict(self, outcome, Some(links as f64), format!("{} links", links)))
+    }
+}
+
+/// Flags comments matching a moderator-maintained list of words, domains,
+/// email addresses or IPs, read from the `comment_blocklist` setting
+pub struct BlocklistFilter {
+    db_pool: DbPool,
+}
+
+impl BlocklistFilter {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    fn load_terms(&self) -> Result<Vec<String>, SpamFilterError> {
+        let conn = self
+            .db_pool
+            .get()
+            .map_err(|e| SpamFilterError::DatabaseError(e.to_string()))?;
+        let value = settings::table
+            .filter(settings::setting_key.eq(BLOCKLIST_SETTING))
+            .select(settings::setting_value)
+            .first::<Option<String>>(&conn)
+            .optional()?
+            .flatten()
+            .unwrap_or_default();
+
+        Ok(value
+            .lines()
+            .map(|line| line.trim().to_lowercase())
+            .filter(|line| !line.is_empty())
+            .collect())
+    }
+}
+
+/// The first blocklist term found in any part of the comment
+fn blocklist_match<'t>(terms: &'t [String], comment: &CommentCandidate<'_>) -> Option<&'t str> {
+    let ip = comment.ip.map(|ip| ip.to_string()).unwrap_or_default();
+    let haystacks = [
+        comment.content.to_lowercase(),
+        comment.author_name.to_lowercase(),
+        comment.author_email.to_lowercase(),
+    ];
+
+    terms
+        .iter()
+        .find(|term| *term == &ip || haystacks.iter().any(|h| h.contains(term.as_str())))
+        .map(String::as_str)
+}
+
+#[async_trait]
+impl SpamFilter for BlocklistFilter {
+    fn name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    async fn check(&self, comment: &CommentCandidate<'_>) -> Result<SpamVerdict, SpamFilterError> {
+        let terms = self.load_terms()?;
+        Ok(match blocklist_match(&terms, comment) {
+            Some(term) => verdict(self, SpamOutcome::Spam, None, format!("Matched blocklist entry \"{}\"", term)),
+            None => verdict(self, SpamOutcome::Pass, None, "No blocklist matches"),
+        })
+    }
+}
+
+/// Naive-Bayes classifier trained on moderator decisions. Token and
+/// document counts live in `spam_tokens` and `spam_corpus`.
+pub struct BayesFilter {
+    db_pool: DbPool,
+}
+
+impl BayesFilter {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    fn get_connection(
+        &self,
+    ) -> Result<diesel::r2d2::PooledConnection<diesel::r2d2::ConnectionManager<PgConnection>>, SpamFilterError> {
+        self.db_pool
+            .get()
+            .map_err(|e| SpamFilterError::DatabaseError(e.to_string()))
+    }
+}
+
+/// Distinct lower-cased words of a comment, plus the domains it links to
+/// and the domain of the author's email
+pub fn tokenize(comment: &CommentCandidate<'_>) -> BTreeSet<String> {
+    let mut tokens = BTreeSet::new();
+    let text = format!("{} {}", comment.author_name, comment.content).to_lowercase();
+
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let length = word.chars().count();
+        if (MIN_TOKEN_CHARS..=MAX_TOKEN_CHARS).contains(&length) {
+            tokens.insert(word.to_string());
+        }
+    }
+    for (i, scheme) in text.match_indices("://") {
+        let host: String = text[i + scheme.len()..]
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '.' || *c == '-')
+            .collect();
+        if !host.is_empty() {
+            tokens.insert(format!("url:{}", host.trim_start_matches("www.")));
+        }
+    }
+    if let Some((_, domain)) = comment.author_email.to_lowercase().split_once('@') {
+        tokens.insert(format!("email:{}", domain));
+    }
+    tokens
+}
+
+/// Probability that a document is spam given the training counts of the
+/// tokens it contains, as `(spam_count, ham_count)` pairs. Uses Laplace
+/// smoothing and the corpus class ratio as the prior.
+pub fn spam_probability(token_counts: &[(i32, i32)], spam_docs: i32, ham_docs: i32) -> f64 {
+    let spam_docs = f64::from(spam_docs.max(0));
+    let ham_docs = f64::from(ham_docs.max(0));
+    let mut log_spam = ((spam_docs + 1.0) / (spam_docs + ham_docs + 2.0)).ln();
+    let mut log_ham = ((ham_docs + 1.0) / (spam_docs + ham_docs + 2.0)).ln();
+
+    for &(spam_count, ham_count) in token_counts {
+        log_spam += ((f64::from(spam_count) + 1.0) / (spam_docs + 2.0)).ln();
+        log_ham += ((f64::from(ham_count) + 1.0) / (ham_docs + 2.0)).ln();
+    }
+
+    1.0 / (1.0 + (log_ham - log_spam).exp())
+}
+
+#[async_trait]
+impl SpamFilter for BayesFilter {
+    fn name(&self) -> &'static str {
+        "bayes"
+    }
+
+    async fn check(&self, comment: &CommentCandidate<'_>) -> Result<SpamVerdict, SpamFilterError> {
+        let conn = self.get_connection()?;
+        let (spam_docs, ham_docs) = spam_corpus::table
+            .select((spam_corpus::spam_docs, spam_corpus::ham_docs))
+            .first::<(i32, i32)>(&conn)?;
+
+        if spam_docs < BAYES_MIN_DOCS || ham_docs < BAYES_MIN_DOCS {
+            return Ok(verdict(
+                self,
+                SpamOutcome::Pass,
+                None,
+                format!("Not enough training yet ({} spam, {} ham)", spam_docs, ham_docs),
+            ));
+        }
+
+        let tokens: Vec<String> = tokenize(comment).into_iter().collect();
+        let counts = spam_tokens::table
+            .filter(spam_tokens::token.eq_any(&tokens))
+            .select((spam_tokens::spam_count, spam_tokens::ham_count))
+            .load::<(i32, i32)>(&conn)?;
+
+        let probability = spam_probability(&counts, spam_docs, ham_docs);
+        let outcome = if probability >= BAYES_SPAM_AT {
+            SpamOutcome::Spam
+        } else if probability >= BAYES_HOLD_AT {
+            SpamOutcome::Hold
+        } else {
+            SpamOutcome::Pass
+        };
+        Ok(verdict(
+            self,
+            outcome,
+            Some(probability),
+            format!("{:.0}% likely spam from {} known tokens", probability * 100.0, counts.len()),
+        ))
+    }
+
+    async fn learn(
+        &self,
+        comment: &CommentCandidate<'_>,
+        label: SpamLabel,
+        previous: Option<SpamLabel>,
+    ) -> Result<(), SpamFilterError> {
+        if previous == Some(label) {
+            return Ok(());
+        }
+
+        let delta = |l: SpamLabel| -> (i32, i32) {
+            let learn = i32::from(label == l);
+            let unlearn = i32::from(previous == Some(l));
+            (learn, unlearn)
+        };
+        let (spam_in, spam_out) = delta(SpamLabel::Spam);
+        let (ham_in, ham_out) = delta(SpamLabel::Ham);
+        let spam_delta = spam_in - spam_out;
+        let ham_delta = ham_in - ham_out;
+
+        let tokens: Vec<String> = tokenize(comment).into_iter().collect();
+        let conn = self.get_connection()?;
+        conn.transaction::<_, SpamFilterError, _>(|| {
+            diesel::sql_query(
+                "INSERT INTO spam_tokens (token, spam_count, ham_count) \
+                 SELECT t, GREATEST($2, 0), GREATEST($3, 0) FROM unnest($1::text[]) AS t \
+                 ON CONFLICT (token) DO UPDATE SET \
+                     spam_count = GREATEST(spam_tokens.spam_count + $2, 0), \
+                     ham_count = GREATEST(spam_tokens.ham_count + $3, 0)",
+            )
+            .bind::<Array<Text>, _>(&tokens)
+            .bind::<Integer, _>(spam_delta)
+            .bind::<Integer, _>(ham_delta)
+            .execute(&conn)?;
+
+            diesel::update(spam_corpus::table)
+                .set((
+                    spam_corpus::spam_docs.eq(diesel::dsl::sql(&format!(
+                        "GREATEST(spam_docs + ({}), 0)",
+                        spam_delta
+                    ))),
+                    spam_corpus::ham_docs.eq(diesel::dsl::sql(&format!(
+                        "GREATEST(ham_docs + ({}), 0)",
+                        ham_delta
+                    ))),
+                ))
+                .execute(&conn)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(content: &str) -> CommentCandidate<'_> {
+        CommentCandidate {
+            content,
+            author_name: "Reader",
+            author_email: "reader@example.com",
+            ip: Some("203.0.113.7".parse().unwrap()),
+            honeypot: "",
+        }
+    }
+
+    #[test]
+    fn rate_limit_refuses_once_the_window_is_full() {
+        let limiter = RateLimitFilter::new(2, Duration::from_secs(60));
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let start = Instant::now();
+
+        assert_eq!(limiter.record(ip, start), Some(1));
+        assert_eq!(limiter.record(ip, start + Duration::from_secs(1)), Some(2));
+        assert_eq!(limiter.record(ip, start + Duration::from_secs(2)), None);
+        // Other addresses are unaffected, and the first attempt eventually expires
+        assert_eq!(limiter.record("198.51.100.1".parse().unwrap(), start), Some(1));
+        assert_eq!(limiter.record(ip, start + Duration::from_secs(61)), Some(2));
+    }
+
+    #[test]
+    fn links_are_counted_once_each() {
+        assert_eq!(count_links("no links here"), 0);
+        assert_eq!(count_links("see https://www.example.com and www.example.org"), 2);
+        assert_eq!(count_links("<a href=\"http://a.test\">a</a> <a href=\"http://b.test\">b</a>"), 2);
+    }
+
+    #[test]
+    fn blocklist_matches_content_email_and_ip() {
+        let terms = vec!["cheap pills".to_string(), "203.0.113.7".to_string()];
+        assert_eq!(blocklist_match(&terms[..1], &candidate("Buy CHEAP pills now")), Some("cheap pills"));
+        assert_eq!(blocklist_match(&terms[1..], &candidate("hello")), Some("203.0.113.7"));
+        assert_eq!(blocklist_match(&terms[..1], &candidate("hello")), None);
+    }
+
+    #[test]
+    fn tokens_include_link_and_email_domains() {
+        let tokens = tokenize(&candidate("Visit https://www.spam.test/offer now"));
+        assert!(tokens.contains("visit"));
+        assert!(tokens.contains("url:spam.test"));
+        assert!(tokens.contains("email:example.com"));
+        assert!(!tokens.contains("now"));
+    }
+
+    #[test]
+    fn classifier_leans_towards_the_class_its_tokens_came_from() {
+        // Tokens seen mostly in spam push the probability up, and vice versa
+        let spammy = spam_probability(&[(40, 1), (35, 2)], 50, 50);
+        let hammy = spam_probability(&[(1, 40), (2, 30)], 50, 50);
+        assert!(spammy > BAYES_SPAM_AT);
+        assert!(hammy < 0.1);
+        // With no known tokens the prior decides
+        assert!((spam_probability(&[], 50, 50) - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,338 @@
+// src/backend/services/tag_service.rs
+
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use thiserror::Error;
+use tracing::error;
+
+use crate::backend::models::tag::{NewTag, PostTag, Tag, UpdateTag};
+use crate::backend::schema::{post_tags, tags};
+use crate::backend::utils::db::DbPool;
+use crate::shared::tags::{slugify, TagRequest, TagSummary, MAX_TAG_CHARS};
+
+/// Suggestions returned by autocompletion
+pub const AUTOCOMPLETE_LIMIT: usize = 10;
+
+#[derive(Debug, Error)]
+pub enum TagServiceError {
+    #[error("{0}")]
+    InvalidData(String),
+    #[error("A tag with the slug {0:?} already exists")]
+    AlreadyExists(String),
+    #[error("Tag not found")]
+    NotFound,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for TagServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => TagServiceError::NotFound,
+            e => {
+                error!("Database error: {:?}", e);
+                TagServiceError::DatabaseError(e.to_string())
+            }
+        }
+    }
+}
+
+pub struct TagService {
+    db_pool: DbPool,
+}
+
+impl TagService {
+    pub fn new(db_pool: DbPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Every tag with its post count, by name
+    pub async fn list(&self) -> Result<Vec<TagSummary>, TagServiceError> {
+        let conn = self.get_connection()?;
+        let all = tags::table.order(tags::name.asc()).load::<Tag>(&conn)?;
+        Ok(summaries(&conn, all)?)
+    }
+
+    /// Tags whose name or slug starts with `prefix`, most used first
+    pub async fn autocomplete(&self, prefix: &str) -> Result<Vec<TagSummary>, TagServiceError> {
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.get_connection()?;
+        let matching = tags::table
+            .filter(
+                tags::name
+                    .ilike(like_prefix(prefix))
+                    .or(tags::slug.like(like_prefix(&slugify(prefix)))),
+            )
+            .load::<Tag>(&conn)?;
+        let mut suggestions = summaries(&conn, matching)?;
+        suggestions.sort_by(|a, b| b.post_count.cmp(&a.post_count).then_with(|| a.name.cmp(&b.name)));
+        suggestions.truncate(AUTOCOMPLETE_LIMIT);
+        Ok(suggestions)
+    }
+
+    pub async fn get(&self, tag_id: i32) -> Result<TagSummary, TagServiceError> {
+        let conn = self.get_connection()?;
+        summary(&conn, tags::table.find(tag_id).first::<Tag>(&conn)?)
+    }
+
+    pub async fn create(&self, request: TagRequest) -> Result<TagSummary, TagServiceError> {
+        let new_tag = prepare(&request.name, request.slug.as_deref())?;
+        let conn = self.get_connection()?;
+        conn.transaction::<_, TagServiceError, _>(|| {
+            if slug_taken(&conn, &new_tag.slug, None)? {
+                return Err(TagServiceError::AlreadyExists(new_tag.slug.clone()));
+            }
+            let tag = diesel::insert_into(tags::table)
+                .values(&new_tag)
+                .get_result::<Tag>(&conn)?;
+            summary(&conn, tag)
+        })
+    }
+
+    /// Rename a tag. Its slug follows the new name unless one is given.
+    pub async fn update(&self, tag_id: i32, request: TagRequest) -> Result<TagSummary, TagServiceError> {
+        let NewTag { name, slug } = prepare(&request.name, request.slug.as_deref())?;
+        let conn = self.get_connection()?;
+        conn.transaction::<_, TagServiceError, _>(|| {
+            if slug_taken(&conn, &slug, Some(tag_id))? {
+                return Err(TagServiceError::AlreadyExists(slug.clone()));
+            }
+            let tag = diesel::update(tags::table.find(tag_id))
+                .set(&UpdateTag { name, slug: slug.clone() })
+                .get_result::<Tag>(&conn)?;
+            summary(&conn, tag)
+        })
+    }
+
+    /// Delete a tag, taking it off every post
+    pub async fn delete(&self, tag_id: i32) -> Result<(), TagServiceError> {
+        let conn = self.get_connection()?;
+        let deleted = diesel::delete(tags::table.find(tag_id)).execute(&conn)?;
+        if deleted == 0 {
+            Err(TagServiceError::NotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fold duplicate tags into `target_id`: their posts get the target tag
+    /// and the duplicates are deleted
+    pub async fn merge(&self, target_id: i32, source_ids: &[i32]) -> Result<TagSummary, TagServiceError> {
+        let mut sources: Vec<i32> = source_ids.iter().copied().filter(|id| *id != target_id).collect();
+        // Each duplicate counts once when checking they all existed
+        sources.sort_unstable();
+        sources.dedup();
+        if sources.is_empty() {
+            return Err(TagServiceError::InvalidData("Name at least one other tag to merge".to_string()));
+        }
+        let conn = self.get_connection()?;
+        conn.transaction::<_, TagServiceError, _>(|| {
+            let target = tags::table.find(target_id).for_update().first::<Tag>(&conn)?;
+            let moved: Vec<PostTag> = post_tags::table
+                .filter(post_tags::tag_id.eq_any(&sources))
+                .select(post_tags::post_id)
+                .distinct()
+                .load::<i32>(&conn)?
+                .into_iter()
+                .map(|post_id| PostTag { post_id, tag_id: target_id })
+                .collect();
+            insert_links(&conn, &moved)?;
+            let deleted = diesel::delete(tags::table.filter(tags::id.eq_any(&sources))).execute(&conn)?;
+            if deleted != sources.len() {
+                return Err(TagServiceError::NotFound);
+            }
+            summary(&conn, target)
+        })
+    }
+
+    /// Tags on a post, by name
+    pub async fn post_tags(&self, post_id: i32) -> Result<Vec<TagSummary>, TagServiceError> {
+        let conn = self.get_connection()?;
+        Ok(tags_of_post(&conn, post_id)?)
+    }
+
+    /// Add tags to a post by name, creating the ones that don't exist yet.
+    /// Returns all of the post's tags.
+    pub async fn attach(&self, post_id: i32, names: &[String]) -> Result<Vec<TagSummary>, TagServiceError> {
+        let conn = self.get_connection()?;
+        conn.transaction::<_, TagServiceError, _>(|| {
+            let links: Vec<PostTag> = resolve(&conn, names)?
+                .into_iter()
+                .map(|tag| PostTag { post_id, tag_id: tag.id })
+                .collect();
+            insert_links(&conn, &links)?;
+            Ok(tags_of_post(&conn, post_id)?)
+        })
+    }
+
+    /// Give a post exactly these tags, by name
+    pub async fn replace(&self, post_id: i32, names: &[String]) -> Result<Vec<TagSummary>, TagServiceError> {
+        let conn = self.get_connection()?;
+        conn.transaction::<_, TagServiceError, _>(|| {
+            let tag_ids: Vec<i32> = resolve(&conn, names)?.iter().map(|tag| tag.id).collect();
+            diesel::delete(
+                post_tags::table
+                    .filter(post_tags::post_id.eq(post_id))
+                    .filter(post_tags::tag_id.ne_all(&tag_ids)),
+            )
+            .execute(&conn)?;
+            let links: Vec<PostTag> = tag_ids
+                .into_iter()
+                .map(|tag_id| PostTag { post_id, tag_id })
+                .collect();
+            insert_links(&conn, &links)?;
+            Ok(tags_of_post(&conn, post_id)?)
+        })
+    }
+
+    /// Take a tag off a post. The tag itself stays, even if no post has it.
+    pub async fn detach(&self, post_id: i32, tag_id: i32) -> Result<(), TagServiceError> {
+        let conn = self.get_connection()?;
+        let deleted = diesel::delete(post_tags::table.find((post_id, tag_id))).execute(&conn)?;
+        if deleted == 0 {
+            Err(TagServiceError::NotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, TagServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            TagServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+/// Check a tag name and work out its slug
+fn prepare(name: &str, slug: Option<&str>) -> Result<NewTag, TagServiceError> {
+    let name = name.trim();
+    if name.chars().count() > MAX_TAG_CHARS {
+        return Err(TagServiceError::InvalidData(format!(
+            "Tag names are limited to {} characters",
+            MAX_TAG_CHARS
+        )));
+    }
+    let slug = slugify(slug.unwrap_or(name));
+    if slug.is_empty() {
+        return Err(TagServiceError::InvalidData(
+            "Tags need at least one letter or digit".to_string(),
+        ));
+    }
+    Ok(NewTag {
+        name: name.to_string(),
+        slug,
+    })
+}
+
+/// The tags with these names, created where missing. Names with the same
+/// slug give the same tag.
+fn resolve(conn: &PgConnection, names: &[String]) -> Result<Vec<Tag>, TagServiceError> {
+    let mut resolved: Vec<Tag> = Vec::new();
+    for name in names {
+        let new_tag = prepare(name, None)?;
+        if resolved.iter().any(|tag| tag.slug == new_tag.slug) {
+            continue;
+        }
+        // Another request may create the same tag at the same time
+        diesel::insert_into(tags::table)
+            .values(&new_tag)
+            .on_conflict(tags::slug)
+            .do_nothing()
+            .execute(conn)?;
+        resolved.push(tags::table.filter(tags::slug.eq(&new_tag.slug)).first::<Tag>(conn)?);
+    }
+    Ok(resolved)
+}
+
+fn insert_links(conn: &PgConnection, links: &[PostTag]) -> QueryResult<()> {
+    if !links.is_empty() {
+        diesel::insert_into(post_tags::table)
+            .values(links)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn slug_taken(conn: &PgConnection, slug: &str, except: Option<i32>) -> QueryResult<bool> {
+    let mut query = tags::table.filter(tags::slug.eq(slug)).into_boxed();
+    if let Some(tag_id) = except {
+        query = query.filter(tags::id.ne(tag_id));
+    }
+    query.select(tags::id).first::<i32>(conn).optional().map(|found| found.is_some())
+}
+
+fn tags_of_post(conn: &PgConnection, post_id: i32) -> QueryResult<Vec<TagSummary>> {
+    let tagged = tags::table
+        .filter(tags::id.eq_any(post_tags::table.filter(post_tags::post_id.eq(post_id)).select(post_tags::tag_id)))
+        .order(tags::name.asc())
+        .load::<Tag>(conn)?;
+    summaries(conn, tagged)
+}
+
+fn post_counts(conn: &PgConnection, ids: &[i32]) -> QueryResult<HashMap<i32, i64>> {
+    let counts = post_tags::table
+        .filter(post_tags::tag_id.eq_any(ids))
+        .group_by(post_tags::tag_id)
+        .select((post_tags::tag_id, diesel::dsl::count_star()))
+        .load::<(i32, i64)>(conn)?;
+    Ok(counts.into_iter().collect())
+}
+
+fn summaries(conn: &PgConnection, tags: Vec<Tag>) -> QueryResult<Vec<TagSummary>> {
+    let ids: Vec<i32> = tags.iter().map(|tag| tag.id).collect();
+    let counts = post_counts(conn, &ids)?;
+    Ok(tags
+        .into_iter()
+        .map(|tag| TagSummary {
+            post_count: counts.get(&tag.id).copied().unwrap_or(0),
+            id: tag.id,
+            name: tag.name,
+            slug: tag.slug,
+        })
+        .collect())
+}
+
+fn summary(conn: &PgConnection, tag: Tag) -> Result<TagSummary, TagServiceError> {
+    summaries(conn, vec![tag])?
+        .pop()
+        .ok_or(TagServiceError::NotFound)
+}
+
+/// `LIKE` pattern matching values that start with `prefix`
+fn like_prefix(prefix: &str) -> String {
+    let escaped = prefix
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("{}%", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepares_slugs_from_names() {
+        let tag = prepare("  Web Assembly ", None).unwrap();
+        assert_eq!((tag.name.as_str(), tag.slug.as_str()), ("Web Assembly", "web-assembly"));
+
+        let tag = prepare("Web Assembly", Some("WASM")).unwrap();
+        assert_eq!(tag.slug, "wasm");
+
+        assert!(matches!(prepare("!!", None), Err(TagServiceError::InvalidData(_))));
+        assert!(matches!(prepare(&"a".repeat(MAX_TAG_CHARS + 1), None), Err(TagServiceError::InvalidData(_))));
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(like_prefix("50%_off"), "50\\%\\_off%");
+    }
+}
@@ -0,0 +1,774 @@
+// src/backend/services/theme_service.rs
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Component as PathComponent, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::backend::models::theme::{
+    NewTheme, NewThemeActivation, Theme, ThemeActivation, ThemePreview, ThemeSummary,
+};
+use crate::backend::schema::{theme_activations, themes};
+use crate::backend::services::render_service::escape_html;
+use crate::backend::services::theme_template::Template;
+use crate::backend::utils::db::DbPool;
+use crate::shared::builder::is_safe_style_value;
+
+/// Name of the manifest at the root of every theme package
+pub const MANIFEST_FILE: &str = "theme.json";
+
+/// Largest theme zip accepted for upload
+pub const MAX_PACKAGE_BYTES: usize = 20 * 1024 * 1024;
+/// Limits on what a package may unpack to
+const MAX_UNPACKED_BYTES: u64 = 50 * 1024 * 1024;
+const MAX_FILES: usize = 500;
+const MAX_TEMPLATE_BYTES: u64 = 256 * 1024;
+
+/// How long a preview link keeps working
+const PREVIEW_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Files a theme may ship under `assets/`, with the type they are served as.
+/// Scripts are deliberately absent: page interactivity goes through builder actions.
+const ASSET_TYPES: [(&str, &str); 11] = [
+    ("css", "text/css; charset=utf-8"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+];
+
+#[derive(Debug, Error)]
+pub enum ThemeServiceError {
+    #[error("Invalid theme package: {0}")]
+    InvalidPackage(String),
+    #[error("Version {1} of theme {0} is already installed")]
+    AlreadyInstalled(String, String),
+    #[error("Theme not found")]
+    NotFound,
+    #[error("No earlier theme to roll back to")]
+    NothingToRollBack,
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for ThemeServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => ThemeServiceError::NotFound,
+            e => {
+                error!("Database error: {:?}", e);
+                ThemeServiceError::DatabaseError(e.to_string())
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ThemeServiceError {
+    fn from(err: io::Error) -> Self {
+        error!("Theme storage error: {:?}", err);
+        ThemeServiceError::Io(err.to_string())
+    }
+}
+
+/// Public views a theme can override, each from `templates/<view>.html`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeView {
+    /// The document shell around every view; must include `{{{ head }}}` and `{{{ body }}}`
+    Layout,
+    Post,
+    Page,
+    Category,
+    Tag,
+    Archive,
+}
+
+impl ThemeView {
+    pub const ALL: [ThemeView; 6] = [
+        ThemeView::Layout,
+        ThemeView::Post,
+        ThemeView::Page,
+        ThemeView::Category,
+        ThemeView::Tag,
+        ThemeView::Archive,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThemeView::Layout => "layout",
+            ThemeView::Post => "post",
+            ThemeView::Page => "page",
+            ThemeView::Category => "category",
+            ThemeView::Tag => "tag",
+            ThemeView::Archive => "archive",
+        }
+    }
+
+    fn from_file_name(name: &str) -> Option<Self> {
+        let stem = name.strip_suffix(".html")?;
+        ThemeView::ALL.iter().copied().find(|view| view.as_str() == stem)
+    }
+}
+
+/// Colors, fonts and spacing, exposed to stylesheets as CSS custom
+/// properties such as `--color-primary`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DesignTokens {
+    #[serde(default)]
+    pub colors: BTreeMap<String, String>,
+    #[serde(default)]
+    pub fonts: BTreeMap<String, String>,
+    #[serde(default)]
+    pub spacing: BTreeMap<String, String>,
+}
+
+impl DesignTokens {
+    fn groups(&self) -> [(&'static str, &BTreeMap<String, String>); 3] {
+        [("color", &self.colors), ("font", &self.fonts), ("spacing", &self.spacing)]
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (prefix, tokens) in self.groups() {
+            for (name, value) in tokens {
+                if !is_token_name(name) {
+                    return Err(format!("{} token name \"{}\" must be letters, digits and '-'", prefix, name));
+                }
+                if !is_safe_style_value(value) {
+                    return Err(format!("{} token \"{}\" has an unsupported value", prefix, name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `:root` rule declaring every token; empty when there are none
+    pub fn to_css(&self) -> String {
+        let mut css = String::new();
+        for (prefix, tokens) in self.groups() {
+            for (name, value) in tokens {
+                let _ = write!(css, "--{}-{}: {}; ", prefix, name, value.trim());
+            }
+        }
+        if css.is_empty() {
+            css
+        } else {
+            format!(":root {{ {}}}", css)
+        }
+    }
+}
+
+/// A theme package's `theme.json`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ThemeManifest {
+    pub slug: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Stylesheet under `assets/`, loaded after the built-in one
+    #[serde(default)]
+    pub stylesheet: Option<String>,
+    #[serde(default)]
+    pub tokens: DesignTokens,
+    /// Views overridden by the package; filled in on install from `templates/`
+    #[serde(default)]
+    pub templates: Vec<ThemeView>,
+}
+
+impl ThemeManifest {
+    fn validate(&self) -> Result<(), String> {
+        if !is_slug(&self.slug) {
+            return Err("slug must be 1-64 lowercase letters, digits and '-'".to_string());
+        }
+        if self.name.trim().is_empty() || self.name.chars().count() > 100 {
+            return Err("name must be 1-100 characters".to_string());
+        }
+        // The version names a directory, so it can't start with '.'
+        let version_ok = !self.version.is_empty()
+            && !self.version.starts_with('.')
+            && self.version.len() <= 32
+            && self.version.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+        if !version_ok {
+            return Err("version must be up to 32 letters, digits, '.' and '-', not starting with '.'".to_string());
+        }
+        if let Some(stylesheet) = &self.stylesheet {
+            if asset_key(stylesheet).map_or(true, |key| !key.ends_with(".css")) {
+                return Err("stylesheet must be a .css file under assets/".to_string());
+            }
+        }
+        self.tokens.validate()
+    }
+}
+
+/// A validated theme, ready to render
+#[derive(Debug)]
+pub struct LoadedTheme {
+    pub id: i32,
+    pub manifest: ThemeManifest,
+    templates: HashMap<ThemeView, Template>,
+}
+
+impl LoadedTheme {
+    fn open(theme: &Theme) -> Result<Self, ThemeServiceError> {
+        let (manifest, templates) = read_package(Path::new(&theme.path))?;
+        Ok(LoadedTheme {
+            id: theme.id,
+            manifest,
+            templates,
+        })
+    }
+
+    pub fn overrides(&self, view: ThemeView) -> bool {
+        self.templates.contains_key(&view)
+    }
+
+    /// URL prefix the theme's assets are served under
+    pub fn assets_url(&self) -> String {
+        format!("/theme-assets/{}", self.id)
+    }
+
+    /// Markup the theme adds to `<head>`: its stylesheet and design tokens
+    pub fn head(&self) -> String {
+        let mut head = String::new();
+        if let Some(key) = self.manifest.stylesheet.as_deref().and_then(asset_key) {
+            let url = format!("{}/{}", self.assets_url(), key);
+            let _ = write!(head, "\n    <link rel=\"stylesheet\" href=\"{}\">", escape_html(&url));
+        }
+        let tokens = self.manifest.tokens.to_css();
+        if !tokens.is_empty() {
+            let _ = write!(head, "\n    <style>{}</style>", tokens);
+        }
+        head
+    }
+
+    /// Render `view` through the theme's override, if it has one. `theme`
+    /// (name, version and assets URL) is added to the context.
+    pub fn render(&self, view: ThemeView, mut context: Value) -> Option<String> {
+        let template = self.templates.get(&view)?;
+        if let Value::Object(fields) = &mut context {
+            fields.insert(
+                "theme".to_string(),
+                json!({
+                    "name": self.manifest.name,
+                    "version": self.manifest.version,
+                    "assets": self.assets_url(),
+                }),
+            );
+        }
+        Some(template.render(&context))
+    }
+}
+
+/// Installs theme packages, keeps track of the active theme and hands the
+/// public renderer the theme to use for each request.
+///
+/// Packages are unpacked to `<root>/<slug>/<version>`. Theme directories
+/// dropped into `<root>/incoming` can be installed without an upload.
+pub struct ThemeService {
+    db_pool: DbPool,
+    root: PathBuf,
+    active: RwLock<Option<Arc<LoadedTheme>>>,
+    previews: Mutex<HashMap<String, (Arc<LoadedTheme>, Instant)>>,
+}
+
+impl ThemeService {
+    pub fn new(db_pool: DbPool, root: impl Into<PathBuf>) -> Self {
+        Self {
+            db_pool,
+            root: root.into(),
+            active: RwLock::new(None),
+            previews: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Load the active theme from the database. If it can't be read the
+    /// built-in templates are used until the next activation.
+    pub fn load_active(&self) -> Result<(), ThemeServiceError> {
+        let conn = self.get_connection()?;
+        let loaded = match active_theme(&conn)? {
+            Some(theme) => match LoadedTheme::open(&theme) {
+                Ok(loaded) => Some(Arc::new(loaded)),
+                Err(e) => {
+                    warn!("Falling back to built-in templates; theme {} failed to load: {}", theme.slug, e);
+                    None
+                }
+            },
+            None => None,
+        };
+        if let Some(theme) = &loaded {
+            info!("Using theme {} {}", theme.manifest.slug, theme.manifest.version);
+        }
+        *self.active.write().unwrap_or_else(|e| e.into_inner()) = loaded;
+        Ok(())
+    }
+
+    /// Theme for a public request: the previewed one if `preview` is a live
+    /// preview token, otherwise the active one. `None` means built-in templates.
+    pub fn resolve(&self, preview: Option<&str>) -> Option<Arc<LoadedTheme>> {
+        if let Some(token) = preview {
+            let mut previews = self.previews.lock().unwrap_or_else(|e| e.into_inner());
+            previews.retain(|_, (_, created)| created.elapsed() < PREVIEW_TTL);
+            if let Some((theme, _)) = previews.get(token) {
+                return Some(theme.clone());
+            }
+        }
+        self.active.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Installed themes, newest version of each slug first
+    pub async fn list(&self) -> Result<Vec<ThemeSummary>, ThemeServiceError> {
+        let conn = self.get_connection()?;
+        let active_id = active_theme(&conn)?.map(|theme| theme.id);
+        let rows = themes::table
+            .order((themes::slug.asc(), themes::installed_at.desc()))
+            .load::<Theme>(&conn)?;
+        Ok(rows.iter().map(|theme| summarize(theme, active_id)).collect())
+    }
+
+    /// Install an uploaded zip. The manifest may sit at the root of the zip
+    /// or inside a single top-level directory.
+    pub async fn install_zip(&self, bytes: &[u8]) -> Result<ThemeSummary, ThemeServiceError> {
+        if bytes.len() > MAX_PACKAGE_BYTES {
+            return Err(ThemeServiceError::InvalidPackage(format!(
+                "package exceeds the {} byte limit",
+                MAX_PACKAGE_BYTES
+            )));
+        }
+        let staging = self.staging_dir()?;
+        let result = unpack_zip(bytes, &staging).and_then(|_| self.install_staged(&staging));
+        remove_dir_quietly(&staging);
+        result
+    }
+
+    /// Install a theme directory from `<root>/incoming/<name>`
+    pub async fn install_directory(&self, name: &str) -> Result<ThemeSummary, ThemeServiceError> {
+        if !is_slug(name) {
+            return Err(ThemeServiceError::InvalidPackage("directory name must be lowercase letters, digits and '-'".to_string()));
+        }
+        let source = self.root.join("incoming").join(name);
+        if !source.is_dir() {
+            return Err(ThemeServiceError::NotFound);
+        }
+        let staging = self.staging_dir()?;
+        let result = copy_package(&source, &staging).and_then(|_| self.install_staged(&staging));
+        remove_dir_quietly(&staging);
+        result
+    }
+
+    /// Make a theme the active one
+    pub async fn activate(&self, theme_id: i32, user_id: i32) -> Result<ThemeSummary, ThemeServiceError> {
+        let conn = self.get_connection()?;
+        let theme = themes::table.find(theme_id).first::<Theme>(&conn)?;
+        // Refuse a theme whose files have gone missing before switching to it
+        let loaded = Arc::new(LoadedTheme::open(&theme)?);
+
+        if active_theme(&conn)?.map(|active| active.id) != Some(theme.id) {
+            diesel::insert_into(theme_activations::table)
+                .values(&NewThemeActivation {
+                    theme_id: theme.id,
+                    activated_by: Some(user_id),
+                })
+                .execute(&conn)?;
+        }
+        *self.active.write().unwrap_or_else(|e| e.into_inner()) = Some(loaded);
+        Ok(summarize(&theme, Some(theme.id)))
+    }
+
+    /// Return to the theme active before the current one. `None` means the
+    /// site is back on the built-in templates.
+    pub async fn rollback(&self) -> Result<Option<ThemeSummary>, ThemeServiceError> {
+        let conn = self.get_connection()?;
+        let latest = theme_activations::table
+            .order(theme_activations::id.desc())
+            .first::<ThemeActivation>(&conn)
+            .optional()?
+            .ok_or(ThemeServiceError::NothingToRollBack)?;
+        diesel::delete(theme_activations::table.find(latest.id)).execute(&conn)?;
+
+        self.load_active()?;
+        let active = active_theme(&conn)?;
+        Ok(active.map(|theme| summarize(&theme, Some(theme.id))))
+    }
+
+    /// Issue a link that shows the public site with `theme_id`, without activating it
+    pub async fn preview(&self, theme_id: i32) -> Result<ThemePreview, ThemeServiceError> {
+        let conn = self.get_connection()?;
+        let theme = themes::table.find(theme_id).first::<Theme>(&conn)?;
+        let loaded = Arc::new(LoadedTheme::open(&theme)?);
+
+        let token = Uuid::new_v4().simple().to_string();
+        let mut previews = self.previews.lock().unwrap_or_else(|e| e.into_inner());
+        previews.retain(|_, (_, created)| created.elapsed() < PREVIEW_TTL);
+        previews.insert(token.clone(), (loaded, Instant::now()));
+
+        Ok(ThemePreview {
+            url: format!("/blog?theme_preview={}", token),
+            token,
+            expires_in_secs: PREVIEW_TTL.as_secs(),
+        })
+    }
+
+    /// File and content type of an installed theme's asset, `key` being its
+    /// path under `assets/`
+    pub async fn asset(&self, theme_id: i32, key: &str) -> Result<(PathBuf, &'static str), ThemeServiceError> {
+        let content_type = asset_type(key).ok_or(ThemeServiceError::NotFound)?;
+        if !is_safe_key(key) {
+            return Err(ThemeServiceError::NotFound);
+        }
+        let conn = self.get_connection()?;
+        let theme = themes::table.find(theme_id).first::<Theme>(&conn)?;
+        let path = Path::new(&theme.path).join("assets").join(key);
+        if path.is_file() {
+            Ok((path, content_type))
+        } else {
+            Err(ThemeServiceError::NotFound)
+        }
+    }
+
+    /// Validate an unpacked package and move it into place
+    fn install_staged(&self, staging: &Path) -> Result<ThemeSummary, ThemeServiceError> {
+        let package = package_root(staging)?;
+        let (mut manifest, templates) = read_package(&package)?;
+        manifest.templates = templates.keys().copied().collect();
+        manifest.templates.sort();
+
+        let conn = self.get_connection()?;
+        let exists = themes::table
+            .filter(themes::slug.eq(&manifest.slug))
+            .filter(themes::version.eq(&manifest.version))
+            .count()
+            .get_result::<i64>(&conn)?
+            > 0;
+        let target = self.root.join(&manifest.slug).join(&manifest.version);
+        if exists || target.exists() {
+            return Err(ThemeServiceError::AlreadyInstalled(manifest.slug, manifest.version));
+        }
+        fs::create_dir_all(self.root.join(&manifest.slug))?;
+        fs::rename(&package, &target)?;
+
+        let manifest_json = serde_json::to_value(&manifest)
+            .map_err(|e| ThemeServiceError::InvalidPackage(e.to_string()))?;
+        let inserted = diesel::insert_into(themes::table)
+            .values(&NewTheme {
+                slug: manifest.slug.clone(),
+                name: manifest.name.clone(),
+                version: manifest.version.clone(),
+                manifest: manifest_json,
+                path: target.to_string_lossy().into_owned(),
+            })
+            .get_result::<Theme>(&conn);
+        match inserted {
+            Ok(theme) => Ok(summarize(&theme, active_theme(&conn)?.map(|t| t.id))),
+            Err(e) => {
+                remove_dir_quietly(&target);
+                Err(e.into())
+            }
+        }
+    }
+
+    fn staging_dir(&self) -> Result<PathBuf, ThemeServiceError> {
+        let dir = self.root.join(".staging").join(Uuid::new_v4().simple().to_string());
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Helper function to get a database connection
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, ThemeServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            ThemeServiceError::DatabaseError(e.to_string())
+        })
+    }
+}
+
+fn active_theme(conn: &PgConnection) -> QueryResult<Option<Theme>> {
+    theme_activations::table
+        .inner_join(themes::table)
+        .order(theme_activations::id.desc())
+        .select(themes::all_columns)
+        .first::<Theme>(conn)
+        .optional()
+}
+
+fn summarize(theme: &Theme, active_id: Option<i32>) -> ThemeSummary {
+    let manifest = serde_json::from_value::<ThemeManifest>(theme.manifest.clone()).ok();
+    ThemeSummary {
+        id: theme.id,
+        slug: theme.slug.clone(),
+        name: theme.name.clone(),
+        version: theme.version.clone(),
+        description: manifest.as_ref().and_then(|m| m.description.clone()),
+        templates: manifest
+            .map(|m| m.templates.iter().map(|view| view.as_str().to_string()).collect())
+            .unwrap_or_default(),
+        active: active_id == Some(theme.id),
+        installed_at: theme.installed_at,
+    }
+}
+
+/// Read and check a package directory: its manifest, its templates, and
+/// that it holds nothing but those and allowed assets
+fn read_package(dir: &Path) -> Result<(ThemeManifest, HashMap<ThemeView, Template>), ThemeServiceError> {
+    let invalid = |reason: String| ThemeServiceError::InvalidPackage(reason);
+
+    let manifest_text = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|_| invalid(format!("{} is missing or unreadable", MANIFEST_FILE)))?;
+    let manifest: ThemeManifest = serde_json::from_str(&manifest_text)
+        .map_err(|e| invalid(format!("{}: {}", MANIFEST_FILE, e)))?;
+    manifest.validate().map_err(|reason| invalid(format!("{}: {}", MANIFEST_FILE, reason)))?;
+
+    let mut templates = HashMap::new();
+    for (key, size) in package_files(dir)? {
+        if key == MANIFEST_FILE {
+            continue;
+        }
+        if let Some(name) = key.strip_prefix("templates/") {
+            let view = ThemeView::from_file_name(name)
+                .ok_or_else(|| invalid(format!("{} is not a known template", key)))?;
+            if size > MAX_TEMPLATE_BYTES {
+                return Err(invalid(format!("{} is larger than {} bytes", key, MAX_TEMPLATE_BYTES)));
+            }
+            let source = fs::read_to_string(dir.join(&key))
+                .map_err(|_| invalid(format!("{} is not UTF-8 text", key)))?;
+            let template = Template::parse(&source).map_err(|e| invalid(format!("{}: {}", key, e)))?;
+            templates.insert(view, template);
+        } else if key.strip_prefix("assets/").map_or(true, |asset| asset_type(asset).is_none()) {
+            return Err(invalid(format!("{} is not allowed in a theme", key)));
+        }
+    }
+
+    if let Some(stylesheet) = manifest.stylesheet.as_deref().and_then(asset_key) {
+        if !dir.join("assets").join(stylesheet).is_file() {
+            return Err(invalid(format!("stylesheet assets/{} is missing", stylesheet)));
+        }
+    }
+    Ok((manifest, templates))
+}
+
+/// Every file under `dir` as a `/`-separated key with its size. Links and
+/// oversized packages are refused.
+fn package_files(dir: &Path) -> Result<Vec<(String, u64)>, ThemeServiceError> {
+    let mut files = Vec::new();
+    let mut total = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let entry = entry?;
+            let metadata = fs::symlink_metadata(entry.path())?;
+            if metadata.file_type().is_symlink() {
+                return Err(ThemeServiceError::InvalidPackage("links are not allowed in a theme".to_string()));
+            }
+            if metadata.is_dir() {
+                pending.push(entry.path());
+                continue;
+            }
+            let key = entry
+                .path()
+                .strip_prefix(dir)
+                .ok()
+                .and_then(|relative| relative.to_str())
+                .map(|relative| relative.replace('\\', "/"))
+                .ok_or_else(|| ThemeServiceError::InvalidPackage("file names must be UTF-8".to_string()))?;
+            total += metadata.len();
+            files.push((key, metadata.len()));
+        }
+        if files.len() > MAX_FILES || total > MAX_UNPACKED_BYTES {
+            return Err(ThemeServiceError::InvalidPackage(format!(
+                "a theme may hold at most {} files and {} bytes",
+                MAX_FILES, MAX_UNPACKED_BYTES
+            )));
+        }
+    }
+    Ok(files)
+}
+
+/// The directory holding the manifest: `staging` itself, or its only subdirectory
+fn package_root(staging: &Path) -> Result<PathBuf, ThemeServiceError> {
+    if staging.join(MANIFEST_FILE).is_file() {
+        return Ok(staging.to_path_buf());
+    }
+    let entries = fs::read_dir(staging)?.collect::<Result<Vec<_>, _>>()?;
+    match entries.as_slice() {
+        [only] if only.path().join(MANIFEST_FILE).is_file() => Ok(only.path()),
+        _ => Err(ThemeServiceError::InvalidPackage(format!("{} is missing", MANIFEST_FILE))),
+    }
+}
+
+fn unpack_zip(bytes: &[u8], dest: &Path) -> Result<(), ThemeServiceError> {
+    let invalid = |reason: String| ThemeServiceError::InvalidPackage(reason);
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| invalid(format!("not a zip file: {}", e)))?;
+    if archive.len() > MAX_FILES * 2 {
+        return Err(invalid(format!("a theme may hold at most {} files", MAX_FILES)));
+    }
+
+    let mut total = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|e| invalid(e.to_string()))?;
+        let relative = entry
+            .enclosed_name()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| invalid(format!("{} points outside the package", entry.name())))?;
+        // Archivers' metadata, e.g. `__MACOSX/` and `.DS_Store`
+        let hidden = relative.components().any(|part| match part {
+            PathComponent::Normal(name) => {
+                let name = name.to_string_lossy();
+                name.starts_with('.') || name == "__MACOSX"
+            }
+            _ => false,
+        });
+        if hidden || entry.is_dir() {
+            continue;
+        }
+
+        let target = dest.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::File::create(&target)?;
+        let remaining = MAX_UNPACKED_BYTES - total;
+        total += io::copy(&mut (&mut entry).take(remaining + 1), &mut file)?;
+        if total > MAX_UNPACKED_BYTES {
+            return Err(invalid(format!("package unpacks to more than {} bytes", MAX_UNPACKED_BYTES)));
+        }
+    }
+    Ok(())
+}
+
+/// Copy a theme directory, refusing links
+fn copy_package(source: &Path, dest: &Path) -> Result<(), ThemeServiceError> {
+    for (key, _) in package_files(source)? {
+        let target = dest.join(&key);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source.join(&key), target)?;
+    }
+    Ok(())
+}
+
+fn remove_dir_quietly(dir: &Path) {
+    if let Err(e) = fs::remove_dir_all(dir) {
+        if e.kind() != io::ErrorKind::NotFound {
+            warn!("Failed to remove {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// `assets/style.css` -> `style.css`, for paths that stay inside `assets/`
+fn asset_key(path: &str) -> Option<&str> {
+    path.strip_prefix("assets/").filter(|key| is_safe_key(key))
+}
+
+fn asset_type(key: &str) -> Option<&'static str> {
+    let extension = key.rsplit_once('.')?.1.to_ascii_lowercase();
+    ASSET_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, content_type)| *content_type)
+}
+
+/// Relative `/`-separated paths without empty, `.` or `..` parts
+fn is_safe_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('/')
+        && key.split('/').all(|part| !part.is_empty() && part != "." && part != "..")
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.'))
+}
+
+fn is_slug(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 64
+        && value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn is_token_name(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 32
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, key: &str, contents: &str) {
+        let path = dir.join(key);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn package(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("theme-test-{}-{}", name, Uuid::new_v4().simple()));
+        write(
+            &dir,
+            MANIFEST_FILE,
+            r##"{
+                "slug": "aurora",
+                "name": "Aurora",
+                "version": "1.0.0",
+                "stylesheet": "assets/style.css",
+                "tokens": { "colors": { "primary": "#3355ff" }, "spacing": { "md": "16px" } }
+            }"##,
+        );
+        write(&dir, "assets/style.css", "body { color: var(--color-primary); }");
+        write(&dir, "templates/post.html", "<article>{{ post.title }}{{{ content }}}</article>");
+        dir
+    }
+
+    #[test]
+    fn packages_are_read_with_their_templates_and_tokens() {
+        let dir = package("read");
+        let (manifest, templates) = read_package(&dir).unwrap();
+        assert_eq!(manifest.slug, "aurora");
+        assert!(templates.contains_key(&ThemeView::Post));
+        assert!(!templates.contains_key(&ThemeView::Layout));
+        assert_eq!(manifest.tokens.to_css(), ":root { --color-primary: #3355ff; --spacing-md: 16px; }");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn packages_with_scripts_or_bad_templates_are_refused() {
+        let dir = package("script");
+        write(&dir, "assets/app.js", "alert(1)");
+        assert!(matches!(read_package(&dir), Err(ThemeServiceError::InvalidPackage(_))));
+        fs::remove_dir_all(dir).unwrap();
+
+        let dir = package("template");
+        write(&dir, "templates/tag.html", "{{#posts}}");
+        let err = read_package(&dir).unwrap_err().to_string();
+        assert!(err.contains("templates/tag.html"), "{}", err);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn asset_paths_stay_inside_the_package() {
+        assert_eq!(asset_key("assets/css/site.css"), Some("css/site.css"));
+        assert_eq!(asset_key("assets/../theme.json"), None);
+        assert_eq!(asset_key("templates/post.html"), None);
+        assert_eq!(asset_type("fonts/Body.WOFF2"), Some("font/woff2"));
+        assert_eq!(asset_type("app.js"), None);
+    }
+}
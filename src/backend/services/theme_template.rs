@@ -0,0 +1,276 @@
+// src/backend/services/theme_template.rs
+
+//! Logic-less templates for theme overrides.
+//!
+//! Themes can't ship Rust, so their views are HTML with a small
+//! Mustache-like syntax:
+//!
+//! - `{{ post.title }}` inserts a value, HTML-escaped
+//! - `{{{ content }}}` inserts markup the server already rendered or
+//!   sanitized; only allowed for the names in [`RAW_KEYS`]
+//! - `{{#posts}}...{{/posts}}` repeats for each item of a list, or renders
+//!   once if the value is present and not false or empty
+//! - `{{^posts}}...{{/posts}}` renders only if the value is missing, false or empty
+//! - `{{! comment }}` is dropped
+//!
+//! Templates are parsed when a theme is installed, so syntax errors are
+//! reported then rather than on the public site.
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::backend::services::render_service::escape_html;
+
+/// Values that hold HTML and may be inserted without escaping
+pub const RAW_KEYS: [&str; 3] = ["body", "content", "head"];
+
+/// Nesting allowed for sections, so a template can't recurse the renderer deep
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TemplateError {
+    #[error("line {line}: unclosed tag")]
+    Unclosed { line: usize },
+    #[error("line {line}: invalid name \"{name}\"")]
+    InvalidName { line: usize, name: String },
+    #[error("line {line}: \"{name}\" holds text and can't be inserted with triple braces")]
+    RawNotAllowed { line: usize, name: String },
+    #[error("line {line}: \"{{{{/{found}}}}}\" doesn't close \"{expected}\"")]
+    Mismatched { line: usize, expected: String, found: String },
+    #[error("line {line}: section \"{name}\" is never closed")]
+    UnclosedSection { line: usize, name: String },
+    #[error("line {line}: sections are nested too deeply")]
+    TooDeep { line: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Value { path: Vec<String>, raw: bool },
+    Section { path: Vec<String>, inverted: bool, children: Vec<Node> },
+}
+
+/// A parsed theme template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+/// A section being parsed: its name, whether it is inverted, the line it
+/// opened on, and the nodes before it
+struct Open {
+    name: String,
+    inverted: bool,
+    line: usize,
+    outer: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut nodes = Vec::new();
+        let mut open: Vec<Open> = Vec::new();
+        let mut rest = source;
+        let mut line = 1;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+            line += rest[..start].matches('\n').count();
+            let triple = rest[start..].starts_with("{{{");
+            let (open_len, close) = if triple { (3, "}}}") } else { (2, "}}") };
+            let after = &rest[start + open_len..];
+            let end = after.find(close).ok_or(TemplateError::Unclosed { line })?;
+            let tag = after[..end].trim();
+            line += after[..end].matches('\n').count();
+            rest = &after[end + close.len()..];
+
+            if triple {
+                let path = parse_path(tag, line)?;
+                if !path.last().map_or(false, |name| RAW_KEYS.contains(&name.as_str())) {
+                    return Err(TemplateError::RawNotAllowed { line, name: tag.to_string() });
+                }
+                nodes.push(Node::Value { path, raw: true });
+                continue;
+            }
+
+            match tag.chars().next() {
+                Some('!') => {}
+                Some(sigil @ ('#' | '^')) => {
+                    let name = tag[1..].trim().to_string();
+                    parse_path(&name, line)?;
+                    if open.len() >= MAX_DEPTH {
+                        return Err(TemplateError::TooDeep { line });
+                    }
+                    open.push(Open {
+                        name,
+                        inverted: sigil == '^',
+                        line,
+                        outer: std::mem::take(&mut nodes),
+                    });
+                }
+                Some('/') => {
+                    let found = tag[1..].trim().to_string();
+                    let section = match open.pop() {
+                        Some(section) if section.name == found => section,
+                        Some(section) => {
+                            return Err(TemplateError::Mismatched { line, expected: section.name, found })
+                        }
+                        None => {
+                            return Err(TemplateError::Mismatched { line, expected: String::new(), found })
+                        }
+                    };
+                    let children = std::mem::replace(&mut nodes, section.outer);
+                    nodes.push(Node::Section {
+                        path: parse_path(&section.name, section.line)?,
+                        inverted: section.inverted,
+                        children,
+                    });
+                }
+                _ => nodes.push(Node::Value { path: parse_path(tag, line)?, raw: false }),
+            }
+        }
+        if let Some(section) = open.pop() {
+            return Err(TemplateError::UnclosedSection { line: section.line, name: section.name });
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+        Ok(Template { nodes })
+    }
+
+    /// Render against `context`, usually a JSON object built by the caller
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, &mut vec![context], &mut out);
+        out
+    }
+}
+
+/// `post.title` -> `["post", "title"]`; `.` alone is the current list item
+fn parse_path(tag: &str, line: usize) -> Result<Vec<String>, TemplateError> {
+    if tag == "." {
+        return Ok(Vec::new());
+    }
+    let valid = !tag.is_empty()
+        && tag.split('.').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        });
+    if !valid {
+        return Err(TemplateError::InvalidName { line, name: tag.to_string() });
+    }
+    Ok(tag.split('.').map(str::to_string).collect())
+}
+
+/// Look a path up from the innermost context outwards
+fn lookup<'a>(stack: &[&'a Value], path: &[String]) -> Option<&'a Value> {
+    let (first, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return stack.last().copied(),
+    };
+    let mut value = stack.iter().rev().find_map(|context| context.get(first))?;
+    for part in rest {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(_) => true,
+    }
+}
+
+fn render_nodes<'a>(nodes: &[Node], stack: &mut Vec<&'a Value>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value { path, raw } => {
+                let text = match lookup(stack, path) {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Number(n)) => n.to_string(),
+                    Some(Value::Bool(b)) => b.to_string(),
+                    _ => String::new(),
+                };
+                if *raw {
+                    out.push_str(&text);
+                } else {
+                    out.push_str(&escape_html(&text));
+                }
+            }
+            Node::Section { path, inverted, children } => {
+                let value = lookup(stack, path);
+                if *inverted {
+                    if !truthy(value) {
+                        render_nodes(children, stack, out);
+                    }
+                    continue;
+                }
+                match value {
+                    Some(Value::Array(items)) => {
+                        for item in items {
+                            stack.push(item);
+                            render_nodes(children, stack, out);
+                            stack.pop();
+                        }
+                    }
+                    Some(value) if truthy(Some(value)) => {
+                        stack.push(value);
+                        render_nodes(children, stack, out);
+                        stack.pop();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_are_escaped_unless_they_hold_markup() {
+        let template = Template::parse("<h1>{{ post.title }}</h1>{{{ content }}}").unwrap();
+        let html = template.render(&json!({
+            "post": { "title": "<script>" },
+            "content": "<p>Hi</p>",
+        }));
+        assert_eq!(html, "<h1>&lt;script&gt;</h1><p>Hi</p>");
+
+        assert_eq!(
+            Template::parse("{{{ post.title }}}"),
+            Err(TemplateError::RawNotAllowed { line: 1, name: "post.title".to_string() })
+        );
+    }
+
+    #[test]
+    fn sections_repeat_lists_and_fall_back_when_empty() {
+        let template = Template::parse(
+            "{{! listing }}<ul>{{#posts}}<li>{{ title }} in {{ term.name }}</li>{{/posts}}</ul>{{^posts}}None{{/posts}}",
+        )
+        .unwrap();
+        let term = json!({ "name": "News" });
+        assert_eq!(
+            template.render(&json!({ "term": term, "posts": [{ "title": "A" }, { "title": "B" }] })),
+            "<ul><li>A in News</li><li>B in News</li></ul>"
+        );
+        assert_eq!(template.render(&json!({ "posts": [] })), "<ul></ul>None");
+    }
+
+    #[test]
+    fn syntax_errors_name_the_line() {
+        assert_eq!(
+            Template::parse("a\n{{#posts}}\nb"),
+            Err(TemplateError::UnclosedSection { line: 2, name: "posts".to_string() })
+        );
+        assert!(matches!(Template::parse("{{#a}}{{/b}}"), Err(TemplateError::Mismatched { .. })));
+        assert!(matches!(Template::parse("{{ a b }}"), Err(TemplateError::InvalidName { .. })));
+        assert!(matches!(Template::parse("{{ title"), Err(TemplateError::Unclosed { .. })));
+    }
+}
@@ -1,173 +1,129 @@
-use serde::{Deserialize, Serialize};
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
-use gloo_net::http::Request;
-use log::{info, error};
+// src/backend/services/user_service.rs
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
 use thiserror::Error;
+use tracing::error;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct User {
-    pub id: Option<i32>,
-    pub username: String,
-    pub email: String,
-    pub is_admin: bool,
-}
+use crate::backend::models::user::{NewUser, NewUserRequest, UpdateUserRequest, User, UserChanges};
+use crate::backend::schema::users::dsl::*;
+use crate::backend::services::auth_service::{AuthService, AuthServiceError};
+use crate::backend::utils::db::DbPool;
+use crate::shared::types::UserRole;
 
 #[derive(Debug, Error)]
 pub enum UserServiceError {
-    #[error("Network error: {0}")]
-    NetworkError(String),
-    #[error("Serialization error: {0}")]
-    SerializationError(String),
-    #[error("Deserialization error: {0}")]
-    DeserializationError(String),
-    #[error("Resource not found")]
+    #[error("User not found")]
     NotFound,
-    #[error("Unauthorized access")]
-    Unauthorized,
-    #[error("API error with status code: {0}")]
-    ApiError(u16),
-}
-
-impl From<gloo_net::Error> for UserServiceError {
-    fn from(error: gloo_net::Error) -> Self {
-        UserServiceError::NetworkError(error.to_string())
-    }
-}
-
-impl From<serde_json::Error> for UserServiceError {
-    fn from(error: serde_json::Error) -> Self {
-        UserServiceError::DeserializationError(error.to_string())
+    #[error("A user with that username or email already exists")]
+    AlreadyExists,
+    #[error("{0}")]
+    Invalid(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl From<diesel::result::Error> for UserServiceError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => UserServiceError::NotFound,
+            diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
+                UserServiceError::AlreadyExists
+            }
+            err => {
+                error!("Database error: {:?}", err);
+                UserServiceError::DatabaseError(err.to_string())
+            }
+        }
     }
 }
 
-impl From<String> for UserServiceError {
-    fn from(error: String) -> Self {
-        UserServiceError::SerializationError(error)
+impl From<AuthServiceError> for UserServiceError {
+    fn from(err: AuthServiceError) -> Self {
+        match err {
+            AuthServiceError::UserAlreadyExists => UserServiceError::AlreadyExists,
+            err => UserServiceError::DatabaseError(err.to_string()),
+        }
     }
 }
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_name = API_BASE_URL)]
-    static API_BASE_URL: JsValue;
+/// Normalize a role name from a request, rejecting unknown roles
+fn parse_role(name: &str) -> Result<String, UserServiceError> {
+    name.parse::<UserRole>()
+        .map(|parsed| parsed.as_str().to_string())
+        .map_err(UserServiceError::Invalid)
 }
 
-fn get_api_base_url() -> String {
-    API_BASE_URL.as_string().unwrap_or_else(|| "".to_string())
+/// Account management for administrators. Passwords are hashed and set
+/// through the auth service, so sessions are revoked on a change.
+pub struct UserService {
+    db_pool: DbPool,
+    auth_service: Arc<AuthService>,
 }
 
-enum HttpMethod {
-    GET,
-    POST,
-    PUT,
-    DELETE,
-}
-
-impl HttpMethod {
-    fn as_str(&self) -> &'static str {
-        match self {
-            HttpMethod::GET => "GET",
-            HttpMethod::POST => "POST",
-            HttpMethod::PUT => "PUT",
-            HttpMethod::DELETE => "DELETE",
-        }
+impl UserService {
+    pub fn new(db_pool: DbPool, auth_service: Arc<AuthService>) -> Self {
+        Self { db_pool, auth_service }
     }
-}
-
-async fn make_request(
-    method: HttpMethod,
-    endpoint: &str,
-    body: Option<impl Into<JsValue>>,
-) -> Result<gloo_net::http::Response, UserServiceError> {
-    let url = format!("{}{}", get_api_base_url(), endpoint);
-    let mut request = Request::new(&url).method(method.as_str());
 
-    if let Some(body) = body {
-        request = request
-            .header("Content-Type", "application/json")
-            .body(body);
+    /// All users, by username
+    pub async fn list_users(&self) -> Result<Vec<User>, UserServiceError> {
+        let conn = self.get_connection()?;
+        Ok(users.order(username.asc()).load::<User>(&conn)?)
     }
 
-    let response = request.send().await.map_err(UserServiceError::from)?;
+    pub async fn get_user(&self, user_id: i32) -> Result<User, UserServiceError> {
+        let conn = self.get_connection()?;
+        Ok(users.find(user_id).first::<User>(&conn)?)
+    }
 
-    if response.ok() {
-        Ok(response)
-    } else {
-        match response.status() {
-            401 => {
-                error!("Unauthorized access");
-                Err(UserServiceError::Unauthorized)
-            }
-            404 => {
-                error!("Resource not found");
-                Err(UserServiceError::NotFound)
-            }
-            status => Err(UserServiceError::ApiError(status)),
+    pub async fn create_user(&self, request: NewUserRequest) -> Result<User, UserServiceError> {
+        if request.password.is_empty() {
+            return Err(UserServiceError::Invalid("A password is required".to_string()));
         }
+        let new_user = NewUser {
+            username: request.username,
+            email: request.email,
+            // Hashed by the auth service
+            password_hash: request.password,
+            role: parse_role(&request.role)?,
+        };
+        Ok(self.auth_service.register_user(new_user).await?)
     }
-}
-
-async fn handle_api_response<T>(response: gloo_net::http::Response) -> Result<T, UserServiceError>
-where
-    T: for<'de> Deserialize<'de>,
-{
-    response
-        .json::<T>()
-        .await
-        .map_err(UserServiceError::from)
-}
-
-// Fetch all users
-pub async fn fetch_users() -> Result<Vec<User>, UserServiceError> {
-    info!("Fetching all users...");
-    let response = make_request(HttpMethod::GET, "/api/users", None::<JsValue>).await?;
-    handle_api_response::<Vec<User>>(response).await
-}
 
-// Fetch user by ID
-pub async fn fetch_user_by_id(user_id: i32) -> Result<User, UserServiceError> {
-    info!("Fetching user with ID: {}", user_id);
-    let endpoint = format!("/api/users/{}", user_id);
-
-    let response = make_request(HttpMethod::GET, &endpoint, None::<JsValue>).await?;
-    handle_api_response::<User>(response).await
-}
-
-// Create a new user
-pub async fn create_user(new_user: &User) -> Result<User, UserServiceError> {
-    info!("Creating new user: {:?}", new_user);
-    let body = serde_json::to_string(new_user).map_err(UserServiceError::from)?;
-
-    let response = make_request(HttpMethod::POST, "/api/users", Some(body)).await?;
-    handle_api_response::<User>(response).await
-}
-
-// Update an existing user
-pub async fn update_user(user_id: i32, updated_user: &User) -> Result<User, UserServiceError> {
-    info!("Updating user ID {}: {:?}", user_id, updated_user);
-    let endpoint = format!("/api/users/{}", user_id);
-    let body = serde_json::to_string(updated_user).map_err(UserServiceError::from)?;
-
-    let response = make_request(HttpMethod::PUT, &endpoint, Some(body)).await?;
-    handle_api_response::<User>(response).await
-}
-
-// Delete a user
-pub async fn delete_user(user_id: i32) -> Result<(), UserServiceError> {
-    info!("Deleting user with ID {}", user_id);
-    let endpoint = format!("/api/users/{}", user_id);
-
-    let response = make_request(HttpMethod::DELETE, &endpoint, None::<JsValue>).await?;
+    /// Change a user's email, role or password; absent fields stay as they are
+    pub async fn update_user(&self, user_id: i32, request: UpdateUserRequest) -> Result<User, UserServiceError> {
+        let changes = UserChanges {
+            email: request.email,
+            role: request.role.as_deref().map(parse_role).transpose()?,
+            updated_at: Utc::now().naive_utc(),
+        };
+        let conn = self.get_connection()?;
+        let user = diesel::update(users.find(user_id))
+            .set(&changes)
+            .get_result::<User>(&conn)?;
+        drop(conn);
+        if let Some(password) = request.password.filter(|password| !password.is_empty()) {
+            self.auth_service.set_password(user_id, &password).await?;
+        }
+        Ok(user)
+    }
 
-    if response.ok() {
-        Ok(())
-    } else {
-        error!("Failed to delete user: {:?}", response.status());
-        match response.status() {
-            401 => Err(UserServiceError::Unauthorized),
-            404 => Err(UserServiceError::NotFound),
-            status => Err(UserServiceError::ApiError(status)),
+    pub async fn delete_user(&self, user_id: i32) -> Result<(), UserServiceError> {
+        let conn = self.get_connection()?;
+        match diesel::delete(users.find(user_id)).execute(&conn)? {
+            0 => Err(UserServiceError::NotFound),
+            _ => Ok(()),
         }
     }
+
+    fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, UserServiceError> {
+        self.db_pool.get().map_err(|e| {
+            error!("Database connection error: {:?}", e);
+            UserServiceError::DatabaseError(e.to_string())
+        })
+    }
 }
@@ -0,0 +1,241 @@
+use yew::prelude::*;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use crate::frontend::services::api_service::{fetch_audit_log, ApiServiceError};
+use crate::shared::audit::{AuditAction, AuditEntity, AuditEntry, AuditFilter, AuditPage};
+
+/// `api_key` → `Api key`
+fn label(name: &str) -> String {
+    let name = name.replace('_', " ");
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn input_value(input: &NodeRef) -> Option<String> {
+    input
+        .cast::<HtmlInputElement>()
+        .map(|input| input.value().trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn pretty(value: &Option<serde_json::Value>) -> String {
+    value
+        .as_ref()
+        .and_then(|value| serde_json::to_string_pretty(value).ok())
+        .unwrap_or_else(|| "—".to_string())
+}
+
+/// Filterable, paginated view of the audit log. Renders nothing for users
+/// who can't view it.
+#[function_component(AuditLogView)]
+pub fn audit_log_view() -> Html {
+    let filter = use_state(AuditFilter::default);
+    let page = use_state(|| 1i64);
+    let entries = use_state(|| None::<AuditPage>);
+    let forbidden = use_state(|| false);
+    let error = use_state(|| None::<String>);
+    let expanded = use_state(|| None::<i32>);
+    let action = use_state(|| None::<AuditAction>);
+    let entity_type = use_state(|| None::<AuditEntity>);
+    let actor_input = use_node_ref();
+    let entity_id_input = use_node_ref();
+    let from_input = use_node_ref();
+    let to_input = use_node_ref();
+
+    {
+        let entries = entries.clone();
+        let forbidden = forbidden.clone();
+        let error = error.clone();
+        use_effect_with_deps(
+            move |(filter, page): &(AuditFilter, i64)| {
+                let filter = filter.clone();
+                let page = *page;
+                wasm_bindgen_futures::spawn_local(async move {
+                    match fetch_audit_log(&filter, page).await {
+                        Ok(fetched) => {
+                            error.set(None);
+                            entries.set(Some(fetched));
+                        }
+                        Err(ApiServiceError::ServerError(403)) => forbidden.set(true),
+                        Err(ApiServiceError::ServerError(422)) => {
+                            error.set(Some("Dates must be given as YYYY-MM-DD.".to_string()))
+                        }
+                        Err(err) => {
+                            log::error!("Error getting the audit log: {:?}", err);
+                            error.set(Some("The audit log couldn't be loaded.".to_string()));
+                        }
+                    }
+                });
+                || ()
+            },
+            ((*filter).clone(), *page),
+        );
+    }
+
+    let on_action = {
+        let action = action.clone();
+        Callback::from(move |e: Event| {
+            action.set(e.target_unchecked_into::<HtmlSelectElement>().value().parse().ok())
+        })
+    };
+
+    let on_entity_type = {
+        let entity_type = entity_type.clone();
+        Callback::from(move |e: Event| {
+            entity_type.set(e.target_unchecked_into::<HtmlSelectElement>().value().parse().ok())
+        })
+    };
+
+    let on_filter = {
+        let filter = filter.clone();
+        let page = page.clone();
+        let expanded = expanded.clone();
+        let action = action.clone();
+        let entity_type = entity_type.clone();
+        let actor_input = actor_input.clone();
+        let entity_id_input = entity_id_input.clone();
+        let from_input = from_input.clone();
+        let to_input = to_input.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            filter.set(AuditFilter {
+                actor: input_value(&actor_input),
+                action: *action,
+                entity_type: *entity_type,
+                entity_id: input_value(&entity_id_input),
+                from: input_value(&from_input),
+                to: input_value(&to_input),
+            });
+            page.set(1);
+            expanded.set(None);
+        })
+    };
+
+    let go_to = |target: i64| {
+        let page = page.clone();
+        let expanded = expanded.clone();
+        Callback::from(move |_| {
+            page.set(target);
+            expanded.set(None);
+        })
+    };
+
+    if *forbidden {
+        return html! {};
+    }
+
+    let row = |entry: &AuditEntry| {
+        let id = entry.id;
+        let open = *expanded == Some(id);
+        let toggle = {
+            let expanded = expanded.clone();
+            Callback::from(move |_| expanded.set(if open { None } else { Some(id) }))
+        };
+        let entity = match &entry.entity_id {
+            Some(entity_id) => format!("{} {}", label(entry.entity_type.as_str()), entity_id),
+            None => label(entry.entity_type.as_str()),
+        };
+        html! {
+            <>
+                <tr>
+                    <td>{ &entry.created_at }</td>
+                    <td>
+                        { entry.actor.clone().unwrap_or_else(|| "System".to_string()) }
+                        if let Some(key_id) = entry.api_key_id {
+                            <span class="audit-log-key">{ format!(" (API key #{})", key_id) }</span>
+                        }
+                    </td>
+                    <td>{ entry.ip.clone().unwrap_or_default() }</td>
+                    <td>{ label(entry.action.as_str()) }</td>
+                    <td>{ entity }</td>
+                    <td>
+                        <button onclick={toggle}>{ if open { "Hide" } else { "Details" } }</button>
+                    </td>
+                </tr>
+                if open {
+                    <tr class="audit-log-details">
+                        <td colspan="6">
+                            <div class="audit-log-change">
+                                <div>
+                                    <h4>{ "Before" }</h4>
+                                    <pre>{ pretty(&entry.before) }</pre>
+                                </div>
+                                <div>
+                                    <h4>{ "After" }</h4>
+                                    <pre>{ pretty(&entry.after) }</pre>
+                                </div>
+                            </div>
+                        </td>
+                    </tr>
+                }
+            </>
+        }
+    };
+
+    let body = match entries.as_ref() {
+        None => html! { <p>{ "Loading…" }</p> },
+        Some(result) if result.items.is_empty() => html! { <p>{ "No entries match these filters." }</p> },
+        Some(result) => {
+            let pages = ((result.total + result.per_page - 1) / result.per_page).max(1);
+            html! {
+                <>
+                    <table class="audit-log-table">
+                        <thead>
+                            <tr>
+                                <th>{ "Time (UTC)" }</th>
+                                <th>{ "Who" }</th>
+                                <th>{ "Address" }</th>
+                                <th>{ "Action" }</th>
+                                <th>{ "Record" }</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for result.items.iter().map(row) }
+                        </tbody>
+                    </table>
+                    <div class="audit-log-pager">
+                        <button disabled={result.page <= 1} onclick={go_to(result.page - 1)}>{ "Previous" }</button>
+                        <span>{ format!("Page {} of {} ({} entries)", result.page, pages, result.total) }</span>
+                        <button disabled={result.page >= pages} onclick={go_to(result.page + 1)}>{ "Next" }</button>
+                    </div>
+                </>
+            }
+        }
+    };
+
+    html! {
+        <section class="audit-log">
+            <h3>{ "Audit log" }</h3>
+            <form class="audit-log-filters" onsubmit={on_filter}>
+                <input type="text" placeholder="Username" ref={actor_input} />
+                <select onchange={on_action}>
+                    <option value="" selected={action.is_none()}>{ "Any action" }</option>
+                    { for AuditAction::ALL.iter().map(|option| html! {
+                        <option value={option.as_str()} selected={*action == Some(*option)}>
+                            { label(option.as_str()) }
+                        </option>
+                    }) }
+                </select>
+                <select onchange={on_entity_type}>
+                    <option value="" selected={entity_type.is_none()}>{ "Any record" }</option>
+                    { for AuditEntity::ALL.iter().map(|option| html! {
+                        <option value={option.as_str()} selected={*entity_type == Some(*option)}>
+                            { label(option.as_str()) }
+                        </option>
+                    }) }
+                </select>
+                <input type="text" placeholder="Record ID" ref={entity_id_input} />
+                <label>{ "From " }<input type="date" ref={from_input} /></label>
+                <label>{ "To " }<input type="date" ref={to_input} /></label>
+                <button type="submit">{ "Filter" }</button>
+            </form>
+            if let Some(message) = error.as_ref() {
+                <p class="error">{ message }</p>
+            }
+            { body }
+        </section>
+    }
+}
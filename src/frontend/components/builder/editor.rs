@@ -1,10 +1,243 @@
 use yew::prelude::*;
+use crate::frontend::components::builder::module::Module;
+use crate::frontend::components::builder::tree::{
+    copy_section, drop_index, new_id, new_module, new_row, new_section, BuilderAction, DragPayload, ROW_LAYOUTS,
+};
+use crate::frontend::services::builder_service::{LibraryItem, LibraryKind};
+use crate::frontend::templates::document_template::DocumentTemplate;
+use crate::shared::builder::{Column, PageDocument, Row, Section};
 
+#[derive(Properties, PartialEq)]
+pub struct EditorProps {
+    pub document: PageDocument,
+    /// Id of the module whose properties are open
+    pub selected: Option<String>,
+    /// Library items, for showing the global blocks the page uses
+    #[prop_or_default]
+    pub library: Vec<LibraryItem>,
+    pub on_action: Callback<BuilderAction>,
+    pub on_select: Callback<Option<String>>,
+    /// Save a section to the library as the given kind
+    #[prop_or_default]
+    pub on_save_section: Option<Callback<(Section, LibraryKind)>>,
+}
+
+/// Handle a drop into `column_id` at `index` (as displayed): insert a new
+/// module from the toolbox, or move an existing one
+fn drop_handler(props: &EditorProps, column_id: String, index: usize) -> Callback<DragEvent> {
+    let document = props.document.clone();
+    let on_action = props.on_action.clone();
+    let on_select = props.on_select.clone();
+    Callback::from(move |e: DragEvent| {
+        e.prevent_default();
+        e.stop_propagation();
+        let payload = e
+            .data_transfer()
+            .and_then(|data| data.get_data(DragPayload::FORMAT).ok())
+            .and_then(|data| DragPayload::decode(&data));
+
+        match payload {
+            Some(DragPayload::New(type_name)) => {
+                if let Some(module) = new_module(&type_name, new_id("m")) {
+                    let id = module.id.clone();
+                    on_action.emit(BuilderAction::InsertModule {
+                        column_id: column_id.clone(),
+                        index,
+                        module,
+                    });
+                    on_select.emit(Some(id));
+                }
+            }
+            Some(DragPayload::Existing(module_id)) => {
+                let index = drop_index(&document, &module_id, &column_id, index);
+                on_action.emit(BuilderAction::MoveModule {
+                    module_id,
+                    column_id: column_id.clone(),
+                    index,
+                });
+            }
+            None => {}
+        }
+    })
+}
+
+fn render_column(props: &EditorProps, column: &Column) -> Html {
+    let on_select = props.on_select.reform(Some);
+    let on_remove = props
+        .on_action
+        .reform(|module_id| BuilderAction::RemoveModule { module_id });
+
+    html! {
+        <div key={column.id.clone()}
+            class="builder-column"
+            style={format!("flex: {}", column.width)}
+            ondragover={Callback::from(|e: DragEvent| e.prevent_default())}
+            ondrop={drop_handler(props, column.id.clone(), column.modules.len())}>
+            {
+                for column.modules.iter().enumerate().map(|(index, module)| html! {
+                    <Module key={module.id.clone()}
+                        module={module.clone()}
+                        selected={props.selected.as_deref() == Some(module.id.as_str())}
+                        on_select={on_select.clone()}
+                        on_remove={on_remove.clone()}
+                        on_drop={drop_handler(props, column.id.clone(), index)} />
+                })
+            }
+            if column.modules.is_empty() {
+                <div class="builder-drop-hint">{ "Drop modules here" }</div>
+            }
+        </div>
+    }
+}
+
+fn render_row(props: &EditorProps, row: &Row, index: usize, count: usize) -> Html {
+    let move_to = |target: usize| {
+        let row_id = row.id.clone();
+        props
+            .on_action
+            .reform(move |_: MouseEvent| BuilderAction::MoveRow { row_id: row_id.clone(), index: target })
+    };
+    let remove = {
+        let row_id = row.id.clone();
+        props
+            .on_action
+            .reform(move |_: MouseEvent| BuilderAction::RemoveRow { row_id: row_id.clone() })
+    };
+
+    html! {
+        <div key={row.id.clone()} class="builder-row-wrap">
+            <div class="builder-controls">
+                <button disabled={index == 0} onclick={move_to(index.saturating_sub(1))}>{ "↑" }</button>
+                <button disabled={index + 1 >= count} onclick={move_to(index + 1)}>{ "↓" }</button>
+                <button onclick={remove}>{ "Remove row" }</button>
+            </div>
+            <div class="builder-row">
+                { for row.columns.iter().map(|column| render_column(props, column)) }
+            </div>
+        </div>
+    }
+}
+
+/// A global block: shown read-only, edited from the library
+fn render_block(props: &EditorProps, section: &Section, block_id: i32, controls: Html) -> Html {
+    let block = props.library.iter().find(|item| item.id == block_id);
+    // Detaching copies the block's content into the page, keeping the section's place
+    let detach = block.map(|block| {
+        let id = section.id.clone();
+        let content = block.section.clone();
+        props.on_action.reform(move |_: MouseEvent| BuilderAction::UpdateSection {
+            section: Section {
+                id: id.clone(),
+                ..copy_section(&content, new_id)
+            },
+        })
+    });
+
+    html! {
+        <div key={section.id.clone()} class="builder-section builder-global-block">
+            <div class="builder-controls">
+                <strong>{ format!("Global block: {}", block.map_or(section.name.as_str(), |b| b.name.as_str())) }</strong>
+                { controls }
+                if let Some(detach) = detach {
+                    <button onclick={detach} title="Copy the block into this page so it can be edited here">{ "Detach" }</button>
+                }
+            </div>
+            {
+                match block {
+                    Some(block) => html! {
+                        <DocumentTemplate document={PageDocument {
+                            sections: vec![block.section.clone()],
+                            ..PageDocument::default()
+                        }} />
+                    },
+                    None => html! { <p class="builder-drop-hint">{ "This block is missing from the library" }</p> },
+                }
+            }
+        </div>
+    }
+}
+
+fn render_section(props: &EditorProps, section: &Section, index: usize, count: usize) -> Html {
+    let move_to = |target: usize| {
+        let section_id = section.id.clone();
+        props.on_action.reform(move |_: MouseEvent| BuilderAction::MoveSection {
+            section_id: section_id.clone(),
+            index: target,
+        })
+    };
+    let remove = {
+        let section_id = section.id.clone();
+        props
+            .on_action
+            .reform(move |_: MouseEvent| BuilderAction::RemoveSection { section_id: section_id.clone() })
+    };
+    let controls = html! {
+        <>
+            <button disabled={index == 0} onclick={move_to(index.saturating_sub(1))}>{ "↑" }</button>
+            <button disabled={index + 1 >= count} onclick={move_to(index + 1)}>{ "↓" }</button>
+            <button onclick={remove}>{ "Remove section" }</button>
+        </>
+    };
+    if let Some(block_id) = section.block_id {
+        return render_block(props, section, block_id, controls);
+    }
+
+    let save_as = |kind: LibraryKind, label: &str| match &props.on_save_section {
+        Some(on_save_section) => {
+            let section = section.clone();
+            let onclick = on_save_section.reform(move |_: MouseEvent| (section.clone(), kind));
+            html! { <button {onclick}>{ label }</button> }
+        }
+        None => html! {},
+    };
+    let row_count = section.rows.len();
+
+    html! {
+        <div key={section.id.clone()} class="builder-section">
+            <div class="builder-controls">
+                <strong>{ if section.name.is_empty() { "Section" } else { section.name.as_str() } }</strong>
+                { controls }
+                { save_as(LibraryKind::SavedSection, "Save to library") }
+                { save_as(LibraryKind::GlobalBlock, "Make global") }
+            </div>
+            { for section.rows.iter().enumerate().map(|(i, row)| render_row(props, row, i, row_count)) }
+            <div class="builder-add-row">
+                { "Add row: " }
+                {
+                    for ROW_LAYOUTS.iter().map(|widths| {
+                        let section_id = section.id.clone();
+                        let label = widths.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("+");
+                        let onclick = props.on_action.reform(move |_: MouseEvent| BuilderAction::InsertRow {
+                            section_id: section_id.clone(),
+                            index: row_count,
+                            row: new_row(widths, new_id),
+                        });
+                        html! { <button {onclick}>{ label }</button> }
+                    })
+                }
+            </div>
+        </div>
+    }
+}
+
+/// The builder canvas: sections, rows and columns that modules are dragged into
 #[function_component(Editor)]
-pub fn editor() -> Html {
+pub fn editor(props: &EditorProps) -> Html {
+    let count = props.document.sections.len();
+    let add_section = props.on_action.reform(move |_: MouseEvent| BuilderAction::InsertSection {
+        index: count,
+        section: new_section(new_id),
+    });
+    let deselect = props.on_select.reform(|_: MouseEvent| None);
+
     html! {
-        <div class="editor">
-            { "Drag and drop modules here" }
+        <div class="editor" onclick={deselect}>
+            {
+                for props.document.sections.iter().enumerate().map(|(i, section)| {
+                    render_section(props, section, i, count)
+                })
+            }
+            <button class="builder-add-section" onclick={add_section}>{ "Add section" }</button>
         </div>
     }
 }
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use yew::prelude::*;
+use web_sys::HtmlSelectElement;
+use crate::frontend::components::builder::property_panel::field_value;
+use crate::shared::builder::{Action, EventBinding, Module, Trigger};
+
+/// Something on the page an action can point at
+#[derive(Clone, PartialEq)]
+pub struct ActionTarget {
+    pub id: String,
+    pub label: String,
+    pub section: bool,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct EventsPanelProps {
+    pub module: Module,
+    /// Sections and modules of the page
+    pub targets: Vec<ActionTarget>,
+    pub on_change: Callback<Module>,
+}
+
+const ACTION_TYPES: [(&str, &str); 4] = [
+    ("open_url", "Open link"),
+    ("toggle_class", "Toggle class"),
+    ("show_modal", "Show section as dialog"),
+    ("track_event", "Track analytics event"),
+];
+
+/// A fresh action of the named type, pointing at `target` where it needs one
+fn new_action(type_name: &str, target: &str) -> Action {
+    let target = target.to_string();
+    match type_name {
+        "toggle_class" => Action::ToggleClass { target, class: "is-active".to_string() },
+        "show_modal" => Action::ShowModal { target },
+        "track_event" => Action::TrackEvent { name: "click".to_string(), properties: BTreeMap::new() },
+        _ => Action::OpenUrl { url: "/".to_string(), new_tab: false },
+    }
+}
+
+/// Event properties are edited as `key=value` lines
+fn properties_text(properties: &BTreeMap<String, String>) -> String {
+    properties
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse_properties(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+/// Callback applying `edit` to a copy of the module's bindings
+fn edit_events(
+    module: &Module,
+    on_change: &Callback<Module>,
+    edit: impl Fn(&mut Vec<EventBinding>, Event) + 'static,
+) -> Callback<Event> {
+    let module = module.clone();
+    on_change.reform(move |e: Event| {
+        let mut updated = module.clone();
+        edit(&mut updated.events, e);
+        updated
+    })
+}
+
+/// Like [`edit_events`], for buttons
+fn click_events(
+    module: &Module,
+    on_change: &Callback<Module>,
+    edit: impl Fn(&mut Vec<EventBinding>) + 'static,
+) -> Callback<MouseEvent> {
+    let change = edit_events(module, on_change, move |events, _| edit(events));
+    Callback::from(move |e: MouseEvent| change.emit(e.into()))
+}
+
+fn select_value(e: &Event) -> String {
+    e.target_dyn_into::<HtmlSelectElement>()
+        .map(|select| select.value())
+        .unwrap_or_default()
+}
+
+/// Actions the selected module runs on click or when scrolled into view
+#[function_component(EventsPanel)]
+pub fn events_panel(props: &EventsPanelProps) -> Html {
+    let module = &props.module;
+    let first_target = props.targets.first().map(|t| t.id.clone()).unwrap_or_default();
+
+    let target_select = |b: usize, a: usize, current: &str, section_only: bool| {
+        let onchange = edit_events(module, &props.on_change, move |events, e| {
+            let value = select_value(&e);
+            match &mut events[b].actions[a] {
+                Action::ToggleClass { target, .. } | Action::ShowModal { target } => *target = value,
+                _ => {}
+            }
+        });
+        html! {
+            <label>
+                { "Target" }
+                <select {onchange}>
+                    { for props.targets.iter().filter(|t| t.section || !section_only).map(|t| html! {
+                        <option value={t.id.clone()} selected={t.id == current}>{ &t.label }</option>
+                    }) }
+                </select>
+            </label>
+        }
+    };
+
+    let render_action = |b: usize, a: usize, action: &Action| {
+        let fields = match action {
+            Action::OpenUrl { url, new_tab } => html! {
+                <>
+                    <label>
+                        { "URL" }
+                        <input type="text" value={url.clone()} onchange={edit_events(module, &props.on_change, move |events, e| {
+                            if let Action::OpenUrl { url, .. } = &mut events[b].actions[a] { *url = field_value(&e).trim().to_string(); }
+                        })} />
+                    </label>
+                    <label>
+                        <input type="checkbox" checked={*new_tab} onchange={edit_events(module, &props.on_change, move |events, e| {
+                            if let Action::OpenUrl { new_tab, .. } = &mut events[b].actions[a] { *new_tab = field_value(&e) == "true"; }
+                        })} />
+                        { " Open in a new tab" }
+                    </label>
+                </>
+            },
+            Action::ToggleClass { target, class } => html! {
+                <>
+                    { target_select(b, a, target, false) }
+                    <label>
+                        { "Class" }
+                        <input type="text" value={class.clone()} onchange={edit_events(module, &props.on_change, move |events, e| {
+                            if let Action::ToggleClass { class, .. } = &mut events[b].actions[a] { *class = field_value(&e).trim().to_string(); }
+                        })} />
+                    </label>
+                </>
+            },
+            Action::ShowModal { target } => target_select(b, a, target, true),
+            Action::TrackEvent { name, properties } => html! {
+                <>
+                    <label>
+                        { "Event name" }
+                        <input type="text" value={name.clone()} onchange={edit_events(module, &props.on_change, move |events, e| {
+                            if let Action::TrackEvent { name, .. } = &mut events[b].actions[a] { *name = field_value(&e).trim().to_string(); }
+                        })} />
+                    </label>
+                    <label>
+                        { "Properties (key=value per line)" }
+                        <textarea rows="3" value={properties_text(properties)} onchange={edit_events(module, &props.on_change, move |events, e| {
+                            if let Action::TrackEvent { properties, .. } = &mut events[b].actions[a] { *properties = parse_properties(&field_value(&e)); }
+                        })} />
+                    </label>
+                </>
+            },
+        };
+
+        let on_type = {
+            let first_target = first_target.clone();
+            edit_events(module, &props.on_change, move |events, e| {
+                events[b].actions[a] = new_action(&select_value(&e), &first_target);
+            })
+        };
+        let on_remove = click_events(module, &props.on_change, move |events| {
+            events[b].actions.remove(a);
+        });
+
+        html! {
+            <li class="events-action">
+                <select onchange={on_type}>
+                    { for ACTION_TYPES.iter().map(|(value, label)| html! {
+                        <option value={*value} selected={*value == action.type_name()}>{ *label }</option>
+                    }) }
+                </select>
+                { fields }
+                <button class="builder-remove" onclick={on_remove}>{ "Remove action" }</button>
+            </li>
+        }
+    };
+
+    let render_binding = |b: usize, binding: &EventBinding| {
+        let on_trigger = edit_events(module, &props.on_change, move |events, e| {
+            if let Some(trigger) = Trigger::parse(&select_value(&e)) {
+                events[b].trigger = trigger;
+            }
+        });
+        let on_add = {
+            let first_target = first_target.clone();
+            click_events(module, &props.on_change, move |events| {
+                events[b].actions.push(new_action("open_url", &first_target));
+            })
+        };
+        let on_remove = click_events(module, &props.on_change, move |events| {
+            events.remove(b);
+        });
+
+        html! {
+            <div class="events-binding">
+                <label>
+                    { "When" }
+                    <select onchange={on_trigger}>
+                        { for Trigger::ALL.iter().map(|trigger| html! {
+                            <option value={trigger.as_str()} selected={*trigger == binding.trigger}>
+                                { match trigger {
+                                    Trigger::Click => "Clicked",
+                                    Trigger::Visible => "Scrolled into view",
+                                } }
+                            </option>
+                        }) }
+                    </select>
+                </label>
+                <ol>
+                    { for binding.actions.iter().enumerate().map(|(a, action)| render_action(b, a, action)) }
+                </ol>
+                <button onclick={on_add}>{ "Add action" }</button>
+                <button class="builder-remove" onclick={on_remove}>{ "Remove event" }</button>
+            </div>
+        }
+    };
+
+    let on_add_binding = {
+        let first_target = first_target.clone();
+        click_events(module, &props.on_change, move |events| {
+            events.push(EventBinding {
+                trigger: Trigger::Click,
+                actions: vec![new_action("open_url", &first_target)],
+            });
+        })
+    };
+
+    html! {
+        <div class="property-panel events-panel">
+            <h3>{ "Events" }</h3>
+            if module.events.is_empty() {
+                <p class="toolbox-hint">{ "No events. Actions run on the published page." }</p>
+            }
+            { for module.events.iter().enumerate().map(|(b, binding)| render_binding(b, binding)) }
+            <button onclick={on_add_binding}>{ "Add event" }</button>
+        </div>
+    }
+}
@@ -0,0 +1,197 @@
+// src/frontend/components/builder/history.rs
+
+use serde::{Deserialize, Serialize};
+use crate::frontend::components::builder::tree::{apply, BuilderAction};
+use crate::shared::builder::PageDocument;
+
+/// Content edits to the same module within this many milliseconds of each
+/// other are undone together, so typing a sentence is one step
+pub const TYPING_GROUP_MS: f64 = 1000.0;
+/// Oldest commands are dropped beyond this many
+pub const MAX_HISTORY: usize = 200;
+
+/// An applied edit and the edit that reverses it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Command {
+    pub redo: BuilderAction,
+    pub undo: BuilderAction,
+    /// Commands with the same group recorded in quick succession are merged
+    #[serde(default)]
+    pub group: Option<String>,
+    /// When the command was last extended, in milliseconds
+    pub at: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+    /// Set after undo/redo so the next edit starts a new command
+    #[serde(default)]
+    sealed: bool,
+}
+
+/// Group for edits that change a module's content but not its style
+fn typing_group(redo: &BuilderAction, undo: &BuilderAction) -> Option<String> {
+    match (redo, undo) {
+        (BuilderAction::UpdateModule { module: next }, BuilderAction::UpdateModule { module: previous })
+            if next.style == previous.style
+                && next.breakpoints == previous.breakpoints
+                && next.events.len() == previous.events.len()
+                && next.kind.type_name() == previous.kind.type_name() =>
+        {
+            Some(format!("content:{}", next.id))
+        }
+        _ => None,
+    }
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Record an edit that has been applied, given its inverse
+    pub fn record(&mut self, redo: BuilderAction, undo: BuilderAction, now: f64) {
+        let group = typing_group(&redo, &undo);
+        self.redo.clear();
+
+        if let (Some(group), Some(last), false) = (&group, self.undo.last_mut(), self.sealed) {
+            if last.group.as_ref() == Some(group) && now - last.at < TYPING_GROUP_MS {
+                // Keep the oldest inverse so undo goes back to before the typing started
+                last.redo = redo;
+                last.at = now;
+                return;
+            }
+        }
+
+        self.sealed = false;
+        self.undo.push(Command { redo, undo, group, at: now });
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Revert the last command. Returns false if there was nothing to undo
+    /// or the command no longer applies, in which case it is dropped.
+    pub fn undo(&mut self, document: &mut PageDocument) -> bool {
+        let command = match self.undo.pop() {
+            Some(command) => command,
+            None => return false,
+        };
+        self.sealed = true;
+        match apply(document, command.undo.clone()) {
+            Ok(_) => {
+                self.redo.push(command);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Re-apply the last undone command
+    pub fn redo(&mut self, document: &mut PageDocument) -> bool {
+        let command = match self.redo.pop() {
+            Some(command) => command,
+            None => return false,
+        };
+        self.sealed = true;
+        match apply(document, command.redo.clone()) {
+            Ok(_) => {
+                self.undo.push(command);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::components::builder::tree::{find_module, new_module, new_section};
+    use crate::shared::builder::ModuleKind;
+
+    fn document() -> PageDocument {
+        let mut ids = ["s1", "r1", "c1"].iter();
+        let mut doc = PageDocument::default();
+        doc.sections.push(new_section(|_: &str| ids.next().unwrap().to_string()));
+        doc
+    }
+
+    /// Apply and record an edit
+    fn edit(doc: &mut PageDocument, history: &mut History, action: BuilderAction, now: f64) {
+        let inverse = apply(doc, action.clone()).unwrap();
+        history.record(action, inverse, now);
+    }
+
+    fn set_text(doc: &PageDocument, text: &str) -> BuilderAction {
+        let mut module = find_module(doc, "m1").unwrap().clone();
+        module.kind = ModuleKind::Text { html: text.to_string() };
+        BuilderAction::UpdateModule { module }
+    }
+
+    fn text(doc: &PageDocument) -> String {
+        match &find_module(doc, "m1").unwrap().kind {
+            ModuleKind::Text { html } => html.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_history() {
+        let mut doc = document();
+        let mut history = History::default();
+        let module = new_module("text", "m1".into()).unwrap();
+        edit(&mut doc, &mut history, BuilderAction::InsertModule { column_id: "c1".into(), index: 0, module }, 0.0);
+        let inserted = doc.clone();
+
+        assert!(history.undo(&mut doc));
+        assert_eq!(doc, document());
+        assert!(!history.undo(&mut doc));
+        assert!(history.redo(&mut doc));
+        assert_eq!(doc, inserted);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn typing_is_undone_in_one_step() {
+        let mut doc = document();
+        let mut history = History::default();
+        let module = new_module("text", "m1".into()).unwrap();
+        edit(&mut doc, &mut history, BuilderAction::InsertModule { column_id: "c1".into(), index: 0, module }, 0.0);
+
+        for (i, typed) in ["H", "He", "Hel", "Hell", "Hello"].iter().enumerate() {
+            let action = set_text(&doc, typed);
+            edit(&mut doc, &mut history, action, 5000.0 + i as f64 * 200.0);
+        }
+        // A pause starts a new group
+        let action = set_text(&doc, "Hello!");
+        edit(&mut doc, &mut history, action, 9000.0);
+
+        history.undo(&mut doc);
+        assert_eq!(text(&doc), "Hello");
+        history.undo(&mut doc);
+        assert_eq!(text(&doc), "<p>New text</p>");
+        history.redo(&mut doc);
+        assert_eq!(text(&doc), "Hello");
+    }
+
+    #[test]
+    fn a_new_edit_clears_the_redo_stack() {
+        let mut doc = document();
+        let mut history = History::default();
+        let module = new_module("text", "m1".into()).unwrap();
+        edit(&mut doc, &mut history, BuilderAction::InsertModule { column_id: "c1".into(), index: 0, module }, 0.0);
+        history.undo(&mut doc);
+        assert!(history.can_redo());
+
+        let module = new_module("button", "m2".into()).unwrap();
+        edit(&mut doc, &mut history, BuilderAction::InsertModule { column_id: "c1".into(), index: 0, module }, 10.0);
+        assert!(!history.can_redo());
+    }
+}
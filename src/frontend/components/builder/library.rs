@@ -0,0 +1,128 @@
+use yew::prelude::*;
+use crate::frontend::services::builder_service::{fetch_block_usage, BlockUsage, LibraryItem, LibraryKind};
+
+#[derive(Properties, PartialEq)]
+pub struct LibraryProps {
+    pub items: Vec<LibraryItem>,
+    pub on_insert: Callback<LibraryItem>,
+    /// Open a global block in the builder
+    pub on_edit: Callback<LibraryItem>,
+    pub on_delete: Callback<LibraryItem>,
+}
+
+#[derive(Properties, PartialEq)]
+struct UsageListProps {
+    block_id: i32,
+}
+
+/// Pages showing a global block, fetched when opened
+#[function_component(UsageList)]
+fn usage_list(props: &UsageListProps) -> Html {
+    let pages = use_state(|| None::<Vec<BlockUsage>>);
+
+    {
+        let pages = pages.clone();
+        use_effect_with_deps(
+            move |block_id| {
+                let block_id = *block_id;
+                wasm_bindgen_futures::spawn_local(async move {
+                    match fetch_block_usage(block_id).await {
+                        Ok(fetched) => pages.set(Some(fetched)),
+                        Err(err) => {
+                            log::error!("Error loading block usage: {:?}", err);
+                            pages.set(Some(Vec::new()));
+                        }
+                    }
+                });
+                || ()
+            },
+            props.block_id,
+        );
+    }
+
+    match pages.as_ref() {
+        None => html! { <p class="library-usage">{ "Loading..." }</p> },
+        Some(pages) if pages.is_empty() => html! { <p class="library-usage">{ "Not used on any page" }</p> },
+        Some(pages) => html! {
+            <ul class="library-usage">
+                { for pages.iter().map(|page| html! {
+                    <li key={page.page_id}>
+                        <a href={format!("/{}", page.slug)} target="_blank">{ &page.title }</a>
+                    </li>
+                }) }
+            </ul>
+        },
+    }
+}
+
+/// Global blocks and saved sections that can be added to the page
+#[function_component(Library)]
+pub fn library(props: &LibraryProps) -> Html {
+    let open_usage = use_state(|| None::<i32>);
+
+    let render_item = |item: &LibraryItem| {
+        let on_insert = {
+            let item = item.clone();
+            props.on_insert.reform(move |_: MouseEvent| item.clone())
+        };
+        let on_delete = {
+            let item = item.clone();
+            props.on_delete.reform(move |_: MouseEvent| item.clone())
+        };
+        let global = item.kind == LibraryKind::GlobalBlock;
+        let in_use = global && item.usage_count > 0;
+
+        html! {
+            <li key={item.id} class="library-item">
+                <strong>{ &item.name }</strong>
+                <div class="library-actions">
+                    <button onclick={on_insert}>{ "Insert" }</button>
+                    if global {
+                        <button onclick={{
+                            let item = item.clone();
+                            props.on_edit.reform(move |_: MouseEvent| item.clone())
+                        }}>{ "Edit" }</button>
+                        <button onclick={{
+                            let open_usage = open_usage.clone();
+                            let id = item.id;
+                            Callback::from(move |_: MouseEvent| {
+                                open_usage.set(if *open_usage == Some(id) { None } else { Some(id) })
+                            })
+                        }}>
+                            { format!("Used on {} page{}", item.usage_count, if item.usage_count == 1 { "" } else { "s" }) }
+                        </button>
+                    }
+                    <button onclick={on_delete} disabled={in_use}
+                        title={in_use.then(|| "Remove it from every page first")}>
+                        { "Delete" }
+                    </button>
+                </div>
+                if *open_usage == Some(item.id) {
+                    <UsageList block_id={item.id} />
+                }
+            </li>
+        }
+    };
+
+    let group = |kind: LibraryKind, title: &str| {
+        let items: Vec<&LibraryItem> = props.items.iter().filter(|item| item.kind == kind).collect();
+        html! {
+            <>
+                <h4>{ title }</h4>
+                if items.is_empty() {
+                    <p class="toolbox-hint">{ "Nothing saved yet" }</p>
+                } else {
+                    <ul>{ for items.into_iter().map(|item| render_item(item)) }</ul>
+                }
+            </>
+        }
+    };
+
+    html! {
+        <div class="library">
+            <h3>{ "Library" }</h3>
+            { group(LibraryKind::GlobalBlock, "Global blocks") }
+            { group(LibraryKind::SavedSection, "Saved sections") }
+        </div>
+    }
+}
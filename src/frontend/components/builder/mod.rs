@@ -0,0 +1,22 @@
+// src/frontend/components/builder/mod.rs
+//
+// Pieces of the page builder, assembled by `pages::page_builder`.
+
+pub mod editor;
+pub mod events_panel;
+pub mod history;
+pub mod library;
+pub mod module;
+pub mod preview;
+pub mod property_panel;
+pub mod state;
+pub mod toolbox;
+pub mod tree;
+
+pub use editor::Editor;
+pub use events_panel::EventsPanel;
+pub use library::Library;
+pub use module::Module;
+pub use preview::Preview;
+pub use property_panel::PropertyPanel;
+pub use toolbox::Toolbox;
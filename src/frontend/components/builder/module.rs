@@ -1,15 +1,70 @@
 use yew::prelude::*;
+use crate::frontend::components::builder::tree::DragPayload;
+use crate::shared::builder::{self, ModuleKind};
 
 #[derive(Properties, PartialEq)]
 pub struct ModuleProps {
-    pub module_type: String,
+    pub module: builder::Module,
+    pub selected: bool,
+    pub on_select: Callback<String>,
+    pub on_remove: Callback<String>,
+    /// Fired when something is dropped on the module, to insert in front of it
+    pub on_drop: Callback<DragEvent>,
 }
 
+/// A module as shown in the editor canvas: a draggable summary card
 #[function_component(Module)]
 pub fn module(props: &ModuleProps) -> Html {
+    let module = &props.module;
+    let summary = match &module.kind {
+        ModuleKind::Text { html } => {
+            let text: String = html
+                .split(|c| c == '<' || c == '>')
+                .enumerate()
+                .filter(|(i, _)| i % 2 == 0)
+                .map(|(_, part)| part)
+                .collect();
+            format!("Text: {}", text.chars().take(60).collect::<String>())
+        }
+        ModuleKind::Image { src, .. } if src.is_empty() => "Image: none chosen".to_string(),
+        ModuleKind::Image { src, .. } => format!("Image: {}", src),
+        ModuleKind::Button { label, url, .. } => format!("Button: {} → {}", label, url),
+    };
+
+    let ondragstart = {
+        let payload = DragPayload::Existing(module.id.clone()).encode();
+        Callback::from(move |e: DragEvent| {
+            e.stop_propagation();
+            if let Some(data) = e.data_transfer() {
+                let _ = data.set_data(DragPayload::FORMAT, &payload);
+                data.set_effect_allowed("move");
+            }
+        })
+    };
+    let onclick = {
+        let id = module.id.clone();
+        props.on_select.reform(move |e: MouseEvent| {
+            e.stop_propagation();
+            id.clone()
+        })
+    };
+    let on_remove = {
+        let id = module.id.clone();
+        props.on_remove.reform(move |e: MouseEvent| {
+            e.stop_propagation();
+            id.clone()
+        })
+    };
+
     html! {
-        <div class={format!("module {}", props.module_type)}>
-            { format!("This is a {}", props.module_type) }
+        <div class={classes!("builder-module", module.kind.type_name(), props.selected.then(|| "selected"))}
+            draggable="true"
+            {ondragstart}
+            ondragover={Callback::from(|e: DragEvent| e.prevent_default())}
+            ondrop={props.on_drop.clone()}
+            {onclick}>
+            <span>{ summary }</span>
+            <button class="builder-remove" title="Remove" onclick={on_remove}>{ "✕" }</button>
         </div>
     }
 }
@@ -1,10 +1,83 @@
 use yew::prelude::*;
+use web_sys::HtmlInputElement;
+use crate::frontend::templates::document_template::DocumentTemplate;
+use crate::shared::builder::{Breakpoint, PageDocument, MAX_CUSTOM_WIDTH, MIN_CUSTOM_WIDTH};
 
+/// Width the preview is shown at for each device, in pixels
+fn device_width(device: Option<Breakpoint>) -> Option<u16> {
+    match device {
+        None => None,
+        Some(Breakpoint::Tablet) => Some(768),
+        Some(Breakpoint::Mobile) => Some(375),
+        Some(Breakpoint::Custom(width)) => Some(width),
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PreviewProps {
+    pub document: PageDocument,
+    /// Device shown, and whose styles the property panel edits; `None` is desktop
+    #[prop_or_default]
+    pub device: Option<Breakpoint>,
+    #[prop_or_default]
+    pub on_device: Callback<Option<Breakpoint>>,
+}
+
+/// Live preview, rendered with the same template as the public page
 #[function_component(Preview)]
-pub fn preview() -> Html {
+pub fn preview(props: &PreviewProps) -> Html {
+    let custom_input = use_node_ref();
+
+    // Custom widths used anywhere in the document, narrowest last
+    let mut custom: Vec<Breakpoint> = props
+        .document
+        .modules()
+        .flat_map(|m| m.breakpoints.iter().map(|b| b.breakpoint))
+        .filter(|b| matches!(b, Breakpoint::Custom(_)))
+        .collect();
+    custom.sort_by(|a, b| b.max_width().cmp(&a.max_width()));
+    custom.dedup();
+
+    let device_button = |device: Option<Breakpoint>| {
+        let label = device.map_or("Desktop".to_string(), |b| b.label());
+        let onclick = props.on_device.reform(move |_: MouseEvent| device);
+        let class = classes!((props.device == device).then(|| "active"));
+        html! { <button {class} {onclick}>{ label }</button> }
+    };
+
+    let on_custom = {
+        let custom_input = custom_input.clone();
+        let on_device = props.on_device.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let width = custom_input
+                .cast::<HtmlInputElement>()
+                .and_then(|input| input.value().trim().parse::<u16>().ok());
+            if let Some(width) = width.filter(|w| (MIN_CUSTOM_WIDTH..=MAX_CUSTOM_WIDTH).contains(w)) {
+                on_device.emit(Some(Breakpoint::Custom(width)));
+            }
+        })
+    };
+
+    let viewport = device_width(props.device);
+    let frame_style = viewport.map(|width| format!("width: {}px", width));
+
     html! {
         <div class="preview">
-            { "Live preview of the page" }
+            <div class="device-switcher">
+                { device_button(None) }
+                { device_button(Some(Breakpoint::Tablet)) }
+                { device_button(Some(Breakpoint::Mobile)) }
+                { for custom.into_iter().map(|b| device_button(Some(b))) }
+                <form onsubmit={on_custom}>
+                    <input ref={custom_input} type="number" placeholder="Width"
+                        min={MIN_CUSTOM_WIDTH.to_string()} max={MAX_CUSTOM_WIDTH.to_string()} />
+                    <button type="submit">{ "Add width" }</button>
+                </form>
+            </div>
+            <div class="preview-frame" style={frame_style}>
+                <DocumentTemplate document={props.document.clone()} {viewport} />
+            </div>
         </div>
     }
 }
@@ -0,0 +1,152 @@
+use yew::prelude::*;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use crate::shared::builder::{Breakpoint, Module, ModuleKind, STYLE_PROPERTIES};
+
+#[derive(Properties, PartialEq)]
+pub struct PropertyPanelProps {
+    pub module: Module,
+    /// Breakpoint whose styles are edited; `None` is desktop
+    #[prop_or_default]
+    pub breakpoint: Option<Breakpoint>,
+    pub on_change: Callback<Module>,
+    pub on_remove: Callback<String>,
+}
+
+/// Value of the input or textarea an event came from
+pub fn field_value(e: &Event) -> String {
+    if let Some(input) = e.target_dyn_into::<HtmlInputElement>() {
+        if input.type_() == "checkbox" {
+            return input.checked().to_string();
+        }
+        return input.value();
+    }
+    e.target_dyn_into::<HtmlTextAreaElement>()
+        .map(|area| area.value())
+        .unwrap_or_default()
+}
+
+/// Value a property has at `breakpoint` before its own override: the
+/// desktop value as adjusted by any wider breakpoints
+fn inherited_value(module: &Module, breakpoint: Option<Breakpoint>, property: &str) -> Option<String> {
+    let width = breakpoint?.max_width();
+    let mut value = module.style.get(property);
+    for override_ in &module.breakpoints {
+        if override_.breakpoint.max_width() > width {
+            value = override_.style.get(property).or(value);
+        }
+    }
+    value.cloned()
+}
+
+/// Settings of the selected module, applied as they are typed
+#[function_component(PropertyPanel)]
+pub fn property_panel(props: &PropertyPanelProps) -> Html {
+    let module = &props.module;
+
+    // Callback applying `edit` to a copy of the module with the field's new value
+    let field = |edit: fn(&mut Module, String)| {
+        let module = module.clone();
+        props.on_change.reform(move |e: Event| {
+            let mut updated = module.clone();
+            edit(&mut updated, field_value(&e));
+            updated
+        })
+    };
+    let on_input = |edit: fn(&mut Module, String)| {
+        let change = field(edit);
+        Callback::from(move |e: InputEvent| change.emit(e.into()))
+    };
+
+    let settings = match &module.kind {
+        ModuleKind::Text { html } => html! {
+            <label>
+                { "Text (HTML)" }
+                <textarea rows="8" value={html.clone()} oninput={on_input(|m, value| {
+                    if let ModuleKind::Text { html } = &mut m.kind { *html = value; }
+                })} />
+            </label>
+        },
+        ModuleKind::Image { src, alt, .. } => html! {
+            <>
+                <label>
+                    { "Image URL" }
+                    <input type="text" value={src.clone()} oninput={on_input(|m, value| {
+                        if let ModuleKind::Image { src, .. } = &mut m.kind { *src = value; }
+                    })} />
+                </label>
+                <label>
+                    { "Alt text" }
+                    <input type="text" value={alt.clone()} oninput={on_input(|m, value| {
+                        if let ModuleKind::Image { alt, .. } = &mut m.kind { *alt = value; }
+                    })} />
+                </label>
+            </>
+        },
+        ModuleKind::Button { label, url, new_tab } => html! {
+            <>
+                <label>
+                    { "Label" }
+                    <input type="text" value={label.clone()} oninput={on_input(|m, value| {
+                        if let ModuleKind::Button { label, .. } = &mut m.kind { *label = value; }
+                    })} />
+                </label>
+                <label>
+                    { "Link" }
+                    <input type="text" value={url.clone()} oninput={on_input(|m, value| {
+                        if let ModuleKind::Button { url, .. } = &mut m.kind { *url = value; }
+                    })} />
+                </label>
+                <label>
+                    <input type="checkbox" checked={*new_tab} onchange={field(|m, value| {
+                        if let ModuleKind::Button { new_tab, .. } = &mut m.kind { *new_tab = value == "true"; }
+                    })} />
+                    { " Open in a new tab" }
+                </label>
+            </>
+        },
+    };
+
+    let breakpoint = props.breakpoint;
+    let styles = STYLE_PROPERTIES.iter().map(|property| {
+        let value = module
+            .style_at(breakpoint)
+            .and_then(|style| style.get(*property))
+            .cloned()
+            .unwrap_or_default();
+        let placeholder = inherited_value(module, breakpoint, property).unwrap_or_default();
+        let module = module.clone();
+        let onchange = props.on_change.reform(move |e: Event| {
+            let mut updated = module.clone();
+            updated.set_style(breakpoint, property, field_value(&e).trim());
+            updated
+        });
+        html! {
+            <label>
+                { *property }
+                <input type="text" {value} {placeholder} {onchange} />
+            </label>
+        }
+    });
+    let style_heading = match breakpoint {
+        None => "Style (desktop)".to_string(),
+        Some(breakpoint) => format!("Style ({}, up to {}px)", breakpoint.label(), breakpoint.max_width()),
+    };
+
+    let on_remove = {
+        let id = module.id.clone();
+        props.on_remove.reform(move |_: MouseEvent| id.clone())
+    };
+
+    html! {
+        <div class="property-panel">
+            <h3>{ format!("{} settings", module.kind.type_name()) }</h3>
+            { settings }
+            <h4>{ style_heading }</h4>
+            if breakpoint.is_some() {
+                <p class="toolbox-hint">{ "Leave a field empty to keep the wider screen's value" }</p>
+            }
+            { for styles }
+            <button class="builder-remove" onclick={on_remove}>{ "Remove module" }</button>
+        </div>
+    }
+}
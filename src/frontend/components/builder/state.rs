@@ -0,0 +1,95 @@
+// src/frontend/components/builder/state.rs
+
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+use crate::frontend::components::builder::history::History;
+use crate::frontend::components::builder::tree::{apply, BuilderAction};
+use crate::shared::builder::PageDocument;
+
+/// The document being edited, owned by the page builder
+#[derive(Debug, Default, PartialEq)]
+pub struct BuilderState {
+    pub document: PageDocument,
+    pub history: History,
+    /// Whether there are edits that have not been saved
+    pub dirty: bool,
+    /// Bumped on every change, for effects that depend on the state
+    pub revision: u64,
+    /// Why the last edit was refused, if it was
+    pub error: Option<String>,
+}
+
+/// Unsaved work kept in local storage so it survives a reload or crash
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Recovery {
+    pub document: PageDocument,
+    pub history: History,
+    /// When it was stored, in milliseconds since the epoch
+    pub saved_at: f64,
+}
+
+pub enum BuilderMsg {
+    /// Replace the document and start a fresh history
+    Load(PageDocument),
+    /// Take the document the server stored, keeping the history
+    Saved(PageDocument),
+    Edit(BuilderAction),
+    Undo,
+    Redo,
+    Restore(Recovery),
+}
+
+impl BuilderState {
+    fn next(&self, document: PageDocument, history: History, dirty: bool) -> Rc<Self> {
+        Rc::new(BuilderState {
+            document,
+            history,
+            dirty,
+            revision: self.revision + 1,
+            error: None,
+        })
+    }
+}
+
+impl Reducible for BuilderState {
+    type Action = BuilderMsg;
+
+    fn reduce(self: Rc<Self>, msg: BuilderMsg) -> Rc<Self> {
+        match msg {
+            BuilderMsg::Load(document) => self.next(document, History::default(), false),
+            BuilderMsg::Saved(document) => self.next(document, self.history.clone(), false),
+            BuilderMsg::Restore(recovery) => self.next(recovery.document, recovery.history, true),
+            BuilderMsg::Edit(action) => {
+                let mut document = self.document.clone();
+                match apply(&mut document, action.clone()) {
+                    Ok(inverse) => {
+                        let mut history = self.history.clone();
+                        history.record(action, inverse, js_sys::Date::now());
+                        self.next(document, history, true)
+                    }
+                    Err(err) => Rc::new(BuilderState {
+                        document: self.document.clone(),
+                        history: self.history.clone(),
+                        dirty: self.dirty,
+                        revision: self.revision,
+                        error: Some(err),
+                    }),
+                }
+            }
+            BuilderMsg::Undo | BuilderMsg::Redo => {
+                let mut document = self.document.clone();
+                let mut history = self.history.clone();
+                let changed = match msg {
+                    BuilderMsg::Undo => history.undo(&mut document),
+                    _ => history.redo(&mut document),
+                };
+                if changed {
+                    self.next(document, history, true)
+                } else {
+                    self
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use yew::prelude::*;
+use crate::frontend::components::builder::tree::{DragPayload, MODULE_TYPES};
 
 #[function_component(Toolbox)]
 pub fn toolbox() -> Html {
@@ -6,10 +7,22 @@ pub fn toolbox() -> Html {
         <div class="toolbox">
             <h3>{ "Modules" }</h3>
             <ul>
-                <li>{ "Text" }</li>
-                <li>{ "Image" }</li>
-                <li>{ "Button" }</li>
+                {
+                    for MODULE_TYPES.iter().map(|(type_name, label)| {
+                        let payload = DragPayload::New(type_name.to_string()).encode();
+                        let ondragstart = Callback::from(move |e: DragEvent| {
+                            if let Some(data) = e.data_transfer() {
+                                let _ = data.set_data(DragPayload::FORMAT, &payload);
+                                data.set_effect_allowed("copy");
+                            }
+                        });
+                        html! {
+                            <li class="toolbox-item" draggable="true" {ondragstart}>{ *label }</li>
+                        }
+                    })
+                }
             </ul>
+            <p class="toolbox-hint">{ "Drag a module into a column" }</p>
         </div>
     }
 }
@@ -0,0 +1,427 @@
+// src/frontend/components/builder/tree.rs
+//
+// Edits to the builder document. Every change the editor makes goes through
+// `apply` as a `BuilderAction`, so the page builder owns the only copy of
+// the document and every edit can be undone.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::shared::builder::{Column, Module, ModuleKind, PageDocument, Row, Section, GRID_UNITS};
+
+/// Module types offered by the toolbox: (type name, label)
+pub const MODULE_TYPES: &[(&str, &str)] = &[
+    ("text", "Text"),
+    ("image", "Image"),
+    ("button", "Button"),
+];
+
+/// Column widths of the row layouts offered when adding a row
+pub const ROW_LAYOUTS: &[&[u8]] = &[&[12], &[6, 6], &[4, 4, 4], &[4, 8], &[8, 4], &[3, 3, 3, 3]];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BuilderAction {
+    InsertModule { column_id: String, index: usize, module: Module },
+    /// Move a module so it ends up at `index` in the target column
+    MoveModule { module_id: String, column_id: String, index: usize },
+    RemoveModule { module_id: String },
+    /// Replace the module with the same id
+    UpdateModule { module: Module },
+    InsertSection { index: usize, section: Section },
+    MoveSection { section_id: String, index: usize },
+    RemoveSection { section_id: String },
+    /// Replace the section with the same id, e.g. to turn it into a global block
+    UpdateSection { section: Section },
+    InsertRow { section_id: String, index: usize, row: Row },
+    MoveRow { row_id: String, index: usize },
+    RemoveRow { row_id: String },
+}
+
+/// What is being dragged onto a column
+#[derive(Debug, Clone, PartialEq)]
+pub enum DragPayload {
+    /// A module type from the toolbox
+    New(String),
+    /// An existing module, by id
+    Existing(String),
+}
+
+impl DragPayload {
+    /// Drag data format the payload travels in
+    pub const FORMAT: &'static str = "text/plain";
+
+    pub fn encode(&self) -> String {
+        match self {
+            DragPayload::New(kind) => format!("new:{}", kind),
+            DragPayload::Existing(id) => format!("move:{}", id),
+        }
+    }
+
+    pub fn decode(data: &str) -> Option<Self> {
+        match data.split_once(':')? {
+            ("new", kind) => Some(DragPayload::New(kind.to_string())),
+            ("move", id) => Some(DragPayload::Existing(id.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// A fresh, reasonably unique node id such as `m-k2x9q1`
+pub fn new_id(prefix: &str) -> String {
+    let n = (js_sys::Math::random() * 2f64.powi(48)) as u64;
+    let mut digits = Vec::new();
+    let mut rest = n;
+    loop {
+        digits.push(std::char::from_digit((rest % 36) as u32, 36).unwrap_or('0'));
+        rest /= 36;
+        if rest == 0 {
+            break;
+        }
+    }
+    format!("{}-{}", prefix, digits.into_iter().rev().collect::<String>())
+}
+
+/// A module of the given type with placeholder content
+pub fn new_module(type_name: &str, id: String) -> Option<Module> {
+    let kind = match type_name {
+        "text" => ModuleKind::Text {
+            html: "<p>New text</p>".to_string(),
+        },
+        "image" => ModuleKind::Image {
+            media_id: None,
+            src: String::new(),
+            alt: String::new(),
+        },
+        "button" => ModuleKind::Button {
+            label: "Click me".to_string(),
+            url: "#".to_string(),
+            new_tab: false,
+        },
+        _ => return None,
+    };
+    Some(Module {
+        id,
+        kind,
+        style: BTreeMap::new(),
+        breakpoints: Vec::new(),
+        events: Vec::new(),
+    })
+}
+
+/// A row with one empty column per width, ids drawn from `new_id`
+pub fn new_row(widths: &[u8], mut new_id: impl FnMut(&str) -> String) -> Row {
+    Row {
+        id: new_id("r"),
+        columns: widths
+            .iter()
+            .map(|width| Column {
+                id: new_id("c"),
+                width: (*width).min(GRID_UNITS),
+                modules: Vec::new(),
+            })
+            .collect(),
+    }
+}
+
+/// A section holding a single full-width row
+pub fn new_section(mut new_id: impl FnMut(&str) -> String) -> Section {
+    Section {
+        id: new_id("s"),
+        name: String::new(),
+        rows: vec![new_row(&[GRID_UNITS], &mut new_id)],
+        block_id: None,
+    }
+}
+
+/// A copy of a saved section with fresh ids throughout, so it can be
+/// inserted any number of times
+pub fn copy_section(section: &Section, mut new_id: impl FnMut(&str) -> String) -> Section {
+    Section {
+        id: new_id("s"),
+        name: section.name.clone(),
+        rows: section
+            .rows
+            .iter()
+            .map(|row| Row {
+                id: new_id("r"),
+                columns: row
+                    .columns
+                    .iter()
+                    .map(|column| Column {
+                        id: new_id("c"),
+                        width: column.width,
+                        modules: column
+                            .modules
+                            .iter()
+                            .map(|module| Module {
+                                id: new_id("m"),
+                                ..module.clone()
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+        block_id: None,
+    }
+}
+
+/// A section showing the global block `block_id`
+pub fn block_section(id: String, name: &str, block_id: i32) -> Section {
+    Section {
+        id,
+        name: name.to_string(),
+        rows: Vec::new(),
+        block_id: Some(block_id),
+    }
+}
+
+/// Position of a module: (section, row, column, module) indices
+pub fn locate_module(document: &PageDocument, module_id: &str) -> Option<(usize, usize, usize, usize)> {
+    for (s, section) in document.sections.iter().enumerate() {
+        for (r, row) in section.rows.iter().enumerate() {
+            for (c, column) in row.columns.iter().enumerate() {
+                if let Some(m) = column.modules.iter().position(|m| m.id == module_id) {
+                    return Some((s, r, c, m));
+                }
+            }
+        }
+    }
+    None
+}
+
+pub fn find_module<'a>(document: &'a PageDocument, module_id: &str) -> Option<&'a Module> {
+    let (s, r, c, m) = locate_module(document, module_id)?;
+    Some(&document.sections[s].rows[r].columns[c].modules[m])
+}
+
+fn find_column_mut<'a>(document: &'a mut PageDocument, column_id: &str) -> Option<&'a mut Column> {
+    document
+        .sections
+        .iter_mut()
+        .flat_map(|s| &mut s.rows)
+        .flat_map(|r| &mut r.columns)
+        .find(|c| c.id == column_id)
+}
+
+/// Position of a row: (section, row) indices
+fn locate_row(document: &PageDocument, row_id: &str) -> Option<(usize, usize)> {
+    document.sections.iter().enumerate().find_map(|(s, section)| {
+        section.rows.iter().position(|r| r.id == row_id).map(|r| (s, r))
+    })
+}
+
+/// Final index for a module dropped in front of the module shown at
+/// `displayed_index` in `column_id`. Moving down within the same column
+/// shifts everything after the module up by one.
+pub fn drop_index(document: &PageDocument, module_id: &str, column_id: &str, displayed_index: usize) -> usize {
+    match locate_module(document, module_id) {
+        Some((s, r, c, m))
+            if document.sections[s].rows[r].columns[c].id == column_id && m < displayed_index =>
+        {
+            displayed_index - 1
+        }
+        _ => displayed_index,
+    }
+}
+
+/// Apply an edit to the document, returning the edit that reverses it.
+/// Edits naming nodes that don't exist fail without changing anything.
+pub fn apply(document: &mut PageDocument, action: BuilderAction) -> Result<BuilderAction, String> {
+    let inverse = match action {
+        BuilderAction::InsertModule { column_id, index, module } => {
+            let column = find_column_mut(document, &column_id).ok_or("Column not found")?;
+            let index = index.min(column.modules.len());
+            let module_id = module.id.clone();
+            column.modules.insert(index, module);
+            BuilderAction::RemoveModule { module_id }
+        }
+        BuilderAction::MoveModule { module_id, column_id, index } => {
+            find_column_mut(document, &column_id).ok_or("Column not found")?;
+            let (s, r, c, m) = locate_module(document, &module_id).ok_or("Module not found")?;
+            let from_column = document.sections[s].rows[r].columns[c].id.clone();
+            let module = document.sections[s].rows[r].columns[c].modules.remove(m);
+            let column = find_column_mut(document, &column_id).ok_or("Column not found")?;
+            let index = index.min(column.modules.len());
+            column.modules.insert(index, module);
+            BuilderAction::MoveModule { module_id, column_id: from_column, index: m }
+        }
+        BuilderAction::RemoveModule { module_id } => {
+            let (s, r, c, m) = locate_module(document, &module_id).ok_or("Module not found")?;
+            let column = &mut document.sections[s].rows[r].columns[c];
+            let module = column.modules.remove(m);
+            BuilderAction::InsertModule { column_id: column.id.clone(), index: m, module }
+        }
+        BuilderAction::UpdateModule { module } => {
+            let (s, r, c, m) = locate_module(document, &module.id).ok_or("Module not found")?;
+            let previous = std::mem::replace(&mut document.sections[s].rows[r].columns[c].modules[m], module);
+            BuilderAction::UpdateModule { module: previous }
+        }
+        BuilderAction::InsertSection { index, section } => {
+            let index = index.min(document.sections.len());
+            let section_id = section.id.clone();
+            document.sections.insert(index, section);
+            BuilderAction::RemoveSection { section_id }
+        }
+        BuilderAction::MoveSection { section_id, index } => {
+            let from = document
+                .sections
+                .iter()
+                .position(|s| s.id == section_id)
+                .ok_or("Section not found")?;
+            let section = document.sections.remove(from);
+            let index = index.min(document.sections.len());
+            document.sections.insert(index, section);
+            BuilderAction::MoveSection { section_id, index: from }
+        }
+        BuilderAction::RemoveSection { section_id } => {
+            let index = document
+                .sections
+                .iter()
+                .position(|s| s.id == section_id)
+                .ok_or("Section not found")?;
+            let section = document.sections.remove(index);
+            BuilderAction::InsertSection { index, section }
+        }
+        BuilderAction::UpdateSection { section } => {
+            let current = document
+                .sections
+                .iter_mut()
+                .find(|s| s.id == section.id)
+                .ok_or("Section not found")?;
+            let previous = std::mem::replace(current, section);
+            BuilderAction::UpdateSection { section: previous }
+        }
+        BuilderAction::InsertRow { section_id, index, row } => {
+            let section = document
+                .sections
+                .iter_mut()
+                .find(|s| s.id == section_id)
+                .ok_or("Section not found")?;
+            let index = index.min(section.rows.len());
+            let row_id = row.id.clone();
+            section.rows.insert(index, row);
+            BuilderAction::RemoveRow { row_id }
+        }
+        BuilderAction::MoveRow { row_id, index } => {
+            let (s, r) = locate_row(document, &row_id).ok_or("Row not found")?;
+            let rows = &mut document.sections[s].rows;
+            let row = rows.remove(r);
+            let index = index.min(rows.len());
+            rows.insert(index, row);
+            BuilderAction::MoveRow { row_id, index: r }
+        }
+        BuilderAction::RemoveRow { row_id } => {
+            let (s, r) = locate_row(document, &row_id).ok_or("Row not found")?;
+            let section = &mut document.sections[s];
+            let row = section.rows.remove(r);
+            BuilderAction::InsertRow { section_id: section.id.clone(), index: r, row }
+        }
+    };
+    Ok(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document() -> PageDocument {
+        let mut ids = ["s1", "r1", "c1", "r2", "c2", "c3"].iter();
+        let mut next = |_: &str| ids.next().unwrap().to_string();
+        let mut section = new_section(&mut next);
+        section.rows.push(new_row(&[6, 6], &mut next));
+        let mut doc = PageDocument::default();
+        doc.sections.push(section);
+        for (column, id) in [("c1", "m1"), ("c1", "m2"), ("c1", "m3")] {
+            let module = new_module("text", id.to_string()).unwrap();
+            apply(&mut doc, BuilderAction::InsertModule { column_id: column.to_string(), index: 99, module }).unwrap();
+        }
+        doc
+    }
+
+    fn ids(doc: &PageDocument, column: usize) -> Vec<String> {
+        let columns: Vec<&Column> = doc.sections[0].rows.iter().flat_map(|r| &r.columns).collect();
+        columns[column].modules.iter().map(|m| m.id.clone()).collect()
+    }
+
+    #[test]
+    fn moving_down_within_a_column_lands_in_front_of_the_drop_target() {
+        let mut doc = document();
+        // Drop m1 in front of m3, shown at index 2
+        let index = drop_index(&doc, "m1", "c1", 2);
+        apply(&mut doc, BuilderAction::MoveModule { module_id: "m1".into(), column_id: "c1".into(), index }).unwrap();
+        assert_eq!(ids(&doc, 0), vec!["m2", "m1", "m3"]);
+    }
+
+    #[test]
+    fn modules_move_between_columns() {
+        let mut doc = document();
+        apply(&mut doc, BuilderAction::MoveModule { module_id: "m2".into(), column_id: "c3".into(), index: 0 }).unwrap();
+        assert_eq!(ids(&doc, 0), vec!["m1", "m3"]);
+        assert_eq!(ids(&doc, 2), vec!["m2"]);
+    }
+
+    #[test]
+    fn edits_to_missing_nodes_leave_the_document_alone() {
+        let mut doc = document();
+        let before = doc.clone();
+        assert!(apply(&mut doc, BuilderAction::MoveModule { module_id: "m1".into(), column_id: "nope".into(), index: 0 }).is_err());
+        assert!(apply(&mut doc, BuilderAction::RemoveRow { row_id: "nope".into() }).is_err());
+        assert_eq!(doc, before);
+    }
+
+    #[test]
+    fn every_edit_is_undone_by_its_inverse() {
+        let mut ids = ["s2", "r3", "c4", "s3", "r4", "c5"].iter();
+        let mut next = |_: &str| ids.next().unwrap().to_string();
+        let mut base = document();
+        base.sections.push(new_section(&mut next));
+        let inserted = new_section(&mut next);
+        let mut restyled = new_module("text", "m2".into()).unwrap();
+        restyled.style.insert("color".into(), "red".into());
+
+        let actions = vec![
+            BuilderAction::InsertModule { column_id: "c2".into(), index: 0, module: new_module("button", "m9".into()).unwrap() },
+            BuilderAction::MoveModule { module_id: "m1".into(), column_id: "c3".into(), index: 0 },
+            BuilderAction::MoveModule { module_id: "m3".into(), column_id: "c1".into(), index: 0 },
+            BuilderAction::RemoveModule { module_id: "m2".into() },
+            BuilderAction::UpdateModule { module: restyled },
+            BuilderAction::InsertSection { index: 0, section: inserted },
+            BuilderAction::MoveSection { section_id: "s1".into(), index: 1 },
+            BuilderAction::RemoveSection { section_id: "s1".into() },
+            BuilderAction::UpdateSection { section: block_section("s2".into(), "Header", 4) },
+            BuilderAction::InsertRow { section_id: "s1".into(), index: 1, row: new_row(&[12], |p: &str| format!("{}-new", p)) },
+            BuilderAction::MoveRow { row_id: "r2".into(), index: 0 },
+            BuilderAction::RemoveRow { row_id: "r1".into() },
+        ];
+        for action in actions {
+            let mut doc = base.clone();
+            let inverse = apply(&mut doc, action.clone()).unwrap();
+            assert_ne!(doc, base, "{:?} changed nothing", action);
+            apply(&mut doc, inverse).unwrap();
+            assert_eq!(doc, base, "{:?} was not undone", action);
+        }
+    }
+
+    #[test]
+    fn copied_sections_get_fresh_ids() {
+        let doc = document();
+        let mut n = 0;
+        let copy = copy_section(&doc.sections[0], |prefix: &str| {
+            n += 1;
+            format!("{}-copy{}", prefix, n)
+        });
+
+        let mut both = doc.clone();
+        both.sections.push(copy.clone());
+        assert_eq!(both.validate(), Ok(()));
+        assert_eq!(copy.rows[0].columns[0].modules[0].kind, doc.sections[0].rows[0].columns[0].modules[0].kind);
+    }
+
+    #[test]
+    fn drag_payloads_round_trip() {
+        for payload in [DragPayload::New("image".into()), DragPayload::Existing("m-1:x".into())] {
+            assert_eq!(DragPayload::decode(&payload.encode()), Some(payload));
+        }
+        assert_eq!(DragPayload::decode("https://example.com"), None);
+    }
+}
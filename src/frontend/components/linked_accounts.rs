@@ -0,0 +1,84 @@
+use yew::prelude::*;
+use crate::frontend::services::api_service::{login_options, sso_link_url, SsoProvider};
+
+fn query_param(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search).ok()?.get(name)
+}
+
+fn provider_name<'a>(providers: &'a [SsoProvider], provider_id: &'a str) -> &'a str {
+    providers
+        .iter()
+        .find(|provider| provider.id == provider_id)
+        .map_or(provider_id, |provider| provider.name.as_str())
+}
+
+/// Links the signed-in user to accounts at the single sign-on providers.
+/// Hidden when no provider is configured.
+#[function_component(LinkedAccounts)]
+pub fn linked_accounts() -> Html {
+    let providers = use_state(Vec::<SsoProvider>::new);
+    let linked = use_state(|| query_param("sso_linked"));
+    let error = use_state(|| None::<String>);
+
+    {
+        let providers = providers.clone();
+        use_effect_with_deps(
+            move |_| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match login_options().await {
+                        Ok(options) => providers.set(options.providers),
+                        Err(err) => log::error!("Error getting sign-in providers: {:?}", err),
+                    }
+                });
+                || ()
+            },
+            (),
+        );
+    }
+
+    if providers.is_empty() {
+        return html! {};
+    }
+
+    let on_link = |provider_id: String| {
+        let linked = linked.clone();
+        let error = error.clone();
+        Callback::from(move |_| {
+            let provider_id = provider_id.clone();
+            let linked = linked.clone();
+            let error = error.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match sso_link_url(&provider_id).await {
+                    Ok(url) => {
+                        if let Some(window) = web_sys::window() {
+                            let _ = window.location().set_href(&url);
+                        }
+                    }
+                    Err(err) => {
+                        linked.set(None);
+                        error.set(Some(format!("Couldn't start linking: {:?}", err)));
+                    }
+                }
+            });
+        })
+    };
+
+    html! {
+        <section class="linked-accounts">
+            <h3>{ "Single sign-on" }</h3>
+            <p>{ "Link an account at your identity provider to sign in with it." }</p>
+            { for providers.iter().map(|provider| html! {
+                <button onclick={on_link(provider.id.clone())}>
+                    { format!("Link {}", provider.name) }
+                </button>
+            }) }
+            if let Some(provider_id) = linked.as_ref() {
+                <p>{ format!("Your {} account is linked.", provider_name(&providers, provider_id)) }</p>
+            }
+            if let Some(message) = error.as_ref() {
+                <p class="error">{ message }</p>
+            }
+        </section>
+    }
+}
@@ -1,19 +1,102 @@
 use yew::prelude::*;
-use crate::frontend::services::api_service::{login, AuthData};
+use crate::frontend::components::two_factor::TwoFactorSetup;
+use crate::frontend::services::api_service::{
+    get_auth_token, login, login_options, redeem_sso_code, request_password_reset, resend_verification,
+    reset_password, sso_login_url, verify_email, verify_login, ApiServiceError, AuthData, LoginOptions, LoginStep,
+};
 use yew::functional::use_state;
 use yew::events::InputEvent;
 use web_sys::HtmlInputElement;
 use log::info;
 
+/// What the page shows besides the login form, picked from the link that
+/// opened it
+#[derive(Clone, PartialEq)]
+enum Screen {
+    SignIn,
+    Forgot,
+    ForgotSent,
+    Reset(String),
+    ResetDone,
+    Verify(String),
+}
+
+impl Screen {
+    fn from_url() -> Self {
+        if let Some(token) = query_param("reset_token") {
+            Screen::Reset(token)
+        } else if let Some(token) = query_param("verify_token") {
+            Screen::Verify(token)
+        } else {
+            Screen::SignIn
+        }
+    }
+}
+
+fn query_param(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search).ok()?.get(name)
+}
+
+/// The code a single sign-on login sent the browser back with. It is
+/// removed from the address bar, since it can only be redeemed once.
+fn take_sso_code() -> Option<String> {
+    let window = web_sys::window()?;
+    let code = query_param("sso_code")?;
+    let path = window.location().pathname().ok()?;
+    if let Ok(history) = window.history() {
+        let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&path));
+    }
+    Some(code)
+}
+
 #[function_component(LoginPage)]
 pub fn login_page() -> Html {
+    let screen = use_state(Screen::from_url);
+    let email = use_state(String::new);
+    let new_password = use_state(String::new);
+    let verified = use_state(|| None::<bool>);
+    let options = use_state(|| None::<LoginOptions>);
     let username = use_state(|| String::new());
     let password = use_state(|| String::new());
+    let code = use_state(String::new);
+    let step = use_state(|| None::<LoginStep>);
     let login_error = use_state(|| None::<String>);
 
+    {
+        let step = step.clone();
+        let login_error = login_error.clone();
+        let options = options.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(code) = take_sso_code() {
+                    let step = step.clone();
+                    let login_error = login_error.clone();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        match redeem_sso_code(&code).await {
+                            Ok(next) => step.set(Some(next)),
+                            Err(err) => login_error.set(Some(format!("Login failed: {:?}", err))),
+                        }
+                    });
+                } else if let Some(message) = query_param("sso_error") {
+                    login_error.set(Some(message));
+                }
+                wasm_bindgen_futures::spawn_local(async move {
+                    match login_options().await {
+                        Ok(fetched) => options.set(Some(fetched)),
+                        Err(err) => log::error!("Error getting login options: {:?}", err),
+                    }
+                });
+                || ()
+            },
+            (),
+        );
+    }
+
     let on_login_click = {
         let username = username.clone();
         let password = password.clone();
+        let step = step.clone();
         let login_error = login_error.clone();
 
         Callback::from(move |_| {
@@ -22,12 +105,20 @@ pub fn login_page() -> Html {
                 password: (*password).clone(),
             };
 
+            let step = step.clone();
             let login_error = login_error.clone();
             wasm_bindgen_futures::spawn_local(async move {
                 match login(auth_data).await {
-                    Ok(_) => {
-                        info!("Login successful!");
+                    Ok(next) => {
+                        if next == LoginStep::Done {
+                            info!("Login successful!");
+                        }
+                        login_error.set(None);
+                        step.set(Some(next));
                     },
+                    Err(ApiServiceError::ServerError(429)) => login_error.set(Some(
+                        "Too many failed attempts. Wait a few minutes and try again.".to_string(),
+                    )),
                     Err(err) => {
                         login_error.set(Some(format!("Login failed: {:?}", err)));
                     }
@@ -36,22 +127,253 @@ pub fn login_page() -> Html {
         })
     };
 
+    let on_verify_click = {
+        let code = code.clone();
+        let step = step.clone();
+        let login_error = login_error.clone();
+
+        Callback::from(move |_| {
+            let mfa_token = match step.as_ref() {
+                Some(LoginStep::VerifyCode { mfa_token }) => mfa_token.clone(),
+                _ => return,
+            };
+            let submitted = (*code).clone();
+            let step = step.clone();
+            let login_error = login_error.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match verify_login(&mfa_token, &submitted).await {
+                    Ok(()) => {
+                        info!("Login successful!");
+                        login_error.set(None);
+                        step.set(Some(LoginStep::Done));
+                    }
+                    Err(ApiServiceError::ServerError(429)) => login_error.set(Some(
+                        "Too many failed attempts. Wait a few minutes and try again.".to_string(),
+                    )),
+                    Err(_) => login_error.set(Some("That code didn't match.".to_string())),
+                }
+            });
+        })
+    };
+
+    let on_enrolled = {
+        let step = step.clone();
+        Callback::from(move |_| {
+            info!("Login successful!");
+            step.set(Some(LoginStep::Done));
+        })
+    };
+
+    {
+        let verified = verified.clone();
+        use_effect_with_deps(
+            move |screen: &Screen| {
+                if let Screen::Verify(token) = screen.clone() {
+                    wasm_bindgen_futures::spawn_local(async move {
+                        verified.set(Some(verify_email(&token).await.is_ok()));
+                    });
+                }
+                || ()
+            },
+            (*screen).clone(),
+        );
+    }
+
+    let on_forgot_submit = {
+        let email = email.clone();
+        let screen = screen.clone();
+        let login_error = login_error.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let submitted = (*email).clone();
+            let screen = screen.clone();
+            let login_error = login_error.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match request_password_reset(&submitted).await {
+                    Ok(()) => {
+                        login_error.set(None);
+                        screen.set(Screen::ForgotSent);
+                    }
+                    Err(err) => login_error.set(Some(format!("Could not send the link: {}", err))),
+                }
+            });
+        })
+    };
+
+    let on_reset_submit = {
+        let new_password = new_password.clone();
+        let screen = screen.clone();
+        let login_error = login_error.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let token = match &*screen {
+                Screen::Reset(token) => token.clone(),
+                _ => return,
+            };
+            let submitted = (*new_password).clone();
+            let screen = screen.clone();
+            let login_error = login_error.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match reset_password(&token, &submitted).await {
+                    Ok(()) => {
+                        login_error.set(None);
+                        screen.set(Screen::ResetDone);
+                    }
+                    Err(ApiServiceError::ServerError(422)) => {
+                        login_error.set(Some("Passwords must be at least 8 characters.".to_string()))
+                    }
+                    Err(_) => login_error.set(Some(
+                        "This link is invalid or has expired. Ask for a new one.".to_string(),
+                    )),
+                }
+            });
+        })
+    };
+
+    let on_resend_click = {
+        let login_error = login_error.clone();
+        Callback::from(move |_| {
+            let login_error = login_error.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match resend_verification().await {
+                    Ok(()) => login_error.set(Some("We sent you a new link.".to_string())),
+                    Err(err) => login_error.set(Some(format!("Could not send a new link: {}", err))),
+                }
+            });
+        })
+    };
+
+    let show = |next: Screen| {
+        let screen = screen.clone();
+        let login_error = login_error.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            login_error.set(None);
+            screen.set(next.clone());
+        })
+    };
+
+    let login_form = match (*step).clone() {
+        Some(LoginStep::VerifyCode { .. }) => html! {
+            <>
+                <p>{ "Enter the code from your authenticator app, or a recovery code." }</p>
+                <input
+                    type="text"
+                    autocomplete="one-time-code"
+                    placeholder="Code"
+                    value={(*code).clone()}
+                    oninput={Callback::from(move |e: InputEvent| code.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                />
+                <button onclick={on_verify_click}>{ "Verify" }</button>
+            </>
+        },
+        Some(LoginStep::Enrol { mfa_token }) => html! {
+            <>
+                <p>{ "Your role requires two-factor authentication. Set it up to finish signing in." }</p>
+                <TwoFactorSetup mfa_token={Some(mfa_token)} on_done={on_enrolled} />
+            </>
+        },
+        Some(LoginStep::Done) => html! { <p>{ "You're signed in." }</p> },
+        None => {
+            let providers = options.as_ref().map(|options| options.providers.clone()).unwrap_or_default();
+            let local_login = options.as_ref().map_or(true, |options| options.local_login);
+            html! {
+                <>
+                    if !providers.is_empty() {
+                        <div class="sso-providers">
+                            { for providers.iter().map(|provider| html! {
+                                <a class="button" href={sso_login_url(&provider.id)}>
+                                    { format!("Sign in with {}", provider.name) }
+                                </a>
+                            }) }
+                        </div>
+                    }
+                    if local_login {
+                        <input
+                            type="text"
+                            placeholder="Username"
+                            value={(*username).clone()}
+                            oninput={Callback::from(move |e: InputEvent| username.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                        />
+                        <input
+                            type="password"
+                            placeholder="Password"
+                            value={(*password).clone()}
+                            oninput={Callback::from(move |e: InputEvent| password.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                        />
+                        <button onclick={on_login_click}>{ "Login" }</button>
+                        <p><a href="#" onclick={show(Screen::Forgot)}>{ "Forgot password?" }</a></p>
+                    }
+                </>
+            }
+        }
+    };
+
+    let (title, form) = match (*screen).clone() {
+        Screen::SignIn => ("Login", login_form),
+        Screen::Forgot => ("Reset your password", html! {
+            <form onsubmit={on_forgot_submit}>
+                <p>{ "Enter your account's email and we'll send you a link to choose a new password." }</p>
+                <input
+                    type="email"
+                    placeholder="Email"
+                    value={(*email).clone()}
+                    oninput={Callback::from(move |e: InputEvent| email.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                />
+                <button type="submit">{ "Send link" }</button>
+                <p><a href="#" onclick={show(Screen::SignIn)}>{ "Back to login" }</a></p>
+            </form>
+        }),
+        Screen::ForgotSent => ("Check your email", html! {
+            <>
+                <p>{ "If an account uses that address, a link to reset its password is on its way. It works for an hour." }</p>
+                <p><a href="#" onclick={show(Screen::SignIn)}>{ "Back to login" }</a></p>
+            </>
+        }),
+        Screen::Reset(_) => ("Choose a new password", html! {
+            <form onsubmit={on_reset_submit}>
+                <input
+                    type="password"
+                    autocomplete="new-password"
+                    placeholder="New password"
+                    value={(*new_password).clone()}
+                    oninput={Callback::from(move |e: InputEvent| new_password.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                />
+                <button type="submit">{ "Save password" }</button>
+            </form>
+        }),
+        Screen::ResetDone => ("Password changed", html! {
+            <>
+                <p>{ "Your password has been changed and you've been signed out everywhere." }</p>
+                <p><a href="#" onclick={show(Screen::SignIn)}>{ "Log in" }</a></p>
+            </>
+        }),
+        Screen::Verify(_) => ("Verify your email", match *verified {
+            None => html! { <p>{ "Checking your link…" }</p> },
+            Some(true) => html! {
+                <>
+                    <p>{ "Thanks, your email address is verified." }</p>
+                    <p><a href="#" onclick={show(Screen::SignIn)}>{ "Continue" }</a></p>
+                </>
+            },
+            Some(false) => html! {
+                <>
+                    <p>{ "This link is invalid or has expired." }</p>
+                    if get_auth_token().is_some() {
+                        <button onclick={on_resend_click}>{ "Send a new link" }</button>
+                    } else {
+                        <p>{ "Log in to send yourself a new one." }</p>
+                    }
+                    <p><a href="#" onclick={show(Screen::SignIn)}>{ "Back to login" }</a></p>
+                </>
+            },
+        }),
+    };
+
     html! {
         <div>
-            <h1>{ "Login" }</h1>
-            <input 
-                type="text" 
-                placeholder="Username" 
-                value={(*username).clone()} 
-                oninput={Callback::from(move |e: InputEvent| username.set(e.target_unchecked_into::<HtmlInputElement>().value()))} 
-            />
-            <input 
-                type="password" 
-                placeholder="Password" 
-                value={(*password).clone()} 
-                oninput={Callback::from(move |e: InputEvent| password.set(e.target_unchecked_into::<HtmlInputElement>().value()))} 
-            />
-            <button onclick={on_login_click}>{ "Login" }</button>
+            <h1>{ title }</h1>
+            { form }
             if let Some(error) = (*login_error).clone() {
                 <p style="color: red;">{ error }</p>
             }
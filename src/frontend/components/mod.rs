@@ -1,3 +1,10 @@
+pub mod audit_log;  // Filterable audit log for the dashboard
+pub mod builder;  // Page builder editor, toolbox and preview
+pub mod linked_accounts;  // Linking single sign-on accounts, on the dashboard
 pub mod login_page;  // This file should handle the login functionality
 pub mod post_explorer;  // This module manages the post explorer view
+pub mod post_item;  // Summary card used by archive listings
+pub mod raw_html;  // Injects sanitized HTML content, filled in by the server renderer
 pub mod tabbed_view;  // This module handles the tabbed interface for posts
+pub mod tag_input;  // Tag chips with autocompletion, used by the post editor
+pub mod two_factor;  // Two-factor setup, at login and on the dashboard
@@ -1,11 +1,28 @@
 use yew::prelude::*;
-use crate::frontend::services::api_service::{get_posts, Post};
+use web_sys::HtmlInputElement;
+use crate::frontend::services::api_service::{get_posts, search, Post};
+use crate::frontend::components::raw_html::RawHtml;
 use crate::frontend::components::tabbed_view::TabbedView;
+use crate::frontend::pages::post_editor::PostEditor;
+use crate::shared::types::{SearchKind, SearchResults};
+
+/// Run a search and store the results, logging failures
+fn run_search(query: String, page: i64, results: UseStateHandle<Option<SearchResults>>) {
+    wasm_bindgen_futures::spawn_local(async move {
+        match search(&query, page).await {
+            Ok(found) => results.set(Some(found)),
+            Err(err) => log::error!("Error searching for {:?}: {:?}", query, err),
+        }
+    });
+}
 
 #[function_component(PostExplorer)]
 pub fn post_explorer() -> Html {
     let posts = use_state(|| Vec::new());
     let selected_post = use_state(|| None::<Post>);
+    let creating = use_state(|| false);
+    let search_input = use_node_ref();
+    let search_results = use_state(|| None::<SearchResults>);
 
     {
         let posts = posts.clone();
@@ -22,30 +39,154 @@ pub fn post_explorer() -> Html {
 
     let on_post_click = {
         let selected_post = selected_post.clone();
+        let creating = creating.clone();
+        Callback::from(move |post: Post| {
+            creating.set(false);
+            selected_post.set(Some(post));
+        })
+    };
+
+    let on_new_post = {
+        let selected_post = selected_post.clone();
+        let creating = creating.clone();
+        Callback::from(move |_| {
+            selected_post.set(None);
+            creating.set(true);
+        })
+    };
+
+    let on_created = {
+        let posts = posts.clone();
+        let selected_post = selected_post.clone();
+        let creating = creating.clone();
         Callback::from(move |post: Post| {
+            let mut updated = (*posts).clone();
+            updated.insert(0, post.clone());
+            posts.set(updated);
+            creating.set(false);
             selected_post.set(Some(post));
         })
     };
 
+    let on_search = {
+        let search_input = search_input.clone();
+        let search_results = search_results.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let query = search_input
+                .cast::<HtmlInputElement>()
+                .map(|input| input.value())
+                .unwrap_or_default();
+            if query.trim().is_empty() {
+                search_results.set(None);
+            } else {
+                run_search(query, 1, search_results.clone());
+            }
+        })
+    };
+
+    let on_clear = {
+        let search_input = search_input.clone();
+        let search_results = search_results.clone();
+        Callback::from(move |_| {
+            if let Some(input) = search_input.cast::<HtmlInputElement>() {
+                input.set_value("");
+            }
+            search_results.set(None);
+        })
+    };
+
+    let on_page = {
+        let search_results = search_results.clone();
+        Callback::from(move |page: i64| {
+            if let Some(current) = search_results.as_ref() {
+                run_search(current.query.clone(), page, search_results.clone());
+            }
+        })
+    };
+
+    let results_view = search_results.as_ref().map(|results| {
+        let last_page = (results.total + results.per_page - 1) / results.per_page;
+        let page = results.page;
+        html! {
+            <div class="search-results">
+                <p>{ format!("{} results for \u{201c}{}\u{201d}", results.total, results.query) }</p>
+                <ul>
+                    {
+                        for results.hits.iter().map(|hit| {
+                            // Post hits open the post; comment hits open the post they belong to
+                            let post_id = match hit.kind {
+                                SearchKind::Post => Some(hit.id),
+                                SearchKind::Comment => hit.post_id,
+                                SearchKind::Page => None,
+                            };
+                            let post = post_id.and_then(|id| posts.iter().find(|p| p.id == Some(id)).cloned());
+                            let onclick = {
+                                let on_post_click = on_post_click.clone();
+                                move |_| {
+                                    if let Some(post) = post.clone() {
+                                        on_post_click.emit(post);
+                                    }
+                                }
+                            };
+                            html! {
+                                <li class="search-hit" {onclick}>
+                                    <span class="search-kind">{ hit.kind.as_str() }</span>
+                                    <strong>{ &hit.title }</strong>
+                                    <RawHtml slot="search-snippet" html={hit.snippet.clone()} class="search-snippet" />
+                                </li>
+                            }
+                        })
+                    }
+                </ul>
+                if last_page > 1 {
+                    <div class="search-pagination">
+                        <button disabled={page <= 1} onclick={on_page.reform(move |_| page - 1)}>{ "Previous" }</button>
+                        <span>{ format!("Page {} of {}", page, last_page) }</span>
+                        <button disabled={page >= last_page} onclick={on_page.reform(move |_| page + 1)}>{ "Next" }</button>
+                    </div>
+                }
+            </div>
+        }
+    });
+
     html! {
         <div class="post-explorer">
             <h3>{ "Posts" }</h3>
-            <ul>
-                {
-                    for posts.iter().cloned().map(|post| {
-                        let post_display = post.clone(); // Clone for display
-                        let post_clone = post.clone(); // Clone for closure
-                        let on_post_click = on_post_click.clone();
-                        html! {
-                            <li onclick={move |_| on_post_click.emit(post_clone.clone())}>
-                                { format!("{} ({})", post_display.title, post_display.category) }
-                            </li>
-                        }
-                    })
+            <button onclick={on_new_post}>{ "New post" }</button>
+            <form class="search-box" onsubmit={on_search}>
+                <input ref={search_input} type="search" placeholder="Search posts, pages and comments" />
+                <button type="submit">{ "Search" }</button>
+                if search_results.is_some() {
+                    <button type="button" onclick={on_clear}>{ "Clear" }</button>
                 }
-            </ul>
+            </form>
+            if let Some(results_view) = results_view {
+                { results_view }
+            } else {
+                <ul>
+                    {
+                        for posts.iter().cloned().map(|post| {
+                            let post_display = post.clone(); // Clone for display
+                            let post_clone = post.clone(); // Clone for closure
+                            let on_post_click = on_post_click.clone();
+                            html! {
+                                <li onclick={move |_| on_post_click.emit(post_clone.clone())}>
+                                    { format!("{} ({})", post_display.title, post_display.category) }
+                                </li>
+                            }
+                        })
+                    }
+                </ul>
+            }
+            if *creating {
+                <PostEditor {on_created} />
+            }
             { selected_post.as_ref().map(|post| html! {
-                <TabbedView post={post.clone()} />
+                <>
+                    <TabbedView post={post.clone()} />
+                    <PostEditor key={post.id.unwrap_or_default()} post={Some(post.clone())} />
+                </>
             }) }
         </div>
     }
@@ -4,13 +4,21 @@ use yew::prelude::*;
 pub struct PostItemProps {
     pub title: String,
     pub summary: String,
+    #[prop_or_default]
+    pub href: Option<String>,
 }
 
 #[function_component(PostItem)]
 pub fn post_item(props: &PostItemProps) -> Html {
     html! {
         <div class="post-item">
-            <h2>{ &props.title }</h2>
+            <h2>
+                if let Some(href) = &props.href {
+                    <a href={href.clone()}>{ &props.title }</a>
+                } else {
+                    { &props.title }
+                }
+            </h2>
             <p>{ &props.summary }</p>
         </div>
     }
@@ -0,0 +1,38 @@
+use yew::prelude::*;
+use web_sys::Element;
+
+#[derive(Properties, PartialEq)]
+pub struct RawHtmlProps {
+    /// Name the server renderer uses to find this element and fill it in
+    pub slot: AttrValue,
+    pub html: AttrValue,
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+/// Renders trusted, already-sanitized HTML.
+///
+/// In the browser the markup is set through `inner_html`. Effects don't run
+/// during server-side rendering, so there the element is left empty and the
+/// backend renderer fills it by its `data-raw-html` slot name.
+#[function_component(RawHtml)]
+pub fn raw_html(props: &RawHtmlProps) -> Html {
+    let node_ref = use_node_ref();
+
+    {
+        let node_ref = node_ref.clone();
+        use_effect_with_deps(
+            move |html| {
+                if let Some(element) = node_ref.cast::<Element>() {
+                    element.set_inner_html(html);
+                }
+                || ()
+            },
+            props.html.clone(),
+        );
+    }
+
+    html! {
+        <div ref={node_ref} class={props.class.clone()} data-raw-html={props.slot.clone()}></div>
+    }
+}
@@ -0,0 +1,144 @@
+use yew::prelude::*;
+use web_sys::HtmlInputElement;
+use crate::frontend::services::api_service::autocomplete_tags;
+use crate::shared::tags::{slugify, TagSummary};
+
+#[derive(Properties, PartialEq)]
+pub struct TagInputProps {
+    /// Tag names, in the order they were added
+    pub tags: Vec<String>,
+    pub on_change: Callback<Vec<String>>,
+}
+
+/// Tags as removable chips, with a text box that suggests existing tags.
+/// Enter or a comma adds what has been typed; names that only differ in
+/// case or punctuation count as the same tag.
+#[function_component(TagInput)]
+pub fn tag_input(props: &TagInputProps) -> Html {
+    let text = use_state(String::new);
+    let suggestions = use_state(Vec::<TagSummary>::new);
+    let input_ref = use_node_ref();
+
+    let add = {
+        let tags = props.tags.clone();
+        let on_change = props.on_change.clone();
+        let text = text.clone();
+        let suggestions = suggestions.clone();
+        Callback::from(move |name: String| {
+            let name = name.trim().to_string();
+            let slug = slugify(&name);
+            if !slug.is_empty() && !tags.iter().any(|tag| slugify(tag) == slug) {
+                let mut next = tags.clone();
+                next.push(name);
+                on_change.emit(next);
+            }
+            text.set(String::new());
+            suggestions.set(Vec::new());
+        })
+    };
+
+    let remove = {
+        let tags = props.tags.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |index: usize| {
+            let mut next = tags.clone();
+            if index < next.len() {
+                next.remove(index);
+                on_change.emit(next);
+            }
+        })
+    };
+
+    let on_input = {
+        let text = text.clone();
+        let suggestions = suggestions.clone();
+        let input_ref = input_ref.clone();
+        let tags = props.tags.clone();
+        Callback::from(move |e: InputEvent| {
+            let value = e.target_unchecked_into::<HtmlInputElement>().value();
+            text.set(value.clone());
+            if value.trim().is_empty() {
+                suggestions.set(Vec::new());
+                return;
+            }
+            let suggestions = suggestions.clone();
+            let input_ref = input_ref.clone();
+            let taken: Vec<String> = tags.iter().map(|tag| slugify(tag)).collect();
+            wasm_bindgen_futures::spawn_local(async move {
+                match autocomplete_tags(&value).await {
+                    Ok(found) => {
+                        // Drop answers to anything but what's typed now
+                        let current = input_ref.cast::<HtmlInputElement>().map(|input| input.value());
+                        if current.as_deref() == Some(value.as_str()) {
+                            suggestions.set(
+                                found.into_iter().filter(|tag| !taken.contains(&tag.slug)).collect(),
+                            );
+                        }
+                    }
+                    Err(err) => log::error!("Error getting tag suggestions: {:?}", err),
+                }
+            });
+        })
+    };
+
+    let on_keydown = {
+        let text = text.clone();
+        let add = add.clone();
+        let remove = remove.clone();
+        let count = props.tags.len();
+        Callback::from(move |e: KeyboardEvent| match e.key().as_str() {
+            "Enter" | "," => {
+                e.prevent_default();
+                add.emit((*text).clone());
+            }
+            "Backspace" if text.is_empty() && count > 0 => remove.emit(count - 1),
+            _ => {}
+        })
+    };
+
+    html! {
+        <div class="tag-input">
+            <ul class="tag-input-chips">
+                { for props.tags.iter().enumerate().map(|(index, tag)| {
+                    let remove = remove.clone();
+                    html! {
+                        <li>
+                            { tag }
+                            <button
+                                type="button"
+                                aria-label={format!("Remove {}", tag)}
+                                onclick={Callback::from(move |_| remove.emit(index))}
+                            >
+                                { "×" }
+                            </button>
+                        </li>
+                    }
+                }) }
+            </ul>
+            <input
+                type="text"
+                placeholder="Add tags"
+                ref={input_ref}
+                value={(*text).clone()}
+                oninput={on_input}
+                onkeydown={on_keydown}
+            />
+            if !suggestions.is_empty() {
+                <ul class="tag-input-suggestions">
+                    { for suggestions.iter().map(|tag| {
+                        let add = add.clone();
+                        let name = tag.name.clone();
+                        html! {
+                            <li>
+                                <button type="button" onclick={Callback::from(move |_| add.emit(name.clone()))}>
+                                    { &tag.name }
+                                    <span class="tag-input-count">{ format!(" ({})", tag.post_count) }</span>
+                                </button>
+                            </li>
+                        }
+                    }) }
+                </ul>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,256 @@
+use yew::prelude::*;
+use web_sys::HtmlInputElement;
+use crate::frontend::services::api_service::{
+    begin_two_factor, disable_two_factor, enable_two_factor, regenerate_recovery_codes, two_factor_status,
+    TwoFactorSetup as Setup, TwoFactorStatus,
+};
+
+#[derive(Properties, PartialEq)]
+pub struct TwoFactorSetupProps {
+    /// Set when setup is the second step of a login that requires it
+    #[prop_or_default]
+    pub mfa_token: Option<String>,
+    /// Called once the user has seen their recovery codes
+    pub on_done: Callback<()>,
+}
+
+/// Adds an authenticator: shows the secret, checks a first code, then shows
+/// the recovery codes once
+#[function_component(TwoFactorSetup)]
+pub fn two_factor_setup(props: &TwoFactorSetupProps) -> Html {
+    let setup = use_state(|| None::<Setup>);
+    let code = use_state(String::new);
+    let recovery_codes = use_state(|| None::<Vec<String>>);
+    let error = use_state(|| None::<String>);
+
+    {
+        let setup = setup.clone();
+        let error = error.clone();
+        use_effect_with_deps(
+            move |mfa_token: &Option<String>| {
+                let mfa_token = mfa_token.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    match begin_two_factor(mfa_token.as_deref()).await {
+                        Ok(started) => setup.set(Some(started)),
+                        Err(err) => error.set(Some(format!("Could not start setup: {}", err))),
+                    }
+                });
+                || ()
+            },
+            props.mfa_token.clone(),
+        );
+    }
+
+    let on_submit = {
+        let code = code.clone();
+        let recovery_codes = recovery_codes.clone();
+        let error = error.clone();
+        let mfa_token = props.mfa_token.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let submitted = (*code).clone();
+            let recovery_codes = recovery_codes.clone();
+            let error = error.clone();
+            let mfa_token = mfa_token.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match enable_two_factor(&submitted, mfa_token.as_deref()).await {
+                    Ok(codes) => {
+                        error.set(None);
+                        recovery_codes.set(Some(codes));
+                    }
+                    Err(_) => error.set(Some("That code didn't match. Check your device's clock and try again.".to_string())),
+                }
+            });
+        })
+    };
+
+    let on_code_input = {
+        let code = code.clone();
+        Callback::from(move |e: InputEvent| code.set(e.target_unchecked_into::<HtmlInputElement>().value()))
+    };
+
+    let body = match (recovery_codes.as_ref(), setup.as_ref()) {
+        (Some(codes), _) => html! {
+            <>
+                <p>{ "Two-factor authentication is on. Keep these recovery codes somewhere safe; each signs you in once if you lose your device. They won't be shown again." }</p>
+                <ul class="recovery-codes">
+                    { for codes.iter().map(|code| html! { <li><code>{ code }</code></li> }) }
+                </ul>
+                <button onclick={props.on_done.reform(|_| ())}>{ "I've saved my recovery codes" }</button>
+            </>
+        },
+        (None, Some(setup)) => html! {
+            <form onsubmit={on_submit}>
+                <p>
+                    { "Add this account to your authenticator app by " }
+                    <a href={setup.provisioning_uri.clone()}>{ "opening this link" }</a>
+                    { " on your phone, or by entering the key:" }
+                </p>
+                <p><code class="two-factor-secret">{ &setup.secret }</code></p>
+                <label>
+                    { "Code from the app " }
+                    <input
+                        type="text"
+                        inputmode="numeric"
+                        autocomplete="one-time-code"
+                        maxlength="6"
+                        value={(*code).clone()}
+                        oninput={on_code_input}
+                    />
+                </label>
+                <button type="submit">{ "Turn on" }</button>
+            </form>
+        },
+        (None, None) => html! { <p>{ "Preparing setup…" }</p> },
+    };
+
+    html! {
+        <div class="two-factor-setup">
+            <h3>{ "Set up two-factor authentication" }</h3>
+            { body }
+            if let Some(message) = error.as_ref() {
+                <p class="error">{ message }</p>
+            }
+        </div>
+    }
+}
+
+/// The signed-in user's two-factor authentication: set it up, replace
+/// recovery codes or turn it off
+#[function_component(TwoFactorSettings)]
+pub fn two_factor_settings() -> Html {
+    let status = use_state(|| None::<TwoFactorStatus>);
+    let reload = use_state(|| 0u32);
+    let setting_up = use_state(|| false);
+    let code = use_state(String::new);
+    let recovery_codes = use_state(|| None::<Vec<String>>);
+    let error = use_state(|| None::<String>);
+
+    {
+        let status = status.clone();
+        use_effect_with_deps(
+            move |_| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match two_factor_status().await {
+                        Ok(fetched) => status.set(Some(fetched)),
+                        Err(err) => log::error!("Error getting two-factor status: {:?}", err),
+                    }
+                });
+                || ()
+            },
+            *reload,
+        );
+    }
+
+    let on_setup_done = {
+        let setting_up = setting_up.clone();
+        let reload = reload.clone();
+        Callback::from(move |_| {
+            setting_up.set(false);
+            reload.set(*reload + 1);
+        })
+    };
+
+    let on_code_input = {
+        let code = code.clone();
+        Callback::from(move |e: InputEvent| code.set(e.target_unchecked_into::<HtmlInputElement>().value()))
+    };
+
+    let on_regenerate = {
+        let code = code.clone();
+        let recovery_codes = recovery_codes.clone();
+        let error = error.clone();
+        Callback::from(move |_| {
+            let submitted = (*code).clone();
+            let code = code.clone();
+            let recovery_codes = recovery_codes.clone();
+            let error = error.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match regenerate_recovery_codes(&submitted).await {
+                    Ok(codes) => {
+                        code.set(String::new());
+                        error.set(None);
+                        recovery_codes.set(Some(codes));
+                    }
+                    Err(_) => error.set(Some("That code didn't match.".to_string())),
+                }
+            });
+        })
+    };
+
+    let on_disable = {
+        let code = code.clone();
+        let error = error.clone();
+        let reload = reload.clone();
+        Callback::from(move |_| {
+            let submitted = (*code).clone();
+            let code = code.clone();
+            let error = error.clone();
+            let reload = reload.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match disable_two_factor(&submitted).await {
+                    Ok(()) => {
+                        code.set(String::new());
+                        error.set(None);
+                        reload.set(*reload + 1);
+                    }
+                    Err(_) => error.set(Some("That code didn't match.".to_string())),
+                }
+            });
+        })
+    };
+
+    let body = match status.as_ref() {
+        None => html! { <p>{ "Loading…" }</p> },
+        Some(_) if *setting_up => html! { <TwoFactorSetup on_done={on_setup_done} /> },
+        Some(status) if !status.enabled => {
+            let setting_up = setting_up.clone();
+            html! {
+                <>
+                    <p>
+                        { "Two-factor authentication is off." }
+                        if status.required {
+                            { " Your role requires it; you'll be asked to set it up at your next login." }
+                        }
+                    </p>
+                    <button onclick={Callback::from(move |_| setting_up.set(true))}>
+                        { "Set up two-factor authentication" }
+                    </button>
+                </>
+            }
+        }
+        Some(status) => html! {
+            <>
+                <p>{ "Two-factor authentication is on." }</p>
+                <label>
+                    { "Current code " }
+                    <input
+                        type="text"
+                        autocomplete="one-time-code"
+                        value={(*code).clone()}
+                        oninput={on_code_input}
+                    />
+                </label>
+                <button onclick={on_regenerate}>{ "New recovery codes" }</button>
+                if !status.required {
+                    <button onclick={on_disable}>{ "Turn off" }</button>
+                }
+                if let Some(codes) = recovery_codes.as_ref() {
+                    <ul class="recovery-codes">
+                        { for codes.iter().map(|code| html! { <li><code>{ code }</code></li> }) }
+                    </ul>
+                }
+            </>
+        },
+    };
+
+    html! {
+        <section class="two-factor-settings">
+            <h3>{ "Two-factor authentication" }</h3>
+            { body }
+            if let Some(message) = error.as_ref() {
+                <p class="error">{ message }</p>
+            }
+        </section>
+    }
+}
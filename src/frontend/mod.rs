@@ -1,2 +1,3 @@
 pub mod components;
-pub mod services;
\ No newline at end of file
+pub mod services;
+pub mod templates;
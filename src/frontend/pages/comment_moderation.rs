@@ -1,11 +1,271 @@
+use std::collections::HashSet;
 use yew::prelude::*;
+use web_sys::HtmlInputElement;
+use crate::frontend::services::comment_service::{bulk_moderate, fetch_moderation_queue, CommentPage};
+
+/// Status tabs, in display order; `None` shows every comment
+const STATUS_TABS: &[(Option<&str>, &str)] = &[
+    (Some("pending"), "Pending"),
+    (Some("approved"), "Approved"),
+    (Some("spam"), "Spam"),
+    (Some("trash"), "Trash"),
+    (None, "All"),
+];
+
+/// Bulk actions offered for the selected comments: (API action, button label)
+const BULK_ACTIONS: &[(&str, &str)] = &[
+    ("approve", "Approve"),
+    ("unapprove", "Unapprove"),
+    ("reject", "Reject"),
+    ("spam", "Mark as spam"),
+];
 
 #[function_component(CommentModeration)]
 pub fn comment_moderation() -> Html {
+    let status = use_state(|| Some("pending"));
+    let post_filter = use_state(|| None::<i32>);
+    let page = use_state(|| 1i64);
+    let reload = use_state(|| 0u32);
+    let queue = use_state(|| None::<CommentPage>);
+    let selected = use_state(HashSet::<i32>::new);
+    let post_input = use_node_ref();
+
+    {
+        let queue = queue.clone();
+        let selected = selected.clone();
+        use_effect_with_deps(
+            move |(status, post_id, page, _)| {
+                let (status, post_id, page) = (*status, *post_id, *page);
+                wasm_bindgen_futures::spawn_local(async move {
+                    match fetch_moderation_queue(status, post_id, page).await {
+                        Ok(fetched) => {
+                            selected.set(HashSet::new());
+                            queue.set(Some(fetched));
+                        }
+                        Err(err) => log::error!("Error getting comments: {:?}", err),
+                    }
+                });
+                || ()
+            },
+            (*status, *post_filter, *page, *reload),
+        );
+    }
+
+    let on_tab = {
+        let status = status.clone();
+        let page = page.clone();
+        Callback::from(move |tab: Option<&'static str>| {
+            status.set(tab);
+            page.set(1);
+        })
+    };
+
+    let on_post_filter = {
+        let post_input = post_input.clone();
+        let post_filter = post_filter.clone();
+        let page = page.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let value = post_input
+                .cast::<HtmlInputElement>()
+                .map(|input| input.value())
+                .unwrap_or_default();
+            post_filter.set(value.trim().parse::<i32>().ok());
+            page.set(1);
+        })
+    };
+
+    // Apply an action to the given comments, then refresh the queue
+    let moderate = {
+        let reload = reload.clone();
+        Callback::from(move |(action, ids): (&'static str, Vec<i32>)| {
+            if ids.is_empty() {
+                return;
+            }
+            let reload = reload.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if bulk_moderate(action, &ids).await.is_ok() {
+                    reload.set(*reload + 1);
+                }
+            });
+        })
+    };
+
+    let toggle = {
+        let selected = selected.clone();
+        Callback::from(move |id: i32| {
+            let mut next = (*selected).clone();
+            if !next.remove(&id) {
+                next.insert(id);
+            }
+            selected.set(next);
+        })
+    };
+
+    let toggle_all = {
+        let selected = selected.clone();
+        let queue = queue.clone();
+        Callback::from(move |_| {
+            let ids: HashSet<i32> = queue
+                .as_ref()
+                .map(|q| q.items.iter().map(|c| c.id).collect())
+                .unwrap_or_default();
+            if !ids.is_empty() && ids.is_subset(&selected) {
+                selected.set(HashSet::new());
+            } else {
+                selected.set(ids);
+            }
+        })
+    };
+
+    let counts = queue.as_ref().map(|q| q.counts.clone()).unwrap_or_default();
+    let count_for = |tab: Option<&str>| match tab {
+        Some("pending") => Some(counts.pending),
+        Some("approved") => Some(counts.approved),
+        Some("spam") => Some(counts.spam),
+        Some("trash") => Some(counts.trash),
+        _ => None,
+    };
+
+    let last_page = queue
+        .as_ref()
+        .map(|q| ((q.total + q.per_page - 1) / q.per_page).max(1))
+        .unwrap_or(1);
+    let current_page = *page;
+    let selected_ids: Vec<i32> = selected.iter().copied().collect();
+
     html! {
         <div class="comment-moderation">
             <h2>{ "Comment Moderation" }</h2>
-            { "Moderate comments here." }
+
+            <ul class="moderation-tabs">
+                {
+                    for STATUS_TABS.iter().map(|(tab, label)| {
+                        let tab = *tab;
+                        let active = *status == tab;
+                        let text = match count_for(tab) {
+                            Some(count) => format!("{} ({})", label, count),
+                            None => label.to_string(),
+                        };
+                        html! {
+                            <li class={classes!(active.then(|| "active"))} onclick={on_tab.reform(move |_| tab)}>
+                                { text }
+                            </li>
+                        }
+                    })
+                }
+            </ul>
+
+            <form class="moderation-filters" onsubmit={on_post_filter}>
+                <label>
+                    { "Post ID " }
+                    <input ref={post_input} type="number" min="1" />
+                </label>
+                <button type="submit">{ "Filter" }</button>
+            </form>
+
+            <div class="moderation-bulk">
+                {
+                    for BULK_ACTIONS.iter().map(|(action, label)| {
+                        let action = *action;
+                        let ids = selected_ids.clone();
+                        html! {
+                            <button disabled={selected.is_empty()} onclick={moderate.reform(move |_| (action, ids.clone()))}>
+                                { *label }
+                            </button>
+                        }
+                    })
+                }
+                <span>{ format!("{} selected", selected.len()) }</span>
+            </div>
+
+            {
+                match queue.as_ref() {
+                    None => html! { <p>{ "Loading comments…" }</p> },
+                    Some(q) if q.items.is_empty() => html! { <p>{ "No comments here." }</p> },
+                    Some(q) => html! {
+                        <table class="moderation-queue">
+                            <thead>
+                                <tr>
+                                    <th><input type="checkbox" onclick={toggle_all} /></th>
+                                    <th>{ "Author" }</th>
+                                    <th>{ "Comment" }</th>
+                                    <th>{ "Post" }</th>
+                                    <th>{ "Submitted" }</th>
+                                    <th>{ "Status" }</th>
+                                    <th></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {
+                                    for q.items.iter().map(|comment| {
+                                        let id = comment.id;
+                                        html! {
+                                            <tr key={id} class={classes!("comment-row", comment.status.clone())}>
+                                                <td>
+                                                    <input type="checkbox"
+                                                        checked={selected.contains(&id)}
+                                                        onclick={toggle.reform(move |_| id)} />
+                                                </td>
+                                                <td>
+                                                    <strong>{ &comment.author_name }</strong>
+                                                    if !comment.author_email.is_empty() {
+                                                        <br /><small>{ &comment.author_email }</small>
+                                                    }
+                                                    if let Some(ip) = &comment.author_ip {
+                                                        <br /><small>{ ip }</small>
+                                                    }
+                                                </td>
+                                                <td>
+                                                    if let Some(parent_id) = comment.parent_id {
+                                                        <small>{ format!("In reply to #{}", parent_id) }</small>
+                                                    }
+                                                    <p>{ &comment.content }</p>
+                                                    <ul class="spam-flags">
+                                                        {
+                                                            for comment.spam_verdicts.iter().filter(|v| v.outcome != "pass").map(|v| html! {
+                                                                <li class={classes!(v.outcome.clone())}>
+                                                                    { format!("{}: {}", v.filter, v.reason) }
+                                                                </li>
+                                                            })
+                                                        }
+                                                    </ul>
+                                                </td>
+                                                <td>{ format!("#{}", comment.post_id) }</td>
+                                                <td>{ &comment.created_at }</td>
+                                                <td>{ &comment.status }</td>
+                                                <td class="row-actions">
+                                                    if comment.status != "approved" {
+                                                        <button onclick={moderate.reform(move |_| ("approve", vec![id]))}>{ "Approve" }</button>
+                                                    }
+                                                    if comment.status != "spam" {
+                                                        <button onclick={moderate.reform(move |_| ("spam", vec![id]))}>{ "Spam" }</button>
+                                                    }
+                                                    if comment.status != "trash" {
+                                                        <button onclick={moderate.reform(move |_| ("reject", vec![id]))}>{ "Trash" }</button>
+                                                    }
+                                                </td>
+                                            </tr>
+                                        }
+                                    })
+                                }
+                            </tbody>
+                        </table>
+                    },
+                }
+            }
+
+            if last_page > 1 {
+                <div class="moderation-pagination">
+                    <button disabled={current_page <= 1} onclick={{ let page = page.clone(); move |_| page.set(current_page - 1) }}>
+                        { "Previous" }
+                    </button>
+                    <span>{ format!("Page {} of {}", current_page, last_page) }</span>
+                    <button disabled={current_page >= last_page} onclick={{ let page = page.clone(); move |_| page.set(current_page + 1) }}>
+                        { "Next" }
+                    </button>
+                </div>
+            }
         </div>
     }
 }
@@ -1,4 +1,7 @@
 use yew::prelude::*;
+use crate::frontend::components::audit_log::AuditLogView;
+use crate::frontend::components::linked_accounts::LinkedAccounts;
+use crate::frontend::components::two_factor::TwoFactorSettings;
 
 #[function_component(Dashboard)]
 pub fn dashboard() -> Html {
@@ -6,6 +9,9 @@ pub fn dashboard() -> Html {
         <div class="dashboard">
             <h2>{ "Dashboard" }</h2>
             { "Welcome to your dashboard." }
+            <TwoFactorSettings />
+            <LinkedAccounts />
+            <AuditLogView />
         </div>
     }
 }
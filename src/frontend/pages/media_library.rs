@@ -1,11 +1,64 @@
 use yew::prelude::*;
+use crate::frontend::services::media_service::{fetch_all_media, media_url, Media};
+
+/// Preview tile for one library item: the generated thumbnail when there is
+/// one, otherwise a placeholder describing the file
+fn media_preview(item: &Media) -> Html {
+    if let Some(src) = item.thumbnail_url() {
+        return html! {
+            <img src={src} alt={item.alt_text.clone()} loading="lazy" width="150" height="150" />
+        };
+    }
+
+    let label = match (item.is_image(), item.renditions_status.as_str()) {
+        (true, "pending" | "processing") => "Generating preview…".to_string(),
+        (true, _) => "Preview unavailable".to_string(),
+        (false, _) => item
+            .media_type
+            .clone()
+            .unwrap_or_else(|| "File".to_string()),
+    };
+    html! { <div class="media-placeholder">{ label }</div> }
+}
 
 #[function_component(MediaLibrary)]
 pub fn media_library() -> Html {
+    let media = use_state(|| Vec::<Media>::new());
+
+    {
+        let media = media.clone();
+        use_effect_with_deps(move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                match fetch_all_media().await {
+                    Ok(items) => media.set(items),
+                    Err(err) => log::error!("Error getting media: {:?}", err),
+                }
+            });
+            || ()
+        }, ());
+    }
+
     html! {
         <div class="media-library">
             <h2>{ "Media Library" }</h2>
-            { "Here you can manage your media files." }
+            if media.is_empty() {
+                <p>{ "No media uploaded yet." }</p>
+            } else {
+                <ul class="media-grid">
+                    {
+                        for media.iter().map(|item| html! {
+                            <li class="media-tile" key={item.id}>
+                                <a href={media_url(&item.url)} target="_blank" rel="noopener">
+                                    { media_preview(item) }
+                                </a>
+                                <span class="media-name" title={item.file_name.clone()}>
+                                    { &item.file_name }
+                                </span>
+                            </li>
+                        })
+                    }
+                </ul>
+            }
         </div>
     }
 }
@@ -1,14 +1,493 @@
+use std::collections::HashMap;
 use yew::prelude::*;
-use crate::components::builder::{Editor, Toolbox, Preview};
+use gloo_events::EventListener;
+use gloo_storage::{LocalStorage, Storage};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{EventTarget, HtmlElement, HtmlInputElement, KeyboardEvent};
+use crate::frontend::components::builder::{Editor, EventsPanel, Library, PropertyPanel, Toolbox, Preview};
+use crate::frontend::components::builder::events_panel::ActionTarget;
+use crate::frontend::components::builder::state::{BuilderMsg, BuilderState, Recovery};
+use crate::frontend::components::builder::tree::{block_section, copy_section, find_module, new_id, BuilderAction};
+use crate::frontend::services::builder_service::{
+    create_page, delete_library_item, fetch_library, fetch_page, save_library_item, save_page_document,
+    update_library_item, BuilderError, BuilderPage, LibraryItem, LibraryKind,
+};
+use crate::shared::builder::{Breakpoint, DocumentError, PageDocument, Section};
+
+#[derive(Properties, PartialEq)]
+pub struct PageBuilderProps {
+    /// Page to edit; a new page is created on first save when absent
+    #[prop_or_default]
+    pub page_id: Option<i32>,
+    /// Global block to edit instead of a page
+    #[prop_or_default]
+    pub block: Option<LibraryItem>,
+    /// Leave the block being edited
+    #[prop_or_default]
+    pub on_close: Callback<()>,
+}
+
+/// Whether key presses on `target` edit text: form fields and contenteditable
+fn is_editable(target: Option<EventTarget>) -> bool {
+    target
+        .and_then(|target| target.dyn_into::<HtmlElement>().ok())
+        .map_or(false, |element| {
+            element.is_content_editable() || matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+        })
+}
+
+/// Local storage key for the unsaved work on a page or block
+fn recovery_key(page_id: Option<i32>, block_id: Option<i32>) -> String {
+    match (block_id, page_id) {
+        (Some(id), _) => format!("builder-recovery-block-{}", id),
+        (None, Some(id)) => format!("builder-recovery-{}", id),
+        (None, None) => "builder-recovery-new".to_string(),
+    }
+}
+
+/// Unsaved work that differs from `document`, if any
+fn pending_recovery(key: &str, document: &PageDocument) -> Option<Recovery> {
+    LocalStorage::get::<Recovery>(key)
+        .ok()
+        .filter(|recovery| &recovery.document != document)
+}
+
+/// A document holding just the section of a global block
+fn block_document(block: &LibraryItem) -> PageDocument {
+    PageDocument {
+        sections: vec![block.section.clone()],
+        ..PageDocument::default()
+    }
+}
+
+/// Human-readable summary of a failed save
+fn save_error_message(err: &BuilderError) -> String {
+    match err {
+        BuilderError::InvalidDocument(errors) => errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; "),
+        other => other.to_string(),
+    }
+}
 
 #[function_component(PageBuilder)]
-pub fn page_builder() -> Html {
+pub fn page_builder(props: &PageBuilderProps) -> Html {
+    let page = use_state(|| None::<BuilderPage>);
+    let state = use_reducer(BuilderState::default);
+    let selected = use_state(|| None::<String>);
+    // Device previewed, and whose styles are edited
+    let device = use_state(|| None::<Breakpoint>);
+    let status = use_state(|| None::<String>);
+    let recovery = use_state(|| None::<Recovery>);
+    let library = use_state(Vec::<LibraryItem>::new);
+    // Bumped to fetch the library again
+    let library_revision = use_state(|| 0u32);
+    let editing_block = use_state(|| None::<LibraryItem>);
+    let title_input = use_node_ref();
+    let slug_input = use_node_ref();
+    let block_id = props.block.as_ref().map(|block| block.id);
+    let key = recovery_key(page.as_ref().map(|p| p.id).or(props.page_id), block_id);
+
+    {
+        let page = page.clone();
+        let state = state.clone();
+        let status = status.clone();
+        let recovery = recovery.clone();
+        let block = props.block.clone();
+        use_effect_with_deps(
+            move |(page_id, _)| {
+                match (block, *page_id) {
+                    (Some(block), _) => {
+                        let document = block_document(&block);
+                        recovery.set(pending_recovery(&recovery_key(None, Some(block.id)), &document));
+                        state.dispatch(BuilderMsg::Load(document));
+                    }
+                    (None, Some(page_id)) => wasm_bindgen_futures::spawn_local(async move {
+                        match fetch_page(page_id).await {
+                            Ok(fetched) => {
+                                // Looked up before loading, which clears the stored copy
+                                recovery.set(pending_recovery(&recovery_key(Some(page_id), None), &fetched.document));
+                                state.dispatch(BuilderMsg::Load(fetched.document.clone()));
+                                page.set(Some(fetched));
+                            }
+                            Err(err) => {
+                                log::error!("Error loading page: {:?}", err);
+                                status.set(Some("Could not load the page".to_string()));
+                            }
+                        }
+                    }),
+                    (None, None) => recovery.set(pending_recovery(&recovery_key(None, None), &PageDocument::default())),
+                }
+                || ()
+            },
+            (props.page_id, block_id),
+        );
+    }
+
+    // The library, fetched again after returning from editing a block.
+    // Blocks can't contain other blocks, so it isn't offered while editing one.
+    {
+        let library = library.clone();
+        let editing = editing_block.is_some();
+        use_effect_with_deps(
+            move |(_, editing, editing_a_block)| {
+                if !*editing && !*editing_a_block {
+                    wasm_bindgen_futures::spawn_local(async move {
+                        match fetch_library().await {
+                            Ok(items) => library.set(items),
+                            Err(err) => log::error!("Error loading the library: {:?}", err),
+                        }
+                    });
+                }
+                || ()
+            },
+            (*library_revision, editing, block_id.is_some()),
+        );
+    }
+
+    // Keep unsaved work in local storage until it is saved or discarded
+    {
+        let state = state.clone();
+        let key = key.clone();
+        use_effect_with_deps(
+            move |(_, key)| {
+                if state.dirty {
+                    let stored = Recovery {
+                        document: state.document.clone(),
+                        history: state.history.clone(),
+                        saved_at: js_sys::Date::now(),
+                    };
+                    if let Err(err) = LocalStorage::set(key, stored) {
+                        log::error!("Error storing unsaved changes: {:?}", err);
+                    }
+                } else if state.revision > 0 {
+                    LocalStorage::delete(key);
+                }
+                || ()
+            },
+            (state.revision, key),
+        );
+    }
+
+    // Ctrl+Z undoes, Ctrl+Shift+Z or Ctrl+Y redoes (Cmd on macOS). Left to
+    // the block builder while one is open, and to the browser's own text
+    // undo inside fields.
+    {
+        let state = state.clone();
+        use_effect_with_deps(
+            move |editing| {
+                let listener = web_sys::window().filter(|_| !*editing).map(|window| {
+                    EventListener::new(&window, "keydown", move |event| {
+                        let event = match event.dyn_ref::<KeyboardEvent>() {
+                            Some(event) => event,
+                            None => return,
+                        };
+                        if !(event.ctrl_key() || event.meta_key()) || is_editable(event.target()) {
+                            return;
+                        }
+                        let msg = match event.key().to_lowercase().as_str() {
+                            "z" if event.shift_key() => BuilderMsg::Redo,
+                            "z" => BuilderMsg::Undo,
+                            "y" => BuilderMsg::Redo,
+                            _ => return,
+                        };
+                        event.prevent_default();
+                        state.dispatch(msg);
+                    })
+                });
+                move || drop(listener)
+            },
+            editing_block.is_some(),
+        );
+    }
+
+    let on_action = {
+        let state = state.clone();
+        Callback::from(move |action: BuilderAction| state.dispatch(BuilderMsg::Edit(action)))
+    };
+    let on_select = {
+        let selected = selected.clone();
+        Callback::from(move |id: Option<String>| selected.set(id))
+    };
+    let on_undo = {
+        let state = state.clone();
+        Callback::from(move |_: MouseEvent| state.dispatch(BuilderMsg::Undo))
+    };
+    let on_redo = {
+        let state = state.clone();
+        Callback::from(move |_: MouseEvent| state.dispatch(BuilderMsg::Redo))
+    };
+    let on_restore = {
+        let state = state.clone();
+        let recovery = recovery.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(stored) = (*recovery).clone() {
+                state.dispatch(BuilderMsg::Restore(stored));
+            }
+            recovery.set(None);
+        })
+    };
+    let on_discard = {
+        let recovery = recovery.clone();
+        let key = key.clone();
+        Callback::from(move |_: MouseEvent| {
+            LocalStorage::delete(&key);
+            recovery.set(None);
+        })
+    };
+    let on_library_insert = {
+        let count = state.document.sections.len();
+        on_action.reform(move |item: LibraryItem| BuilderAction::InsertSection {
+            index: count,
+            section: match item.kind {
+                LibraryKind::GlobalBlock => block_section(new_id("s"), &item.name, item.id),
+                LibraryKind::SavedSection => copy_section(&item.section, new_id),
+            },
+        })
+    };
+    let on_library_edit = {
+        let editing_block = editing_block.clone();
+        Callback::from(move |item: LibraryItem| editing_block.set(Some(item)))
+    };
+    let on_library_delete = {
+        let library_revision = library_revision.clone();
+        let status = status.clone();
+        Callback::from(move |item: LibraryItem| {
+            let confirmed = web_sys::window()
+                .and_then(|window| window.confirm_with_message(&format!("Delete \"{}\" from the library?", item.name)).ok())
+                .unwrap_or(false);
+            if !confirmed {
+                return;
+            }
+            let library_revision = library_revision.clone();
+            let status = status.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match delete_library_item(item.id).await {
+                    Ok(()) => library_revision.set(*library_revision + 1),
+                    Err(err) => status.set(Some(format!("Could not delete \"{}\": {}", item.name, err))),
+                }
+            });
+        })
+    };
+    let on_save_section = {
+        let state = state.clone();
+        let status = status.clone();
+        let library_revision = library_revision.clone();
+        Callback::from(move |(section, kind): (Section, LibraryKind)| {
+            let suggested = if section.name.is_empty() { "Untitled section" } else { section.name.as_str() };
+            let name = match web_sys::window()
+                .and_then(|window| window.prompt_with_message_and_default("Name", suggested).ok().flatten())
+            {
+                Some(name) if !name.trim().is_empty() => name,
+                _ => return,
+            };
+            let state = state.clone();
+            let status = status.clone();
+            let library_revision = library_revision.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match save_library_item(&name, kind, &section).await {
+                    Ok(item) => {
+                        // The section now shows the block instead of its own copy
+                        if kind == LibraryKind::GlobalBlock {
+                            state.dispatch(BuilderMsg::Edit(BuilderAction::UpdateSection {
+                                section: block_section(section.id.clone(), &item.name, item.id),
+                            }));
+                        }
+                        library_revision.set(*library_revision + 1);
+                        status.set(Some(format!("Saved \"{}\" to the library", item.name)));
+                    }
+                    Err(err) => status.set(Some(format!(
+                        "Could not save to the library: {}",
+                        save_error_message(&err)
+                    ))),
+                }
+            });
+        })
+    };
+    let on_device = {
+        let device = device.clone();
+        Callback::from(move |breakpoint: Option<Breakpoint>| device.set(breakpoint))
+    };
+    let on_module_change = on_action.reform(|module| BuilderAction::UpdateModule { module });
+    let on_module_remove = {
+        let selected = selected.clone();
+        on_action.reform(move |module_id: String| {
+            selected.set(None);
+            BuilderAction::RemoveModule { module_id }
+        })
+    };
+
+    let document = &state.document;
+    // Checked locally as well so problems show up before saving
+    let mut problems = document.validate().err().unwrap_or_default();
+    if props.block.is_some() && document.sections.len() != 1 {
+        problems.push(DocumentError {
+            path: "sections".to_string(),
+            message: "a global block holds exactly one section".to_string(),
+        });
+    }
+    // Global blocks are shown with their current content
+    let blocks: HashMap<i32, Section> = library
+        .iter()
+        .filter(|item| item.kind == LibraryKind::GlobalBlock)
+        .map(|item| (item.id, item.section.clone()))
+        .collect();
+    let mut rendered = document.clone();
+    rendered.resolve_blocks(&blocks);
+    let selected_module = selected
+        .as_deref()
+        .and_then(|id| find_module(document, id))
+        .cloned();
+    let targets: Vec<ActionTarget> = document
+        .sections
+        .iter()
+        .map(|section| ActionTarget {
+            id: section.id.clone(),
+            label: format!("Section: {}", section.name),
+            section: true,
+        })
+        .chain(document.modules().map(|module| ActionTarget {
+            id: module.id.clone(),
+            label: format!("{} ({})", module.kind.type_name(), module.id),
+            section: false,
+        }))
+        .collect();
+
+    let on_save = {
+        let page = page.clone();
+        let state = state.clone();
+        let status = status.clone();
+        let title_input = title_input.clone();
+        let slug_input = slug_input.clone();
+        let block = props.block.clone();
+        Callback::from(move |_| {
+            let page = page.clone();
+            let state = state.clone();
+            let status = status.clone();
+            let current = state.document.clone();
+            let existing = page.as_ref().map(|p| p.id);
+
+            if let Some(block) = block.clone() {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match update_library_item(block.id, &current.sections[0]).await {
+                        Ok(saved) => {
+                            state.dispatch(BuilderMsg::Saved(block_document(&saved)));
+                            status.set(Some(format!(
+                                "Saved; {} page(s) using this block now show the change",
+                                saved.usage_count
+                            )));
+                        }
+                        Err(err) => status.set(Some(format!("Save failed: {}", save_error_message(&err)))),
+                    }
+                });
+                return;
+            }
+
+            let value_of = |node: &NodeRef| {
+                node.cast::<HtmlInputElement>()
+                    .map(|input| input.value())
+                    .unwrap_or_default()
+            };
+            let (title, slug) = (value_of(&title_input), value_of(&slug_input));
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let saved = match existing {
+                    Some(id) => save_page_document(id, &current).await,
+                    None => create_page(&title, &slug, &current).await,
+                };
+                match saved {
+                    Ok(saved) => {
+                        if existing.is_none() {
+                            LocalStorage::delete(recovery_key(None, None));
+                        }
+                        // Pick up the sanitized text the server stored
+                        state.dispatch(BuilderMsg::Saved(saved.document.clone()));
+                        page.set(Some(saved));
+                        status.set(Some("Saved".to_string()));
+                    }
+                    Err(err) => status.set(Some(format!("Save failed: {}", save_error_message(&err)))),
+                }
+            });
+        })
+    };
+
+    if let Some(block) = editing_block.as_ref() {
+        let on_close = {
+            let editing_block = editing_block.clone();
+            Callback::from(move |_| editing_block.set(None))
+        };
+        return html! { <PageBuilder block={block.clone()} {on_close} /> };
+    }
+
     html! {
         <div class="page-builder">
-            <h2>{ "Page Builder" }</h2>
-            <Toolbox />
-            <Editor />
-            <Preview />
+            <div class="builder-bar">
+                <h2>{ "Page Builder" }</h2>
+                {
+                    match (props.block.as_ref(), page.as_ref()) {
+                        (Some(block), _) => html! {
+                            <>
+                                <span>{ format!("Global block: {}", block.name) }</span>
+                                <button onclick={props.on_close.reform(|_: MouseEvent| ())}>{ "Back to page" }</button>
+                            </>
+                        },
+                        (None, Some(page)) => html! { <span>{ format!("{} (/{})", page.title, page.slug) }</span> },
+                        (None, None) => html! {
+                            <>
+                                <input ref={title_input} type="text" placeholder="Title" />
+                                <input ref={slug_input} type="text" placeholder="Slug" />
+                            </>
+                        },
+                    }
+                }
+                <button onclick={on_undo} disabled={!state.history.can_undo()} title="Ctrl+Z">{ "Undo" }</button>
+                <button onclick={on_redo} disabled={!state.history.can_redo()} title="Ctrl+Shift+Z">{ "Redo" }</button>
+                <button onclick={on_save} disabled={!problems.is_empty()}>{ "Save" }</button>
+                if let Some(message) = state.error.as_ref().or(status.as_ref()) {
+                    <span class="builder-status">{ message }</span>
+                }
+            </div>
+            if let Some(stored) = recovery.as_ref() {
+                <div class="builder-recovery">
+                    <span>
+                        {
+                            format!(
+                                "There are unsaved changes from {}.",
+                                String::from(js_sys::Date::new(&stored.saved_at.into()).to_locale_string("default", &JsValue::UNDEFINED))
+                            )
+                        }
+                    </span>
+                    <button onclick={on_restore}>{ "Restore" }</button>
+                    <button onclick={on_discard}>{ "Discard" }</button>
+                </div>
+            }
+            if !problems.is_empty() {
+                <ul class="builder-problems">
+                    { for problems.iter().map(|problem| html! { <li>{ problem.to_string() }</li> }) }
+                </ul>
+            }
+            <div class="builder-sidebar">
+                <Toolbox />
+                if let Some(module) = selected_module {
+                    <PropertyPanel module={module.clone()} breakpoint={*device} on_change={on_module_change.clone()} on_remove={on_module_remove} />
+                    <EventsPanel {module} {targets} on_change={on_module_change} />
+                }
+                if props.block.is_none() {
+                    <Library items={(*library).clone()}
+                        on_insert={on_library_insert}
+                        on_edit={on_library_edit}
+                        on_delete={on_library_delete} />
+                }
+            </div>
+            <Editor document={document.clone()}
+                selected={(*selected).clone()}
+                library={(*library).clone()}
+                {on_action}
+                {on_select}
+                on_save_section={props.block.is_none().then(|| on_save_section)} />
+            <Preview document={rendered} device={*device} {on_device} />
         </div>
     }
 }
@@ -1,14 +1,179 @@
 use yew::prelude::*;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use crate::frontend::components::tag_input::TagInput;
+use crate::frontend::services::api_service::{create_post, fetch_post_tags, set_post_tags, NewPost, Post};
+use crate::shared::tags::slugify;
+
+#[derive(Properties, PartialEq)]
+pub struct PostEditorProps {
+    /// The post whose tags are edited; tags are saved as they change. When
+    /// absent, the editor writes a new post and saves its tags with it.
+    #[prop_or_default]
+    pub post: Option<Post>,
+    /// Called with a new post once it is saved, after its tags
+    #[prop_or_default]
+    pub on_created: Callback<Post>,
+}
 
 #[function_component(PostEditor)]
-pub fn post_editor() -> Html {
+pub fn post_editor(props: &PostEditorProps) -> Html {
+    let post_id = props.post.as_ref().and_then(|post| post.id);
+    let title = use_state(String::new);
+    let slug = use_state(String::new);
+    // The slug follows the title until it is edited by hand
+    let slug_edited = use_state(|| false);
+    let content = use_state(String::new);
+    let tags = use_state(Vec::<String>::new);
+    let saving = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    {
+        let tags = tags.clone();
+        use_effect_with_deps(
+            move |post_id: &Option<i32>| {
+                if let Some(post_id) = *post_id {
+                    wasm_bindgen_futures::spawn_local(async move {
+                        match fetch_post_tags(post_id).await {
+                            Ok(fetched) => tags.set(fetched.into_iter().map(|tag| tag.name).collect()),
+                            Err(err) => log::error!("Error getting post tags: {:?}", err),
+                        }
+                    });
+                }
+                || ()
+            },
+            post_id,
+        );
+    }
+
+    let on_tags_change = {
+        let tags = tags.clone();
+        let error = error.clone();
+        Callback::from(move |names: Vec<String>| match post_id {
+            Some(post_id) => {
+                let tags = tags.clone();
+                let error = error.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    match set_post_tags(post_id, &names).await {
+                        Ok(saved) => {
+                            error.set(None);
+                            tags.set(saved.into_iter().map(|tag| tag.name).collect());
+                        }
+                        Err(_) => error.set(Some("The tags couldn't be saved.".to_string())),
+                    }
+                });
+            }
+            // Kept until the post is saved
+            None => tags.set(names),
+        })
+    };
+
+    // Tags of an existing post are all this editor changes
+    if post_id.is_some() {
+        return html! {
+            <div class="post-editor">
+                <TagInput tags={(*tags).clone()} on_change={on_tags_change} />
+                if let Some(message) = error.as_ref() {
+                    <p class="error">{ message }</p>
+                }
+            </div>
+        };
+    }
+
+    let on_submit = {
+        let title = title.clone();
+        let slug = slug.clone();
+        let content = content.clone();
+        let tags = tags.clone();
+        let saving = saving.clone();
+        let error = error.clone();
+        let on_created = props.on_created.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            if *saving {
+                return;
+            }
+            let new_post = NewPost {
+                title: (*title).clone(),
+                slug: (*slug).clone(),
+                content: (*content).clone(),
+                status: "draft".to_string(),
+            };
+            let names = (*tags).clone();
+            let saving = saving.clone();
+            let error = error.clone();
+            let on_created = on_created.clone();
+            saving.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                let created = match create_post(&new_post).await {
+                    Ok(created) => created,
+                    Err(_) => {
+                        saving.set(false);
+                        error.set(Some("The post couldn't be saved.".to_string()));
+                        return;
+                    }
+                };
+                if let (Some(post_id), false) = (created.id, names.is_empty()) {
+                    if let Err(err) = set_post_tags(post_id, &names).await {
+                        // The post exists now; its tags can be added from the post list
+                        log::error!("Error saving tags of new post {}: {:?}", post_id, err);
+                        error.set(Some("The post was saved, but its tags weren't.".to_string()));
+                    }
+                }
+                saving.set(false);
+                on_created.emit(created);
+            });
+        })
+    };
+
+    let on_title_input = {
+        let title = title.clone();
+        let slug = slug.clone();
+        let slug_edited = slug_edited.clone();
+        Callback::from(move |e: InputEvent| {
+            let value = e.target_unchecked_into::<HtmlInputElement>().value();
+            if !*slug_edited {
+                slug.set(slugify(&value));
+            }
+            title.set(value);
+        })
+    };
+
+    let on_slug_input = {
+        let slug = slug.clone();
+        Callback::from(move |e: InputEvent| {
+            let value = e.target_unchecked_into::<HtmlInputElement>().value();
+            // Clearing the slug hands it back to the title
+            slug_edited.set(!value.is_empty());
+            slug.set(value);
+        })
+    };
+
     html! {
         <div class="post-editor">
-            <h2>{ "Edit Post" }</h2>
-            <form>
-                <input type="text" placeholder="Title" />
-                <textarea placeholder="Content"></textarea>
-                <button type="submit">{ "Save Post" }</button>
+            <h2>{ "New Post" }</h2>
+            <form onsubmit={on_submit}>
+                <input
+                    type="text"
+                    placeholder="Title"
+                    value={(*title).clone()}
+                    oninput={on_title_input}
+                />
+                <input
+                    type="text"
+                    placeholder="Slug"
+                    value={(*slug).clone()}
+                    oninput={on_slug_input}
+                />
+                <textarea
+                    placeholder="Content"
+                    value={(*content).clone()}
+                    oninput={Callback::from(move |e: InputEvent| content.set(e.target_unchecked_into::<HtmlTextAreaElement>().value()))}
+                />
+                <TagInput tags={(*tags).clone()} on_change={on_tags_change} />
+                if let Some(message) = error.as_ref() {
+                    <p class="error">{ message }</p>
+                }
+                <button type="submit" disabled={*saving}>{ "Save Post" }</button>
             </form>
         </div>
     }
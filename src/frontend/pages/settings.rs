@@ -1,11 +1,267 @@
+use std::collections::{BTreeMap, HashMap};
+use serde_json::Value;
 use yew::prelude::*;
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use crate::frontend::services::settings_service::{fetch_settings, reset_setting, save_settings, SettingsError};
+use crate::shared::settings::{SettingGroupValues, SettingKind, SettingValue};
 
+/// Site settings, with one form section per registered group. Fields are
+/// generated from each setting's kind, so modules only declare settings.
 #[function_component(Settings)]
 pub fn settings() -> Html {
+    let groups = use_state(|| None::<Vec<SettingGroupValues>>);
+    // Values changed since the last load or save, by key
+    let edits = use_state(BTreeMap::<String, Value>::new);
+    // Validation messages, by key
+    let errors = use_state(HashMap::<String, String>::new);
+    let notice = use_state(|| None::<String>);
+
+    {
+        let groups = groups.clone();
+        use_effect_with_deps(
+            move |_| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match fetch_settings().await {
+                        Ok(fetched) => groups.set(Some(fetched)),
+                        Err(err) => log::error!("Error getting settings: {:?}", err),
+                    }
+                });
+                || ()
+            },
+            (),
+        );
+    }
+
+    let on_edit = {
+        let edits = edits.clone();
+        let notice = notice.clone();
+        Callback::from(move |(key, value): (String, Value)| {
+            let mut next = (*edits).clone();
+            next.insert(key, value);
+            edits.set(next);
+            notice.set(None);
+        })
+    };
+
+    let on_save = {
+        let groups = groups.clone();
+        let edits = edits.clone();
+        let errors = errors.clone();
+        let notice = notice.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            if edits.is_empty() {
+                return;
+            }
+
+            // Check locally first; the backend checks again
+            let defs = groups.as_deref().map(definitions).unwrap_or_default();
+            let mut rejected = HashMap::new();
+            for (key, value) in edits.iter() {
+                if let Some(Err(message)) = defs.get(key).map(|setting| setting.def.validate(value)) {
+                    rejected.insert(key.clone(), message);
+                }
+            }
+            if !rejected.is_empty() {
+                errors.set(rejected);
+                return;
+            }
+
+            let values = (*edits).clone();
+            let groups = groups.clone();
+            let edits = edits.clone();
+            let errors = errors.clone();
+            let notice = notice.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match save_settings(&values).await {
+                    Ok(saved) => {
+                        groups.set(Some(saved));
+                        edits.set(BTreeMap::new());
+                        errors.set(HashMap::new());
+                        notice.set(Some("Settings saved.".to_string()));
+                    }
+                    Err(SettingsError::Invalid(rejected)) => {
+                        errors.set(rejected.into_iter().map(|err| (err.key, err.message)).collect());
+                    }
+                    Err(err) => notice.set(Some(format!("Failed to save settings: {}", err))),
+                }
+            });
+        })
+    };
+
+    let on_reset = {
+        let groups = groups.clone();
+        let edits = edits.clone();
+        let errors = errors.clone();
+        Callback::from(move |key: String| {
+            let groups = groups.clone();
+            let edits = edits.clone();
+            let errors = errors.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match reset_setting(&key).await {
+                    Ok(default) => {
+                        if let Some(current) = groups.as_ref() {
+                            let mut next = current.clone();
+                            for setting in next.iter_mut().flat_map(|group| group.settings.iter_mut()) {
+                                if setting.def.key == key {
+                                    setting.value = default.clone();
+                                }
+                            }
+                            groups.set(Some(next));
+                        }
+                        let mut next_edits = (*edits).clone();
+                        next_edits.remove(&key);
+                        edits.set(next_edits);
+                        let mut next_errors = (*errors).clone();
+                        next_errors.remove(&key);
+                        errors.set(next_errors);
+                    }
+                    Err(err) => log::error!("Error resetting setting {}: {:?}", key, err),
+                }
+            });
+        })
+    };
+
     html! {
         <div class="settings">
             <h2>{ "Settings" }</h2>
-            { "Configure your site settings here." }
+            {
+                match groups.as_ref() {
+                    None => html! { <p>{ "Loading settings…" }</p> },
+                    Some(groups) => html! {
+                        <form class="settings-form" onsubmit={on_save}>
+                            {
+                                for groups.iter().map(|group| html! {
+                                    <fieldset class="settings-group">
+                                        <legend>{ &group.label }</legend>
+                                        {
+                                            for group.settings.iter().map(|setting| {
+                                                let value = edits.get(&setting.def.key).unwrap_or(&setting.value);
+                                                setting_field(
+                                                    setting,
+                                                    value,
+                                                    errors.get(&setting.def.key),
+                                                    &on_edit,
+                                                    &on_reset,
+                                                )
+                                            })
+                                        }
+                                    </fieldset>
+                                })
+                            }
+                            <div class="settings-actions">
+                                <button type="submit" disabled={edits.is_empty()}>{ "Save settings" }</button>
+                                if let Some(message) = notice.as_ref() {
+                                    <span class="settings-notice">{ message }</span>
+                                }
+                            </div>
+                        </form>
+                    },
+                }
+            }
+        </div>
+    }
+}
+
+/// Every setting by key
+fn definitions(groups: &[SettingGroupValues]) -> HashMap<String, SettingValue> {
+    groups
+        .iter()
+        .flat_map(|group| group.settings.iter())
+        .map(|setting| (setting.def.key.clone(), setting.clone()))
+        .collect()
+}
+
+/// The input for one setting, chosen by its kind
+fn setting_field(
+    setting: &SettingValue,
+    value: &Value,
+    error: Option<&String>,
+    on_edit: &Callback<(String, Value)>,
+    on_reset: &Callback<String>,
+) -> Html {
+    let key = setting.def.key.clone();
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    let edit_text = {
+        let key = key.clone();
+        on_edit.reform(move |text: String| (key.clone(), Value::String(text)))
+    };
+    let from_input = edit_text.reform(|e: InputEvent| e.target_unchecked_into::<HtmlInputElement>().value());
+
+    let input = match &setting.def.kind {
+        SettingKind::Text { multiline: true, max_chars, .. } => html! {
+            <textarea
+                id={key.clone()}
+                rows="6"
+                maxlength={max_chars.map(|max| max.to_string())}
+                value={text}
+                oninput={edit_text.reform(|e: InputEvent| e.target_unchecked_into::<HtmlTextAreaElement>().value())}
+            />
+        },
+        SettingKind::Text { max_chars, required, .. } => html! {
+            <input
+                id={key.clone()}
+                type="text"
+                required={*required}
+                maxlength={max_chars.map(|max| max.to_string())}
+                value={text}
+                oninput={from_input}
+            />
+        },
+        SettingKind::Integer { min, max } => html! {
+            <input id={key.clone()} type="number" min={min.to_string()} max={max.to_string()} value={text} oninput={from_input} />
+        },
+        SettingKind::Boolean => {
+            let key = key.clone();
+            html! {
+                <input
+                    id={key.clone()}
+                    type="checkbox"
+                    checked={value.as_bool().unwrap_or(false)}
+                    onchange={on_edit.reform(move |e: Event| {
+                        let checked = e.target_unchecked_into::<HtmlInputElement>().checked();
+                        (key.clone(), Value::Bool(checked))
+                    })}
+                />
+            }
+        }
+        SettingKind::Email { required } => html! {
+            <input id={key.clone()} type="email" required={*required} value={text} oninput={from_input} />
+        },
+        SettingKind::Url => html! {
+            <input id={key.clone()} type="text" placeholder="/path or https://…" value={text} oninput={from_input} />
+        },
+        SettingKind::Choice { options } => html! {
+            <select
+                id={key.clone()}
+                onchange={edit_text.reform(|e: Event| e.target_unchecked_into::<HtmlSelectElement>().value())}
+            >
+                {
+                    for options.iter().map(|option| html! {
+                        <option value={option.clone()} selected={*option == text}>{ option }</option>
+                    })
+                }
+            </select>
+        },
+    };
+
+    html! {
+        <div class={classes!("settings-field", error.map(|_| "invalid"))}>
+            <label for={key.clone()}>{ &setting.def.label }</label>
+            { input }
+            if let Some(description) = &setting.def.description {
+                <p class="settings-help">{ description }</p>
+            }
+            if let Some(message) = error {
+                <p class="settings-error">{ format!("{} {}", setting.def.label, message) }</p>
+            }
+            <button type="button" class="settings-reset" onclick={on_reset.reform(move |_| key.clone())}>
+                { "Reset to default" }
+            </button>
         </div>
     }
 }
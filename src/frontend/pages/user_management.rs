@@ -1,11 +1,329 @@
+use std::collections::HashSet;
 use yew::prelude::*;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use crate::frontend::services::api_service::{create_api_key, fetch_api_keys, revoke_api_key, ApiServiceError};
+use crate::shared::api_keys::{ApiAccess, ApiKeyOwner, ApiKeySummary, ApiResource, ApiScope, NewApiKeyRequest};
+
+/// Roles a site key can act with: (stored value, label)
+const SITE_ROLES: &[(&str, &str)] = &[
+    ("subscriber", "Subscriber"),
+    ("contributor", "Contributor"),
+    ("author", "Author"),
+    ("editor", "Editor"),
+    ("admin", "Admin"),
+];
 
 #[function_component(UserManagement)]
 pub fn user_management() -> Html {
     html! {
         <div class="user-management">
             <h2>{ "User Management" }</h2>
-            { "Manage your users here." }
+            <ApiKeys />
         </div>
     }
 }
+
+fn owner_label(owner: &ApiKeyOwner) -> String {
+    match owner {
+        ApiKeyOwner::Personal { username } => username.clone(),
+        ApiKeyOwner::Site { role } => format!("Site ({})", role),
+    }
+}
+
+/// Key times are UTC `YYYY-MM-DD HH:MM`, so they compare as strings
+fn now_utc() -> String {
+    String::from(js_sys::Date::new_0().to_iso_string())
+        .replacen('T', " ", 1)
+        .chars()
+        .take(16)
+        .collect()
+}
+
+fn key_status(key: &ApiKeySummary, now: &str) -> &'static str {
+    match &key.expires_at {
+        _ if key.revoked_at.is_some() => "Revoked",
+        Some(expires_at) if expires_at.as_str() <= now => "Expired",
+        _ => "Active",
+    }
+}
+
+/// Create, list and revoke API keys
+#[function_component(ApiKeys)]
+fn api_keys() -> Html {
+    let keys = use_state(Vec::<ApiKeySummary>::new);
+    let reload = use_state(|| 0u32);
+    let site_key = use_state(|| false);
+    let role = use_state(|| "editor".to_string());
+    let scopes = use_state(HashSet::<ApiScope>::new);
+    let secret = use_state(|| None::<String>);
+    let error = use_state(|| None::<String>);
+    let name_input = use_node_ref();
+    let username_input = use_node_ref();
+    let expiry_input = use_node_ref();
+
+    {
+        let keys = keys.clone();
+        use_effect_with_deps(
+            move |_| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match fetch_api_keys().await {
+                        Ok(fetched) => keys.set(fetched),
+                        Err(err) => log::error!("Error getting API keys: {:?}", err),
+                    }
+                });
+                || ()
+            },
+            *reload,
+        );
+    }
+
+    let toggle_scope = {
+        let scopes = scopes.clone();
+        Callback::from(move |scope: ApiScope| {
+            let mut next = (*scopes).clone();
+            if !next.remove(&scope) {
+                next.insert(scope);
+            }
+            scopes.set(next);
+        })
+    };
+
+    let on_owner = {
+        let site_key = site_key.clone();
+        Callback::from(move |e: Event| {
+            site_key.set(e.target_unchecked_into::<HtmlSelectElement>().value() == "site");
+        })
+    };
+
+    let on_role = {
+        let role = role.clone();
+        Callback::from(move |e: Event| role.set(e.target_unchecked_into::<HtmlSelectElement>().value()))
+    };
+
+    let on_create = {
+        let reload = reload.clone();
+        let site_key = site_key.clone();
+        let role = role.clone();
+        let scopes = scopes.clone();
+        let secret = secret.clone();
+        let error = error.clone();
+        let name_input = name_input.clone();
+        let username_input = username_input.clone();
+        let expiry_input = expiry_input.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let value = |node: &NodeRef| {
+                node.cast::<HtmlInputElement>()
+                    .map(|input| input.value().trim().to_string())
+                    .unwrap_or_default()
+            };
+            let expiry = value(&expiry_input);
+            let expires_in_days = if expiry.is_empty() {
+                None
+            } else {
+                match expiry.parse::<u32>() {
+                    Ok(days) if days > 0 => Some(days),
+                    _ => {
+                        error.set(Some("Expiry must be a whole number of days".to_string()));
+                        return;
+                    }
+                }
+            };
+            let owner = if *site_key {
+                ApiKeyOwner::Site { role: (*role).clone() }
+            } else {
+                ApiKeyOwner::Personal {
+                    username: value(&username_input),
+                }
+            };
+            let request = NewApiKeyRequest {
+                name: value(&name_input),
+                owner,
+                scopes: scopes.iter().copied().collect(),
+                expires_in_days,
+            };
+
+            let reload = reload.clone();
+            let scopes = scopes.clone();
+            let secret = secret.clone();
+            let error = error.clone();
+            let name_input = name_input.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match create_api_key(&request).await {
+                    Ok(created) => {
+                        if let Some(input) = name_input.cast::<HtmlInputElement>() {
+                            input.set_value("");
+                        }
+                        scopes.set(HashSet::new());
+                        error.set(None);
+                        secret.set(Some(created.secret));
+                        reload.set(*reload + 1);
+                    }
+                    Err(ApiServiceError::ServerError(422)) => error.set(Some(
+                        "Check the name and owner, and choose at least one scope".to_string(),
+                    )),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+        })
+    };
+
+    let on_revoke = {
+        let reload = reload.clone();
+        Callback::from(move |key_id: i32| {
+            let reload = reload.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match revoke_api_key(key_id).await {
+                    Ok(()) => reload.set(*reload + 1),
+                    Err(err) => log::error!("Error revoking API key {}: {:?}", key_id, err),
+                }
+            });
+        })
+    };
+
+    let dismiss_secret = {
+        let secret = secret.clone();
+        Callback::from(move |_| secret.set(None))
+    };
+
+    let now = now_utc();
+
+    html! {
+        <section class="api-keys">
+            <h3>{ "API keys" }</h3>
+            <p class="hint">
+                { "Scripts send a key as " }<code>{ "Authorization: Bearer <key>" }</code>
+                { ". A key can only do what both its scopes and its owner's role allow." }
+            </p>
+
+            if let Some(secret_value) = (*secret).clone() {
+                <div class="api-key-secret">
+                    <p>{ "Copy this key now. It won't be shown again." }</p>
+                    <code>{ secret_value }</code>
+                    <button onclick={dismiss_secret}>{ "Done" }</button>
+                </div>
+            }
+
+            <form class="api-key-form" onsubmit={on_create}>
+                <label>
+                    { "Name" }
+                    <input type="text" ref={name_input} placeholder="Deploy script" />
+                </label>
+                <label>
+                    { "Acts as" }
+                    <select onchange={on_owner}>
+                        <option value="personal" selected={!*site_key}>{ "A user" }</option>
+                        <option value="site" selected={*site_key}>{ "The site, with a role" }</option>
+                    </select>
+                </label>
+                if *site_key {
+                    <label>
+                        { "Role" }
+                        <select onchange={on_role}>
+                            {
+                                for SITE_ROLES.iter().map(|(value, label)| html! {
+                                    <option value={*value} selected={*role == *value}>{ *label }</option>
+                                })
+                            }
+                        </select>
+                    </label>
+                } else {
+                    <label>
+                        { "Username" }
+                        <input type="text" ref={username_input} />
+                    </label>
+                }
+                <label>
+                    { "Expires after (days)" }
+                    <input type="number" min="1" ref={expiry_input} placeholder="Never" />
+                </label>
+
+                <table class="api-key-scopes">
+                    <thead>
+                        <tr><th>{ "Scope" }</th><th>{ "Read" }</th><th>{ "Write" }</th></tr>
+                    </thead>
+                    <tbody>
+                        {
+                            for ApiResource::ALL.iter().map(|resource| {
+                                let cell = |access: ApiAccess| {
+                                    let scope = ApiScope { access, resource: *resource };
+                                    let toggle_scope = toggle_scope.clone();
+                                    html! {
+                                        <td>
+                                            <input
+                                                type="checkbox"
+                                                checked={scopes.contains(&scope)}
+                                                onchange={Callback::from(move |_| toggle_scope.emit(scope))}
+                                            />
+                                        </td>
+                                    }
+                                };
+                                html! {
+                                    <tr>
+                                        <td>{ resource.as_str() }</td>
+                                        { cell(ApiAccess::Read) }
+                                        { cell(ApiAccess::Write) }
+                                    </tr>
+                                }
+                            })
+                        }
+                    </tbody>
+                </table>
+
+                if let Some(message) = (*error).clone() {
+                    <p class="error">{ message }</p>
+                }
+                <button type="submit">{ "Create key" }</button>
+            </form>
+
+            <table class="api-key-list">
+                <thead>
+                    <tr>
+                        <th>{ "Name" }</th>
+                        <th>{ "Key" }</th>
+                        <th>{ "Owner" }</th>
+                        <th>{ "Scopes" }</th>
+                        <th>{ "Created" }</th>
+                        <th>{ "Last used" }</th>
+                        <th>{ "Expires" }</th>
+                        <th>{ "Status" }</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {
+                        for keys.iter().map(|key| {
+                            let key_id = key.id;
+                            let on_revoke = on_revoke.clone();
+                            let scope_list = key.scopes.iter().map(ApiScope::to_string).collect::<Vec<_>>().join(", ");
+                            html! {
+                                <tr class={classes!((key_status(key, &now) != "Active").then_some("inactive"))}>
+                                    <td>{ &key.name }</td>
+                                    <td><code>{ format!("{}…", key.prefix) }</code></td>
+                                    <td>{ owner_label(&key.owner) }</td>
+                                    <td>{ scope_list }</td>
+                                    <td>
+                                        { &key.created_at }
+                                        if let Some(creator) = &key.created_by {
+                                            <br /><small>{ format!("by {}", creator) }</small>
+                                        }
+                                    </td>
+                                    <td>{ key.last_used_at.clone().unwrap_or_else(|| "Never".to_string()) }</td>
+                                    <td>{ key.expires_at.clone().unwrap_or_else(|| "Never".to_string()) }</td>
+                                    <td>{ key_status(key, &now) }</td>
+                                    <td>
+                                        if key.revoked_at.is_none() {
+                                            <button onclick={Callback::from(move |_| on_revoke.emit(key_id))}>
+                                                { "Revoke" }
+                                            </button>
+                                        }
+                                    </td>
+                                </tr>
+                            }
+                        })
+                    }
+                </tbody>
+            </table>
+        </section>
+    }
+}
@@ -0,0 +1,138 @@
+// Runtime for builder page actions.
+//
+// Modules carry their event bindings as JSON in `data-bp-events`, validated
+// by the server when the page was saved. This file is the only code that
+// acts on them: it never evaluates strings, only looks up elements by their
+// `data-bp-id`, and re-checks every URL before navigating.
+(function () {
+    "use strict";
+
+    var SAFE_URL = /^(\/(?!\/)|#|https?:\/\/|mailto:|tel:)/i;
+    var SAFE_NAME = /^[A-Za-z][A-Za-z0-9_-]*$/;
+
+    function byId(id) {
+        var found = null;
+        document.querySelectorAll("[data-bp-id]").forEach(function (el) {
+            if (!found && el.getAttribute("data-bp-id") === id) {
+                found = el;
+            }
+        });
+        return found;
+    }
+
+    function closeModal(modal) {
+        modal.classList.remove("bp-modal-open");
+        modal.removeAttribute("aria-modal");
+        modal.removeAttribute("role");
+    }
+
+    function showModal(target) {
+        var modal = byId(target);
+        if (!modal || !modal.classList.contains("bp-modal")) {
+            return;
+        }
+        modal.classList.add("bp-modal-open");
+        modal.setAttribute("role", "dialog");
+        modal.setAttribute("aria-modal", "true");
+    }
+
+    function track(name, properties) {
+        if (!SAFE_NAME.test(name)) {
+            return;
+        }
+        var detail = { name: name, properties: properties || {} };
+        if (Array.isArray(window.dataLayer)) {
+            window.dataLayer.push(Object.assign({ event: name }, detail.properties));
+        }
+        document.dispatchEvent(new CustomEvent("bp:track", { detail: detail }));
+    }
+
+    var actions = {
+        open_url: function (action) {
+            var url = String(action.url || "");
+            if (!SAFE_URL.test(url)) {
+                return;
+            }
+            if (action.new_tab) {
+                window.open(url, "_blank", "noopener,noreferrer");
+            } else {
+                window.location.assign(url);
+            }
+        },
+        toggle_class: function (action) {
+            var el = byId(action.target);
+            if (el && SAFE_NAME.test(action.class)) {
+                el.classList.toggle(action.class);
+            }
+        },
+        show_modal: function (action) {
+            showModal(action.target);
+        },
+        track_event: function (action) {
+            track(action.name, action.properties);
+        }
+    };
+
+    function run(list) {
+        list.forEach(function (action) {
+            var handler = action && Object.prototype.hasOwnProperty.call(actions, action.action)
+                ? actions[action.action]
+                : null;
+            if (handler) {
+                handler(action);
+            }
+        });
+    }
+
+    function bind(el, bindings) {
+        bindings.forEach(function (binding) {
+            var list = Array.isArray(binding.actions) ? binding.actions : [];
+            if (binding.trigger === "click") {
+                el.addEventListener("click", function (event) {
+                    event.preventDefault();
+                    run(list);
+                });
+            } else if (binding.trigger === "visible" && "IntersectionObserver" in window) {
+                var observer = new IntersectionObserver(function (entries) {
+                    if (entries.some(function (entry) { return entry.isIntersecting; })) {
+                        observer.disconnect();
+                        run(list);
+                    }
+                });
+                observer.observe(el);
+            }
+        });
+    }
+
+    function init() {
+        document.querySelectorAll("[data-bp-events]").forEach(function (el) {
+            var bindings;
+            try {
+                bindings = JSON.parse(el.getAttribute("data-bp-events"));
+            } catch (e) {
+                return;
+            }
+            if (Array.isArray(bindings)) {
+                bind(el, bindings);
+            }
+        });
+
+        // Dialogs close on the backdrop or Escape
+        document.addEventListener("click", function (event) {
+            if (event.target.classList && event.target.classList.contains("bp-modal-open")) {
+                closeModal(event.target);
+            }
+        });
+        document.addEventListener("keydown", function (event) {
+            if (event.key === "Escape") {
+                document.querySelectorAll(".bp-modal-open").forEach(closeModal);
+            }
+        });
+    }
+
+    if (document.readyState === "loading") {
+        document.addEventListener("DOMContentLoaded", init);
+    } else {
+        init();
+    }
+})();
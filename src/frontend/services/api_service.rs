@@ -6,19 +6,43 @@ use gloo_storage::{LocalStorage, Storage};
 use log::{error, info};
 use thiserror::Error;
 use web_sys::window;
+use crate::shared::api_keys::{ApiKeySummary, CreatedApiKey, NewApiKeyRequest};
+use crate::shared::audit::{AuditFilter, AuditPage};
+use crate::shared::tags::{PostTagsRequest, TagSummary};
+use crate::shared::types::SearchResults;
 
 // Define the storage key for the auth token
 const AUTH_TOKEN_KEY: &str = "auth_token";
+// Storage key for the refresh token that renews it
+const REFRESH_TOKEN_KEY: &str = "refresh_token";
 
 /// Post structure
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Post {
     pub id: Option<i32>, // Made optional to handle cases where the post hasn't been saved yet
     pub title: String,
+    /// Not part of posts the backend returns
+    #[serde(default)]
     pub category: String,
     pub content: String,
 }
 
+/// Body of `POST /posts`. The backend makes the signed-in user the author.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NewPost {
+    pub title: String,
+    pub slug: String,
+    pub content: String,
+    /// `draft`, `pending_review`, `scheduled` or `published`
+    pub status: String,
+}
+
+/// Envelope the backend wraps successful payloads in
+#[derive(Deserialize)]
+struct ApiData<T> {
+    data: T,
+}
+
 /// AuthData structure for login
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AuthData {
@@ -26,6 +50,80 @@ pub struct AuthData {
     pub password: String,
 }
 
+/// Tokens returned by login and refresh
+#[derive(Deserialize, Debug, Clone)]
+struct TokenPair {
+    access_token: String,
+    refresh_token: String,
+}
+
+/// Response to the password step of a login
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum LoginResponse {
+    Tokens(TokenPair),
+    SecondFactor {
+        second_factor: String,
+        mfa_token: String,
+    },
+}
+
+/// Where to send the browser to link a provider account
+#[derive(Deserialize, Debug, Clone)]
+struct SsoLink {
+    url: String,
+}
+
+/// Where a login stands after the password step
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginStep {
+    /// Signed in
+    Done,
+    /// Enter a code from the authenticator, or a recovery code
+    VerifyCode { mfa_token: String },
+    /// Two-factor authentication is required but not set up yet
+    Enrol { mfa_token: String },
+}
+
+/// Secret to add to an authenticator app
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    pub required: bool,
+}
+
+#[derive(Deserialize)]
+struct TwoFactorEnabled {
+    recovery_codes: Vec<String>,
+    tokens: Option<TokenPair>,
+}
+
+#[derive(Deserialize)]
+struct RecoveryCodes {
+    recovery_codes: Vec<String>,
+}
+
+/// A single sign-on provider offered on the login page
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SsoProvider {
+    pub id: String,
+    pub name: String,
+}
+
+/// How users can sign in
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct LoginOptions {
+    pub providers: Vec<SsoProvider>,
+    /// False when only single sign-on is allowed
+    pub local_login: bool,
+}
+
 /// Define custom error types for API operations
 #[derive(Debug, Error)]
 pub enum ApiServiceError {
@@ -91,11 +189,49 @@ impl HttpMethod {
     }
 }
 
-/// Centralized function to make HTTP requests
+/// Centralized function to make HTTP requests. An expired access token is
+/// renewed once with the refresh token and the request retried.
 async fn make_request(
     method: HttpMethod,
     endpoint: &str,
     body: Option<&str>,
+) -> Result<Response, ApiServiceError> {
+    let response = send_request(&method, endpoint, body).await?;
+    let response = if response.status() == 401
+        && !endpoint.starts_with("/api/auth/login")
+        && refresh_session().await.is_ok()
+    {
+        send_request(&method, endpoint, body).await?
+    } else {
+        response
+    };
+
+    // Handle response status codes
+    if response.ok() {
+        Ok(response)
+    } else {
+        match response.status() {
+            401 => {
+                error!("Unauthorized access");
+                Err(ApiServiceError::Unauthorized)
+            }
+            404 => {
+                error!("Resource not found");
+                Err(ApiServiceError::NotFound)
+            }
+            status => {
+                error!("Server error with status code: {}", status);
+                Err(ApiServiceError::ServerError(status))
+            }
+        }
+    }
+}
+
+/// Send one request with the current access token
+async fn send_request(
+    method: &HttpMethod,
+    endpoint: &str,
+    body: Option<&str>,
 ) -> Result<Response, ApiServiceError> {
     let url = format!("{}{}", get_api_base_url(), endpoint);
     
@@ -116,38 +252,18 @@ async fn make_request(
     };
 
     // Conditionally set the body and send the request
-    let response = if let Some(body_str) = body {
+    if let Some(body_str) = body {
         request_builder
             .body(body_str)
             .map_err(ApiServiceError::from)?
             .send()
             .await
-            .map_err(ApiServiceError::from)?
+            .map_err(ApiServiceError::from)
     } else {
         request_builder
             .send()
             .await
-            .map_err(ApiServiceError::from)?
-    };
-
-    // Handle response status codes
-    if response.ok() {
-        Ok(response)
-    } else {
-        match response.status() {
-            401 => {
-                error!("Unauthorized access");
-                Err(ApiServiceError::Unauthorized)
-            }
-            404 => {
-                error!("Resource not found");
-                Err(ApiServiceError::NotFound)
-            }
-            status => {
-                error!("Server error with status code: {}", status);
-                Err(ApiServiceError::ServerError(status))
-            }
-        }
+            .map_err(ApiServiceError::from)
     }
 }
 
@@ -163,7 +279,7 @@ where
 }
 
 /// Helper function to get auth token from local storage
-fn get_auth_token() -> Option<String> {
+pub(crate) fn get_auth_token() -> Option<String> {
     LocalStorage::get::<String>(AUTH_TOKEN_KEY).ok()
 }
 
@@ -175,10 +291,39 @@ fn set_auth_token(token: String) -> Result<(), ApiServiceError> {
 
 /// Helper function to remove auth token from local storage
 fn remove_auth_token() -> Result<(), ApiServiceError> {
-    LocalStorage::delete(AUTH_TOKEN_KEY)
+    LocalStorage::delete(AUTH_TOKEN_KEY);
+    LocalStorage::delete(REFRESH_TOKEN_KEY);
+    Ok(())
+}
+
+/// Store both tokens of a new or refreshed session
+fn store_tokens(tokens: &TokenPair) -> Result<(), ApiServiceError> {
+    set_auth_token(tokens.access_token.clone())?;
+    LocalStorage::set(REFRESH_TOKEN_KEY, tokens.refresh_token.clone())
         .map_err(|e| ApiServiceError::SerializationError(e.to_string()))
 }
 
+fn get_refresh_token() -> Option<String> {
+    LocalStorage::get::<String>(REFRESH_TOKEN_KEY).ok()
+}
+
+/// Exchange the refresh token for new tokens. If the server refuses, the
+/// session is over and both tokens are dropped.
+pub async fn refresh_session() -> Result<(), ApiServiceError> {
+    let refresh_token = get_refresh_token().ok_or(ApiServiceError::Unauthorized)?;
+    let body = serde_json::json!({ "refresh_token": refresh_token }).to_string();
+    let response = send_request(&HttpMethod::POST, "/api/auth/refresh", Some(&body)).await?;
+
+    if response.ok() {
+        let tokens = handle_api_response::<TokenPair>(response).await?;
+        store_tokens(&tokens)
+    } else {
+        info!("Session ended; signing out");
+        remove_auth_token()?;
+        Err(ApiServiceError::Unauthorized)
+    }
+}
+
 /// Fetch all posts
 pub async fn get_posts() -> Result<Vec<Post>, ApiServiceError> {
     info!("Fetching all posts...");
@@ -187,11 +332,13 @@ pub async fn get_posts() -> Result<Vec<Post>, ApiServiceError> {
 }
 
 /// Create a new post
-pub async fn create_post(new_post: &Post) -> Result<Post, ApiServiceError> {
+pub async fn create_post(new_post: &NewPost) -> Result<Post, ApiServiceError> {
     info!("Creating new post: {:?}", new_post);
     let body = serde_json::to_string(new_post)?;
     let response = make_request(HttpMethod::POST, "/api/posts", Some(&body)).await?;
-    handle_api_response::<Post>(response).await
+    handle_api_response::<ApiData<Post>>(response)
+        .await
+        .map(|post| post.data)
 }
 
 /// Update an existing post
@@ -218,17 +365,120 @@ pub async fn delete_post(post_id: i32) -> Result<(), ApiServiceError> {
     }
 }
 
-/// Login API call
-pub async fn login(auth_data: AuthData) -> Result<String, ApiServiceError> {
+/// Tags on a post
+pub async fn fetch_post_tags(post_id: i32) -> Result<Vec<TagSummary>, ApiServiceError> {
+    let endpoint = format!("/api/posts/{}/tags", post_id);
+    let response = make_request(HttpMethod::GET, &endpoint, None::<&str>).await?;
+    handle_api_response::<ApiData<Vec<TagSummary>>>(response)
+        .await
+        .map(|tags| tags.data)
+}
+
+/// Give a post exactly these tags, creating the ones that don't exist yet
+pub async fn set_post_tags(post_id: i32, names: &[String]) -> Result<Vec<TagSummary>, ApiServiceError> {
+    info!("Setting tags of post {}: {:?}", post_id, names);
+    let endpoint = format!("/api/posts/{}/tags", post_id);
+    let body = serde_json::to_string(&PostTagsRequest { tags: names.to_vec() })?;
+    let response = make_request(HttpMethod::PUT, &endpoint, Some(&body)).await?;
+    handle_api_response::<ApiData<Vec<TagSummary>>>(response)
+        .await
+        .map(|tags| tags.data)
+}
+
+/// Existing tags starting with `prefix`, most used first
+pub async fn autocomplete_tags(prefix: &str) -> Result<Vec<TagSummary>, ApiServiceError> {
+    let endpoint = format!(
+        "/api/tags/autocomplete?q={}",
+        String::from(js_sys::encode_uri_component(prefix))
+    );
+    let response = make_request(HttpMethod::GET, &endpoint, None::<&str>).await?;
+    handle_api_response::<ApiData<Vec<TagSummary>>>(response)
+        .await
+        .map(|tags| tags.data)
+}
+
+/// Full-text search across posts, pages and comments
+pub async fn search(query: &str, page: i64) -> Result<SearchResults, ApiServiceError> {
+    info!("Searching for {:?} (page {})", query, page);
+    let endpoint = format!(
+        "/api/search?q={}&page={}",
+        String::from(js_sys::encode_uri_component(query)),
+        page
+    );
+    let response = make_request(HttpMethod::GET, &endpoint, None::<&str>).await?;
+    handle_api_response::<ApiData<SearchResults>>(response)
+        .await
+        .map(|results| results.data)
+}
+
+/// Every API key, revoked and expired ones included
+pub async fn fetch_api_keys() -> Result<Vec<ApiKeySummary>, ApiServiceError> {
+    let response = make_request(HttpMethod::GET, "/api/api-keys", None::<&str>).await?;
+    handle_api_response::<ApiData<Vec<ApiKeySummary>>>(response)
+        .await
+        .map(|keys| keys.data)
+}
+
+/// Create an API key. The secret in the result is not shown again. Fails
+/// with `ServerError(422)` for an unknown user or a request without scopes.
+pub async fn create_api_key(request: &NewApiKeyRequest) -> Result<CreatedApiKey, ApiServiceError> {
+    info!("Creating API key {:?}", request.name);
+    let body = serde_json::to_string(request)?;
+    let response = make_request(HttpMethod::POST, "/api/api-keys", Some(&body)).await?;
+    handle_api_response::<ApiData<CreatedApiKey>>(response)
+        .await
+        .map(|created| created.data)
+}
+
+pub async fn revoke_api_key(key_id: i32) -> Result<(), ApiServiceError> {
+    info!("Revoking API key {}", key_id);
+    let endpoint = format!("/api/api-keys/{}", key_id);
+    make_request(HttpMethod::DELETE, &endpoint, None::<&str>).await?;
+    Ok(())
+}
+
+/// A page of the audit log matching `filter`. Fails with `ServerError(403)`
+/// for users who can't view it and `ServerError(422)` for a malformed date.
+pub async fn fetch_audit_log(filter: &AuditFilter, page: i64) -> Result<AuditPage, ApiServiceError> {
+    let mut params = vec![("page", page.to_string())];
+    let text_filters = [
+        ("actor", &filter.actor),
+        ("entity_id", &filter.entity_id),
+        ("from", &filter.from),
+        ("to", &filter.to),
+    ];
+    for (name, value) in text_filters {
+        if let Some(value) = value.as_deref().filter(|value| !value.is_empty()) {
+            params.push((name, value.to_string()));
+        }
+    }
+    if let Some(action) = filter.action {
+        params.push(("action", action.as_str().to_string()));
+    }
+    if let Some(entity_type) = filter.entity_type {
+        params.push(("entity_type", entity_type.as_str().to_string()));
+    }
+    let query = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, String::from(js_sys::encode_uri_component(value))))
+        .collect::<Vec<_>>()
+        .join("&");
+    let endpoint = format!("/api/audit?{}", query);
+    let response = make_request(HttpMethod::GET, &endpoint, None::<&str>).await?;
+    handle_api_response::<ApiData<AuditPage>>(response)
+        .await
+        .map(|page| page.data)
+}
+
+/// Login API call. Users with two-factor authentication finish signing in
+/// with [`verify_login`] or, if they must set it up first, [`enable_two_factor`].
+pub async fn login(auth_data: AuthData) -> Result<LoginStep, ApiServiceError> {
     info!("Attempting to log in user: {}", auth_data.username);
     let body = serde_json::to_string(&auth_data)?;
     let response = make_request(HttpMethod::POST, "/api/auth/login", Some(&body)).await?;
 
     if response.ok() {
-        let token = response.text().await.map_err(ApiServiceError::from)?;
-        info!("User logged in successfully!");
-        set_auth_token(token.clone())?;
-        Ok(token)
+        login_step(handle_api_response::<LoginResponse>(response).await?)
     } else {
         match response.status() {
             401 => {
@@ -243,8 +493,210 @@ pub async fn login(auth_data: AuthData) -> Result<String, ApiServiceError> {
     }
 }
 
-/// Logout API call
-pub fn logout() -> Result<(), ApiServiceError> {
+/// Keep the tokens of a login, or say which second factor step comes next
+fn login_step(response: LoginResponse) -> Result<LoginStep, ApiServiceError> {
+    match response {
+        LoginResponse::Tokens(tokens) => {
+            info!("User logged in successfully!");
+            store_tokens(&tokens)?;
+            Ok(LoginStep::Done)
+        }
+        LoginResponse::SecondFactor { second_factor, mfa_token } if second_factor == "enrol" => {
+            Ok(LoginStep::Enrol { mfa_token })
+        }
+        LoginResponse::SecondFactor { mfa_token, .. } => Ok(LoginStep::VerifyCode { mfa_token }),
+    }
+}
+
+/// Single sign-on providers, and whether password login is open
+pub async fn login_options() -> Result<LoginOptions, ApiServiceError> {
+    let response = make_request(HttpMethod::GET, "/api/auth/oidc/providers", None::<&str>).await?;
+    handle_api_response::<ApiData<LoginOptions>>(response)
+        .await
+        .map(|options| options.data)
+}
+
+/// Page that starts a single sign-on login; the browser navigates there
+pub fn sso_login_url(provider_id: &str) -> String {
+    format!(
+        "{}/api/auth/oidc/{}/login",
+        get_api_base_url(),
+        String::from(js_sys::encode_uri_component(provider_id))
+    )
+}
+
+/// Redeem the code a single sign-on login sent the browser back with. Like
+/// [`login`], this may leave a second factor step to finish.
+pub async fn redeem_sso_code(code: &str) -> Result<LoginStep, ApiServiceError> {
+    let body = serde_json::json!({ "code": code }).to_string();
+    let response = make_request(HttpMethod::POST, "/api/auth/oidc/exchange", Some(&body)).await?;
+    if !response.ok() {
+        error!("Failed to redeem the single sign-on code with status code: {}", response.status());
+        return Err(ApiServiceError::ServerError(response.status()));
+    }
+    info!("User signed in with single sign-on");
+    login_step(handle_api_response::<LoginResponse>(response).await?)
+}
+
+/// Provider page where the signed-in user links their account there to
+/// this one; the browser navigates there
+pub async fn sso_link_url(provider_id: &str) -> Result<String, ApiServiceError> {
+    let endpoint = format!(
+        "/api/auth/oidc/{}/link",
+        String::from(js_sys::encode_uri_component(provider_id))
+    );
+    let response = make_request(HttpMethod::POST, &endpoint, None::<&str>).await?;
+    handle_api_response::<ApiData<SsoLink>>(response)
+        .await
+        .map(|link| link.data.url)
+}
+
+/// Second login step: an authenticator or recovery code
+pub async fn verify_login(mfa_token: &str, code: &str) -> Result<(), ApiServiceError> {
+    let body = serde_json::json!({ "mfa_token": mfa_token, "code": code }).to_string();
+    let response = make_request(HttpMethod::POST, "/api/auth/login/verify", Some(&body)).await?;
+    let tokens = handle_api_response::<TokenPair>(response).await?;
+    info!("User logged in successfully!");
+    store_tokens(&tokens)
+}
+
+/// Whether the current user has two-factor authentication, and must
+pub async fn two_factor_status() -> Result<TwoFactorStatus, ApiServiceError> {
+    let response = make_request(HttpMethod::GET, "/api/auth/2fa", None::<&str>).await?;
+    handle_api_response::<ApiData<TwoFactorStatus>>(response)
+        .await
+        .map(|status| status.data)
+}
+
+/// Start setting up an authenticator, as the signed-in user or with the
+/// `mfa_token` of a login that requires it
+pub async fn begin_two_factor(mfa_token: Option<&str>) -> Result<TwoFactorSetup, ApiServiceError> {
+    let body = serde_json::json!({ "mfa_token": mfa_token }).to_string();
+    let response = make_request(HttpMethod::POST, "/api/auth/2fa/setup", Some(&body)).await?;
+    handle_api_response::<ApiData<TwoFactorSetup>>(response)
+        .await
+        .map(|setup| setup.data)
+}
+
+/// Finish setup with a code from the authenticator, returning the recovery
+/// codes. With an `mfa_token` this also completes the login.
+pub async fn enable_two_factor(code: &str, mfa_token: Option<&str>) -> Result<Vec<String>, ApiServiceError> {
+    let body = serde_json::json!({ "code": code, "mfa_token": mfa_token }).to_string();
+    let response = make_request(HttpMethod::POST, "/api/auth/2fa/enable", Some(&body)).await?;
+    let enabled = handle_api_response::<ApiData<TwoFactorEnabled>>(response).await?.data;
+    if let Some(tokens) = &enabled.tokens {
+        store_tokens(tokens)?;
+    }
+    Ok(enabled.recovery_codes)
+}
+
+pub async fn disable_two_factor(code: &str) -> Result<(), ApiServiceError> {
+    let body = serde_json::json!({ "code": code }).to_string();
+    make_request(HttpMethod::POST, "/api/auth/2fa/disable", Some(&body)).await?;
+    Ok(())
+}
+
+/// Replace the recovery codes; the old ones stop working
+pub async fn regenerate_recovery_codes(code: &str) -> Result<Vec<String>, ApiServiceError> {
+    let body = serde_json::json!({ "code": code }).to_string();
+    let response = make_request(HttpMethod::POST, "/api/auth/2fa/recovery-codes", Some(&body)).await?;
+    handle_api_response::<ApiData<RecoveryCodes>>(response)
+        .await
+        .map(|codes| codes.data.recovery_codes)
+}
+
+/// Ask for a password reset link. The server accepts any address.
+pub async fn request_password_reset(email: &str) -> Result<(), ApiServiceError> {
+    let body = serde_json::json!({ "email": email }).to_string();
+    make_request(HttpMethod::POST, "/api/auth/password/forgot", Some(&body)).await?;
+    Ok(())
+}
+
+/// Choose a new password with the token from a reset link. Fails with
+/// `ServerError(422)` when the password is too short.
+pub async fn reset_password(token: &str, password: &str) -> Result<(), ApiServiceError> {
+    let body = serde_json::json!({ "token": token, "password": password }).to_string();
+    make_request(HttpMethod::POST, "/api/auth/password/reset", Some(&body)).await?;
+    Ok(())
+}
+
+/// Confirm an email address with the token from a verification link
+pub async fn verify_email(token: &str) -> Result<(), ApiServiceError> {
+    let body = serde_json::json!({ "token": token }).to_string();
+    make_request(HttpMethod::POST, "/api/auth/email/verify", Some(&body)).await?;
+    Ok(())
+}
+
+/// Mail the signed-in user a new verification link
+pub async fn resend_verification() -> Result<(), ApiServiceError> {
+    make_request(HttpMethod::POST, "/api/auth/email/verify/send", None::<&str>).await?;
+    Ok(())
+}
+
+/// Logout API call. The local tokens are dropped even if the server can't
+/// be reached.
+pub async fn logout() -> Result<(), ApiServiceError> {
     info!("Logging out user...");
+    if let Some(refresh_token) = get_refresh_token() {
+        let body = serde_json::json!({ "refresh_token": refresh_token }).to_string();
+        if let Err(e) = send_request(&HttpMethod::POST, "/api/auth/logout", Some(&body)).await {
+            error!("Failed to end the session on the server: {}", e);
+        }
+    }
     remove_auth_token()
 }
+
+/// Sign out on every device, including this one
+pub async fn logout_everywhere() -> Result<(), ApiServiceError> {
+    info!("Logging out all sessions...");
+    make_request(HttpMethod::POST, "/api/auth/logout-all", None::<&str>).await?;
+    remove_auth_token()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn new_posts_carry_what_the_backend_requires() {
+        let new_post = NewPost {
+            title: "Hello, World".to_string(),
+            slug: "hello-world".to_string(),
+            content: "<p>Hi</p>".to_string(),
+            status: "draft".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&new_post).unwrap(),
+            json!({
+                "title": "Hello, World",
+                "slug": "hello-world",
+                "content": "<p>Hi</p>",
+                "status": "draft",
+            })
+        );
+    }
+
+    #[test]
+    fn reads_created_posts_from_the_envelope() {
+        // As sent by `POST /posts`
+        let response = json!({
+            "data": {
+                "id": 42,
+                "title": "Hello, World",
+                "slug": "hello-world",
+                "content": "<p>Hi</p>",
+                "status": "draft",
+                "author_id": 7,
+                "created_at": "2024-10-11T09:00:00",
+                "updated_at": "2024-10-11T09:00:00",
+                "publish_at": null,
+                "published_at": null,
+            }
+        });
+        let created = serde_json::from_value::<ApiData<Post>>(response).unwrap().data;
+        assert_eq!(created.id, Some(42));
+        assert_eq!(created.title, "Hello, World");
+        assert_eq!(created.category, "");
+    }
+}
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsValue;
+use reqwasm::http::Request;
+use std::fmt;
+use thiserror::Error;
+use web_sys::console;
+use crate::frontend::services::api_service::get_auth_token;
+use crate::shared::builder::{DocumentError, PageDocument, Section};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LibraryKind {
+    /// Shown live on every page that uses it
+    GlobalBlock,
+    /// Copied into the page on insert
+    SavedSection,
+}
+
+/// A global block or saved section
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LibraryItem {
+    pub id: i32,
+    pub name: String,
+    pub kind: LibraryKind,
+    pub section: Section,
+    #[serde(default)]
+    pub usage_count: i64,
+    pub updated_at: Option<String>,
+}
+
+/// A page that shows a global block
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockUsage {
+    pub page_id: i32,
+    pub title: String,
+    pub slug: String,
+}
+
+/// A page as edited in the builder
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BuilderPage {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+    pub document: PageDocument,
+    pub updated_at: Option<String>,
+}
+
+/// Envelope the backend wraps successful payloads in
+#[derive(Deserialize)]
+struct ApiData<T> {
+    data: T,
+}
+
+/// Body of a 422 response to an invalid document
+#[derive(Deserialize)]
+struct InvalidDocument {
+    details: Vec<DocumentError>,
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = API_BASE_URL)]
+    static API_BASE_URL: JsValue;
+}
+
+fn get_api_base_url() -> String {
+    API_BASE_URL
+        .as_string()
+        .unwrap_or_else(|| "http://localhost:8080".to_string())
+}
+
+#[derive(Debug, Error)]
+pub enum BuilderError {
+    #[error("Request error: {0}")]
+    RequestError(String),
+    #[error("Parse error: {0}")]
+    ParseError(String),
+    #[error("The page document was rejected")]
+    InvalidDocument(Vec<DocumentError>),
+    #[error("Unknown error occurred")]
+    UnknownError,
+}
+
+impl From<reqwasm::Error> for BuilderError {
+    fn from(err: reqwasm::Error) -> Self {
+        BuilderError::RequestError(err.to_string())
+    }
+}
+
+impl From<JsValue> for BuilderError {
+    fn from(_: JsValue) -> Self {
+        BuilderError::UnknownError
+    }
+}
+
+enum HttpMethod {
+    GET,
+    POST,
+    DELETE,
+    PUT,
+    PATCH,
+}
+
+impl HttpMethod {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::GET => "GET",
+            HttpMethod::POST => "POST",
+            HttpMethod::DELETE => "DELETE",
+            HttpMethod::PUT => "PUT",
+            HttpMethod::PATCH => "PATCH",
+        }
+    }
+}
+
+async fn make_request(
+    method: HttpMethod,
+    url: &str,
+    body: Option<impl Into<JsValue>>,
+) -> Result<reqwasm::Response, BuilderError> {
+    let mut request = Request::new(url).method(method.as_str());
+
+    if let Some(token) = get_auth_token() {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+    if let Some(body) = body {
+        request = request
+            .header("Content-Type", "application/json")
+            .body(body);
+    }
+
+    let response = request.send().await.map_err(BuilderError::from)?;
+
+    if response.ok() {
+        Ok(response)
+    } else if response.status() == 422 {
+        let invalid = response
+            .json::<InvalidDocument>()
+            .await
+            .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+        Err(BuilderError::InvalidDocument(invalid.details))
+    } else {
+        Err(BuilderError::RequestError(format!(
+            "Failed with status: {}",
+            response.status()
+        )))
+    }
+}
+
+/// Fetches a builder page and its document
+pub async fn fetch_page(id: i32) -> Result<BuilderPage, BuilderError> {
+    let url = format!("{}/api/builder/pages/{}", get_api_base_url(), id);
+
+    let response = make_request(HttpMethod::GET, &url, None::<JsValue>).await?;
+    let page = response
+        .json::<ApiData<BuilderPage>>()
+        .await
+        .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+
+    Ok(page.data)
+}
+
+/// Creates a new builder page
+pub async fn create_page(title: &str, slug: &str, document: &PageDocument) -> Result<BuilderPage, BuilderError> {
+    let url = format!("{}/api/builder/pages", get_api_base_url());
+    let body = serde_json::json!({ "title": title, "slug": slug, "document": document }).to_string();
+
+    let response = make_request(HttpMethod::POST, &url, Some(body)).await?;
+    let page = response
+        .json::<ApiData<BuilderPage>>()
+        .await
+        .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+
+    Ok(page.data)
+}
+
+/// Saves the document of an existing builder page
+pub async fn save_page_document(id: i32, document: &PageDocument) -> Result<BuilderPage, BuilderError> {
+    let url = format!("{}/api/builder/pages/{}", get_api_base_url(), id);
+    let body = serde_json::json!({ "document": document }).to_string();
+
+    let result = make_request(HttpMethod::PUT, &url, Some(body)).await;
+
+    match result {
+        Ok(response) => {
+            console::log_1(&"Page saved successfully!".into());
+            response
+                .json::<ApiData<BuilderPage>>()
+                .await
+                .map(|page| page.data)
+                .map_err(|e| BuilderError::ParseError(e.to_string()))
+        }
+        Err(e) => {
+            console::error_1(&format!("Failed to save page: {}", e).into());
+            Err(e)
+        }
+    }
+}
+
+/// Fetches the global blocks and saved sections
+pub async fn fetch_library() -> Result<Vec<LibraryItem>, BuilderError> {
+    let url = format!("{}/api/components", get_api_base_url());
+
+    let response = make_request(HttpMethod::GET, &url, None::<JsValue>).await?;
+    let items = response
+        .json::<ApiData<Vec<LibraryItem>>>()
+        .await
+        .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+
+    Ok(items.data)
+}
+
+/// Saves a section to the library
+pub async fn save_library_item(name: &str, kind: LibraryKind, section: &Section) -> Result<LibraryItem, BuilderError> {
+    let url = format!("{}/api/components", get_api_base_url());
+    let body = serde_json::json!({ "name": name, "kind": kind, "section": section }).to_string();
+
+    let response = make_request(HttpMethod::POST, &url, Some(body)).await?;
+    let item = response
+        .json::<ApiData<LibraryItem>>()
+        .await
+        .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+
+    Ok(item.data)
+}
+
+/// Replaces the section of a library item; pages using a global block show the change
+pub async fn update_library_item(id: i32, section: &Section) -> Result<LibraryItem, BuilderError> {
+    let url = format!("{}/api/components/{}", get_api_base_url(), id);
+    let body = serde_json::json!({ "section": section }).to_string();
+
+    let response = make_request(HttpMethod::PUT, &url, Some(body)).await?;
+    let item = response
+        .json::<ApiData<LibraryItem>>()
+        .await
+        .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+
+    Ok(item.data)
+}
+
+/// Deletes a library item by ID. Global blocks still in use are refused.
+pub async fn delete_library_item(id: i32) -> Result<(), BuilderError> {
+    let url = format!("{}/api/components/{}", get_api_base_url(), id);
+
+    let result = make_request(HttpMethod::DELETE, &url, None::<JsValue>).await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            console::error_1(&format!("Failed to delete library item: {}", e).into());
+            Err(e)
+        }
+    }
+}
+
+/// Fetches the pages that show a global block
+pub async fn fetch_block_usage(id: i32) -> Result<Vec<BlockUsage>, BuilderError> {
+    let url = format!("{}/api/components/{}/usage", get_api_base_url(), id);
+
+    let response = make_request(HttpMethod::GET, &url, None::<JsValue>).await?;
+    let pages = response
+        .json::<ApiData<Vec<BlockUsage>>>()
+        .await
+        .map_err(|e| BuilderError::ParseError(e.to_string()))?;
+
+    Ok(pages.data)
+}
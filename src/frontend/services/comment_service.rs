@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsValue;
+use reqwasm::http::Request;
+use thiserror::Error;
+use web_sys::console;
+use crate::frontend::services::api_service::get_auth_token;
+
+/// One spam filter's opinion of a comment
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpamVerdict {
+    pub filter: String,
+    /// `pass`, `hold`, `spam` or `throttle`
+    pub outcome: String,
+    pub score: Option<f64>,
+    pub reason: String,
+}
+
+/// A comment as seen by moderators
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub id: i32,
+    pub post_id: i32,
+    pub user_id: Option<i32>,
+    pub parent_id: Option<i32>,
+    pub content: String,
+    pub status: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub author_ip: Option<String>,
+    #[serde(default)]
+    pub spam_verdicts: Vec<SpamVerdict>,
+    pub created_at: String,
+}
+
+/// A new comment or reply
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NewComment {
+    pub post_id: i32,
+    pub parent_id: Option<i32>,
+    pub content: String,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Honeypot; must stay empty, so comment forms keep it hidden
+    pub website: String,
+}
+
+/// Number of comments in each moderation state
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct StatusCounts {
+    pub pending: i64,
+    pub approved: i64,
+    pub spam: i64,
+    pub trash: i64,
+}
+
+/// A page of the moderation queue
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CommentPage {
+    pub items: Vec<Comment>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub counts: StatusCounts,
+}
+
+/// Envelope the backend wraps successful payloads in
+#[derive(Deserialize)]
+struct ApiData<T> {
+    data: T,
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = API_BASE_URL)]
+    static API_BASE_URL: JsValue;
+}
+
+fn get_api_base_url() -> String {
+    API_BASE_URL
+        .as_string()
+        .unwrap_or_else(|| "http://localhost:8080".to_string())
+}
+
+#[derive(Debug, Error)]
+pub enum CommentError {
+    #[error("Request error: {0}")]
+    RequestError(String),
+    #[error("Parse error: {0}")]
+    ParseError(String),
+    #[error("Unknown error occurred")]
+    UnknownError,
+}
+
+impl From<reqwasm::Error> for CommentError {
+    fn from(err: reqwasm::Error) -> Self {
+        CommentError::RequestError(err.to_string())
+    }
+}
+
+impl From<JsValue> for CommentError {
+    fn from(_: JsValue) -> Self {
+        CommentError::UnknownError
+    }
+}
+
+enum HttpMethod {
+    GET,
+    POST,
+    DELETE,
+    PUT,
+    PATCH,
+}
+
+impl HttpMethod {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::GET => "GET",
+            HttpMethod::POST => "POST",
+            HttpMethod::DELETE => "DELETE",
+            HttpMethod::PUT => "PUT",
+            HttpMethod::PATCH => "PATCH",
+        }
+    }
+}
+
+async fn make_request(
+    method: HttpMethod,
+    url: &str,
+    body: Option<impl Into<JsValue>>,
+) -> Result<reqwasm::Response, CommentError> {
+    let mut request = Request::new(url).method(method.as_str());
+
+    if let Some(token) = get_auth_token() {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+    if let Some(body) = body {
+        request = request
+            .header("Content-Type", "application/json")
+            .body(body);
+    }
+
+    let response = request.send().await.map_err(CommentError::from)?;
+
+    if response.ok() {
+        Ok(response)
+    } else {
+        Err(CommentError::RequestError(format!(
+            "Failed with status: {}",
+            response.status()
+        )))
+    }
+}
+
+/// Sends a POST request to save a new comment on the server.
+pub async fn save_comment(comment: NewComment) -> Result<Comment, CommentError> {
+    let url = format!("{}/api/comments", get_api_base_url());
+    let body = serde_json::to_string(&comment)
+        .map_err(|e| CommentError::ParseError(e.to_string()))?;
+
+    let result = make_request(HttpMethod::POST, &url, Some(body)).await;
+
+    match result {
+        Ok(response) => {
+            console::log_1(&"Comment saved successfully!".into());
+            response
+                .json::<ApiData<Comment>>()
+                .await
+                .map(|saved| saved.data)
+                .map_err(|e| CommentError::ParseError(e.to_string()))
+        }
+        Err(e) => {
+            console::error_1(&format!("Failed to save comment: {}", e).into());
+            Err(e)
+        }
+    }
+}
+
+/// Fetches a page of the moderation queue, optionally narrowed to one status or post.
+pub async fn fetch_moderation_queue(
+    status: Option<&str>,
+    post_id: Option<i32>,
+    page: i64,
+) -> Result<CommentPage, CommentError> {
+    let mut url = format!("{}/api/comments?page={}", get_api_base_url(), page);
+    if let Some(status) = status {
+        url.push_str(&format!("&status={}", status));
+    }
+    if let Some(post_id) = post_id {
+        url.push_str(&format!("&post_id={}", post_id));
+    }
+
+    let response = make_request(HttpMethod::GET, &url, None::<JsValue>).await?;
+    let queue = response
+        .json::<ApiData<CommentPage>>()
+        .await
+        .map_err(|e| CommentError::ParseError(e.to_string()))?;
+
+    Ok(queue.data)
+}
+
+/// Applies a moderation action (`approve`, `unapprove`, `reject` or `spam`) to several comments.
+pub async fn bulk_moderate(action: &str, ids: &[i32]) -> Result<(), CommentError> {
+    let url = format!("{}/api/comments/bulk/{}", get_api_base_url(), action);
+    let body = serde_json::json!({ "ids": ids }).to_string();
+
+    let result = make_request(HttpMethod::POST, &url, Some(body)).await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            console::error_1(&format!("Failed to {} comments: {}", action, e).into());
+            Err(e)
+        }
+    }
+}
+
+/// Deletes a comment by sending a DELETE request to the server.
+pub async fn delete_comment(id: i32) -> Result<(), CommentError> {
+    let url = format!("{}/api/comments/{}", get_api_base_url(), id);
+
+    let result = make_request(HttpMethod::DELETE, &url, None::<JsValue>).await;
+
+    match result {
+        Ok(_) => {
+            console::log_1(&"Comment deleted successfully!".into());
+            Ok(())
+        }
+        Err(e) => {
+            console::error_1(&format!("Failed to delete comment: {}", e).into());
+            Err(e)
+        }
+    }
+}
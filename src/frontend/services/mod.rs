@@ -1,3 +1,7 @@
 // src/frontend/services/mod.rs (or src/frontend/services.rs)
 
 pub mod api_service; // assuming api_service exists in services
+pub mod builder_service;
+pub mod comment_service;
+pub mod media_service;
+pub mod settings_service;
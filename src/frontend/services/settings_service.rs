@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsValue;
+use reqwasm::http::Request;
+use thiserror::Error;
+use web_sys::console;
+use crate::frontend::services::api_service::get_auth_token;
+use crate::shared::settings::{SettingError, SettingGroupValues};
+
+/// Envelope the backend wraps successful payloads in
+#[derive(Deserialize)]
+struct ApiData<T> {
+    data: T,
+}
+
+/// Body of `DELETE /settings/:key`
+#[derive(Deserialize)]
+struct ResetResponse {
+    value: Value,
+}
+
+/// Body of a 422 response: one entry per rejected setting
+#[derive(Deserialize)]
+struct InvalidResponse {
+    details: Vec<SettingError>,
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = API_BASE_URL)]
+    static API_BASE_URL: JsValue;
+}
+
+fn get_api_base_url() -> String {
+    API_BASE_URL
+        .as_string()
+        .unwrap_or_else(|| "http://localhost:8080".to_string())
+}
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("Request error: {0}")]
+    RequestError(String),
+    /// The backend rejected some values; nothing was saved
+    #[error("Invalid settings")]
+    Invalid(Vec<SettingError>),
+    #[error("Parse error: {0}")]
+    ParseError(String),
+    #[error("Unknown error occurred")]
+    UnknownError,
+}
+
+impl From<reqwasm::Error> for SettingsError {
+    fn from(err: reqwasm::Error) -> Self {
+        SettingsError::RequestError(err.to_string())
+    }
+}
+
+impl From<JsValue> for SettingsError {
+    fn from(_: JsValue) -> Self {
+        SettingsError::UnknownError
+    }
+}
+
+enum HttpMethod {
+    GET,
+    POST,
+    DELETE,
+    PUT,
+    PATCH,
+}
+
+impl HttpMethod {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::GET => "GET",
+            HttpMethod::POST => "POST",
+            HttpMethod::DELETE => "DELETE",
+            HttpMethod::PUT => "PUT",
+            HttpMethod::PATCH => "PATCH",
+        }
+    }
+}
+
+async fn make_request(
+    method: HttpMethod,
+    url: &str,
+    body: Option<impl Into<JsValue>>,
+) -> Result<reqwasm::Response, SettingsError> {
+    let mut request = Request::new(url).method(method.as_str());
+
+    if let Some(token) = get_auth_token() {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+    if let Some(body) = body {
+        request = request
+            .header("Content-Type", "application/json")
+            .body(body);
+    }
+
+    let response = request.send().await.map_err(SettingsError::from)?;
+
+    if response.ok() {
+        Ok(response)
+    } else if response.status() == 422 {
+        let rejected = response
+            .json::<InvalidResponse>()
+            .await
+            .map_err(|e| SettingsError::ParseError(e.to_string()))?;
+        Err(SettingsError::Invalid(rejected.details))
+    } else {
+        Err(SettingsError::RequestError(format!(
+            "Failed with status: {}",
+            response.status()
+        )))
+    }
+}
+
+/// Fetches every setting, grouped, with its schema and current value.
+pub async fn fetch_settings() -> Result<Vec<SettingGroupValues>, SettingsError> {
+    let url = format!("{}/api/settings", get_api_base_url());
+
+    let response = make_request(HttpMethod::GET, &url, None::<JsValue>).await?;
+    let groups = response
+        .json::<ApiData<Vec<SettingGroupValues>>>()
+        .await
+        .map_err(|e| SettingsError::ParseError(e.to_string()))?;
+
+    Ok(groups.data)
+}
+
+/// Saves the given settings; the backend saves all of them or none.
+pub async fn save_settings(values: &BTreeMap<String, Value>) -> Result<Vec<SettingGroupValues>, SettingsError> {
+    let url = format!("{}/api/settings", get_api_base_url());
+    let body = serde_json::to_string(values)
+        .map_err(|e| SettingsError::ParseError(e.to_string()))?;
+
+    let result = make_request(HttpMethod::PUT, &url, Some(body)).await;
+
+    match result {
+        Ok(response) => {
+            console::log_1(&"Settings saved successfully!".into());
+            response
+                .json::<ApiData<Vec<SettingGroupValues>>>()
+                .await
+                .map(|saved| saved.data)
+                .map_err(|e| SettingsError::ParseError(e.to_string()))
+        }
+        Err(e) => {
+            console::error_1(&format!("Failed to save settings: {}", e).into());
+            Err(e)
+        }
+    }
+}
+
+/// Resets one setting to its default, returning the default.
+pub async fn reset_setting(key: &str) -> Result<Value, SettingsError> {
+    let url = format!("{}/api/settings/{}", get_api_base_url(), key);
+
+    let response = make_request(HttpMethod::DELETE, &url, None::<JsValue>).await?;
+    let reset = response
+        .json::<ApiData<ResetResponse>>()
+        .await
+        .map_err(|e| SettingsError::ParseError(e.to_string()))?;
+
+    Ok(reset.data.value)
+}
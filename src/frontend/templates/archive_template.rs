@@ -0,0 +1,32 @@
+use yew::prelude::*;
+use crate::frontend::components::post_item::PostItem;
+use crate::shared::types::PostSummary;
+
+#[derive(Properties, PartialEq)]
+pub struct ArchiveTemplateProps {
+    pub posts: Vec<PostSummary>,
+}
+
+#[function_component(ArchiveTemplate)]
+pub fn archive_template(props: &ArchiveTemplateProps) -> Html {
+    html! {
+        <div class="archive-template">
+            <h1>{ "Blog" }</h1>
+            if props.posts.is_empty() {
+                <p>{ "Nothing published yet." }</p>
+            } else {
+                <ul>
+                    { for props.posts.iter().map(|post| html! {
+                        <li>
+                            <PostItem
+                                title={post.title.clone()}
+                                summary={post.excerpt.clone()}
+                                href={format!("/blog/{}", post.slug)}
+                            />
+                        </li>
+                    }) }
+                </ul>
+            }
+        </div>
+    }
+}
@@ -1,15 +1,33 @@
 use yew::prelude::*;
+use crate::frontend::components::post_item::PostItem;
+use crate::shared::types::{PostSummary, TermView};
+
+#[derive(Properties, PartialEq)]
+pub struct CategoryTemplateProps {
+    pub category: TermView,
+    pub posts: Vec<PostSummary>,
+}
 
 #[function_component(CategoryTemplate)]
-pub fn category_template() -> Html {
+pub fn category_template(props: &CategoryTemplateProps) -> Html {
     html! {
         <div class="category-template">
-            <h1>{ "Category Name" }</h1>
-            <ul>
-                <li>{ "Post 1" }</li>
-                <li>{ "Post 2" }</li>
-                <li>{ "Post 3" }</li>
-            </ul>
+            <h1>{ &props.category.name }</h1>
+            if props.posts.is_empty() {
+                <p>{ "No posts in this category yet." }</p>
+            } else {
+                <ul>
+                    { for props.posts.iter().map(|post| html! {
+                        <li>
+                            <PostItem
+                                title={post.title.clone()}
+                                summary={post.excerpt.clone()}
+                                href={format!("/blog/{}", post.slug)}
+                            />
+                        </li>
+                    }) }
+                </ul>
+            }
         </div>
     }
 }
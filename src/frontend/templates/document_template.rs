@@ -0,0 +1,88 @@
+use yew::prelude::*;
+use crate::frontend::components::raw_html::RawHtml;
+use crate::shared::builder::{compile_events, module_class, Module, ModuleKind, PageDocument, GRID_UNITS};
+
+#[derive(Properties, PartialEq)]
+pub struct DocumentTemplateProps {
+    pub document: PageDocument,
+    /// Screen width to lay the page out for, in pixels, instead of letting
+    /// media queries follow the browser window
+    #[prop_or_default]
+    pub viewport: Option<u16>,
+}
+
+/// Raw-HTML slot holding a text module's markup, filled by the server renderer
+pub fn text_slot(module_id: &str) -> String {
+    format!("text-{}", module_id)
+}
+
+fn render_module(module: &Module) -> Html {
+    let class = classes!("bp-module", format!("bp-{}", module.kind.type_name()), module_class(&module.id));
+    // Read by the page runtime; Yew escapes attribute values
+    let events = compile_events(module);
+    let id = module.id.clone();
+
+    match &module.kind {
+        ModuleKind::Text { html } => html! {
+            <div key={module.id.clone()} {class} data-bp-id={id} data-bp-events={events}>
+                <RawHtml slot={text_slot(&module.id)} html={html.clone()} />
+            </div>
+        },
+        ModuleKind::Image { src, alt, .. } => html! {
+            <div key={module.id.clone()} {class} data-bp-id={id} data-bp-events={events}>
+                if src.is_empty() {
+                    <div class="bp-image-placeholder">{ "Image" }</div>
+                } else {
+                    <img src={src.clone()} alt={alt.clone()} loading="lazy" />
+                }
+            </div>
+        },
+        ModuleKind::Button { label, url, new_tab } => html! {
+            <div key={module.id.clone()} {class} data-bp-id={id} data-bp-events={events}>
+                <a class="bp-button-link" href={url.clone()}
+                    target={new_tab.then(|| "_blank")}
+                    rel={new_tab.then(|| "noopener noreferrer")}>
+                    { label }
+                </a>
+            </div>
+        },
+    }
+}
+
+/// Renders a builder document. Used by the public page template and by the
+/// builder's live preview, so both show the same markup.
+#[function_component(DocumentTemplate)]
+pub fn document_template(props: &DocumentTemplateProps) -> Html {
+    // Module styles, compiled from the document's validated declarations
+    let css = props.document.compile_css(props.viewport);
+    let modals = props.document.modal_sections();
+
+    html! {
+        <div class="builder-page">
+            if !css.is_empty() {
+                <style>{ css }</style>
+            }
+            {
+                for props.document.sections.iter().map(|section| html! {
+                    <section key={section.id.clone()} id={section.id.clone()} data-bp-id={section.id.clone()}
+                        class={classes!("bp-section", modals.contains(section.id.as_str()).then(|| "bp-modal"))}>
+                        {
+                            for section.rows.iter().map(|row| html! {
+                                <div key={row.id.clone()} class="bp-row">
+                                    {
+                                        for row.columns.iter().map(|column| html! {
+                                            <div key={column.id.clone()}
+                                                class={classes!("bp-column", format!("bp-col-{}", column.width.min(GRID_UNITS)))}>
+                                                { for column.modules.iter().map(render_module) }
+                                            </div>
+                                        })
+                                    }
+                                </div>
+                            })
+                        }
+                    </section>
+                })
+            }
+        </div>
+    }
+}
@@ -0,0 +1,12 @@
+// src/frontend/templates/mod.rs
+//
+// Public-site templates. They are rendered to HTML on the server by the
+// backend's render service and can also be mounted in the browser. The
+// active theme may override any of them (see `theme_service`).
+
+pub mod archive_template;
+pub mod category_template;
+pub mod document_template;
+pub mod page_template;
+pub mod post_template;
+pub mod tag_template;
@@ -1,13 +1,25 @@
 use yew::prelude::*;
+use crate::frontend::components::raw_html::RawHtml;
+use crate::frontend::templates::document_template::DocumentTemplate;
+use crate::shared::types::PageView;
+
+#[derive(Properties, PartialEq)]
+pub struct PageTemplateProps {
+    pub page: PageView,
+}
 
 #[function_component(PageTemplate)]
-pub fn page_template() -> Html {
+pub fn page_template(props: &PageTemplateProps) -> Html {
+    let page = &props.page;
+
     html! {
         <div class="page-template">
-            <h1>{ "Page Title" }</h1>
-            <div class="page-content">
-                { "Page content goes here." }
-            </div>
+            <h1>{ &page.title }</h1>
+            if let Some(document) = &page.document {
+                <DocumentTemplate document={document.clone()} />
+            } else {
+                <RawHtml class={classes!("page-content")} slot="content" html={page.content.clone()} />
+            }
         </div>
     }
 }
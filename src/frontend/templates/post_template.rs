@@ -1,13 +1,30 @@
 use yew::prelude::*;
+use crate::frontend::components::raw_html::RawHtml;
+use crate::shared::types::PostView;
+
+#[derive(Properties, PartialEq)]
+pub struct PostTemplateProps {
+    pub post: PostView,
+}
 
 #[function_component(PostTemplate)]
-pub fn post_template() -> Html {
+pub fn post_template(props: &PostTemplateProps) -> Html {
+    let post = &props.post;
+
     html! {
-        <div class="post-template">
-            <h1>{ "Post Title" }</h1>
-            <div class="post-content">
-                { "Post content goes here." }
-            </div>
-        </div>
+        <article class="post-template">
+            <h1>{ &post.title }</h1>
+            if let Some(published_at) = &post.published_at {
+                <time class="post-date" datetime={published_at.clone()}>{ published_at }</time>
+            }
+            <RawHtml class={classes!("post-content")} slot="content" html={post.content.clone()} />
+            if !post.tags.is_empty() {
+                <ul class="post-tags">
+                    { for post.tags.iter().map(|tag| html! {
+                        <li><a href={format!("/tag/{}", tag.slug)}>{ &tag.name }</a></li>
+                    }) }
+                </ul>
+            }
+        </article>
     }
 }
@@ -1,15 +1,33 @@
 use yew::prelude::*;
+use crate::frontend::components::post_item::PostItem;
+use crate::shared::types::{PostSummary, TermView};
+
+#[derive(Properties, PartialEq)]
+pub struct TagTemplateProps {
+    pub tag: TermView,
+    pub posts: Vec<PostSummary>,
+}
 
 #[function_component(TagTemplate)]
-pub fn tag_template() -> Html {
+pub fn tag_template(props: &TagTemplateProps) -> Html {
     html! {
         <div class="tag-template">
-            <h1>{ "Tag Name" }</h1>
-            <ul>
-                <li>{ "Post 1" }</li>
-                <li>{ "Post 2" }</li>
-                <li>{ "Post 3" }</li>
-            </ul>
+            <h1>{ &props.tag.name }</h1>
+            if props.posts.is_empty() {
+                <p>{ "No posts with this tag yet." }</p>
+            } else {
+                <ul>
+                    { for props.posts.iter().map(|post| html! {
+                        <li>
+                            <PostItem
+                                title={post.title.clone()}
+                                summary={post.excerpt.clone()}
+                                href={format!("/blog/{}", post.slug)}
+                            />
+                        </li>
+                    }) }
+                </ul>
+            }
         </div>
     }
 }
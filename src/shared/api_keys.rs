@@ -0,0 +1,232 @@
+// src/shared/api_keys.rs
+//
+// API keys let scripts call the API without logging in. A key acts as a user
+// and, on top of that user's role, is limited to its scopes: one per area of
+// the API and kind of access, e.g. `read:posts` or `write:media`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Part of the API a scope covers, named after its URL prefix
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiResource {
+    /// Posts and their tags
+    Posts,
+    Media,
+    Categories,
+    Comments,
+    /// Pages in the builder, and the global blocks and sections they use
+    Builder,
+    Settings,
+    Search,
+    Themes,
+}
+
+impl ApiResource {
+    pub const ALL: [ApiResource; 8] = [
+        ApiResource::Posts,
+        ApiResource::Media,
+        ApiResource::Categories,
+        ApiResource::Comments,
+        ApiResource::Builder,
+        ApiResource::Settings,
+        ApiResource::Search,
+        ApiResource::Themes,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiResource::Posts => "posts",
+            ApiResource::Media => "media",
+            ApiResource::Categories => "categories",
+            ApiResource::Comments => "comments",
+            ApiResource::Builder => "builder",
+            ApiResource::Settings => "settings",
+            ApiResource::Search => "search",
+            ApiResource::Themes => "themes",
+        }
+    }
+
+    /// The resource a request path belongs to, from its first segment
+    pub fn from_path(path: &str) -> Option<ApiResource> {
+        match path.trim_start_matches('/').split('/').next()? {
+            "posts" | "tags" => Some(ApiResource::Posts),
+            "media" => Some(ApiResource::Media),
+            "categories" => Some(ApiResource::Categories),
+            "comments" => Some(ApiResource::Comments),
+            "builder" | "components" => Some(ApiResource::Builder),
+            "settings" => Some(ApiResource::Settings),
+            "search" => Some(ApiResource::Search),
+            "themes" => Some(ApiResource::Themes),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a request only reads or also changes something
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiAccess {
+    Read,
+    Write,
+}
+
+/// Permission for one kind of access to one resource. Written
+/// `<access>:<resource>`; `write` includes `read`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct ApiScope {
+    pub access: ApiAccess,
+    pub resource: ApiResource,
+}
+
+impl ApiScope {
+    /// Every scope a key can be given
+    pub fn all() -> Vec<ApiScope> {
+        ApiResource::ALL
+            .iter()
+            .flat_map(|resource| {
+                [ApiAccess::Read, ApiAccess::Write]
+                    .into_iter()
+                    .map(move |access| ApiScope { access, resource: *resource })
+            })
+            .collect()
+    }
+
+    pub fn allows(&self, resource: ApiResource, access: ApiAccess) -> bool {
+        self.resource == resource && (self.access == ApiAccess::Write || access == ApiAccess::Read)
+    }
+}
+
+impl fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access = match self.access {
+            ApiAccess::Read => "read",
+            ApiAccess::Write => "write",
+        };
+        write!(f, "{}:{}", access, self.resource.as_str())
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (access, resource) = s.split_once(':').ok_or_else(|| format!("Invalid scope: {}", s))?;
+        let access = match access {
+            "read" => ApiAccess::Read,
+            "write" => ApiAccess::Write,
+            _ => return Err(format!("Invalid scope: {}", s)),
+        };
+        let resource = ApiResource::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == resource)
+            .ok_or_else(|| format!("Invalid scope: {}", s))?;
+        Ok(ApiScope { access, resource })
+    }
+}
+
+impl TryFrom<String> for ApiScope {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ApiScope> for String {
+    fn from(scope: ApiScope) -> Self {
+        scope.to_string()
+    }
+}
+
+/// Whether scopes allow a request
+pub fn scopes_allow(scopes: &[ApiScope], resource: ApiResource, access: ApiAccess) -> bool {
+    scopes.iter().any(|scope| scope.allows(resource, access))
+}
+
+/// Who a key acts as
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ApiKeyOwner {
+    /// Acts as the user, with whatever role they have
+    Personal { username: String },
+    /// Acts with a fixed role; changes are attributed to the admin who
+    /// created it
+    Site { role: String },
+}
+
+/// An API key as listed to admins. The key itself is only shown once, when
+/// it is created. Times are UTC, formatted for display.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ApiKeySummary {
+    pub id: i32,
+    pub name: String,
+    /// First characters of the key, to tell keys apart
+    pub prefix: String,
+    pub owner: ApiKeyOwner,
+    pub scopes: Vec<ApiScope>,
+    pub created_by: Option<String>,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+    pub created_at: String,
+}
+
+/// Body of `POST /api-keys`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewApiKeyRequest {
+    pub name: String,
+    pub owner: ApiKeyOwner,
+    pub scopes: Vec<ApiScope>,
+    /// Never expires when absent
+    #[serde(default)]
+    pub expires_in_days: Option<u32>,
+}
+
+/// A newly created key, with the secret the caller must save now
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CreatedApiKey {
+    pub key: ApiKeySummary,
+    pub secret: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_round_trip_through_strings() {
+        for scope in ApiScope::all() {
+            assert_eq!(scope.to_string().parse::<ApiScope>(), Ok(scope));
+        }
+        assert_eq!(
+            serde_json::to_string(&"write:media".parse::<ApiScope>().unwrap()).unwrap(),
+            "\"write:media\""
+        );
+        assert!("delete:posts".parse::<ApiScope>().is_err());
+        assert!("read:users".parse::<ApiScope>().is_err());
+        assert!("posts".parse::<ApiScope>().is_err());
+    }
+
+    #[test]
+    fn write_scopes_include_read() {
+        let scopes: Vec<ApiScope> = vec!["read:posts".parse().unwrap(), "write:media".parse().unwrap()];
+        assert!(scopes_allow(&scopes, ApiResource::Posts, ApiAccess::Read));
+        assert!(!scopes_allow(&scopes, ApiResource::Posts, ApiAccess::Write));
+        assert!(scopes_allow(&scopes, ApiResource::Media, ApiAccess::Read));
+        assert!(scopes_allow(&scopes, ApiResource::Media, ApiAccess::Write));
+        assert!(!scopes_allow(&scopes, ApiResource::Settings, ApiAccess::Read));
+    }
+
+    #[test]
+    fn paths_map_to_resources() {
+        assert_eq!(ApiResource::from_path("/posts/12"), Some(ApiResource::Posts));
+        assert_eq!(ApiResource::from_path("/tags/autocomplete"), Some(ApiResource::Posts));
+        assert_eq!(ApiResource::from_path("/components/blocks"), Some(ApiResource::Builder));
+        assert_eq!(ApiResource::from_path("/api-keys"), None);
+        assert_eq!(ApiResource::from_path("/"), None);
+    }
+}
@@ -0,0 +1,201 @@
+// src/shared/audit.rs
+//
+// The audit log records who changed what: each entry names the user (and API
+// key) behind a request, the address it came from, the action, the record it
+// touched, and the fields that changed.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What was done
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    /// An earlier revision was made current again
+    Restore,
+    /// Sessions or an API key were ended early
+    Revoke,
+    /// Failed logins locked a username or address
+    Lockout,
+    Unlock,
+    Activate,
+    Rollback,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 9] = [
+        AuditAction::Create,
+        AuditAction::Update,
+        AuditAction::Delete,
+        AuditAction::Restore,
+        AuditAction::Revoke,
+        AuditAction::Lockout,
+        AuditAction::Unlock,
+        AuditAction::Activate,
+        AuditAction::Rollback,
+    ];
+
+    /// Value stored in `audit_log.action`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Revoke => "revoke",
+            AuditAction::Lockout => "lockout",
+            AuditAction::Unlock => "unlock",
+            AuditAction::Activate => "activate",
+            AuditAction::Rollback => "rollback",
+        }
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuditAction::ALL
+            .into_iter()
+            .find(|action| action.as_str() == s)
+            .ok_or_else(|| format!("Unknown audit action: {}", s))
+    }
+}
+
+/// Kind of record an entry is about
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    Post,
+    /// A page made in the builder
+    Page,
+    Media,
+    Category,
+    Tag,
+    User,
+    Comment,
+    Setting,
+    /// A global block or saved section in the builder library
+    Component,
+    Theme,
+    ApiKey,
+    /// A username or address throttled by failed logins, as `account:<name>`
+    /// or `ip:<address>`
+    Login,
+}
+
+impl AuditEntity {
+    pub const ALL: [AuditEntity; 12] = [
+        AuditEntity::Post,
+        AuditEntity::Page,
+        AuditEntity::Media,
+        AuditEntity::Category,
+        AuditEntity::Tag,
+        AuditEntity::User,
+        AuditEntity::Comment,
+        AuditEntity::Setting,
+        AuditEntity::Component,
+        AuditEntity::Theme,
+        AuditEntity::ApiKey,
+        AuditEntity::Login,
+    ];
+
+    /// Value stored in `audit_log.entity_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Post => "post",
+            AuditEntity::Page => "page",
+            AuditEntity::Media => "media",
+            AuditEntity::Category => "category",
+            AuditEntity::Tag => "tag",
+            AuditEntity::User => "user",
+            AuditEntity::Comment => "comment",
+            AuditEntity::Setting => "setting",
+            AuditEntity::Component => "component",
+            AuditEntity::Theme => "theme",
+            AuditEntity::ApiKey => "api_key",
+            AuditEntity::Login => "login",
+        }
+    }
+}
+
+impl FromStr for AuditEntity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuditEntity::ALL
+            .into_iter()
+            .find(|entity| entity.as_str() == s)
+            .ok_or_else(|| format!("Unknown audit entity: {}", s))
+    }
+}
+
+/// One audit log entry. `before` and `after` hold the whole record for
+/// creations and deletions, and only the changed fields for updates. Times
+/// are UTC, formatted for display.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: i32,
+    /// Username of the actor at the time; `None` for the system or anonymous visitors
+    pub actor: Option<String>,
+    pub actor_id: Option<i32>,
+    /// Set when the request was made with an API key
+    pub api_key_id: Option<i32>,
+    pub ip: Option<String>,
+    pub action: AuditAction,
+    pub entity_type: AuditEntity,
+    pub entity_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: String,
+}
+
+/// A page of the audit log, newest entries first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditPage {
+    pub items: Vec<AuditEntry>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+/// Filters of `GET /audit`. Dates are `YYYY-MM-DD`, UTC, inclusive.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AuditFilter {
+    /// Username of the actor
+    #[serde(default)]
+    pub actor: Option<String>,
+    #[serde(default)]
+    pub action: Option<AuditAction>,
+    #[serde(default)]
+    pub entity_type: Option<AuditEntity>,
+    #[serde(default)]
+    pub entity_id: Option<String>,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for action in AuditAction::ALL {
+            assert_eq!(action.as_str().parse::<AuditAction>(), Ok(action));
+            assert_eq!(serde_json::to_value(action).unwrap(), Value::from(action.as_str()));
+        }
+        for entity in AuditEntity::ALL {
+            assert_eq!(entity.as_str().parse::<AuditEntity>(), Ok(entity));
+            assert_eq!(serde_json::to_value(entity).unwrap(), Value::from(entity.as_str()));
+        }
+        assert!("drop".parse::<AuditAction>().is_err());
+    }
+}
@@ -0,0 +1,989 @@
+// src/shared/builder.rs
+//
+// The page builder's document: a versioned tree of sections, rows, columns
+// and modules. The builder edits it in the browser, the backend validates
+// and stores it, and both render it with the same templates.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fmt::Write;
+
+/// Format version written by this build. Bump it, and teach the backend to
+/// upgrade older documents, whenever the shape below changes incompatibly.
+pub const DOCUMENT_VERSION: u32 = 1;
+
+pub const MAX_SECTIONS: usize = 50;
+pub const MAX_ROWS_PER_SECTION: usize = 20;
+pub const MAX_COLUMNS_PER_ROW: usize = 6;
+pub const MAX_MODULES_PER_COLUMN: usize = 50;
+pub const MAX_MODULES: usize = 500;
+/// Columns are sized on a 12-unit grid
+pub const GRID_UNITS: u8 = 12;
+
+const MAX_ID_CHARS: usize = 64;
+const MAX_NAME_CHARS: usize = 100;
+const MAX_TEXT_CHARS: usize = 20_000;
+const MAX_LABEL_CHARS: usize = 100;
+const MAX_ALT_CHARS: usize = 300;
+const MAX_URL_CHARS: usize = 2048;
+const MAX_STYLE_VALUE_CHARS: usize = 64;
+/// Style overrides a module may have, one per breakpoint
+const MAX_BREAKPOINTS: usize = 6;
+/// Event bindings per module, and actions per binding
+const MAX_BINDINGS: usize = 5;
+const MAX_ACTIONS: usize = 10;
+const MAX_CLASS_CHARS: usize = 64;
+const MAX_EVENT_NAME_CHARS: usize = 64;
+const MAX_EVENT_PROPERTIES: usize = 10;
+const MAX_EVENT_VALUE_CHARS: usize = 100;
+/// Range accepted for custom breakpoint widths, in pixels
+pub const MIN_CUSTOM_WIDTH: u16 = 240;
+pub const MAX_CUSTOM_WIDTH: u16 = 2560;
+
+/// CSS properties a module may set on itself
+pub const STYLE_PROPERTIES: &[&str] = &[
+    "background-color",
+    "border-radius",
+    "color",
+    "font-size",
+    "font-weight",
+    "margin",
+    "max-width",
+    "padding",
+    "text-align",
+    "width",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PageDocument {
+    pub version: u32,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+}
+
+impl Default for PageDocument {
+    fn default() -> Self {
+        Self {
+            version: DOCUMENT_VERSION,
+            sections: Vec::new(),
+        }
+    }
+}
+
+/// A full-width band of the page
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Section {
+    pub id: String,
+    /// Label shown in the builder, e.g. "Hero"
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub rows: Vec<Row>,
+    /// Global block shown in place of the section's own rows, which stay
+    /// empty; see [`PageDocument::resolve_blocks`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Row {
+    pub id: String,
+    #[serde(default)]
+    pub columns: Vec<Column>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Column {
+    pub id: String,
+    /// Share of the row in grid units, out of [`GRID_UNITS`]
+    #[serde(default = "full_width")]
+    pub width: u8,
+    #[serde(default)]
+    pub modules: Vec<Module>,
+}
+
+fn full_width() -> u8 {
+    GRID_UNITS
+}
+
+/// A content block placed in a column
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Module {
+    pub id: String,
+    #[serde(flatten)]
+    pub kind: ModuleKind,
+    /// CSS declarations applied to the module, keyed by property. This is
+    /// the desktop style; `breakpoints` override it on narrower screens.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub style: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakpoints: Vec<BreakpointStyle>,
+    /// Interactivity, as actions rather than script
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<EventBinding>,
+}
+
+/// What a visitor does to a module
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    Click,
+    /// The module scrolls into view, the first time only
+    Visible,
+}
+
+impl Trigger {
+    pub const ALL: [Trigger; 2] = [Trigger::Click, Trigger::Visible];
+
+    /// Name stored in `component_events.event_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Trigger::Click => "click",
+            Trigger::Visible => "visible",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Trigger::ALL.iter().copied().find(|t| t.as_str() == name)
+    }
+}
+
+/// Something the page runtime can do. This list is everything it knows how
+/// to do, so nothing in a document can run arbitrary script. Targets are
+/// ids of sections or modules on the same page.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    OpenUrl {
+        url: String,
+        #[serde(default)]
+        new_tab: bool,
+    },
+    ToggleClass { target: String, class: String },
+    /// Show a section as a dialog; sections used this way are hidden until shown
+    ShowModal { target: String },
+    /// Report an analytics event to whatever tracker the site loads
+    TrackEvent {
+        name: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        properties: BTreeMap<String, String>,
+    },
+}
+
+impl Action {
+    /// Id of the section or module the action works on, if any
+    pub fn target_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Action::ToggleClass { target, .. } | Action::ShowModal { target } => Some(target),
+            Action::OpenUrl { .. } | Action::TrackEvent { .. } => None,
+        }
+    }
+
+    /// Name of the action, as tagged in JSON
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Action::OpenUrl { .. } => "open_url",
+            Action::ToggleClass { .. } => "toggle_class",
+            Action::ShowModal { .. } => "show_modal",
+            Action::TrackEvent { .. } => "track_event",
+        }
+    }
+}
+
+/// Actions run, in order, when a trigger fires
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventBinding {
+    pub trigger: Trigger,
+    pub actions: Vec<Action>,
+}
+
+/// A screen width below which styles are overridden. Desktop, the widest,
+/// is a module's base `style` and has no breakpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Breakpoint {
+    Tablet,
+    Mobile,
+    /// Any other width, in pixels
+    Custom(u16),
+}
+
+impl Breakpoint {
+    /// The widest screen, in pixels, the breakpoint applies to
+    pub fn max_width(&self) -> u16 {
+        match self {
+            Breakpoint::Tablet => 1024,
+            Breakpoint::Mobile => 767,
+            Breakpoint::Custom(width) => *width,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Breakpoint::Tablet => "Tablet".to_string(),
+            Breakpoint::Mobile => "Mobile".to_string(),
+            Breakpoint::Custom(width) => format!("{}px", width),
+        }
+    }
+}
+
+/// Declarations that replace or add to a module's style at a breakpoint
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BreakpointStyle {
+    pub breakpoint: Breakpoint,
+    pub style: BTreeMap<String, String>,
+}
+
+impl Module {
+    /// Style declared at a breakpoint, or the desktop style for `None`
+    pub fn style_at(&self, breakpoint: Option<Breakpoint>) -> Option<&BTreeMap<String, String>> {
+        match breakpoint {
+            None => Some(&self.style),
+            Some(breakpoint) => self
+                .breakpoints
+                .iter()
+                .find(|b| b.breakpoint == breakpoint)
+                .map(|b| &b.style),
+        }
+    }
+
+    /// Set or clear (with an empty value) one property at a breakpoint.
+    /// Overrides are kept widest first, and dropped once empty.
+    pub fn set_style(&mut self, breakpoint: Option<Breakpoint>, property: &str, value: &str) {
+        let style = match breakpoint {
+            None => &mut self.style,
+            Some(breakpoint) => {
+                let index = match self.breakpoints.iter().position(|b| b.breakpoint == breakpoint) {
+                    Some(index) => index,
+                    None => {
+                        self.breakpoints.push(BreakpointStyle {
+                            breakpoint,
+                            style: BTreeMap::new(),
+                        });
+                        self.breakpoints
+                            .sort_by(|a, b| b.breakpoint.max_width().cmp(&a.breakpoint.max_width()));
+                        self.breakpoints.iter().position(|b| b.breakpoint == breakpoint).unwrap_or(0)
+                    }
+                };
+                &mut self.breakpoints[index].style
+            }
+        };
+        if value.is_empty() {
+            style.remove(property);
+        } else {
+            style.insert(property.to_string(), value.to_string());
+        }
+        self.breakpoints.retain(|b| !b.style.is_empty());
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ModuleKind {
+    /// Rich text; the backend sanitizes it before storing
+    Text { html: String },
+    Image {
+        /// Library item the image came from, if any
+        #[serde(default)]
+        media_id: Option<i32>,
+        src: String,
+        #[serde(default)]
+        alt: String,
+    },
+    Button {
+        label: String,
+        url: String,
+        #[serde(default)]
+        new_tab: bool,
+    },
+}
+
+impl ModuleKind {
+    /// Name stored in `components.name`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ModuleKind::Text { .. } => "text",
+            ModuleKind::Image { .. } => "image",
+            ModuleKind::Button { .. } => "button",
+        }
+    }
+}
+
+/// A problem with a document, located by a path such as
+/// `sections[0].rows[1].columns[0].modules[2].url`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocumentError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Collects errors while walking a document
+struct Validator {
+    errors: Vec<DocumentError>,
+    ids: HashSet<String>,
+    section_ids: HashSet<String>,
+    /// Action targets, checked once every id is known: (path, id, must be a section)
+    targets: Vec<(String, String, bool)>,
+    modules: usize,
+}
+
+impl Validator {
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.errors.push(DocumentError {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn id(&mut self, path: &str, id: &str) {
+        let path = format!("{}.id", path);
+        if id.is_empty() || id.chars().count() > MAX_ID_CHARS {
+            self.error(&path, format!("must be 1 to {} characters", MAX_ID_CHARS));
+        } else if !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            self.error(&path, "may only contain letters, digits, '-' and '_'");
+        } else if !self.ids.insert(id.to_string()) {
+            self.error(&path, format!("duplicate id \"{}\"", id));
+        }
+    }
+
+    fn max_len(&mut self, path: &str, value: &str, max: usize) {
+        if value.chars().count() > max {
+            self.error(path, format!("must be at most {} characters", max));
+        }
+    }
+
+    fn section(&mut self, path: &str, section: &Section) {
+        self.id(path, &section.id);
+        self.section_ids.insert(section.id.clone());
+        self.max_len(&format!("{}.name", path), &section.name, MAX_NAME_CHARS);
+        if let Some(block_id) = section.block_id {
+            if block_id <= 0 {
+                self.error(&format!("{}.block_id", path), "must be a library item id");
+            }
+            if !section.rows.is_empty() {
+                self.error(&format!("{}.rows", path), "must be empty in a global block");
+            }
+        }
+        if section.rows.len() > MAX_ROWS_PER_SECTION {
+            self.error(&format!("{}.rows", path), format!("at most {} rows are allowed", MAX_ROWS_PER_SECTION));
+        }
+        for (i, row) in section.rows.iter().enumerate() {
+            self.row(&format!("{}.rows[{}]", path, i), row);
+        }
+    }
+
+    fn row(&mut self, path: &str, row: &Row) {
+        self.id(path, &row.id);
+        if row.columns.is_empty() || row.columns.len() > MAX_COLUMNS_PER_ROW {
+            self.error(
+                &format!("{}.columns", path),
+                format!("a row needs 1 to {} columns", MAX_COLUMNS_PER_ROW),
+            );
+        }
+        let total: u32 = row.columns.iter().map(|c| u32::from(c.width)).sum();
+        if total > u32::from(GRID_UNITS) {
+            self.error(
+                &format!("{}.columns", path),
+                format!("column widths add up to {}, more than {}", total, GRID_UNITS),
+            );
+        }
+        for (i, column) in row.columns.iter().enumerate() {
+            self.column(&format!("{}.columns[{}]", path, i), column);
+        }
+    }
+
+    fn column(&mut self, path: &str, column: &Column) {
+        self.id(path, &column.id);
+        if column.width == 0 || column.width > GRID_UNITS {
+            self.error(&format!("{}.width", path), format!("must be between 1 and {}", GRID_UNITS));
+        }
+        if column.modules.len() > MAX_MODULES_PER_COLUMN {
+            self.error(
+                &format!("{}.modules", path),
+                format!("at most {} modules are allowed", MAX_MODULES_PER_COLUMN),
+            );
+        }
+        for (i, module) in column.modules.iter().enumerate() {
+            self.module(&format!("{}.modules[{}]", path, i), module);
+        }
+    }
+
+    fn module(&mut self, path: &str, module: &Module) {
+        self.id(path, &module.id);
+        self.modules += 1;
+
+        match &module.kind {
+            ModuleKind::Text { html } => {
+                self.max_len(&format!("{}.html", path), html, MAX_TEXT_CHARS);
+            }
+            ModuleKind::Image { src, alt, .. } => {
+                let src_path = format!("{}.src", path);
+                // An empty source is a placeholder still waiting for an image
+                if !src.is_empty() && !is_safe_image_src(src) {
+                    self.error(&src_path, "must be a site path or an http(s) URL");
+                }
+                self.max_len(&format!("{}.alt", path), alt, MAX_ALT_CHARS);
+            }
+            ModuleKind::Button { label, url, .. } => {
+                let label_path = format!("{}.label", path);
+                if label.trim().is_empty() {
+                    self.error(&label_path, "is required");
+                }
+                self.max_len(&label_path, label, MAX_LABEL_CHARS);
+                if !is_safe_url(url) {
+                    self.error(&format!("{}.url", path), "must be a site path, #anchor, or an http(s), mailto: or tel: URL");
+                }
+            }
+        }
+
+        self.style(&format!("{}.style", path), &module.style);
+        if module.breakpoints.len() > MAX_BREAKPOINTS {
+            self.error(
+                &format!("{}.breakpoints", path),
+                format!("at most {} breakpoints are allowed", MAX_BREAKPOINTS),
+            );
+        }
+        let mut widths = HashSet::new();
+        for (i, override_) in module.breakpoints.iter().enumerate() {
+            let breakpoint_path = format!("{}.breakpoints[{}]", path, i);
+            if let Breakpoint::Custom(width) = override_.breakpoint {
+                if !(MIN_CUSTOM_WIDTH..=MAX_CUSTOM_WIDTH).contains(&width) {
+                    self.error(
+                        &format!("{}.breakpoint", breakpoint_path),
+                        format!("custom widths must be {} to {} pixels", MIN_CUSTOM_WIDTH, MAX_CUSTOM_WIDTH),
+                    );
+                }
+            }
+            if !widths.insert(override_.breakpoint.max_width()) {
+                self.error(&format!("{}.breakpoint", breakpoint_path), "duplicate breakpoint width");
+            }
+            self.style(&format!("{}.style", breakpoint_path), &override_.style);
+        }
+
+        if module.events.len() > MAX_BINDINGS {
+            self.error(&format!("{}.events", path), format!("at most {} event bindings are allowed", MAX_BINDINGS));
+        }
+        for (i, binding) in module.events.iter().enumerate() {
+            let binding_path = format!("{}.events[{}]", path, i);
+            if binding.actions.is_empty() || binding.actions.len() > MAX_ACTIONS {
+                self.error(
+                    &format!("{}.actions", binding_path),
+                    format!("a binding needs 1 to {} actions", MAX_ACTIONS),
+                );
+            }
+            for (j, action) in binding.actions.iter().enumerate() {
+                self.action(&format!("{}.actions[{}]", binding_path, j), action);
+            }
+        }
+    }
+
+    fn action(&mut self, path: &str, action: &Action) {
+        match action {
+            Action::OpenUrl { url, .. } => {
+                if !is_safe_url(url) {
+                    self.error(&format!("{}.url", path), "must be a site path, #anchor, or an http(s), mailto: or tel: URL");
+                }
+            }
+            Action::ToggleClass { target, class } => {
+                self.targets.push((format!("{}.target", path), target.clone(), false));
+                if !is_safe_identifier(class, MAX_CLASS_CHARS) {
+                    self.error(&format!("{}.class", path), "must be a class name of letters, digits, '-' and '_'");
+                }
+            }
+            Action::ShowModal { target } => {
+                self.targets.push((format!("{}.target", path), target.clone(), true));
+            }
+            Action::TrackEvent { name, properties } => {
+                if !is_safe_identifier(name, MAX_EVENT_NAME_CHARS) {
+                    self.error(&format!("{}.name", path), "must be an event name of letters, digits, '-' and '_'");
+                }
+                if properties.len() > MAX_EVENT_PROPERTIES {
+                    self.error(
+                        &format!("{}.properties", path),
+                        format!("at most {} properties are allowed", MAX_EVENT_PROPERTIES),
+                    );
+                }
+                for (key, value) in properties {
+                    let property_path = format!("{}.properties.{}", path, key);
+                    if !is_safe_identifier(key, MAX_EVENT_NAME_CHARS) {
+                        self.error(&property_path, "must be a name of letters, digits, '-' and '_'");
+                    }
+                    self.max_len(&property_path, value, MAX_EVENT_VALUE_CHARS);
+                }
+            }
+        }
+    }
+
+    /// Check every action target now that all ids have been seen
+    fn targets(&mut self) {
+        for (path, id, section_only) in std::mem::take(&mut self.targets) {
+            if section_only && !self.section_ids.contains(&id) {
+                self.error(&path, format!("no section \"{}\" on this page", id));
+            } else if !self.ids.contains(&id) {
+                self.error(&path, format!("nothing with id \"{}\" on this page", id));
+            }
+        }
+    }
+
+    fn style(&mut self, path: &str, style: &BTreeMap<String, String>) {
+        for (property, value) in style {
+            let style_path = format!("{}.{}", path, property);
+            if !STYLE_PROPERTIES.contains(&property.as_str()) {
+                self.error(&style_path, "is not a supported style property");
+            } else if !is_safe_style_value(value) {
+                self.error(&style_path, "is not a valid value");
+            }
+        }
+    }
+}
+
+impl PageDocument {
+    /// Check the document against the current format, returning every problem found
+    pub fn validate(&self) -> Result<(), Vec<DocumentError>> {
+        let mut validator = Validator {
+            errors: Vec::new(),
+            ids: HashSet::new(),
+            section_ids: HashSet::new(),
+            targets: Vec::new(),
+            modules: 0,
+        };
+
+        if self.version != DOCUMENT_VERSION {
+            validator.error(
+                "version",
+                format!("unsupported version {}, expected {}", self.version, DOCUMENT_VERSION),
+            );
+        }
+        if self.sections.len() > MAX_SECTIONS {
+            validator.error("sections", format!("at most {} sections are allowed", MAX_SECTIONS));
+        }
+        for (i, section) in self.sections.iter().enumerate() {
+            validator.section(&format!("sections[{}]", i), section);
+        }
+        if validator.modules > MAX_MODULES {
+            validator.error("sections", format!("at most {} modules are allowed per page", MAX_MODULES));
+        }
+        validator.targets();
+
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.errors)
+        }
+    }
+
+    /// Ids of the global blocks the document uses, without duplicates
+    pub fn block_ids(&self) -> Vec<i32> {
+        let mut ids: Vec<i32> = self.sections.iter().filter_map(|s| s.block_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Fill each global block section with the rows of its block, for
+    /// rendering. Blocks missing from `blocks` render as empty sections.
+    ///
+    /// A block may be used more than once on a page, so the ids inside it,
+    /// and the action targets naming them, are prefixed with the id of the
+    /// section holding it.
+    pub fn resolve_blocks(&mut self, blocks: &HashMap<i32, Section>) {
+        for section in &mut self.sections {
+            let block = match section.block_id.and_then(|id| blocks.get(&id)) {
+                Some(block) => block,
+                None => continue,
+            };
+            let namespace = |id: &str| format!("{}-{}", section.id, id);
+            let mut inner: HashSet<&str> = HashSet::new();
+            for row in &block.rows {
+                inner.insert(&row.id);
+                for column in &row.columns {
+                    inner.insert(&column.id);
+                    inner.extend(column.modules.iter().map(|m| m.id.as_str()));
+                }
+            }
+
+            let mut rows = block.rows.clone();
+            for row in &mut rows {
+                row.id = namespace(&row.id);
+                for column in &mut row.columns {
+                    column.id = namespace(&column.id);
+                    for module in &mut column.modules {
+                        module.id = namespace(&module.id);
+                        for action in module.events.iter_mut().flat_map(|b| &mut b.actions) {
+                            if let Some(target) = action.target_mut() {
+                                if *target == block.id {
+                                    *target = section.id.clone();
+                                } else if inner.contains(target.as_str()) {
+                                    *target = namespace(target);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            section.rows = rows;
+        }
+    }
+
+    /// Stylesheet for the document's module styles, each rule scoped to its
+    /// module's [`module_class`]. With no `viewport` the breakpoints become
+    /// media queries; with one, only the overrides that apply at that width
+    /// are included, so a narrow preview pane can show a phone layout.
+    /// Rows of several columns stack them at the mobile breakpoint.
+    pub fn compile_css(&self, viewport: Option<u16>) -> String {
+        let mut css = String::new();
+        let mut queries: BTreeMap<std::cmp::Reverse<u16>, String> = BTreeMap::new();
+
+        let stack_width = Breakpoint::Mobile.max_width();
+        let has_columns = self.sections.iter().flat_map(|s| &s.rows).any(|r| r.columns.len() > 1);
+        if has_columns && viewport.map_or(true, |viewport| viewport <= stack_width) {
+            queries
+                .entry(std::cmp::Reverse(stack_width))
+                .or_default()
+                .push_str(".builder-page .bp-row > .bp-column { flex: 1 1 100%; }\n");
+        }
+
+        for module in self.modules() {
+            write_rule(&mut css, &module.id, &module.style);
+            for override_ in &module.breakpoints {
+                let width = override_.breakpoint.max_width();
+                if viewport.map_or(true, |viewport| viewport <= width) {
+                    write_rule(queries.entry(std::cmp::Reverse(width)).or_default(), &module.id, &override_.style);
+                }
+            }
+        }
+
+        // Widest first, so narrower breakpoints win
+        for (std::cmp::Reverse(width), rules) in queries {
+            match viewport {
+                None => {
+                    let _ = write!(css, "@media (max-width: {}px) {{\n{}}}\n", width, rules);
+                }
+                Some(_) => css.push_str(&rules),
+            }
+        }
+        css
+    }
+
+    /// Ids of the sections some action shows as a dialog
+    pub fn modal_sections(&self) -> HashSet<&str> {
+        self.modules()
+            .flat_map(|m| &m.events)
+            .flat_map(|b| &b.actions)
+            .filter_map(|action| match action {
+                Action::ShowModal { target } => Some(target.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every module in the document, in reading order
+    pub fn modules(&self) -> impl Iterator<Item = &Module> {
+        self.sections
+            .iter()
+            .flat_map(|s| &s.rows)
+            .flat_map(|r| &r.columns)
+            .flat_map(|c| &c.modules)
+    }
+
+    /// Mutable access to every module, e.g. to sanitize text before saving
+    pub fn modules_mut(&mut self) -> impl Iterator<Item = &mut Module> {
+        self.sections
+            .iter_mut()
+            .flat_map(|s| &mut s.rows)
+            .flat_map(|r| &mut r.columns)
+            .flat_map(|c| &mut c.modules)
+    }
+}
+
+/// Class a module's compiled style rules are scoped to
+pub fn module_class(module_id: &str) -> String {
+    format!("bp-m-{}", module_id)
+}
+
+fn write_rule(css: &mut String, module_id: &str, style: &BTreeMap<String, String>) {
+    if style.is_empty() {
+        return;
+    }
+    let _ = write!(css, ".builder-page .{} {{", module_class(module_id));
+    for (property, value) in style {
+        let _ = write!(css, " {}: {};", property, value);
+    }
+    css.push_str(" }\n");
+}
+
+/// Event bindings in the form the page runtime reads from a module's
+/// `data-bp-events` attribute. Only validated documents are rendered, so
+/// this is plain data the runtime interprets; nothing in it is executed.
+pub fn compile_events(module: &Module) -> Option<String> {
+    if module.events.is_empty() {
+        return None;
+    }
+    serde_json::to_string(&module.events).ok()
+}
+
+/// Names and classes: letters, digits, '-' and '_', starting with a letter
+fn is_safe_identifier(value: &str, max: usize) -> bool {
+    value.chars().count() <= max
+        && value.chars().next().map_or(false, |c| c.is_ascii_alphabetic())
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Links may point within the site, to an anchor, or to http(s), mailto:
+/// and tel: URLs; anything else (notably `javascript:`) is refused
+pub fn is_safe_url(url: &str) -> bool {
+    if url.is_empty() || url.len() > MAX_URL_CHARS || url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return false;
+    }
+    let lower = url.to_ascii_lowercase();
+    (lower.starts_with('/') && !lower.starts_with("//"))
+        || lower.starts_with('#')
+        || ["http://", "https://", "mailto:", "tel:"]
+            .iter()
+            .any(|scheme| lower.starts_with(scheme) && lower.len() > scheme.len())
+}
+
+/// Images load from a site path or an http(s) URL
+pub fn is_safe_image_src(src: &str) -> bool {
+    let lower = src.to_ascii_lowercase();
+    is_safe_url(src) && (lower.starts_with('/') || lower.starts_with("http://") || lower.starts_with("https://"))
+}
+
+/// Style values are limited to characters that can't break out of a declaration
+pub fn is_safe_style_value(value: &str) -> bool {
+    !value.trim().is_empty()
+        && value.chars().count() <= MAX_STYLE_VALUE_CHARS
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || " #%.,-()".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(modules: Vec<Module>) -> PageDocument {
+        PageDocument {
+            version: DOCUMENT_VERSION,
+            sections: vec![Section {
+                id: "s1".to_string(),
+                name: "Hero".to_string(),
+                rows: vec![Row {
+                    id: "r1".to_string(),
+                    columns: vec![Column {
+                        id: "c1".to_string(),
+                        width: GRID_UNITS,
+                        modules,
+                    }],
+                }],
+                block_id: None,
+            }],
+        }
+    }
+
+    fn button(id: &str, url: &str) -> Module {
+        Module {
+            id: id.to_string(),
+            kind: ModuleKind::Button {
+                label: "Go".to_string(),
+                url: url.to_string(),
+                new_tab: false,
+            },
+            style: BTreeMap::new(),
+            breakpoints: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn modules_round_trip_through_json_with_a_type_tag() {
+        let doc = document(vec![button("m1", "/contact")]);
+        let json = serde_json::to_value(&doc).unwrap();
+        assert_eq!(json["sections"][0]["rows"][0]["columns"][0]["modules"][0]["type"], "button");
+        assert_eq!(serde_json::from_value::<PageDocument>(json).unwrap(), doc);
+    }
+
+    #[test]
+    fn valid_document_passes() {
+        assert_eq!(document(vec![button("m1", "https://example.com")]).validate(), Ok(()));
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_node() {
+        let mut module = button("m1", "javascript:alert(1)");
+        module.style.insert("position".to_string(), "fixed".to_string());
+        let errors = document(vec![module, button("m1", "#top")]).validate().unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+
+        assert!(paths.contains(&"sections[0].rows[0].columns[0].modules[0].url"));
+        assert!(paths.contains(&"sections[0].rows[0].columns[0].modules[0].style.position"));
+        assert!(paths.contains(&"sections[0].rows[0].columns[0].modules[1].id"));
+    }
+
+    #[test]
+    fn column_widths_must_fit_the_grid() {
+        let mut doc = document(vec![]);
+        doc.sections[0].rows[0].columns.push(Column {
+            id: "c2".to_string(),
+            width: 6,
+            modules: vec![],
+        });
+        let errors = doc.validate().unwrap_err();
+        assert_eq!(errors[0].path, "sections[0].rows[0].columns");
+    }
+
+    #[test]
+    fn global_blocks_are_filled_in_for_rendering() {
+        let block = document(vec![button("m1", "/contact")]).sections.remove(0);
+        let mut doc = PageDocument::default();
+        doc.sections.push(Section {
+            id: "s2".to_string(),
+            name: "Header".to_string(),
+            rows: vec![],
+            block_id: Some(7),
+        });
+        assert_eq!(doc.validate(), Ok(()));
+        assert_eq!(doc.block_ids(), vec![7]);
+
+        let mut blocks = HashMap::new();
+        blocks.insert(7, block.clone());
+        doc.resolve_blocks(&blocks);
+        assert_eq!(doc.sections[0].rows.len(), block.rows.len());
+        assert_eq!(doc.modules().next().unwrap().id, "s2-m1");
+        // A resolved block is no longer a valid stored section
+        assert_eq!(doc.validate().unwrap_err()[0].path, "sections[0].rows");
+    }
+
+    #[test]
+    fn a_block_used_twice_gets_distinct_ids() {
+        let mut module = button("m1", "/");
+        module.events.push(EventBinding {
+            trigger: Trigger::Click,
+            actions: vec![Action::ToggleClass { target: "m1".to_string(), class: "open".to_string() }],
+        });
+        let block = document(vec![module]).sections.remove(0);
+        let mut doc = PageDocument::default();
+        for id in ["header", "footer"] {
+            doc.sections.push(Section {
+                id: id.to_string(),
+                name: "Banner".to_string(),
+                rows: vec![],
+                block_id: Some(7),
+            });
+        }
+
+        let mut blocks = HashMap::new();
+        blocks.insert(7, block);
+        doc.resolve_blocks(&blocks);
+        let ids: Vec<&str> = doc.modules().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["header-m1", "footer-m1"]);
+        let targets: Vec<&str> = doc
+            .modules()
+            .flat_map(|m| &m.events)
+            .flat_map(|b| &b.actions)
+            .filter_map(|action| match action {
+                Action::ToggleClass { target, .. } => Some(target.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(targets, vec!["header-m1", "footer-m1"]);
+    }
+
+    #[test]
+    fn breakpoints_compile_to_media_queries_narrowest_last() {
+        let mut module = button("m1", "/contact");
+        module.set_style(None, "padding", "24px");
+        module.set_style(Some(Breakpoint::Mobile), "padding", "8px");
+        module.set_style(Some(Breakpoint::Tablet), "padding", "16px");
+        assert_eq!(module.breakpoints[0].breakpoint, Breakpoint::Tablet);
+        let doc = document(vec![module]);
+        assert_eq!(doc.validate(), Ok(()));
+
+        assert_eq!(
+            doc.compile_css(None),
+            ".builder-page .bp-m-m1 { padding: 24px; }\n\
+             @media (max-width: 1024px) {\n.builder-page .bp-m-m1 { padding: 16px; }\n}\n\
+             @media (max-width: 767px) {\n.builder-page .bp-m-m1 { padding: 8px; }\n}\n"
+        );
+        // A tablet-sized preview gets the tablet override but not the mobile one
+        assert_eq!(
+            doc.compile_css(Some(800)),
+            ".builder-page .bp-m-m1 { padding: 24px; }\n.builder-page .bp-m-m1 { padding: 16px; }\n"
+        );
+    }
+
+    #[test]
+    fn columns_stack_at_the_mobile_breakpoint() {
+        let mut doc = document(vec![button("m1", "/")]);
+        let mut second = doc.sections[0].rows[0].columns[0].clone();
+        second.id = "c2".to_string();
+        second.modules[0].id = "m2".to_string();
+        doc.sections[0].rows[0].columns.push(second);
+
+        assert_eq!(
+            doc.compile_css(None),
+            "@media (max-width: 767px) {\n.builder-page .bp-row > .bp-column { flex: 1 1 100%; }\n}\n"
+        );
+        assert_eq!(doc.compile_css(Some(1024)), "");
+        assert_eq!(doc.compile_css(Some(375)), ".builder-page .bp-row > .bp-column { flex: 1 1 100%; }\n");
+    }
+
+    #[test]
+    fn clearing_the_last_override_drops_the_breakpoint() {
+        let mut module = button("m1", "/");
+        module.set_style(Some(Breakpoint::Custom(600)), "color", "red");
+        assert_eq!(module.style_at(Some(Breakpoint::Custom(600))).unwrap()["color"], "red");
+        module.set_style(Some(Breakpoint::Custom(600)), "color", "");
+        assert!(module.breakpoints.is_empty());
+
+        module.set_style(Some(Breakpoint::Custom(100)), "color", "red");
+        let errors = document(vec![module]).validate().unwrap_err();
+        assert_eq!(errors[0].path, "sections[0].rows[0].columns[0].modules[0].breakpoints[0].breakpoint");
+    }
+
+    #[test]
+    fn actions_are_checked_against_the_page() {
+        let mut module = button("m1", "/");
+        module.events.push(EventBinding {
+            trigger: Trigger::Click,
+            actions: vec![
+                Action::ShowModal { target: "s1".to_string() },
+                Action::ToggleClass { target: "m1".to_string(), class: "is-open".to_string() },
+                Action::TrackEvent { name: "cta_click".to_string(), properties: BTreeMap::new() },
+            ],
+        });
+        let doc = document(vec![module.clone()]);
+        assert_eq!(doc.validate(), Ok(()));
+        assert!(doc.modal_sections().contains("s1"));
+
+        module.events[0].actions = vec![
+            Action::OpenUrl { url: "javascript:alert(1)".to_string(), new_tab: false },
+            Action::ShowModal { target: "m1".to_string() },
+            Action::ToggleClass { target: "nope".to_string(), class: "a\" onclick=\"x".to_string() },
+        ];
+        let errors = document(vec![module]).validate().unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        let base = "sections[0].rows[0].columns[0].modules[0].events[0].actions";
+        assert!(paths.contains(&format!("{}[0].url", base).as_str()));
+        assert!(paths.contains(&format!("{}[1].target", base).as_str()));
+        assert!(paths.contains(&format!("{}[2].target", base).as_str()));
+        assert!(paths.contains(&format!("{}[2].class", base).as_str()));
+    }
+
+    #[test]
+    fn unsafe_urls_and_style_values_are_refused() {
+        assert!(is_safe_url("/about"));
+        assert!(is_safe_url("mailto:hello@example.com"));
+        assert!(!is_safe_url("//evil.example"));
+        assert!(!is_safe_url("JavaScript:alert(1)"));
+        assert!(is_safe_style_value("#ff0000"));
+        assert!(!is_safe_style_value("red; background: url(x)"));
+    }
+}
@@ -0,0 +1,12 @@
+// src/shared/mod.rs
+//
+// Types and helpers used by both the backend and the frontend.
+
+pub mod api_keys;
+pub mod audit;
+pub mod builder;
+pub mod constants;
+pub mod settings;
+pub mod tags;
+pub mod types;
+pub mod utils;
@@ -0,0 +1,204 @@
+// src/shared/settings.rs
+//
+// Schema of the site settings. Backend modules declare the settings they
+// read; the backend validates writes against the declarations and the
+// Settings page builds its forms from them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::shared::builder::is_safe_url;
+
+/// Longest value a text setting may hold unless it declares its own limit
+pub const DEFAULT_MAX_TEXT_CHARS: usize = 10_000;
+
+/// What a setting holds, with its validation rules
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SettingKind {
+    Text {
+        #[serde(default)]
+        max_chars: Option<usize>,
+        /// Edited in a textarea; one entry per line by convention
+        #[serde(default)]
+        multiline: bool,
+        #[serde(default)]
+        required: bool,
+    },
+    Integer { min: i64, max: i64 },
+    Boolean,
+    Email {
+        #[serde(default)]
+        required: bool,
+    },
+    Url,
+    Choice { options: Vec<String> },
+}
+
+/// One setting, as declared by the module that reads it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingDef {
+    /// Key in the `settings` table
+    pub key: String,
+    pub label: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub kind: SettingKind,
+    /// Value used until the setting is saved
+    pub default: Value,
+}
+
+/// Settings shown together on one form
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingGroup {
+    pub id: String,
+    pub label: String,
+    pub settings: Vec<SettingDef>,
+}
+
+/// A setting with its current value, as returned by `GET /settings`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingValue {
+    #[serde(flatten)]
+    pub def: SettingDef,
+    pub value: Value,
+}
+
+/// A group of settings with their current values
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingGroupValues {
+    pub id: String,
+    pub label: String,
+    pub settings: Vec<SettingValue>,
+}
+
+/// A rejected setting write
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingError {
+    pub key: String,
+    pub message: String,
+}
+
+impl SettingDef {
+    pub fn new(key: &str, label: &str, kind: SettingKind, default: Value) -> Self {
+        Self {
+            key: key.to_string(),
+            label: label.to_string(),
+            description: None,
+            kind,
+            default,
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Check a submitted value, returning it normalized (e.g. trimmed)
+    pub fn validate(&self, value: &Value) -> Result<Value, String> {
+        match &self.kind {
+            SettingKind::Text { max_chars, required, .. } => {
+                let text = value.as_str().ok_or("must be text")?;
+                let text = text.trim();
+                if *required && text.is_empty() {
+                    return Err("is required".to_string());
+                }
+                let max = max_chars.unwrap_or(DEFAULT_MAX_TEXT_CHARS);
+                if text.chars().count() > max {
+                    return Err(format!("must be at most {} characters", max));
+                }
+                Ok(Value::String(text.to_string()))
+            }
+            SettingKind::Integer { min, max } => {
+                let number = value
+                    .as_i64()
+                    .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+                    .ok_or("must be a whole number")?;
+                if number < *min || number > *max {
+                    return Err(format!("must be between {} and {}", min, max));
+                }
+                Ok(Value::from(number))
+            }
+            SettingKind::Boolean => value.as_bool().map(Value::Bool).ok_or_else(|| "must be true or false".to_string()),
+            SettingKind::Email { required } => {
+                let email = value.as_str().ok_or("must be text")?.trim();
+                if email.is_empty() {
+                    return if *required { Err("is required".to_string()) } else { Ok(Value::String(String::new())) };
+                }
+                let plausible = email.len() <= 254
+                    && !email.chars().any(char::is_whitespace)
+                    && email
+                        .split_once('@')
+                        .map_or(false, |(user, domain)| !user.is_empty() && domain.contains('.'));
+                if !plausible {
+                    return Err("must be an email address".to_string());
+                }
+                Ok(Value::String(email.to_string()))
+            }
+            SettingKind::Url => {
+                let url = value.as_str().ok_or("must be text")?.trim();
+                if !url.is_empty() && !is_safe_url(url) {
+                    return Err("must be a site path or an http(s) URL".to_string());
+                }
+                Ok(Value::String(url.to_string()))
+            }
+            SettingKind::Choice { options } => {
+                let choice = value.as_str().ok_or("must be text")?;
+                if !options.iter().any(|option| option == choice) {
+                    return Err(format!("must be one of: {}", options.join(", ")));
+                }
+                Ok(Value::String(choice.to_string()))
+            }
+        }
+    }
+
+    /// Text stored in `settings.setting_value` for a validated value
+    pub fn encode(&self, value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Value of a stored setting; the default if it no longer fits the schema
+    pub fn decode(&self, stored: Option<&str>) -> Value {
+        let stored = match stored {
+            Some(stored) => stored,
+            None => return self.default.clone(),
+        };
+        let candidate = match &self.kind {
+            SettingKind::Integer { .. } | SettingKind::Boolean => {
+                serde_json::from_str::<Value>(stored).unwrap_or(Value::Null)
+            }
+            _ => Value::String(stored.to_string()),
+        };
+        self.validate(&candidate).unwrap_or_else(|_| self.default.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_are_checked_and_round_trip_through_storage() {
+        let posts = SettingDef::new("posts_per_page", "Posts per page", SettingKind::Integer { min: 1, max: 100 }, json!(10));
+        assert_eq!(posts.validate(&json!("25")), Ok(json!(25)));
+        assert!(posts.validate(&json!(0)).is_err());
+        assert_eq!(posts.decode(Some(&posts.encode(&json!(25)))), json!(25));
+        // Stored values that no longer fit fall back to the default
+        assert_eq!(posts.decode(Some("500")), json!(10));
+        assert_eq!(posts.decode(None), json!(10));
+
+        let email = SettingDef::new("admin_email", "Admin email", SettingKind::Email { required: true }, json!(""));
+        assert_eq!(email.validate(&json!(" a@example.com ")), Ok(json!("a@example.com")));
+        assert!(email.validate(&json!("nope")).is_err());
+        assert!(email.validate(&json!("")).is_err());
+
+        let flag = SettingDef::new("comments_open", "Comments open", SettingKind::Boolean, json!(true));
+        assert_eq!(flag.decode(Some(&flag.encode(&json!(false)))), json!(false));
+    }
+}
@@ -0,0 +1,74 @@
+// src/shared/tags.rs
+//
+// Tags are free-form labels on posts. Each has a slug for its URL
+// (`/tag/<slug>`), and tags are told apart by slug: tagging a post "Rust"
+// and another "rust " gives both the same tag.
+
+use serde::{Deserialize, Serialize};
+
+/// Longest tag name accepted, in characters
+pub const MAX_TAG_CHARS: usize = 50;
+
+/// A tag and the number of posts it's on
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TagSummary {
+    pub id: i32,
+    pub name: String,
+    pub slug: String,
+    pub post_count: i64,
+}
+
+/// Body of `POST /tags` and `PUT /tags/:id`. The slug is made from the name
+/// unless given.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TagRequest {
+    pub name: String,
+    #[serde(default)]
+    pub slug: Option<String>,
+}
+
+/// Body of `POST /tags/:id/merge`: the tags to fold into `:id`. Their posts
+/// move over and the tags are deleted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MergeTagsRequest {
+    pub tags: Vec<i32>,
+}
+
+/// Body of `PUT` and `POST /posts/:id/tags`: tag names, created as needed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PostTagsRequest {
+    pub tags: Vec<String>,
+}
+
+/// URL form of a tag name or post title: lowercase letters and digits, with anything else
+/// between them turned into single hyphens
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    let mut pending_hyphen = false;
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            if pending_hyphen && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_hyphen = false;
+            slug.extend(c.to_lowercase());
+        } else {
+            pending_hyphen = true;
+        }
+    }
+    slug
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs_collapse_punctuation_and_case() {
+        assert_eq!(slugify("Rust"), "rust");
+        assert_eq!(slugify("  Web  Assembly! "), "web-assembly");
+        assert_eq!(slugify("C++ & C#"), "c-c");
+        assert_eq!(slugify("Café"), "café");
+        assert_eq!(slugify("?!"), "");
+    }
+}
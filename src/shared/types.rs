@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use crate::shared::builder::PageDocument;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UserRole {
     Admin,
     Editor,
@@ -9,9 +13,190 @@ pub enum UserRole {
     Subscriber,
 }
 
+/// Something a user may be allowed to do, granted through their role
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    CreatePosts,
+    EditOwnPosts,
+    EditOthersPosts,
+    PublishPosts,
+    DeleteOwnPosts,
+    DeleteOthersPosts,
+    UploadMedia,
+    DeleteMedia,
+    ManageCategories,
+    /// Rename, merge and delete tags; anyone who can edit a post can tag it
+    ManageTags,
+    ModerateComments,
+    UseBuilder,
+    ManageSettings,
+    ManageUsers,
+    ManageThemes,
+    ViewAuditLog,
+}
+
+impl UserRole {
+    /// Value stored in `users.role`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "admin",
+            UserRole::Editor => "editor",
+            UserRole::Author => "author",
+            UserRole::Contributor => "contributor",
+            UserRole::Subscriber => "subscriber",
+        }
+    }
+
+    /// Capabilities granted to this role
+    pub fn capabilities(&self) -> &'static [Capability] {
+        use Capability::*;
+        match self {
+            UserRole::Admin => &[
+                CreatePosts, EditOwnPosts, EditOthersPosts, PublishPosts,
+                DeleteOwnPosts, DeleteOthersPosts, UploadMedia, DeleteMedia,
+                ManageCategories, ManageTags, ModerateComments, UseBuilder,
+                ManageSettings, ManageUsers, ManageThemes, ViewAuditLog,
+            ],
+            UserRole::Editor => &[
+                CreatePosts, EditOwnPosts, EditOthersPosts, PublishPosts,
+                DeleteOwnPosts, DeleteOthersPosts, UploadMedia, DeleteMedia,
+                ManageCategories, ManageTags, ModerateComments, UseBuilder,
+            ],
+            UserRole::Author => &[
+                CreatePosts, EditOwnPosts, PublishPosts, DeleteOwnPosts, UploadMedia,
+            ],
+            UserRole::Contributor => &[CreatePosts, EditOwnPosts],
+            UserRole::Subscriber => &[],
+        }
+    }
+
+    pub fn can(&self, capability: Capability) -> bool {
+        self.capabilities().contains(&capability)
+    }
+}
+
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "admin" => Ok(UserRole::Admin),
+            "editor" => Ok(UserRole::Editor),
+            "author" => Ok(UserRole::Author),
+            "contributor" => Ok(UserRole::Contributor),
+            "subscriber" => Ok(UserRole::Subscriber),
+            other => Err(format!("Unknown user role: {}", other)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ApiResponse<T> {
     pub data: T,
     pub message: String,
     pub success: bool,
 }
+
+/// A published post as handed to the public templates
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PostView {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+    pub content: String,
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<TermView>,
+}
+
+/// A page as handed to the public templates
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PageView {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+    pub content: String,
+    /// Component tree of pages built in the builder, rendered instead of `content`
+    #[serde(default)]
+    pub document: Option<PageDocument>,
+}
+
+/// A category or tag heading an archive listing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TermView {
+    pub name: String,
+    pub slug: String,
+}
+
+/// One entry of an archive listing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PostSummary {
+    pub title: String,
+    pub slug: String,
+    pub excerpt: String,
+    pub published_at: Option<String>,
+}
+
+/// What kind of content a search hit points at
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    Post,
+    Page,
+    Comment,
+}
+
+impl SearchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchKind::Post => "post",
+            SearchKind::Page => "page",
+            SearchKind::Comment => "comment",
+        }
+    }
+}
+
+impl FromStr for SearchKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "post" => Ok(SearchKind::Post),
+            "page" => Ok(SearchKind::Page),
+            "comment" => Ok(SearchKind::Comment),
+            other => Err(format!("Unknown search kind: {}", other)),
+        }
+    }
+}
+
+/// One ranked search result. `snippet` is escaped HTML in which only the
+/// matched terms are wrapped in `<mark>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    pub id: i32,
+    /// Title of the post or page; for comments, of the post commented on
+    pub title: String,
+    pub slug: String,
+    /// The post a comment belongs to
+    pub post_id: Option<i32>,
+    pub rank: f32,
+    pub snippet: String,
+}
+
+/// A page of search results
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchResults {
+    pub query: String,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub hits: Vec<SearchHit>,
+}